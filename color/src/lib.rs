@@ -81,6 +81,23 @@ impl ColorU {
     pub const fn is_fully_transparent(&self) -> bool {
         self.a == 0
     }
+
+    /// Composites this color over `background` with the source-over operator.
+    ///
+    /// Both colors and the result have straight, not premultiplied, alpha.
+    #[inline]
+    pub fn blend_over(&self, background: ColorU) -> ColorU {
+        let (src, dest) = (self.to_f32().premultiply(), background.to_f32().premultiply());
+        ColorF(src.0 + dest.0 * F32x4::splat(1.0 - src.a())).unpremultiply().to_u8()
+    }
+
+    /// Returns the relative luminance of this sRGB-encoded color, from 0 for black to 1 for
+    /// white. Alpha is ignored.
+    #[inline]
+    pub fn luminance(&self) -> f32 {
+        let color = self.to_f32().from_srgb();
+        0.2126 * color.r() + 0.7152 * color.g() + 0.0722 * color.b()
+    }
 }
 
 impl Debug for ColorU {
@@ -111,10 +128,36 @@ impl ColorF {
         ColorF(F32x4::new(r, g, b, a))
     }
 
+    /// Creates a color from hue, saturation, lightness, and alpha. The hue is in radians and
+    /// wraps around.
     #[inline]
-    pub fn from_hsla(mut h: f32, s: f32, l: f32, a: f32) -> ColorF {
+    pub fn from_hsla(h: f32, s: f32, l: f32, a: f32) -> ColorF {
         // https://en.wikipedia.org/wiki/HSL_and_HSV#HSL_to_RGB
+        let c = (1.0 - f32::abs(2.0 * l - 1.0)) * s;
+        ColorF::from_hue_chroma(h, c, l - 0.5 * c, a)
+    }
+
+    #[inline]
+    pub fn from_hsl(h: f32, s: f32, l: f32) -> ColorF {
+        ColorF::from_hsla(h, s, l, 1.0)
+    }
+
+    /// Creates a color from hue, saturation, value, and alpha. The hue is in radians and wraps
+    /// around.
+    #[inline]
+    pub fn from_hsva(h: f32, s: f32, v: f32, a: f32) -> ColorF {
+        // https://en.wikipedia.org/wiki/HSL_and_HSV#HSV_to_RGB
+        let c = v * s;
+        ColorF::from_hue_chroma(h, c, v - c, a)
+    }
 
+    #[inline]
+    pub fn from_hsv(h: f32, s: f32, v: f32) -> ColorF {
+        ColorF::from_hsva(h, s, v, 1.0)
+    }
+
+    // `m` is the amount to add to each channel to match the lightness or value.
+    fn from_hue_chroma(mut h: f32, c: f32, m: f32, a: f32) -> ColorF {
         // Make sure hue is always positive.
         h %= 2.0 * PI;
         if h < 0.0 {
@@ -123,8 +166,6 @@ impl ColorF {
 
         h *= 3.0 / PI;
 
-        // Calculate chroma.
-        let c = (1.0 - f32::abs(2.0 * l - 1.0)) * s;
         let xc = F32x4::new(c * (1.0 - f32::abs(h % 2.0 - 1.0)), c, 0.0, a);
         let rgba = match f32::ceil(h) as i32 {
             1     => xc.yxzw(),
@@ -135,15 +176,9 @@ impl ColorF {
             0 | 6 => xc.yzxw(),
             _     => xc.zzzw(),
         };
-        let m = l - 0.5 * c;
         ColorF(rgba + F32x4::new(m, m, m, 0.0))
     }
 
-    #[inline]
-    pub fn from_hsl(h: f32, s: f32, l: f32) -> ColorF {
-        ColorF::from_hsla(h, s, l, 1.0)
-    }
-
     #[inline]
     pub fn transparent_black() -> ColorF {
         ColorF::default()
@@ -170,6 +205,86 @@ impl ColorF {
         ColorF(self.0 + (other.0 - self.0) * F32x4::splat(t))
     }
 
+    /// Returns the hue (in radians, from 0 to 2π), saturation, lightness, and alpha of this
+    /// color.
+    pub fn to_hsla(&self) -> (f32, f32, f32, f32) {
+        let (h, max, min) = self.hue_max_min();
+        let l = 0.5 * (max + min);
+        let s = if l <= 0.0 || l >= 1.0 {
+            0.0
+        } else {
+            (max - min) / (1.0 - f32::abs(2.0 * l - 1.0))
+        };
+        (h, s, l, self.a())
+    }
+
+    /// Returns the hue (in radians, from 0 to 2π), saturation, value, and alpha of this color.
+    pub fn to_hsva(&self) -> (f32, f32, f32, f32) {
+        let (h, max, min) = self.hue_max_min();
+        let s = if max <= 0.0 { 0.0 } else { (max - min) / max };
+        (h, s, max, self.a())
+    }
+
+    // https://en.wikipedia.org/wiki/HSL_and_HSV#From_RGB
+    fn hue_max_min(&self) -> (f32, f32, f32) {
+        let (r, g, b) = (self.r(), self.g(), self.b());
+        let (max, min) = (r.max(g).max(b), r.min(g).min(b));
+        let c = max - min;
+
+        // Achromatic colors have no meaningful hue; use 0 by convention.
+        let h = if c == 0.0 {
+            0.0
+        } else if max == r {
+            ((g - b) / c + 6.0) % 6.0
+        } else if max == g {
+            (b - r) / c + 2.0
+        } else {
+            (r - g) / c + 4.0
+        };
+
+        (h * PI / 3.0, max, min)
+    }
+
+    /// Multiplies the red, green, and blue channels by alpha.
+    #[inline]
+    pub fn premultiply(&self) -> ColorF {
+        let mut color = ColorF(self.0 * F32x4::splat(self.a()));
+        color.set_a(self.a());
+        color
+    }
+
+    /// Divides the red, green, and blue channels by alpha, undoing `premultiply()`.
+    ///
+    /// A fully transparent color has no recoverable color information, so this returns
+    /// transparent black for it.
+    #[inline]
+    pub fn unpremultiply(&self) -> ColorF {
+        if self.a() == 0.0 {
+            return ColorF::transparent_black();
+        }
+        let mut color = ColorF(self.0 * F32x4::splat(1.0 / self.a()));
+        color.set_a(self.a());
+        color
+    }
+
+    /// Encodes a color in linear space with the sRGB transfer function. Alpha is unchanged.
+    #[inline]
+    pub fn to_srgb(&self) -> ColorF {
+        ColorF::new(linear_to_srgb(self.r()),
+                    linear_to_srgb(self.g()),
+                    linear_to_srgb(self.b()),
+                    self.a())
+    }
+
+    /// Decodes an sRGB-encoded color to linear space. Alpha is unchanged.
+    #[inline]
+    pub fn from_srgb(&self) -> ColorF {
+        ColorF::new(srgb_to_linear(self.r()),
+                    srgb_to_linear(self.g()),
+                    srgb_to_linear(self.b()),
+                    self.a())
+    }
+
     #[inline]
     pub fn r(&self) -> f32 {
         self.0[0]
@@ -224,6 +339,23 @@ impl Debug for ColorF {
     }
 }
 
+// https://en.wikipedia.org/wiki/SRGB#Transformation
+fn linear_to_srgb(x: f32) -> f32 {
+    if x <= 0.0031308 {
+        x * 12.92
+    } else {
+        1.055 * x.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn srgb_to_linear(x: f32) -> f32 {
+    if x <= 0.04045 {
+        x / 12.92
+    } else {
+        ((x + 0.055) / 1.055).powf(2.4)
+    }
+}
+
 #[inline]
 pub fn color_slice_to_u8_slice(slice: &[ColorU]) -> &[u8] {
     unsafe {
@@ -272,3 +404,112 @@ pub fn rgbf(r: f32, g: f32, b: f32) -> ColorF {
 pub fn rgbaf(r: f32, g: f32, b: f32, a: f32) -> ColorF {
     ColorF::new(r, g, b, a)
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{ColorF, ColorU};
+    use std::f32::consts::PI;
+
+    fn approx_eq(a: ColorF, b: ColorF) -> bool {
+        a.0.approx_eq(b.0, 0.001)
+    }
+
+    #[test]
+    fn test_to_f32() {
+        assert!(approx_eq(ColorU::new(255, 0, 51, 102).to_f32(), ColorF::new(1.0, 0.0, 0.2, 0.4)));
+    }
+
+    #[test]
+    fn test_premultiply() {
+        let color = ColorF::new(1.0, 0.5, 0.25, 0.5);
+        let premultiplied = color.premultiply();
+        assert!(approx_eq(premultiplied, ColorF::new(0.5, 0.25, 0.125, 0.5)));
+        assert!(approx_eq(premultiplied.unpremultiply(), color));
+        let transparent = ColorF::new(1.0, 1.0, 1.0, 0.0);
+        assert_eq!(transparent.premultiply().unpremultiply(), ColorF::transparent_black());
+    }
+
+    #[test]
+    fn test_hsl() {
+        let red = ColorF::new(1.0, 0.0, 0.0, 1.0);
+        let green = ColorF::new(0.0, 1.0, 0.0, 1.0);
+        let blue = ColorF::new(0.0, 0.0, 1.0, 1.0);
+        assert!(approx_eq(ColorF::from_hsl(0.0, 1.0, 0.5), red));
+        assert!(approx_eq(ColorF::from_hsl(2.0 * PI / 3.0, 1.0, 0.5), green));
+        assert!(approx_eq(ColorF::from_hsl(4.0 * PI / 3.0, 1.0, 0.5), blue));
+
+        // Hue wraps around in both directions.
+        assert!(approx_eq(ColorF::from_hsl(2.0 * PI, 1.0, 0.5), red));
+        assert!(approx_eq(ColorF::from_hsl(-2.0 * PI / 3.0, 1.0, 0.5), blue));
+
+        // Zero saturation is gray regardless of hue.
+        let gray = ColorF::new(0.25, 0.25, 0.25, 0.5);
+        assert!(approx_eq(ColorF::from_hsla(1.0, 0.0, 0.25, 0.5), gray));
+        let (h, s, l, a) = gray.to_hsla();
+        assert_eq!((h, s), (0.0, 0.0));
+        assert!((l - 0.25).abs() < 0.001 && a == 0.5);
+
+        let color = ColorF::new(0.2, 0.4, 0.6, 1.0);
+        let (h, s, l, a) = color.to_hsla();
+        assert!(approx_eq(ColorF::from_hsla(h, s, l, a), color));
+    }
+
+    #[test]
+    fn test_hsv() {
+        assert!(approx_eq(ColorF::from_hsv(0.0, 1.0, 1.0), ColorF::new(1.0, 0.0, 0.0, 1.0)));
+        assert!(approx_eq(ColorF::from_hsv(PI, 0.5, 0.5), ColorF::new(0.25, 0.5, 0.5, 1.0)));
+        assert!(approx_eq(ColorF::from_hsv(5.0, 0.0, 0.75), ColorF::new(0.75, 0.75, 0.75, 1.0)));
+
+        let color = ColorF::new(0.9, 0.1, 0.5, 0.25);
+        let (h, s, v, a) = color.to_hsva();
+        assert!((v - 0.9).abs() < 0.001);
+        assert!(approx_eq(ColorF::from_hsva(h, s, v, a), color));
+        assert_eq!(ColorF::black().to_hsva(), (0.0, 0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn test_blend_over() {
+        fn approx_eq_u8(a: ColorU, b: ColorU) -> bool {
+            [(a.r, b.r), (a.g, b.g), (a.b, b.b), (a.a, b.a)].iter().all(|&(a, b)| {
+                (a as i32 - b as i32).abs() <= 1
+            })
+        }
+
+        let red = ColorU::new(255, 0, 0, 255);
+        let blue = ColorU::new(0, 0, 255, 255);
+        assert_eq!(red.blend_over(blue), red);
+        assert_eq!(ColorU::transparent_black().blend_over(blue), blue);
+
+        // Half-transparent red over opaque blue.
+        let half_red = ColorU::new(255, 0, 0, 128);
+        assert!(approx_eq_u8(half_red.blend_over(blue), ColorU::new(128, 0, 127, 255)));
+
+        // Color is weighted by alpha when the background is also translucent.
+        let half_blue = ColorU::new(0, 0, 255, 128);
+        assert!(approx_eq_u8(half_red.blend_over(half_blue), ColorU::new(170, 0, 85, 192)));
+
+        let nothing = ColorU::transparent_black();
+        assert_eq!(nothing.blend_over(nothing), nothing);
+    }
+
+    #[test]
+    fn test_luminance() {
+        assert_eq!(ColorU::black().luminance(), 0.0);
+        assert!((ColorU::white().luminance() - 1.0).abs() < 0.001);
+        assert!((ColorU::new(0, 255, 0, 255).luminance() - 0.7152).abs() < 0.001);
+        let (yellow, blue) = (ColorU::new(255, 255, 0, 255), ColorU::new(0, 0, 255, 255));
+        assert!(yellow.luminance() > blue.luminance());
+        assert_eq!(ColorU::new(128, 128, 128, 0).luminance(),
+                   ColorU::new(128, 128, 128, 255).luminance());
+    }
+
+    #[test]
+    fn test_srgb() {
+        // 50% gray in sRGB is about 21.4% in linear space.
+        let gray = ColorF::new(0.5, 0.5, 0.5, 0.5);
+        assert!(approx_eq(gray.from_srgb(), ColorF::new(0.214, 0.214, 0.214, 0.5)));
+        assert!(approx_eq(gray.from_srgb().to_srgb(), gray));
+        assert!(approx_eq(ColorF::black().to_srgb(), ColorF::black()));
+        assert!(approx_eq(ColorF::white().to_srgb(), ColorF::white()));
+    }
+}
@@ -15,7 +15,7 @@ use crate::window::{View, Window};
 use crate::{BackgroundColor, DemoApp, UIVisibility};
 use image::ColorType;
 use pathfinder_color::{ColorF, ColorU};
-use pathfinder_gpu::{ClearOps, DepthFunc, DepthState, Device, Primitive, RenderOptions};
+use pathfinder_gpu::{AlphaMode, ClearOps, DepthFunc, DepthState, Device, Primitive, RenderOptions};
 use pathfinder_gpu::{RenderState, RenderTarget, TextureData, TextureFormat, UniformData};
 use pathfinder_geometry::rect::RectI;
 use pathfinder_geometry::transform3d::Transform4F;
@@ -70,7 +70,7 @@ impl<W> DemoApp<W> where W: Window {
                     let scene_texture = self
                         .renderer
                         .device()
-                        .create_texture(TextureFormat::RGBA8, viewport.size());
+                        .create_render_target_texture(TextureFormat::RGBA8, viewport.size());
                     self.scene_framebuffer =
                         Some(self.renderer.device().create_framebuffer(scene_texture));
                 }
@@ -235,6 +235,7 @@ impl<W> DemoApp<W> where W: Window {
             program: &self.ground_program.program,
             vertex_array: &self.ground_vertex_array.vertex_array,
             primitive: Primitive::Triangles,
+            first_index: 0,
             textures: &[],
             images: &[],
             storage_buffers: &[],
@@ -271,8 +272,9 @@ impl<W> DemoApp<W> where W: Window {
     pub fn take_raster_screenshot(&mut self, path: PathBuf) {
         let drawable_size = self.window_size.device_size();
         let viewport = RectI::new(Vector2I::default(), drawable_size);
-        let texture_data_receiver =
-            self.renderer.device().read_pixels(&RenderTarget::Default, viewport);
+        let texture_data_receiver = self.renderer.device().read_pixels(&RenderTarget::Default,
+                                                                       viewport,
+                                                                       AlphaMode::Straight);
         let pixels = match self.renderer.device().recv_texture_data(&texture_data_receiver) {
             TextureData::U8(pixels) => pixels,
             _ => panic!("Unexpected pixel format for default framebuffer!"),
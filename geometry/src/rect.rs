@@ -12,9 +12,10 @@
 
 use crate::vector::{IntoVector2F, Vector2F, Vector2I};
 use pathfinder_simd::default::{F32x4, I32x4};
+use std::fmt::{self, Debug, Display, Formatter};
 use std::ops::{Add, AddAssign, Mul, MulAssign, Sub, SubAssign};
 
-#[derive(Clone, Copy, Debug, PartialEq, Default)]
+#[derive(Clone, Copy, PartialEq, Default)]
 pub struct RectF(pub F32x4);
 
 impl RectF {
@@ -167,11 +168,24 @@ impl RectF {
         RectF(self.0.to_i32x4().to_f32x4())
     }
 
+    /// Expands this rectangle to the smallest enclosing rectangle with integer edges.
+    ///
+    /// Adjacent rectangles rounded out never leave a gap between them.
     #[inline]
     pub fn round_out(self) -> RectF {
         RectF::from_points(self.origin().floor(), self.lower_right().ceil())
     }
 
+    /// Shrinks this rectangle to the largest contained rectangle with integer edges.
+    ///
+    /// If no integer edge lies within the rectangle in some dimension, the result is zero-sized in
+    /// that dimension.
+    #[inline]
+    pub fn round_in(self) -> RectF {
+        let origin = self.origin().ceil();
+        RectF::from_points(origin, self.lower_right().floor().max(origin))
+    }
+
     #[inline]
     pub fn dilate<A>(self, amount: A) -> RectF where A: IntoVector2F {
         let amount = amount.into_vector_2f();
@@ -184,12 +198,50 @@ impl RectF {
         RectF::from_points(self.origin() + amount, self.lower_right() - amount)
     }
 
+    /// Shrinks this rectangle symmetrically by `amount` on each edge.
+    ///
+    /// Unlike `contract()`, this never produces an inverted rectangle: if the rectangle is smaller
+    /// than twice `amount`, the result is zero-sized and centered within the original.
+    #[inline]
+    pub fn inset(self, amount: Vector2F) -> RectF {
+        let origin = (self.origin() + amount).min(self.center());
+        let lower_right = (self.lower_right() - amount).max(origin);
+        RectF::from_points(origin, lower_right)
+    }
+
+    /// Grows this rectangle symmetrically by `amount` on each edge.
+    #[inline]
+    pub fn outset(self, amount: Vector2F) -> RectF {
+        RectF::from_points(self.origin() - amount, self.lower_right() + amount)
+    }
+
+    /// Converts this rectangle to integer coordinates, rounding each edge to the nearest integer.
+    ///
+    /// To snap with a different rounding mode, call `round_out()` or `round_in()` first.
     #[inline]
     pub fn to_i32(&self) -> RectI {
         RectI(self.0.to_i32x4())
     }
 }
 
+impl Debug for RectF {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("RectF")
+         .field("x", &self.min_x())
+         .field("y", &self.min_y())
+         .field("width", &self.width())
+         .field("height", &self.height())
+         .finish()
+    }
+}
+
+impl Display for RectF {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "({}, {}, {}, {})", self.min_x(), self.min_y(), self.width(), self.height())
+    }
+}
+
 impl Add<Vector2F> for RectF {
     type Output = RectF;
     #[inline]
@@ -281,7 +333,7 @@ impl SubAssign<f32> for RectF {
 }
 
 /// NB: The origin is inclusive, while the lower right point is exclusive.
-#[derive(Clone, Copy, Debug, PartialEq, Default)]
+#[derive(Clone, Copy, PartialEq, Default)]
 pub struct RectI(pub I32x4);
 
 impl RectI {
@@ -411,12 +463,48 @@ impl RectI {
         RectI::from_points(self.origin() + amount, self.lower_right() - amount)
     }
 
+    /// Shrinks this rectangle symmetrically by `amount` on each edge.
+    ///
+    /// Unlike `contract()`, this never produces an inverted rectangle: if the rectangle is smaller
+    /// than twice `amount`, the result is zero-sized and centered within the original.
+    #[inline]
+    pub fn inset(self, amount: Vector2I) -> RectI {
+        let center = self.origin() + Vector2I::new(self.width() / 2, self.height() / 2);
+        let origin = (self.origin() + amount).min(center);
+        let lower_right = (self.lower_right() - amount).max(origin);
+        RectI::from_points(origin, lower_right)
+    }
+
+    /// Grows this rectangle symmetrically by `amount` on each edge.
+    #[inline]
+    pub fn outset(self, amount: Vector2I) -> RectI {
+        RectI::from_points(self.origin() - amount, self.lower_right() + amount)
+    }
+
     #[inline]
     pub fn to_f32(&self) -> RectF {
         RectF(self.0.to_f32x4())
     }
 }
 
+impl Debug for RectI {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("RectI")
+         .field("x", &self.min_x())
+         .field("y", &self.min_y())
+         .field("width", &self.width())
+         .field("height", &self.height())
+         .finish()
+    }
+}
+
+impl Display for RectI {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "({}, {}, {}, {})", self.min_x(), self.min_y(), self.width(), self.height())
+    }
+}
+
 impl Mul<Vector2I> for RectI {
     type Output = RectI;
     #[inline]
@@ -432,3 +520,73 @@ impl Mul<i32> for RectI {
         RectI(self.0 * I32x4::splat(factor))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::rect::{RectF, RectI};
+    use crate::vector::{vec2f, vec2i};
+
+    #[test]
+    fn test_inset_outset() {
+        let rect = RectI::new(vec2i(10, 20), vec2i(30, 40));
+        assert_eq!(rect.inset(vec2i(5, 10)), RectI::new(vec2i(15, 30), vec2i(20, 20)));
+        assert_eq!(rect.outset(vec2i(5, 10)), RectI::new(vec2i(5, 10), vec2i(40, 60)));
+        assert_eq!(rect.inset(vec2i(5, 10)).outset(vec2i(5, 10)), rect);
+
+        let rect = RectF::new(vec2f(1.0, 2.0), vec2f(4.0, 6.0));
+        assert_eq!(rect.inset(vec2f(1.0, 1.0)), RectF::new(vec2f(2.0, 3.0), vec2f(2.0, 4.0)));
+        assert_eq!(rect.outset(vec2f(1.0, 1.0)), RectF::new(vec2f(0.0, 1.0), vec2f(6.0, 8.0)));
+    }
+
+    #[test]
+    fn test_inset_clamps_to_zero_size() {
+        let rect = RectI::new(vec2i(10, 20), vec2i(4, 30));
+        let inset = rect.inset(vec2i(5, 5));
+        assert_eq!(inset, RectI::new(vec2i(12, 25), vec2i(0, 20)));
+
+        let rect = RectF::new(vec2f(0.0, 0.0), vec2f(2.0, 2.0));
+        assert_eq!(rect.inset(vec2f(3.0, 3.0)), RectF::new(vec2f(1.0, 1.0), vec2f(0.0, 0.0)));
+    }
+
+    #[test]
+    fn test_rounding_modes() {
+        let rect = RectF::from_points(vec2f(1.25, 2.75), vec2f(5.75, 7.25));
+        assert_eq!(rect.round_out().to_i32(), RectI::from_points(vec2i(1, 2), vec2i(6, 8)));
+        assert_eq!(rect.round_in().to_i32(), RectI::from_points(vec2i(2, 3), vec2i(5, 7)));
+        assert_eq!(rect.round().to_i32(), RectI::from_points(vec2i(1, 3), vec2i(6, 7)));
+        assert_eq!(rect.to_i32(), rect.round().to_i32());
+    }
+
+    #[test]
+    fn test_rounding_integer_edges() {
+        let rect = RectF::from_points(vec2f(-3.0, 0.0), vec2f(4.0, 16.0));
+        let expected = RectI::from_points(vec2i(-3, 0), vec2i(4, 16));
+        assert_eq!(rect.round_out().to_i32(), expected);
+        assert_eq!(rect.round_in().to_i32(), expected);
+        assert_eq!(rect.round().to_i32(), expected);
+    }
+
+    #[test]
+    fn test_round_out_leaves_no_gaps() {
+        let left = RectF::from_points(vec2f(0.5, 0.5), vec2f(10.3, 4.5));
+        let right = RectF::from_points(vec2f(10.3, 0.5), vec2f(20.5, 4.5));
+        assert!(left.round_out().max_x() >= right.round_out().min_x());
+    }
+
+    #[test]
+    fn test_round_in_of_thin_rect_is_empty() {
+        let rect = RectF::from_points(vec2f(1.25, 1.0), vec2f(1.75, 3.0));
+        assert_eq!(rect.round_in(), RectF::from_points(vec2f(2.0, 1.0), vec2f(2.0, 3.0)));
+    }
+
+    #[test]
+    fn test_formatting() {
+        let rect = RectI::new(vec2i(10, 20), vec2i(30, 40));
+        assert_eq!(rect.to_string(), "(10, 20, 30, 40)");
+        assert_eq!(format!("{:?}", rect), "RectI { x: 10, y: 20, width: 30, height: 40 }");
+
+        let rect = RectF::new(vec2f(1.5, 2.0), vec2f(3.0, 4.25));
+        assert_eq!(rect.to_string(), "(1.5, 2, 3, 4.25)");
+        assert_eq!(format!("{:?}", rect), "RectF { x: 1.5, y: 2.0, width: 3.0, height: 4.25 }");
+    }
+}
@@ -314,6 +314,11 @@ impl Transform4F {
         (&self.c0) as *const F32x4 as *const f32
     }
 
+    /// Returns the columns of this matrix.
+    ///
+    /// This is the layout that GLSL `mat4` and Metal `float4x4` uniforms expect, so the result
+    /// can be uploaded directly as `UniformData::Mat4`. Note that the translation of an affine
+    /// transform ends up in the last column, not the last row.
     #[inline]
     pub fn to_columns(&self) -> [F32x4; 4] {
         [self.c0, self.c1, self.c2, self.c3]
@@ -425,8 +430,9 @@ impl Mul<RectF> for Perspective {
 
 #[cfg(test)]
 mod test {
-    use crate::vector::Vector4F;
+    use crate::vector::{Vector3F, Vector4F};
     use crate::transform3d::Transform4F;
+    use pathfinder_simd::default::F32x4;
 
     #[test]
     fn test_post_mul() {
@@ -501,4 +507,43 @@ mod test {
         let p2 = m_inv * p1;
         assert!(p0.approx_eq(p2, 0.0001));
     }
+
+    #[test]
+    fn test_to_columns() {
+        let m = Transform4F::from_translation(Vector4F::new(1.0, 2.0, 3.0, 1.0));
+        assert_eq!(m.to_columns(), [
+            F32x4::new(1.0, 0.0, 0.0, 0.0),
+            F32x4::new(0.0, 1.0, 0.0, 0.0),
+            F32x4::new(0.0, 0.0, 1.0, 0.0),
+            F32x4::new(1.0, 2.0, 3.0, 1.0),
+        ]);
+    }
+
+    #[test]
+    fn test_ortho() {
+        let m = Transform4F::from_ortho(0.0, 640.0, 480.0, 0.0, -1.0, 1.0);
+        let p0 = m * Vector4F::new(0.0, 0.0, 0.0, 1.0);
+        let p1 = m * Vector4F::new(640.0, 480.0, 0.0, 1.0);
+        assert!(p0.approx_eq(Vector4F::new(-1.0, 1.0, 0.0, 1.0), 0.0001));
+        assert!(p1.approx_eq(Vector4F::new(1.0, -1.0, 0.0, 1.0), 0.0001));
+    }
+
+    #[test]
+    fn test_perspective() {
+        let m = Transform4F::from_perspective(std::f32::consts::FRAC_PI_2, 1.0, 1.0, 100.0);
+        let near = (m * Vector4F::new(1.0, 1.0, -1.0, 1.0)).to_3d();
+        let far = (m * Vector4F::new(0.0, 0.0, -100.0, 1.0)).to_3d();
+        assert!(near.to_4d().approx_eq(Vector4F::new(1.0, 1.0, -1.0, 1.0), 0.0001));
+        assert!(far.to_4d().approx_eq(Vector4F::new(0.0, 0.0, 1.0, 1.0), 0.0001));
+    }
+
+    #[test]
+    fn test_looking_at() {
+        let eye = Vector3F::new(0.0, 0.0, 5.0);
+        let center = Vector3F::new(0.0, 0.0, 0.0);
+        let m = Transform4F::looking_at(eye, center, Vector3F::new(0.0, 1.0, 0.0));
+        assert!((m * eye.to_4d()).approx_eq(Vector4F::new(0.0, 0.0, 0.0, 1.0), 0.0001));
+        let target = m * Vector4F::new(0.0, 0.0, 0.0, 1.0);
+        assert!(target.approx_eq(Vector4F::new(0.0, 0.0, -5.0, 1.0), 0.0001));
+    }
 }
@@ -11,11 +11,12 @@
 //! A SIMD-optimized point type.
 
 use pathfinder_simd::default::{F32x2, F32x4, I32x2};
+use std::fmt::{self, Debug, Display, Formatter};
 use std::hash::{Hash, Hasher};
 use std::ops::{Add, AddAssign, Div, Mul, MulAssign, Neg, Sub, SubAssign};
 
 /// 2D points with 32-bit floating point coordinates.
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Default)]
 pub struct Vector2F(pub F32x2);
 
 impl Vector2F {
@@ -193,6 +194,19 @@ impl PartialEq for Vector2F {
     }
 }
 
+impl Debug for Vector2F {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("Vector2F").field("x", &self.x()).field("y", &self.y()).finish()
+    }
+}
+
+impl Display for Vector2F {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "({}, {})", self.x(), self.y())
+    }
+}
+
 impl Add<Vector2F> for Vector2F {
     type Output = Vector2F;
     #[inline]
@@ -318,7 +332,7 @@ impl IntoVector2F for f32 {
 }
 
 /// 2D points with 32-bit signed integer coordinates.
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Default)]
 pub struct Vector2I(pub I32x2);
 
 impl Vector2I {
@@ -367,6 +381,11 @@ impl Vector2I {
         Vector2I(self.0.max(other.0))
     }
 
+    #[inline]
+    pub fn clamp(self, min_val: Vector2I, max_val: Vector2I) -> Vector2I {
+        self.max(min_val).min(max_val)
+    }
+
     #[inline]
     pub fn area(self) -> i32 {
         self.x() * self.y()
@@ -384,6 +403,19 @@ pub fn vec2i(x: i32, y: i32) -> Vector2I {
     Vector2I::new(x, y)
 }
 
+impl Debug for Vector2I {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("Vector2I").field("x", &self.x()).field("y", &self.y()).finish()
+    }
+}
+
+impl Display for Vector2I {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "({}, {})", self.x(), self.y())
+    }
+}
+
 impl Add<Vector2I> for Vector2I {
     type Output = Vector2I;
     #[inline]
@@ -714,3 +746,35 @@ impl Default for Vector4F {
         Vector4F(point)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::vector::{vec2f, vec2i};
+
+    #[test]
+    fn test_vector2i_min_max() {
+        let (a, b) = (vec2i(-3, 7), vec2i(2, -5));
+        assert_eq!(a.min(b), vec2i(-3, -5));
+        assert_eq!(a.max(b), vec2i(2, 7));
+        assert_eq!(b.min(a), a.min(b));
+        assert_eq!(b.max(a), a.max(b));
+    }
+
+    #[test]
+    fn test_vector2i_clamp() {
+        let (lo, hi) = (vec2i(-10, 0), vec2i(10, 20));
+        assert_eq!(vec2i(-15, 25).clamp(lo, hi), vec2i(-10, 20));
+        assert_eq!(vec2i(15, -5).clamp(lo, hi), vec2i(10, 0));
+        assert_eq!(vec2i(-4, 6).clamp(lo, hi), vec2i(-4, 6));
+        assert_eq!(lo.clamp(lo, hi), lo);
+        assert_eq!(hi.clamp(lo, hi), hi);
+    }
+
+    #[test]
+    fn test_formatting() {
+        assert_eq!(vec2i(-3, 7).to_string(), "(-3, 7)");
+        assert_eq!(format!("{:?}", vec2i(-3, 7)), "Vector2I { x: -3, y: 7 }");
+        assert_eq!(vec2f(0.5, 2.0).to_string(), "(0.5, 2)");
+        assert_eq!(format!("{:?}", vec2f(0.5, 2.0)), "Vector2F { x: 0.5, y: 2.0 }");
+    }
+}
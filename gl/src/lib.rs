@@ -14,69 +14,440 @@
 extern crate log;
 
 use gl::types::{GLboolean, GLchar, GLenum, GLfloat, GLint, GLintptr, GLsizei, GLsizeiptr, GLsync};
-use gl::types::{GLuint, GLvoid};
+use gl::types::{GLbitfield, GLuint, GLvoid};
 use half::f16;
+use pathfinder_color::{ColorF, ColorU, color_slice_to_u8_slice};
 use pathfinder_geometry::rect::RectI;
-use pathfinder_geometry::vector::Vector2I;
+use pathfinder_geometry::vector::{Vector2I, vec2i};
+use pathfinder_gpu::AlphaMode;
 use pathfinder_gpu::{BlendFactor, BlendOp, BufferData, BufferTarget, BufferUploadMode, ClearOps};
-use pathfinder_gpu::{ComputeDimensions, ComputeState, DepthFunc, Device, FeatureLevel};
-use pathfinder_gpu::{ImageAccess, ImageBinding, Primitive, ProgramKind, RenderOptions};
+use pathfinder_gpu::{ComputeDimensions, ComputeState, CubeFace, DepthFunc, Device};
+use pathfinder_gpu::ExternalImageHandle;
+use pathfinder_gpu::{DeviceLimits, Feature, FeatureLevel, FrameStats};
+use pathfinder_gpu::{ImageAccess, ImageBinding, LoadAction, PipelineStats, PolygonMode};
+use pathfinder_gpu::{Primitive, ProgramKind, RenderOptions, Swizzle};
 use pathfinder_gpu::{RenderState, RenderTarget, ShaderKind, StencilFunc, TextureBinding};
-use pathfinder_gpu::{TextureData, TextureDataRef, TextureFormat, TextureSamplingFlags, UniformData};
+use pathfinder_gpu::{TextureData, TextureDataRef, TextureFormat, TextureSamplingFlags};
+use pathfinder_gpu::{TextureUsage, TextureUsageFlags, TimestampCalibration};
+use pathfinder_gpu::UNINITIALIZED_BUFFER_FILL_BYTE;
+use pathfinder_gpu::UniformData;
 use pathfinder_gpu::{VertexAttrClass, VertexAttrDescriptor, VertexAttrType};
 use pathfinder_resources::ResourceLoader;
 use pathfinder_simd::default::F32x4;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::ffi::{CStr, CString};
 use std::mem;
-use std::ops::Range;
-use std::os::raw::c_char;
+use std::ops::{Deref, DerefMut, Range};
+use std::os::raw::{c_char, c_void};
 use std::ptr;
 use std::rc::Rc;
+use std::slice;
 use std::str;
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 const DUMMY_TEXTURE_LENGTH: i32 = 16;
 
+const MAX_ANISOTROPY: GLfloat = 16.0;
+
+// The signature of `glEGLImageTargetTexture2DOES()`.
+type EGLImageTargetTexture2DFn = unsafe extern "system" fn(target: GLenum, image: *const c_void);
+// The signature of `glDrawMeshTasksNV()`.
+type DrawMeshTasksNVFn = unsafe extern "system" fn(first: GLuint, count: GLuint);
+
+// The texture parameters for swizzling the red, green, blue, and alpha channels, in order.
+static SWIZZLE_PARAMETERS: [GLenum; 4] = [
+    gl::TEXTURE_SWIZZLE_R,
+    gl::TEXTURE_SWIZZLE_G,
+    gl::TEXTURE_SWIZZLE_B,
+    gl::TEXTURE_SWIZZLE_A,
+];
+
+// Shader types from `GL_NV_mesh_shader`, which the `gl` crate doesn't bind.
+const MESH_SHADER_NV: GLenum = 0x9559;
+const TASK_SHADER_NV: GLenum = 0x955a;
+
+// Anisotropic filtering parameters, which are core in OpenGL 4.6 and have the same values in
+// `GL_EXT_texture_filter_anisotropic`.
+const TEXTURE_MAX_ANISOTROPY: GLenum = 0x84fe;
+const MAX_TEXTURE_MAX_ANISOTROPY: GLenum = 0x84ff;
+
+// The query target from `GL_ARB_pipeline_statistics_query`, which is core in OpenGL 4.6 and so
+// isn't bound by the `gl` crate.
+const CLIPPING_OUTPUT_PRIMITIVES: GLenum = 0x82f7;
+
+// How long `wait_for_fence()` blocks in `glClientWaitSync()` before checking again.
+const FENCE_WAIT_TIMEOUT_NS: u64 = 1_000_000_000;
+
 pub struct GLDevice {
     version: GLVersion,
+    capabilities: GLCapabilities,
     default_framebuffer: GLuint,
+    default_framebuffer_size: Vector2I,
     dummy_texture: GLTexture,
+    egl_image_target_texture_2d: Option<EGLImageTargetTexture2DFn>,
+    draw_mesh_tasks_nv: Option<DrawMeshTasksNVFn>,
+    // The anisotropy that `TextureSamplingFlags::ANISOTROPIC` selects, or `None` if anisotropic
+    // filtering is unsupported.
+    max_anisotropy: Option<GLfloat>,
+    // The framebuffer of the render pass begun by `begin_render_pass()`, if any.
+    render_pass_framebuffer: Cell<Option<GLuint>>,
+    // The timestamps being collected for `collect_frame_stats()`, if `reset_frame_stats()` has
+    // been called.
+    frame_stats: RefCell<Option<GLFrameStats>>,
+}
+
+struct GLFrameStats {
+    stats: FrameStats,
+    // How deeply `begin_commands()` calls are nested. Only the outermost scope is timed.
+    scope_depth: u32,
+    scope_start: Option<GLTimerQuery>,
+    // Start and end timestamps of scopes that the GPU may not have reached yet.
+    pending_scopes: Vec<(GLTimerQuery, GLTimerQuery)>,
 }
 
 impl GLDevice {
+    /// Creates a new device for the current OpenGL context.
+    ///
+    /// The actual version of the context is checked against `version`, and a warning is logged if
+    /// the context is too old to support it.
     #[inline]
     pub fn new(version: GLVersion, default_framebuffer: GLuint) -> GLDevice {
+        let capabilities = GLCapabilities::query();
+        if !capabilities.supports(version) {
+            warn!("{:?} was requested, but the OpenGL context only supports {} (GLSL {}); \
+                   shader compilation will likely fail!",
+                  version,
+                  capabilities.version_string,
+                  capabilities.shading_language_version_string);
+        }
+
+        // The initial viewport of a context is the size of the window it was first made current
+        // on, which is the best guess available until `set_default_framebuffer_size()` is called.
+        let mut viewport = [0; 4];
+        unsafe {
+            gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr()); ck();
+        }
+
+        let max_anisotropy = if capabilities.supports_anisotropic_filtering() {
+            let mut max_anisotropy = 1.0;
+            unsafe {
+                gl::GetFloatv(MAX_TEXTURE_MAX_ANISOTROPY, &mut max_anisotropy); ck();
+            }
+            Some(max_anisotropy.min(MAX_ANISOTROPY))
+        } else {
+            None
+        };
+
         let dummy_texture = GLTexture {
-            gl_texture: 0,
+            object: Rc::new(GLTextureObject {
+                gl_texture: 0,
+                renderbuffer: false,
+                level_count: Cell::new(1),
+            }),
+            gl_target: gl::TEXTURE_2D,
             size: Vector2I::zero(),
             format: TextureFormat::RGBA8,
         };
 
-        let mut device = GLDevice { version, default_framebuffer, dummy_texture };
-        let dummy_texture_data =
-            [0; DUMMY_TEXTURE_LENGTH as usize * DUMMY_TEXTURE_LENGTH as usize * 4];
-        device.dummy_texture =
-            device.create_texture_from_data(TextureFormat::RGBA8,
-                                            Vector2I::splat(DUMMY_TEXTURE_LENGTH),
-                                            TextureDataRef::U8(&dummy_texture_data));
+        let mut device = GLDevice {
+            version,
+            capabilities,
+            default_framebuffer,
+            default_framebuffer_size: vec2i(viewport[2], viewport[3]),
+            dummy_texture,
+            egl_image_target_texture_2d: None,
+            draw_mesh_tasks_nv: None,
+            max_anisotropy,
+            render_pass_framebuffer: Cell::new(None),
+            frame_stats: RefCell::new(None),
+        };
+        device.dummy_texture = device.create_texture_filled(TextureFormat::RGBA8,
+                                                            Vector2I::splat(DUMMY_TEXTURE_LENGTH),
+                                                            ColorU::transparent_black());
         device
     }
 
+    /// Creates a new device for the current OpenGL context, choosing the most capable
+    /// `GLVersion` that the context supports.
+    pub fn detect(default_framebuffer: GLuint) -> GLDevice {
+        let capabilities = GLCapabilities::query();
+        let version = if capabilities.es {
+            GLVersion::GLES3
+        } else if capabilities.supports(GLVersion::GL4) {
+            GLVersion::GL4
+        } else {
+            GLVersion::GL3
+        };
+        GLDevice::new(version, default_framebuffer)
+    }
+
+    #[inline]
+    pub fn version(&self) -> GLVersion {
+        self.version
+    }
+
+    #[inline]
+    pub fn capabilities(&self) -> &GLCapabilities {
+        &self.capabilities
+    }
+
     pub fn set_default_framebuffer(&mut self, framebuffer: GLuint) {
         self.default_framebuffer = framebuffer;
     }
 
+    /// Sets the size that `render_target_size()` reports for the default framebuffer.
+    ///
+    /// OpenGL has no way to query this, so call this whenever the window is resized.
+    #[inline]
+    pub fn set_default_framebuffer_size(&mut self, size: Vector2I) {
+        self.default_framebuffer_size = size;
+    }
+
+    /// Loads `glEGLImageTargetTexture2DOES()` from `GL_OES_EGL_image`, which the `gl` crate
+    /// doesn't bind, so that `import_external_texture()` can import EGL images.
+    ///
+    /// `loader` is the same function that was passed to `gl::load_with()`.
+    pub fn load_egl_image_extension<F>(&mut self, mut loader: F)
+                                       where F: FnMut(&str) -> *const c_void {
+        let function = loader("glEGLImageTargetTexture2DOES");
+        self.egl_image_target_texture_2d = if function.is_null() {
+            None
+        } else {
+            Some(unsafe { mem::transmute::<*const c_void, EGLImageTargetTexture2DFn>(function) })
+        };
+    }
+
+    /// Loads `glDrawMeshTasksNV()` from `GL_NV_mesh_shader`, which the `gl` crate doesn't bind.
+    /// `Feature::MeshShaders` is reported only after this has been called on a context that has
+    /// the extension.
+    ///
+    /// `loader` is the same function that was passed to `gl::load_with()`.
+    pub fn load_mesh_shader_extension<F>(&mut self, mut loader: F)
+                                         where F: FnMut(&str) -> *const c_void {
+        let function = loader("glDrawMeshTasksNV");
+        self.draw_mesh_tasks_nv = if function.is_null() {
+            None
+        } else {
+            Some(unsafe { mem::transmute::<*const c_void, DrawMeshTasksNVFn>(function) })
+        };
+    }
+
+    /// Maps the given byte range of a buffer into memory for reading and writing.
+    ///
+    /// The buffer is unmapped when the returned guard is dropped. The buffer must not already be
+    /// mapped, and it must not be used for drawing, uploads, or reads while the guard is alive.
+    #[inline]
+    pub fn map_buffer<'a>(&self, buffer: &'a GLBuffer, range: Range<usize>)
+                          -> GLBufferMapping<'a> {
+        self.map_buffer_with_access(buffer, range, gl::MAP_READ_BIT | gl::MAP_WRITE_BIT)
+    }
+
+    /// Like `map_buffer()`, but writes aren't guaranteed to be visible to the GPU until they're
+    /// flushed with `flush_mapped_range()`.
+    ///
+    /// This can be faster when only small parts of a large mapping are written.
+    #[inline]
+    pub fn map_buffer_non_coherent<'a>(&self, buffer: &'a GLBuffer, range: Range<usize>)
+                                       -> GLBufferMapping<'a> {
+        let access = gl::MAP_READ_BIT | gl::MAP_WRITE_BIT | gl::MAP_FLUSH_EXPLICIT_BIT;
+        self.map_buffer_with_access(buffer, range, access)
+    }
+
+    /// Maps the given byte range of a buffer into memory for writing only, to refill it.
+    ///
+    /// If `invalidate` is true, the old contents of the range are discarded, so the driver
+    /// doesn't have to wait for the GPU to finish reading them. Any bytes of the range that
+    /// aren't written are then undefined. The mapping must not be read from in either case. The
+    /// writes become visible to the GPU when the returned guard is dropped.
+    pub fn map_buffer_write<'a>(&self,
+                                buffer: &'a GLBuffer,
+                                range: Range<usize>,
+                                invalidate: bool)
+                                -> GLBufferMapping<'a> {
+        let mut access = gl::MAP_WRITE_BIT;
+        if invalidate {
+            // Invalidating the whole buffer lets the driver hand out fresh storage.
+            if range.start == 0 && range.end == buffer.object.size.get() {
+                access |= gl::MAP_INVALIDATE_BUFFER_BIT;
+            } else {
+                access |= gl::MAP_INVALIDATE_RANGE_BIT;
+            }
+        }
+        self.map_buffer_with_access(buffer, range, access)
+    }
+
+    fn map_buffer_with_access<'a>(&self,
+                                  buffer: &'a GLBuffer,
+                                  range: Range<usize>,
+                                  access: GLbitfield)
+                                  -> GLBufferMapping<'a> {
+        assert!(buffer.object.mapping.get().is_none(),
+                "Attempted to map a buffer that was already mapped!");
+        assert!(range.start <= range.end);
+
+        let explicit_flush = access & gl::MAP_FLUSH_EXPLICIT_BIT != 0;
+
+        unsafe {
+            gl::BindBuffer(gl::COPY_WRITE_BUFFER, buffer.object.gl_buffer); ck();
+            let ptr = gl::MapBufferRange(gl::COPY_WRITE_BUFFER,
+                                         range.start as GLintptr,
+                                         (range.end - range.start) as GLsizeiptr,
+                                         access); ck();
+            assert!(!ptr.is_null(), "Failed to map buffer!");
+            buffer.object.mapping.set(Some(GLMappedRange {
+                start: range.start,
+                end: range.end,
+                explicit_flush,
+            }));
+            GLBufferMapping { buffer, ptr: ptr as *mut u8, len: range.end - range.start }
+        }
+    }
+
+    #[inline]
+    pub fn is_buffer_mapped(&self, buffer: &GLBuffer) -> bool {
+        buffer.object.mapping.get().is_some()
+    }
+
+    /// Sets the color that shaders see when they sample a texture or image unit that nothing is
+    /// bound to. The default is transparent black.
+    pub fn set_default_texture_color(&self, color: ColorU) {
+        let pixel_count = DUMMY_TEXTURE_LENGTH as usize * DUMMY_TEXTURE_LENGTH as usize;
+        let dummy_texture_data = vec![color; pixel_count];
+        self.upload_to_texture(&self.dummy_texture,
+                               RectI::new(Vector2I::zero(), self.dummy_texture.size),
+                               TextureDataRef::U8(color_slice_to_u8_slice(&dummy_texture_data)));
+    }
+
+    /// Submits all pending commands and presents the frame.
+    ///
+    /// `swap_buffers` should call `swap_buffers()` or the equivalent method that your windowing
+    /// library offers. The returned fence is signaled when the frame's work has completed.
+    pub fn submit_and_present<F>(&self, swap_buffers: F) -> GLFence where F: FnOnce() {
+        let fence = self.add_fence();
+        unsafe {
+            gl::Flush(); ck();
+        }
+        swap_buffers();
+        fence
+    }
+
+    fn format_is_supported(&self, format: TextureFormat) -> bool {
+        match (self.version, format) {
+            (GLVersion::GLES3, TextureFormat::R16) |
+            (GLVersion::GLES3, TextureFormat::RGBA16) => {
+                return self.capabilities.has_extension("GL_EXT_texture_norm16");
+            }
+            (GLVersion::GLES3, TextureFormat::BGRA8) => {
+                return self.capabilities.has_extension("GL_EXT_texture_format_BGRA8888");
+            }
+            (GLVersion::GLES3, _) => return true,
+            (GLVersion::GL3, _) | (GLVersion::GL4, _) => {}
+        }
+
+        // Every format is core in desktop OpenGL 3.0, but drivers may still reject some. Asking
+        // needs OpenGL 4.3 or `GL_ARB_internalformat_query2`.
+        if self.capabilities.version < (4, 3) &&
+                !self.capabilities.has_extension("GL_ARB_internalformat_query2") {
+            return true;
+        }
+
+        let mut supported = 0;
+        unsafe {
+            gl::GetInternalformativ(gl::TEXTURE_2D,
+                                    format.gl_internal_format() as GLenum,
+                                    gl::INTERNALFORMAT_SUPPORTED,
+                                    1,
+                                    &mut supported); ck();
+        }
+        supported == gl::TRUE as GLint
+    }
+
+    fn format_is_renderable(&self, format: TextureFormat) -> bool {
+        match (self.version, format) {
+            // Floating-point color attachments are an extension on OpenGL ES 3.0.
+            (GLVersion::GLES3, TextureFormat::R16F) |
+            (GLVersion::GLES3, TextureFormat::RG16F) |
+            (GLVersion::GLES3, TextureFormat::RGBA16F) |
+            (GLVersion::GLES3, TextureFormat::RGBA32F) => {
+                self.capabilities.has_extension("GL_EXT_color_buffer_float")
+            }
+            // 16-bit normalized formats are an extension on OpenGL ES 3.0.
+            (GLVersion::GLES3, TextureFormat::R16) |
+            (GLVersion::GLES3, TextureFormat::RGBA16) => {
+                self.capabilities.has_extension("GL_EXT_texture_norm16")
+            }
+            _ => true,
+        }
+    }
+
+    // Empty buffers are backed by a single byte, for consistency with Metal, which rejects
+    // zero-length buffers. Their reported size is still zero.
+    fn reallocate_buffer(&self,
+                         buffer: &GLBuffer,
+                         target: GLenum,
+                         size: usize,
+                         data: *const GLvoid) {
+        // Don't read past the end of an empty slice.
+        let data = if size == 0 { ptr::null() } else { data };
+        unsafe {
+            gl::BindBuffer(target, buffer.object.gl_buffer); ck();
+            gl::BufferData(target,
+                           size.max(1) as GLsizeiptr,
+                           data,
+                           buffer.mode.to_gl_usage()); ck();
+        }
+        buffer.object.size.set(size);
+    }
+
+    // Attaches a name to an object so that it shows up in debuggers and GPU captures.
+    fn set_object_label(&self, identifier: GLenum, object: GLuint, name: &str) {
+        if !self.supports_debug_annotations() {
+            return;
+        }
+        unsafe {
+            gl::ObjectLabel(identifier,
+                            object,
+                            name.len() as GLsizei,
+                            name.as_ptr() as *const GLchar); ck();
+        }
+    }
+
+    // Object labels and debug groups require OpenGL 4.3 or `GL_KHR_debug`.
+    fn supports_debug_annotations(&self) -> bool {
+        self.version == GLVersion::GL4 || self.capabilities.has_extension("GL_KHR_debug")
+    }
+
     fn set_render_state(&self, render_state: &RenderState<GLDevice>) {
-        self.bind_render_target(render_state.target);
+        match self.render_pass_framebuffer.get() {
+            None => self.bind_render_target(render_state.target),
+            Some(framebuffer) => {
+                assert_eq!(framebuffer,
+                           self.render_target_framebuffer(render_state.target),
+                           "Draws inside a render pass must use its target!");
+                assert!(render_state.options.load_action == LoadAction::Load &&
+                        !render_state.options.clear_ops.has_ops(),
+                        "Draws inside a render pass can't clear or discard its target!");
+            }
+        }
 
         unsafe {
             let (origin, size) = (render_state.viewport.origin(), render_state.viewport.size());
             gl::Viewport(origin.x(), origin.y(), size.x(), size.y());
+
+            // Set the scissor before clearing, so that clears are scissored too.
+            if let Some(scissor) = render_state.options.scissor {
+                let (origin, size) = (scissor.origin(), scissor.size());
+                gl::Scissor(origin.x(), origin.y(), size.x(), size.y()); ck();
+                gl::Enable(gl::SCISSOR_TEST); ck();
+            }
         }
 
-        if render_state.options.clear_ops.has_ops() {
-            self.clear(&render_state.options.clear_ops);
+        let clear_ops = render_state.options.effective_clear_ops();
+        if render_state.options.load_action == LoadAction::DontCare {
+            self.invalidate_framebuffer(render_state.target, &clear_ops);
+        }
+        if clear_ops.has_ops() {
+            self.clear_framebuffer(&clear_ops);
         }
 
         self.use_program(render_state.program);
@@ -93,6 +464,29 @@ impl GLDevice {
         render_state.uniforms.iter().for_each(|(uniform, data)| self.set_uniform(uniform, data));
 
         self.set_render_options(&render_state.options);
+
+        match render_state.primitive {
+            Primitive::Points => self.set_point_size(render_state.options.point_size),
+            Primitive::Patches(vertex_count) => unsafe {
+                gl::PatchParameteri(gl::PATCH_VERTICES, vertex_count as GLint); ck();
+            },
+            Primitive::Triangles | Primitive::Lines => {}
+        }
+    }
+
+    fn set_point_size(&self, point_size: Option<f32>) {
+        unsafe {
+            match (self.version, point_size) {
+                (GLVersion::GL3, Some(point_size)) | (GLVersion::GL4, Some(point_size)) => {
+                    gl::PointSize(point_size); ck();
+                }
+                (GLVersion::GL3, None) | (GLVersion::GL4, None) => {
+                    gl::Enable(gl::PROGRAM_POINT_SIZE); ck();
+                }
+                // OpenGL ES always takes the point size from the shader.
+                (GLVersion::GLES3, _) => {}
+            }
+        }
     }
 
     fn set_compute_state(&self, compute_state: &ComputeState<GLDevice>) {
@@ -134,7 +528,7 @@ impl GLDevice {
             for (image_unit, uniform) in parameters.images.iter().enumerate() {
                 if (images_bound & (1 << image_unit as u64)) == 0 {
                     gl::BindImageTexture(image_unit as GLuint,
-                                         self.dummy_texture.gl_texture,
+                                         self.dummy_texture.object.gl_texture,
                                          0,
                                          gl::FALSE,
                                          0,
@@ -159,10 +553,33 @@ impl GLDevice {
                                           blend.src_alpha_factor.to_gl_blend_factor(),
                                           blend.dest_alpha_factor.to_gl_blend_factor()); ck();
                     gl::BlendEquation(blend.op.to_gl_blend_op()); ck();
+                    let blend_color = render_options.blend_color;
+                    gl::BlendColor(blend_color.r(),
+                                   blend_color.g(),
+                                   blend_color.b(),
+                                   blend_color.a()); ck();
                     gl::Enable(gl::BLEND); ck();
                 }
             }
 
+            // Set line smoothing. OpenGL ES doesn't support this.
+            if render_options.line_smooth {
+                match self.version {
+                    GLVersion::GL3 | GLVersion::GL4 => {
+                        if render_options.blend.is_none() {
+                            gl::BlendFuncSeparate(gl::SRC_ALPHA,
+                                                  gl::ONE_MINUS_SRC_ALPHA,
+                                                  gl::ONE,
+                                                  gl::ONE_MINUS_SRC_ALPHA); ck();
+                            gl::BlendEquation(gl::FUNC_ADD); ck();
+                            gl::Enable(gl::BLEND); ck();
+                        }
+                        gl::Enable(gl::LINE_SMOOTH); ck();
+                    }
+                    GLVersion::GLES3 => {}
+                }
+            }
+
             // Set depth.
             match render_options.depth {
                 None => {
@@ -175,6 +592,12 @@ impl GLDevice {
                 }
             }
 
+            // Set depth bias.
+            if let Some(ref bias) = render_options.depth_bias {
+                gl::PolygonOffset(bias.slope, bias.constant); ck();
+                gl::Enable(gl::POLYGON_OFFSET_FILL); ck();
+            }
+
             // Set stencil.
             match render_options.stencil {
                 None => {
@@ -198,6 +621,32 @@ impl GLDevice {
             // Set color mask.
             let color_mask = render_options.color_mask as GLboolean;
             gl::ColorMask(color_mask, color_mask, color_mask, color_mask); ck();
+
+            // Set primitive restart.
+            if let Some(restart_index) = render_options.primitive_restart {
+                match self.version {
+                    GLVersion::GL3 | GLVersion::GL4 => {
+                        gl::PrimitiveRestartIndex(restart_index); ck();
+                        gl::Enable(gl::PRIMITIVE_RESTART); ck();
+                    }
+                    GLVersion::GLES3 => {
+                        assert_eq!(restart_index,
+                                   !0,
+                                   "OpenGL ES only supports a primitive restart index of !0!");
+                        gl::Enable(gl::PRIMITIVE_RESTART_FIXED_INDEX); ck();
+                    }
+                }
+            }
+
+            // Set polygon mode. OpenGL ES doesn't support this.
+            if let PolygonMode::Line = render_options.polygon_mode {
+                match self.version {
+                    GLVersion::GL3 | GLVersion::GL4 => {
+                        gl::PolygonMode(gl::FRONT_AND_BACK, gl::LINE); ck();
+                    }
+                    GLVersion::GLES3 => {}
+                }
+            }
         }
     }
 
@@ -244,6 +693,8 @@ impl GLDevice {
         }
     }
 
+    // Binds all of `buffer`. This starts at offset 0, so unlike `bind_storage_buffer_range()` it
+    // can't violate the storage buffer offset alignment.
     fn set_storage_buffer(&self, storage_buffer: &GLStorageBuffer, buffer: &GLBuffer) {
         unsafe {
             gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER,
@@ -261,6 +712,17 @@ impl GLDevice {
     fn reset_render_state(&self, render_state: &RenderState<GLDevice>) {
         self.reset_render_options(&render_state.options);
 
+        if let Primitive::Points = render_state.primitive {
+            unsafe {
+                match self.version {
+                    GLVersion::GL3 | GLVersion::GL4 => {
+                        gl::Disable(gl::PROGRAM_POINT_SIZE); ck();
+                    }
+                    GLVersion::GLES3 => {}
+                }
+            }
+        }
+
         for &(storage_buffer, _) in render_state.storage_buffers {
             self.unset_storage_buffer(storage_buffer);
         }
@@ -301,20 +763,57 @@ impl GLDevice {
 
     fn reset_render_options(&self, render_options: &RenderOptions) {
         unsafe {
-            if render_options.blend.is_some() {
+            if render_options.blend.is_some() || render_options.line_smooth {
                 gl::Disable(gl::BLEND); ck();
             }
 
+            if render_options.line_smooth {
+                match self.version {
+                    GLVersion::GL3 | GLVersion::GL4 => {
+                        gl::Disable(gl::LINE_SMOOTH); ck();
+                    }
+                    GLVersion::GLES3 => {}
+                }
+            }
+
             if render_options.depth.is_some() {
                 gl::Disable(gl::DEPTH_TEST); ck();
             }
 
+            if render_options.depth_bias.is_some() {
+                gl::Disable(gl::POLYGON_OFFSET_FILL); ck();
+            }
+
+            if render_options.scissor.is_some() {
+                gl::Disable(gl::SCISSOR_TEST); ck();
+            }
+
             if render_options.stencil.is_some() {
                 gl::StencilMask(!0); ck();
                 gl::Disable(gl::STENCIL_TEST); ck();
             }
 
             gl::ColorMask(gl::TRUE, gl::TRUE, gl::TRUE, gl::TRUE); ck();
+
+            if render_options.primitive_restart.is_some() {
+                match self.version {
+                    GLVersion::GL3 | GLVersion::GL4 => {
+                        gl::Disable(gl::PRIMITIVE_RESTART); ck();
+                    }
+                    GLVersion::GLES3 => {
+                        gl::Disable(gl::PRIMITIVE_RESTART_FIXED_INDEX); ck();
+                    }
+                }
+            }
+
+            if let PolygonMode::Line = render_options.polygon_mode {
+                match self.version {
+                    GLVersion::GL3 | GLVersion::GL4 => {
+                        gl::PolygonMode(gl::FRONT_AND_BACK, gl::FILL); ck();
+                    }
+                    GLVersion::GLES3 => {}
+                }
+            }
         }
     }
 }
@@ -325,6 +824,8 @@ impl Device for GLDevice {
     type Fence = GLFence;
     type Framebuffer = GLFramebuffer;
     type ImageParameter = GLImageParameter;
+    type OcclusionQuery = GLOcclusionQuery;
+    type PipelineStatsQuery = GLPipelineStatsQuery;
     type Program = GLProgram;
     type Shader = GLShader;
     type StorageBuffer = GLStorageBuffer;
@@ -356,10 +857,159 @@ impl Device for GLDevice {
         }
     }
 
-    fn create_texture(&self, format: TextureFormat, size: Vector2I) -> GLTexture {
-        let mut texture = GLTexture { gl_texture: 0, size, format };
+    fn supports_feature(&self, feature: Feature) -> bool {
+        match (feature, self.version) {
+            // Tessellation shaders are core in OpenGL 4.0, but OpenGL ES 3.0 lacks them.
+            (Feature::TessellationShaders, GLVersion::GL4) => true,
+            (Feature::TessellationShaders, GLVersion::GL3) |
+            (Feature::TessellationShaders, GLVersion::GLES3) => false,
+            (Feature::MeshShaders, GLVersion::GL4) => {
+                self.draw_mesh_tasks_nv.is_some() &&
+                    self.capabilities.has_extension("GL_NV_mesh_shader")
+            }
+            (Feature::MeshShaders, GLVersion::GL3) |
+            (Feature::MeshShaders, GLVersion::GLES3) => false,
+            // Texture swizzle is core in OpenGL 3.3 and OpenGL ES 3.0.
+            (Feature::TextureSwizzle, _) => true,
+        }
+    }
+
+    fn format_is_filterable(&self, format: TextureFormat) -> bool {
+        match (self.version, format) {
+            // 32-bit float textures are filterable only with an extension on OpenGL ES 3.0.
+            (GLVersion::GLES3, TextureFormat::RGBA32F) => {
+                self.capabilities.has_extension("GL_OES_texture_float_linear")
+            }
+            (GLVersion::GLES3, TextureFormat::R16) |
+            (GLVersion::GLES3, TextureFormat::RGBA16) => {
+                self.capabilities.has_extension("GL_EXT_texture_norm16")
+            }
+            _ => true,
+        }
+    }
+
+    fn supports_texture_format(&self, format: TextureFormat, usage: TextureUsage) -> bool {
+        if !self.format_is_supported(format) {
+            return false;
+        }
+        match usage {
+            TextureUsage::Sampled => true,
+            TextureUsage::Filtered => self.format_is_filterable(format),
+            TextureUsage::RenderTarget => self.format_is_renderable(format),
+        }
+    }
+
+    fn supports_non_power_of_two_mipmaps(&self) -> bool {
+        // Non-power-of-two mipmaps are core in OpenGL 3.0 and OpenGL ES 3.0.
+        true
+    }
+
+    fn supported_sample_counts(&self, format: TextureFormat) -> Vec<u32> {
+        // Querying sample counts per format needs OpenGL 4.2, `GL_ARB_internalformat_query`, or
+        // OpenGL ES 3.1, which is also the first version of OpenGL ES with multisampled textures.
+        let can_query_format = match self.version {
+            GLVersion::GLES3 if self.capabilities.version < (3, 1) => return vec![1],
+            GLVersion::GLES3 => true,
+            GLVersion::GL3 | GLVersion::GL4 => {
+                self.capabilities.version >= (4, 2) ||
+                    self.capabilities.has_extension("GL_ARB_internalformat_query")
+            }
+        };
+
+        let mut sample_counts = vec![1];
+        unsafe {
+            if can_query_format {
+                let internal_format = format.gl_internal_format() as GLenum;
+                let mut sample_count_count = 0;
+                gl::GetInternalformativ(gl::TEXTURE_2D_MULTISAMPLE,
+                                        internal_format,
+                                        gl::NUM_SAMPLE_COUNTS,
+                                        1,
+                                        &mut sample_count_count); ck();
+                let mut format_sample_counts = vec![0; sample_count_count as usize];
+                gl::GetInternalformativ(gl::TEXTURE_2D_MULTISAMPLE,
+                                        internal_format,
+                                        gl::SAMPLES,
+                                        sample_count_count,
+                                        format_sample_counts.as_mut_ptr()); ck();
+                sample_counts.extend(format_sample_counts.into_iter().map(|count| count as u32));
+            } else {
+                // Assume that every power of two up to the maximum is supported.
+                let mut max_sample_count = 0;
+                gl::GetIntegerv(gl::MAX_COLOR_TEXTURE_SAMPLES, &mut max_sample_count); ck();
+                let mut sample_count = 2;
+                while sample_count <= max_sample_count as u32 {
+                    sample_counts.push(sample_count);
+                    sample_count *= 2;
+                }
+            }
+        }
+
+        sample_counts.sort();
+        sample_counts.dedup();
+        sample_counts
+    }
+
+    fn device_limits(&self) -> DeviceLimits {
+        let (mut max_texture_size, mut max_vertex_attributes) = (0, 0);
+        let mut max_compute_work_group_size = [0; 3];
+        let mut max_storage_buffer_size = 0;
+        unsafe {
+            gl::GetIntegerv(gl::MAX_TEXTURE_SIZE, &mut max_texture_size); ck();
+            gl::GetIntegerv(gl::MAX_VERTEX_ATTRIBS, &mut max_vertex_attributes); ck();
+
+            // Compute shaders and storage buffers need OpenGL 4.3.
+            if self.version == GLVersion::GL4 {
+                for (index, size) in max_compute_work_group_size.iter_mut().enumerate() {
+                    let mut value = 0;
+                    gl::GetIntegeri_v(gl::MAX_COMPUTE_WORK_GROUP_SIZE, index as GLuint, &mut value);
+                    ck();
+                    *size = value as u32;
+                }
+                gl::GetInteger64v(gl::MAX_SHADER_STORAGE_BLOCK_SIZE, &mut max_storage_buffer_size);
+                ck();
+            }
+        }
+
+        DeviceLimits {
+            max_texture_size: Vector2I::splat(max_texture_size),
+            max_compute_work_group_size,
+            max_storage_buffer_size: max_storage_buffer_size as u64,
+            max_vertex_attributes: max_vertex_attributes as u32,
+        }
+    }
+
+    fn storage_buffer_offset_alignment(&self) -> usize {
+        match self.version {
+            GLVersion::GL4 => {
+                let mut alignment = 0;
+                unsafe {
+                    gl::GetIntegerv(gl::SHADER_STORAGE_BUFFER_OFFSET_ALIGNMENT, &mut alignment);
+                    ck();
+                }
+                alignment as usize
+            }
+            // Storage buffers are unavailable here, so any offset is trivially aligned.
+            GLVersion::GL3 | GLVersion::GLES3 => 1,
+        }
+    }
+
+    fn create_texture_with_usage(&self,
+                                 format: TextureFormat,
+                                 size: Vector2I,
+                                 usage: TextureUsageFlags)
+                                 -> GLTexture {
+        // A texture that's only ever rendered to can be a renderbuffer, which the driver is free
+        // to lay out however suits rendering best.
+        if usage == TextureUsageFlags::RENDER_TARGET {
+            assert!(self.format_is_renderable(format),
+                    "{:?} textures can't be rendered to on this OpenGL context!",
+                    format);
+            return GLTexture::new_renderbuffer(size, format);
+        }
+
+        let texture = GLTexture::new(gl::TEXTURE_2D, size, format);
         unsafe {
-            gl::GenTextures(1, &mut texture.gl_texture); ck();
             self.bind_texture(&texture, 0);
             gl::TexImage2D(gl::TEXTURE_2D,
                            0,
@@ -376,12 +1026,68 @@ impl Device for GLDevice {
         texture
     }
 
+    unsafe fn import_external_texture(&self, handle: ExternalImageHandle) -> GLTexture {
+        let (image, format, size) = match handle {
+            ExternalImageHandle::EGLImage { image, format, size } => (image, format, size),
+            ExternalImageHandle::IOSurface(_) => {
+                panic!("IOSurfaces can't be imported into OpenGL; use the Metal backend!")
+            }
+        };
+        let egl_image_target_texture_2d =
+            self.egl_image_target_texture_2d
+                .expect("Call `GLDevice::load_egl_image_extension()` to import EGL images!");
+
+        let texture = GLTexture::new(gl::TEXTURE_2D, size, format);
+        self.bind_texture(&texture, 0);
+        egl_image_target_texture_2d(gl::TEXTURE_2D, image); ck();
+
+        self.set_texture_sampling_mode(&texture, TextureSamplingFlags::empty());
+        texture
+    }
+
+    fn create_render_target_texture(&self, format: TextureFormat, size: Vector2I) -> GLTexture {
+        assert!(self.format_is_renderable(format),
+                "{:?} textures can't be rendered to on this OpenGL context!",
+                format);
+        self.create_texture(format, size)
+    }
+
+    fn create_cube_texture(&self, format: TextureFormat, size: Vector2I) -> GLTexture {
+        assert_eq!(size.x(), size.y(), "Cube texture faces must be square!");
+        assert!(self.format_is_renderable(format),
+                "{:?} textures can't be rendered to on this OpenGL context!",
+                format);
+
+        let texture = GLTexture::new(gl::TEXTURE_CUBE_MAP, size, format);
+        unsafe {
+            // OpenGL ES always filters across cube faces, but desktop OpenGL only does when asked.
+            if self.version != GLVersion::GLES3 {
+                gl::Enable(gl::TEXTURE_CUBE_MAP_SEAMLESS); ck();
+            }
+
+            self.bind_texture(&texture, 0);
+            for face in CubeFace::ALL.iter() {
+                gl::TexImage2D(face.to_gl_target(),
+                               0,
+                               format.gl_internal_format(),
+                               size.x() as GLsizei,
+                               size.y() as GLsizei,
+                               0,
+                               format.gl_format(),
+                               format.gl_type(),
+                               ptr::null()); ck();
+            }
+        }
+
+        self.set_texture_sampling_mode(&texture, TextureSamplingFlags::empty());
+        texture
+    }
+
     fn create_texture_from_data(&self, format: TextureFormat, size: Vector2I, data: TextureDataRef)
                                 -> GLTexture {
         let data_ptr = data.check_and_extract_data_ptr(size, format);
-        let mut texture = GLTexture { gl_texture: 0, size, format: TextureFormat::R8 };
+        let texture = GLTexture::new(gl::TEXTURE_2D, size, format);
         unsafe {
-            gl::GenTextures(1, &mut texture.gl_texture); ck();
             self.bind_texture(&texture, 0);
             gl::TexImage2D(gl::TEXTURE_2D,
                            0,
@@ -391,7 +1097,7 @@ impl Device for GLDevice {
                            0,
                            format.gl_format(),
                            format.gl_type(),
-                           data_ptr)
+                           data_ptr); ck();
         }
 
         self.set_texture_sampling_mode(&texture, TextureSamplingFlags::empty());
@@ -410,6 +1116,10 @@ impl Device for GLDevice {
             ShaderKind::Vertex   => gl::VERTEX_SHADER,
             ShaderKind::Fragment => gl::FRAGMENT_SHADER,
             ShaderKind::Compute  => gl::COMPUTE_SHADER,
+            ShaderKind::TessControl => gl::TESS_CONTROL_SHADER,
+            ShaderKind::TessEvaluation => gl::TESS_EVALUATION_SHADER,
+            ShaderKind::Task => TASK_SHADER_NV,
+            ShaderKind::Mesh => MESH_SHADER_NV,
         };
 
         unsafe {
@@ -430,11 +1140,15 @@ impl Device for GLDevice {
                                      info_log.len() as GLint,
                                      ptr::null_mut(),
                                      info_log.as_mut_ptr() as *mut GLchar); ck();
-                error!("Shader info log:\n{}", String::from_utf8_lossy(&info_log));
+                error!("Info log for {:?} shader '{}':\n{}",
+                       kind,
+                       name,
+                       String::from_utf8_lossy(&info_log));
                 panic!("{:?} shader '{}' compilation failed", kind, name);
             }
 
-            GLShader { gl_shader }
+            self.set_object_label(gl::SHADER, gl_shader, name);
+            GLShader { gl_shader, name: name.to_owned() }
         }
     }
 
@@ -443,20 +1157,33 @@ impl Device for GLDevice {
                                    name: &str,
                                    shaders: ProgramKind<GLShader>)
                                    -> GLProgram {
+        let attached_shaders = match shaders {
+            ProgramKind::Raster { vertex: ref vertex_shader, fragment: ref fragment_shader } => {
+                vec![vertex_shader, fragment_shader]
+            }
+            ProgramKind::TessellatedRaster {
+                vertex: ref vertex_shader,
+                tess_control: ref tess_control_shader,
+                tess_evaluation: ref tess_evaluation_shader,
+                fragment: ref fragment_shader,
+            } => {
+                vec![vertex_shader, tess_control_shader, tess_evaluation_shader, fragment_shader]
+            }
+            ProgramKind::MeshRaster {
+                task: ref task_shader,
+                mesh: ref mesh_shader,
+                fragment: ref fragment_shader,
+            } => {
+                task_shader.iter().chain(vec![mesh_shader, fragment_shader]).collect()
+            }
+            ProgramKind::Compute(ref compute_shader) => vec![compute_shader],
+        };
+
         let gl_program;
         unsafe {
             gl_program = gl::CreateProgram(); ck();
-            match shaders {
-                ProgramKind::Raster {
-                    vertex: ref vertex_shader,
-                    fragment: ref fragment_shader,
-                } => {
-                    gl::AttachShader(gl_program, vertex_shader.gl_shader); ck();
-                    gl::AttachShader(gl_program, fragment_shader.gl_shader); ck();
-                }
-                ProgramKind::Compute(ref compute_shader) => {
-                    gl::AttachShader(gl_program, compute_shader.gl_shader); ck();
-                }
+            for shader in &attached_shaders {
+                gl::AttachShader(gl_program, shader.gl_shader); ck();
             }
             gl::LinkProgram(gl_program); ck();
 
@@ -470,11 +1197,18 @@ impl Device for GLDevice {
                                       info_log.len() as GLint,
                                       ptr::null_mut(),
                                       info_log.as_mut_ptr() as *mut GLchar); ck();
-                eprintln!("Program info log:\n{}", String::from_utf8_lossy(&info_log));
+                let shader_names: Vec<&str> =
+                    attached_shaders.iter().map(|shader| &*shader.name).collect();
+                error!("Info log for program '{}' (shaders '{}'):\n{}",
+                       name,
+                       shader_names.join("', '"),
+                       String::from_utf8_lossy(&info_log));
                 panic!("Program '{}' linking failed", name);
             }
         }
 
+        self.set_object_label(gl::PROGRAM, gl_program, name);
+
         let parameters = GLProgramParameters { textures: vec![], images: vec![] };
 
         GLProgram { gl_program, shaders, parameters: RefCell::new(parameters) }
@@ -488,7 +1222,11 @@ impl Device for GLDevice {
     #[inline]
     fn create_vertex_array(&self) -> GLVertexArray {
         unsafe {
-            let mut array = GLVertexArray { gl_vertex_array: 0 };
+            let mut array = GLVertexArray {
+                gl_vertex_array: 0,
+                array_buffer: Cell::new(0),
+                instance_attrs: RefCell::new(vec![]),
+            };
             gl::GenVertexArrays(1, &mut array.gl_vertex_array); ck();
             array
         }
@@ -546,6 +1284,28 @@ impl Device for GLDevice {
         GLStorageBuffer { location: binding as GLint }
     }
 
+    fn bind_storage_buffer_range(&self,
+                                 storage_buffer: &GLStorageBuffer,
+                                 buffer: &GLBuffer,
+                                 offset: usize,
+                                 size: usize) {
+        debug_assert_eq!(offset % self.storage_buffer_offset_alignment(),
+                         0,
+                         "Storage buffer offset {} is misaligned!",
+                         offset);
+        assert!(offset + size <= buffer.object.size.get(),
+                "Storage buffer range {:?} is out of bounds of a buffer of {} bytes!",
+                offset..(offset + size),
+                buffer.object.size.get());
+        unsafe {
+            gl::BindBufferRange(gl::SHADER_STORAGE_BUFFER,
+                                storage_buffer.location as GLuint,
+                                buffer.object.gl_buffer,
+                                offset as GLintptr,
+                                size as GLsizeiptr); ck();
+        }
+    }
+
     fn configure_vertex_attr(&self,
                              vertex_array: &GLVertexArray,
                              attr: &GLVertexAttr,
@@ -555,75 +1315,75 @@ impl Device for GLDevice {
         self.bind_vertex_array(vertex_array);
 
         unsafe {
-            let attr_type = descriptor.attr_type.to_gl_type();
-            match descriptor.class {
-                VertexAttrClass::Float | VertexAttrClass::FloatNorm => {
-                    let normalized = if descriptor.class == VertexAttrClass::FloatNorm {
-                        gl::TRUE
-                    } else {
-                        gl::FALSE
-                    };
-                    gl::VertexAttribPointer(attr.attr,
-                                            descriptor.size as GLint,
-                                            attr_type,
-                                            normalized,
-                                            descriptor.stride as GLint,
-                                            descriptor.offset as *const GLvoid); ck();
-                }
-                VertexAttrClass::Int => {
-                    gl::VertexAttribIPointer(attr.attr,
-                                             descriptor.size as GLint,
-                                             attr_type,
-                                             descriptor.stride as GLint,
-                                             descriptor.offset as *const GLvoid); ck();
-                }
-            }
-
+            set_vertex_attr_pointer(attr.attr, descriptor, descriptor.offset);
             gl::VertexAttribDivisor(attr.attr, descriptor.divisor); ck();
             gl::EnableVertexAttribArray(attr.attr); ck();
         }
 
+        // Remember per-instance attributes so that `draw_elements_instanced_base()` can offset
+        // them on OpenGL versions without base instances.
+        let mut instance_attrs = vertex_array.instance_attrs.borrow_mut();
+        instance_attrs.retain(|instance_attr| instance_attr.attr != attr.attr);
+        if descriptor.divisor > 0 {
+            instance_attrs.push(GLInstanceAttr {
+                attr: attr.attr,
+                descriptor: *descriptor,
+                gl_buffer: vertex_array.array_buffer.get(),
+            });
+        }
+        drop(instance_attrs);
+
         self.unbind_vertex_array();
     }
 
     fn create_framebuffer(&self, texture: GLTexture) -> GLFramebuffer {
-        let mut gl_framebuffer = 0;
-        unsafe {
-            gl::GenFramebuffers(1, &mut gl_framebuffer); ck();
-            gl::BindFramebuffer(gl::FRAMEBUFFER, gl_framebuffer); ck();
-            self.bind_texture(&texture, 0);
-            gl::FramebufferTexture2D(gl::FRAMEBUFFER,
-                                     gl::COLOR_ATTACHMENT0,
-                                     gl::TEXTURE_2D,
-                                     texture.gl_texture,
-                                     0); ck();
-            assert_eq!(gl::CheckFramebufferStatus(gl::FRAMEBUFFER), gl::FRAMEBUFFER_COMPLETE);
-        }
+        assert_ne!(texture.gl_target,
+                   gl::TEXTURE_CUBE_MAP,
+                   "Use `create_framebuffer_for_cube_face()` for cube textures!");
+        let gl_target = texture.gl_target;
+        self.create_framebuffer_for_target(texture, gl_target)
+    }
 
-        GLFramebuffer { gl_framebuffer, texture }
+    fn create_framebuffer_for_cube_face(&self, texture: &GLTexture, face: CubeFace)
+                                        -> GLFramebuffer {
+        assert_eq!(texture.gl_target, gl::TEXTURE_CUBE_MAP, "The texture isn't a cube texture!");
+        self.create_framebuffer_for_target(texture.clone(), face.to_gl_target())
     }
 
     fn create_buffer(&self, mode: BufferUploadMode) -> GLBuffer {
         unsafe {
             let mut gl_buffer = 0;
             gl::GenBuffers(1, &mut gl_buffer); ck();
-            let object = Rc::new(GLBufferObject { gl_buffer });
+            let object = Rc::new(GLBufferObject {
+                gl_buffer,
+                size: Cell::new(0),
+                mapping: Cell::new(None),
+            });
             GLBuffer { object, mode }
         }
     }
 
     fn allocate_buffer<T>(&self, buffer: &GLBuffer, data: BufferData<T>, target: BufferTarget) {
         let target = target.to_gl_target();
+        let debug_fill;
         let (ptr, len) = match data {
+            BufferData::Uninitialized(len) if cfg!(debug_assertions) => {
+                debug_fill = vec![UNINITIALIZED_BUFFER_FILL_BYTE; len * mem::size_of::<T>()];
+                (debug_fill.as_ptr() as *const GLvoid, len)
+            }
             BufferData::Uninitialized(len) => (ptr::null(), len),
             BufferData::Memory(buffer) => (buffer.as_ptr() as *const GLvoid, buffer.len()),
         };
-        let len = (len * mem::size_of::<T>()) as GLsizeiptr;
-        let usage = buffer.mode.to_gl_usage();
-        unsafe {
-            gl::BindBuffer(target, buffer.object.gl_buffer); ck();
-            gl::BufferData(target, len, ptr, usage); ck();
-        }
+        let len = len * mem::size_of::<T>();
+        self.reallocate_buffer(buffer, target, len, ptr);
+    }
+
+    fn orphan_buffer(&self, buffer: &GLBuffer) {
+        debug_assert!(buffer.object.mapping.get().is_none(),
+                      "Attempted to orphan a mapped buffer!");
+        // Bind to the copy target so as not to disturb the bound vertex array.
+        let size = buffer.object.size.get();
+        self.reallocate_buffer(buffer, gl::COPY_WRITE_BUFFER, size, ptr::null());
     }
 
     fn upload_to_buffer<T>(&self,
@@ -631,8 +1391,26 @@ impl Device for GLDevice {
                            position: usize,
                            data: &[T],
                            target: BufferTarget) {
+        debug_assert!(buffer.object.mapping.get().is_none(),
+                      "Attempted to upload to a mapped buffer!");
+        if data.is_empty() {
+            return;
+        }
+
         let target = target.to_gl_target();
         let len = (data.len() * mem::size_of::<T>()) as GLsizeiptr;
+        if buffer.object.size.get() == 0 {
+            // Empty buffers grow to fit their first upload.
+            let size = position * mem::size_of::<T>() + len as usize;
+            self.reallocate_buffer(buffer, target, size, ptr::null());
+        } else if let BufferUploadMode::Dynamic = buffer.mode {
+            // Orphan dynamic buffers that are overwritten entirely, so that the driver doesn't
+            // stall until draws reading the old contents finish.
+            if position == 0 && len as usize == buffer.object.size.get() {
+                self.reallocate_buffer(buffer, target, len as usize, ptr::null());
+            }
+        }
+
         unsafe {
             gl::BindBuffer(target, buffer.object.gl_buffer); ck();
             gl::BufferSubData(target,
@@ -642,23 +1420,36 @@ impl Device for GLDevice {
         }
     }
 
+    fn flush_mapped_range(&self, buffer: &GLBuffer, range: Range<usize>) {
+        let mapping = buffer.object.mapping.get().expect("Attempted to flush an unmapped buffer!");
+        assert!(range.start <= range.end &&
+                range.start >= mapping.start &&
+                range.end <= mapping.end,
+                "Flush range {:?} is outside the mapped range {:?}!",
+                range,
+                mapping.start..mapping.end);
+        if !mapping.explicit_flush {
+            return;
+        }
+
+        // The offset is relative to the start of the mapping.
+        unsafe {
+            gl::BindBuffer(gl::COPY_WRITE_BUFFER, buffer.object.gl_buffer); ck();
+            gl::FlushMappedBufferRange(gl::COPY_WRITE_BUFFER,
+                                       (range.start - mapping.start) as GLintptr,
+                                       (range.end - range.start) as GLsizeiptr); ck();
+        }
+    }
+
     #[inline]
     fn framebuffer_texture<'f>(&self, framebuffer: &'f Self::Framebuffer) -> &'f Self::Texture {
         &framebuffer.texture
     }
 
     #[inline]
-    fn destroy_framebuffer(&self, mut framebuffer: Self::Framebuffer) -> Self::Texture {
-        let texture = GLTexture {
-            gl_texture: framebuffer.texture.gl_texture,
-            size: framebuffer.texture.size,
-            format: framebuffer.texture.format,
-        };
-        unsafe {
-            gl::DeleteFramebuffers(1, &mut framebuffer.gl_framebuffer); ck();
-        }
-        mem::forget(framebuffer);
-        texture
+    fn destroy_framebuffer(&self, framebuffer: Self::Framebuffer) -> Self::Texture {
+        // The texture is reference counted, so it outlives the framebuffer.
+        framebuffer.texture.clone()
     }
 
     #[inline]
@@ -671,47 +1462,97 @@ impl Device for GLDevice {
         texture.size
     }
 
+    fn texture_level_count(&self, texture: &Self::Texture) -> u32 {
+        texture.object.level_count.get()
+    }
+
+    #[inline]
+    fn render_target_size(&self, target: &RenderTarget<GLDevice>) -> Vector2I {
+        match *target {
+            RenderTarget::Default => self.default_framebuffer_size,
+            RenderTarget::Framebuffer(framebuffer) => framebuffer.texture.size,
+        }
+    }
+
     fn set_texture_sampling_mode(&self, texture: &Self::Texture, flags: TextureSamplingFlags) {
         self.bind_texture(texture, 0);
         unsafe {
-            gl::TexParameteri(gl::TEXTURE_2D,
-                              gl::TEXTURE_MIN_FILTER,
-                              if flags.contains(TextureSamplingFlags::NEAREST_MIN) {
-                                  gl::NEAREST as GLint
-                              } else {
-                                  gl::LINEAR as GLint
-                              }); ck();
-            gl::TexParameteri(gl::TEXTURE_2D,
+            let min_filter = match (flags.contains(TextureSamplingFlags::NEAREST_MIN),
+                                    flags.contains(TextureSamplingFlags::MIPMAP)) {
+                (false, false) => gl::LINEAR,
+                (true, false) => gl::NEAREST,
+                (false, true) => gl::LINEAR_MIPMAP_LINEAR,
+                (true, true) => gl::NEAREST_MIPMAP_NEAREST,
+            };
+            gl::TexParameteri(texture.gl_target, gl::TEXTURE_MIN_FILTER, min_filter as GLint); ck();
+            gl::TexParameteri(texture.gl_target,
                               gl::TEXTURE_MAG_FILTER,
                               if flags.contains(TextureSamplingFlags::NEAREST_MAG) {
                                   gl::NEAREST as GLint
                               } else {
                                   gl::LINEAR as GLint
                               }); ck();
-            gl::TexParameteri(gl::TEXTURE_2D,
-                              gl::TEXTURE_WRAP_S,
-                              if flags.contains(TextureSamplingFlags::REPEAT_U) {
-                                  gl::REPEAT as GLint
-                              } else {
-                                  gl::CLAMP_TO_EDGE as GLint
-                              }); ck();
-            gl::TexParameteri(gl::TEXTURE_2D,
-                              gl::TEXTURE_WRAP_T,
-                              if flags.contains(TextureSamplingFlags::REPEAT_V) {
-                                  gl::REPEAT as GLint
-                              } else {
-                                  gl::CLAMP_TO_EDGE as GLint
-                              }); ck();
+            let wrap_s = self.wrap_mode(flags.contains(TextureSamplingFlags::REPEAT_U),
+                                        flags.contains(TextureSamplingFlags::CLAMP_TO_BORDER_U));
+            let wrap_t = self.wrap_mode(flags.contains(TextureSamplingFlags::REPEAT_V),
+                                        flags.contains(TextureSamplingFlags::CLAMP_TO_BORDER_V));
+            gl::TexParameteri(texture.gl_target, gl::TEXTURE_WRAP_S, wrap_s as GLint); ck();
+            gl::TexParameteri(texture.gl_target, gl::TEXTURE_WRAP_T, wrap_t as GLint); ck();
+            if let Some(max_anisotropy) = self.max_anisotropy {
+                let anisotropy = if flags.contains(TextureSamplingFlags::ANISOTROPIC) {
+                    max_anisotropy
+                } else {
+                    1.0
+                };
+                gl::TexParameterf(texture.gl_target, TEXTURE_MAX_ANISOTROPY, anisotropy); ck();
+            }
+        }
+    }
+
+    fn set_texture_border_color(&self, texture: &Self::Texture, color: ColorF) {
+        if !self.capabilities.supports_border_clamp() {
+            return;
+        }
+        self.bind_texture(texture, 0);
+        unsafe {
+            let color = [color.r(), color.g(), color.b(), color.a()];
+            gl::TexParameterfv(texture.gl_target, gl::TEXTURE_BORDER_COLOR, color.as_ptr()); ck();
+        }
+    }
+
+    fn set_texture_swizzle(&self, texture: &Self::Texture, swizzle: [Swizzle; 4]) {
+        self.bind_texture(texture, 0);
+        unsafe {
+            for (&pname, &channel) in SWIZZLE_PARAMETERS.iter().zip(swizzle.iter()) {
+                gl::TexParameteri(texture.gl_target, pname, channel.to_gl_swizzle() as GLint); ck();
+            }
+        }
+    }
+
+    fn texture_swizzle(&self, texture: &Self::Texture) -> [Swizzle; 4] {
+        self.bind_texture(texture, 0);
+        let mut swizzle = Swizzle::IDENTITY;
+        unsafe {
+            for (&pname, channel) in SWIZZLE_PARAMETERS.iter().zip(swizzle.iter_mut()) {
+                let mut value = 0;
+                gl::GetTexParameteriv(texture.gl_target, pname, &mut value); ck();
+                *channel = Swizzle::from_gl_swizzle(value as GLenum);
+            }
         }
+        swizzle
     }
 
     fn upload_to_texture(&self, texture: &Self::Texture, rect: RectI, data: TextureDataRef) {
-        let data_ptr = data.check_and_extract_data_ptr(rect.size(), texture.format);
+        assert_eq!(texture.gl_target,
+                   gl::TEXTURE_2D,
+                   "Only 2D textures created with `SAMPLED` can be uploaded to!");
+        assert!(rect.min_x() >= 0 && rect.min_y() >= 0 &&
+                rect.max_x() <= texture.size.x() && rect.max_y() <= texture.size.y(),
+                "Upload rect {:?} is out of bounds of a texture of size {:?}!",
+                rect,
+                texture.size);
 
-        assert!(rect.size().x() >= 0);
-        assert!(rect.size().y() >= 0);
-        assert!(rect.max_x() <= texture.size.x());
-        assert!(rect.max_y() <= texture.size.y());
+        let data_ptr = data.check_and_extract_data_ptr(rect.size(), texture.format);
 
         unsafe {
             self.bind_texture(texture, 0);
@@ -741,7 +1582,72 @@ impl Device for GLDevice {
         self.set_texture_sampling_mode(texture, TextureSamplingFlags::empty());
     }
 
-    fn read_pixels(&self, render_target: &RenderTarget<GLDevice>, viewport: RectI)
+    // `glGenerateMipmap()` allocates any missing levels itself.
+    fn generate_mipmaps(&self, texture: &GLTexture) {
+        assert!(self.supports_texture_format(texture.format, TextureUsage::Filtered) &&
+                self.supports_texture_format(texture.format, TextureUsage::RenderTarget),
+                "Mipmaps can't be generated for {:?}, which must be filterable and renderable!",
+                texture.format);
+        self.bind_texture(texture, 0);
+        unsafe {
+            gl::GenerateMipmap(texture.gl_target); ck();
+        }
+        texture.object.level_count.set(mip_level_count(texture.size));
+    }
+
+    fn copy_texture_to_buffer(&self,
+                              texture: &GLTexture,
+                              rect: RectI,
+                              buffer: &GLBuffer,
+                              buffer_offset: usize,
+                              bytes_per_row: usize) {
+        assert!(rect.min_x() >= 0 && rect.min_y() >= 0 &&
+                rect.max_x() <= texture.size.x() && rect.max_y() <= texture.size.y(),
+                "Copy rect {:?} is out of bounds of a texture of size {:?}!",
+                rect,
+                texture.size);
+
+        let bytes_per_pixel = texture.format.bytes_per_pixel();
+        assert!(bytes_per_row >= rect.width() as usize * bytes_per_pixel,
+                "Row pitch {} is too small for {} pixels of {:?}!",
+                bytes_per_row,
+                rect.width(),
+                texture.format);
+        assert_eq!(bytes_per_row % bytes_per_pixel,
+                   0,
+                   "OpenGL requires the row pitch to be a multiple of the pixel size!");
+
+        unsafe {
+            // `glGetTexImage()` can't read a subrectangle and isn't available on OpenGL ES, so
+            // attach the texture to a temporary framebuffer and read from that instead.
+            let mut gl_framebuffer = 0;
+            gl::GenFramebuffers(1, &mut gl_framebuffer); ck();
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, gl_framebuffer); ck();
+            attach_color_target(gl::READ_FRAMEBUFFER, texture, gl::TEXTURE_2D);
+
+            gl::BindBuffer(gl::PIXEL_PACK_BUFFER, buffer.object.gl_buffer); ck();
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1); ck();
+            gl::PixelStorei(gl::PACK_ROW_LENGTH, (bytes_per_row / bytes_per_pixel) as GLint); ck();
+            gl::ReadPixels(rect.origin_x(),
+                           rect.origin_y(),
+                           rect.width() as GLsizei,
+                           rect.height() as GLsizei,
+                           texture.format.gl_format(),
+                           texture.format.gl_type(),
+                           buffer_offset as *mut GLvoid); ck();
+            gl::PixelStorei(gl::PACK_ROW_LENGTH, 0); ck();
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 4); ck();
+            gl::BindBuffer(gl::PIXEL_PACK_BUFFER, 0); ck();
+
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, 0); ck();
+            gl::DeleteFramebuffers(1, &mut gl_framebuffer); ck();
+        }
+    }
+
+    fn read_pixels(&self,
+                   render_target: &RenderTarget<GLDevice>,
+                   viewport: RectI,
+                   alpha_mode: AlphaMode)
                    -> GLTextureDataReceiver {
         let (origin, size) = (viewport.origin(), viewport.size());
         let format = self.render_target_format(render_target);
@@ -767,27 +1673,134 @@ impl Device for GLDevice {
 
             let gl_sync = gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0);
 
-            GLTextureDataReceiver { gl_pixel_buffer, gl_sync, size, format }
+            GLTextureDataReceiver { gl_pixel_buffer, gl_sync, size, format, alpha_mode }
         }
     }
 
-    fn read_buffer(&self, buffer: &GLBuffer, target: BufferTarget, range: Range<usize>)
+    fn read_buffer(&self, buffer: &GLBuffer, _: BufferTarget, range: Range<usize>)
                    -> GLBufferDataReceiver {
+        let byte_size = range.end - range.start;
         unsafe {
+            // Make writes from compute shaders visible to the copy.
+            if self.version == GLVersion::GL4 {
+                gl::MemoryBarrier(gl::BUFFER_UPDATE_BARRIER_BIT); ck();
+            }
+
+            let mut gl_copy_buffer = 0;
+            gl::GenBuffers(1, &mut gl_copy_buffer); ck();
+            gl::BindBuffer(gl::COPY_WRITE_BUFFER, gl_copy_buffer); ck();
+            gl::BufferData(gl::COPY_WRITE_BUFFER,
+                           byte_size as GLsizeiptr,
+                           ptr::null(),
+                           gl::STREAM_READ); ck();
+            gl::BindBuffer(gl::COPY_READ_BUFFER, buffer.object.gl_buffer); ck();
+            gl::CopyBufferSubData(gl::COPY_READ_BUFFER,
+                                  gl::COPY_WRITE_BUFFER,
+                                  range.start as GLintptr,
+                                  0,
+                                  byte_size as GLsizeiptr); ck();
+
             let gl_sync = gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0);
-            GLBufferDataReceiver { object: buffer.object.clone(), gl_sync, range, target }
+
+            GLBufferDataReceiver { gl_copy_buffer, gl_sync, byte_size }
         }
     }
 
     fn begin_commands(&self) {
         // TODO(pcwalton): Add some checks in debug mode to make sure render commands are bracketed
         // by these?
+        if let Some(ref mut frame_stats) = *self.frame_stats.borrow_mut() {
+            if frame_stats.scope_depth == 0 {
+                frame_stats.scope_start = Some(self.create_timestamp_query());
+            }
+            frame_stats.scope_depth += 1;
+        }
     }
 
     fn end_commands(&self) {
+        if let Some(ref mut frame_stats) = *self.frame_stats.borrow_mut() {
+            frame_stats.scope_depth -= 1;
+            if frame_stats.scope_depth == 0 {
+                let scope_start = frame_stats.scope_start.take().unwrap();
+                let scope_end = self.create_timestamp_query();
+                frame_stats.pending_scopes.push((scope_start, scope_end));
+            }
+        }
+        unsafe { gl::Flush(); }
+    }
+
+    #[inline]
+    fn flush(&self) {
         unsafe { gl::Flush(); }
     }
 
+    fn push_debug_group(&self, name: &str) {
+        if !cfg!(debug_assertions) || !self.supports_debug_annotations() {
+            return;
+        }
+        unsafe {
+            gl::PushDebugGroup(gl::DEBUG_SOURCE_APPLICATION,
+                               0,
+                               name.len() as GLsizei,
+                               name.as_ptr() as *const GLchar); ck();
+        }
+    }
+
+    fn pop_debug_group(&self) {
+        if !cfg!(debug_assertions) || !self.supports_debug_annotations() {
+            return;
+        }
+        unsafe {
+            gl::PopDebugGroup(); ck();
+        }
+    }
+
+    fn set_texture_label(&self, texture: &GLTexture, label: &str) {
+        if cfg!(debug_assertions) {
+            let identifier = if texture.object.renderbuffer {
+                gl::RENDERBUFFER
+            } else {
+                gl::TEXTURE
+            };
+            self.set_object_label(identifier, texture.object.gl_texture, label);
+        }
+    }
+
+    fn set_buffer_label(&self, buffer: &GLBuffer, label: &str) {
+        if !cfg!(debug_assertions) {
+            return;
+        }
+        // Buffer names don't refer to objects until they're first bound, and only objects can be
+        // labeled.
+        unsafe {
+            gl::BindBuffer(gl::COPY_READ_BUFFER, buffer.object.gl_buffer); ck();
+        }
+        self.set_object_label(gl::BUFFER, buffer.object.gl_buffer, label);
+    }
+
+    fn clear(&self, target: &RenderTarget<GLDevice>, ops: &ClearOps) {
+        self.bind_render_target(target);
+        self.clear_framebuffer(ops);
+    }
+
+    fn begin_render_pass(&self,
+                         target: &RenderTarget<GLDevice>,
+                         clear_ops: &ClearOps,
+                         viewport: RectI) {
+        assert!(self.render_pass_framebuffer.get().is_none(), "Render passes can't be nested!");
+        self.bind_render_target(target);
+        unsafe {
+            let (origin, size) = (viewport.origin(), viewport.size());
+            gl::Viewport(origin.x(), origin.y(), size.x(), size.y()); ck();
+        }
+        self.clear_framebuffer(clear_ops);
+        self.render_pass_framebuffer.set(Some(self.render_target_framebuffer(target)));
+    }
+
+    fn end_render_pass(&self) {
+        assert!(self.render_pass_framebuffer.take().is_some(), "No render pass is active!");
+    }
+
     fn draw_arrays(&self, index_count: u32, render_state: &RenderState<Self>) {
         self.set_render_state(render_state);
         unsafe {
@@ -798,43 +1811,236 @@ impl Device for GLDevice {
         self.reset_render_state(render_state);
     }
 
-    fn draw_elements(&self, index_count: u32, render_state: &RenderState<Self>) {
-        self.set_render_state(render_state);
-        unsafe {
-            gl::DrawElements(render_state.primitive.to_gl_primitive(),
-                             index_count as GLsizei,
-                             gl::UNSIGNED_INT,
-                             ptr::null()); ck();
+    fn draw_elements(&self, index_count: u32, render_state: &RenderState<Self>) {
+        self.set_render_state(render_state);
+        self.debug_check_index_range(index_count, render_state);
+        unsafe {
+            gl::DrawElements(render_state.primitive.to_gl_primitive(),
+                             index_count as GLsizei,
+                             gl::UNSIGNED_INT,
+                             index_buffer_offset(render_state)); ck();
+        }
+        self.reset_render_state(render_state);
+    }
+
+    fn draw_elements_instanced(&self,
+                               index_count: u32,
+                               instance_count: u32,
+                               render_state: &RenderState<Self>) {
+        self.set_render_state(render_state);
+        self.debug_check_index_range(index_count, render_state);
+        unsafe {
+            gl::DrawElementsInstanced(render_state.primitive.to_gl_primitive(),
+                                      index_count as GLsizei,
+                                      gl::UNSIGNED_INT,
+                                      index_buffer_offset(render_state),
+                                      instance_count as GLsizei); ck();
+        }
+        self.reset_render_state(render_state);
+    }
+
+    fn draw_elements_instanced_base(&self,
+                                    index_count: u32,
+                                    instance_count: u32,
+                                    base_instance: u32,
+                                    render_state: &RenderState<Self>) {
+        self.set_render_state(render_state);
+        self.debug_check_index_range(index_count, render_state);
+        unsafe {
+            if self.feature_level() == FeatureLevel::D3D11 {
+                gl::DrawElementsInstancedBaseInstance(render_state.primitive.to_gl_primitive(),
+                                                      index_count as GLsizei,
+                                                      gl::UNSIGNED_INT,
+                                                      index_buffer_offset(render_state),
+                                                      instance_count as GLsizei,
+                                                      base_instance); ck();
+            } else {
+                let instance_attrs = render_state.vertex_array.instance_attrs.borrow();
+                offset_instance_attrs(&instance_attrs, base_instance);
+                gl::DrawElementsInstanced(render_state.primitive.to_gl_primitive(),
+                                          index_count as GLsizei,
+                                          gl::UNSIGNED_INT,
+                                          index_buffer_offset(render_state),
+                                          instance_count as GLsizei); ck();
+                offset_instance_attrs(&instance_attrs, 0);
+            }
+        }
+        self.reset_render_state(render_state);
+    }
+
+    fn draw_elements_indirect(&self,
+                              indirect_buffer: &GLBuffer,
+                              offset: usize,
+                              render_state: &RenderState<Self>) {
+        assert_eq!(self.feature_level(),
+                   FeatureLevel::D3D11,
+                   "Indirect draws require OpenGL 4!");
+        assert_eq!(offset % 4, 0, "Indirect draw arguments must be 4-byte aligned!");
+
+        self.set_render_state(render_state);
+        unsafe {
+            // The arguments may have just been written by a compute shader.
+            gl::MemoryBarrier(gl::COMMAND_BARRIER_BIT); ck();
+            gl::BindBuffer(gl::DRAW_INDIRECT_BUFFER, indirect_buffer.object.gl_buffer); ck();
+            gl::DrawElementsIndirect(render_state.primitive.to_gl_primitive(),
+                                     gl::UNSIGNED_INT,
+                                     offset as *const GLvoid); ck();
+            gl::BindBuffer(gl::DRAW_INDIRECT_BUFFER, 0); ck();
+        }
+        self.reset_render_state(render_state);
+    }
+
+    fn dispatch_compute(&self, dimensions: ComputeDimensions, compute_state: &ComputeState<Self>) {
+        assert!(self.render_pass_framebuffer.get().is_none(),
+                "Can't dispatch compute in a render pass!");
+        self.set_compute_state(compute_state);
+        unsafe {
+            gl::DispatchCompute(dimensions.x, dimensions.y, dimensions.z); ck();
+        }
+        self.reset_compute_state(compute_state);
+    }
+
+    fn draw_mesh_tasks(&self, group_count: ComputeDimensions, render_state: &RenderState<Self>) {
+        let draw_mesh_tasks_nv =
+            self.draw_mesh_tasks_nv
+                .expect("Call `GLDevice::load_mesh_shader_extension()` to use mesh shaders!");
+        assert!(group_count.y == 1 && group_count.z == 1,
+                "`GL_NV_mesh_shader` only supports one-dimensional task counts!");
+
+        self.set_render_state(render_state);
+        unsafe {
+            draw_mesh_tasks_nv(0, group_count.x); ck();
+        }
+        self.reset_render_state(render_state);
+    }
+
+    #[inline]
+    fn create_timer_query(&self) -> GLTimerQuery {
+        let mut query = GLTimerQuery { gl_query: 0 };
+        unsafe {
+            gl::GenQueries(1, &mut query.gl_query); ck();
+        }
+        query
+    }
+
+    #[inline]
+    fn begin_timer_query(&self, query: &Self::TimerQuery) {
+        unsafe {
+            gl::BeginQuery(gl::TIME_ELAPSED, query.gl_query); ck();
+        }
+    }
+
+    #[inline]
+    fn end_timer_query(&self, _: &Self::TimerQuery) {
+        unsafe {
+            gl::EndQuery(gl::TIME_ELAPSED); ck();
+        }
+    }
+
+    fn try_recv_timer_query(&self, query: &Self::TimerQuery) -> Option<Duration> {
+        unsafe {
+            let mut result = 0;
+            gl::GetQueryObjectiv(query.gl_query, gl::QUERY_RESULT_AVAILABLE, &mut result); ck();
+            if result == gl::FALSE as GLint {
+                None
+            } else {
+                Some(self.recv_timer_query(query))
+            }
+        }
+    }
+
+    fn recv_timer_query(&self, query: &Self::TimerQuery) -> Duration {
+        unsafe {
+            let mut result = 0;
+            gl::GetQueryObjectui64v(query.gl_query, gl::QUERY_RESULT, &mut result); ck();
+            Duration::from_nanos(result)
+        }
+    }
+
+    fn reset_frame_stats(&self) {
+        *self.frame_stats.borrow_mut() = Some(GLFrameStats {
+            stats: FrameStats::default(),
+            scope_depth: 0,
+            scope_start: None,
+            pending_scopes: vec![],
+        });
+    }
+
+    fn collect_frame_stats(&self) -> FrameStats {
+        let frame_stats = self.frame_stats.borrow_mut().take();
+        let GLFrameStats { mut stats, scope_depth, pending_scopes, .. } =
+            frame_stats.expect("Must call `reset_frame_stats()` first!");
+        assert_eq!(scope_depth, 0, "Can't collect frame stats inside `begin_commands()`!");
+
+        self.wait_idle();
+        for (scope_start, scope_end) in pending_scopes {
+            let (mut start_time, mut end_time) = (0, 0);
+            unsafe {
+                gl::GetQueryObjectui64v(scope_start.gl_query, gl::QUERY_RESULT, &mut start_time);
+                ck();
+                gl::GetQueryObjectui64v(scope_end.gl_query, gl::QUERY_RESULT, &mut end_time); ck();
+            }
+            stats.scope_count += 1;
+            stats.gpu_time += Duration::from_nanos(end_time.saturating_sub(start_time));
+        }
+        stats
+    }
+
+    fn create_pipeline_stats_query(&self) -> GLPipelineStatsQuery {
+        // OpenGL ES has no primitive count queries, and clipping statistics need OpenGL 4.6 or
+        // `GL_ARB_pipeline_statistics_query`.
+        let (count_primitives, count_clipping) = match self.version {
+            GLVersion::GLES3 => (false, false),
+            GLVersion::GL3 => (true, false),
+            GLVersion::GL4 => {
+                (true,
+                 self.capabilities.version >= (4, 6) ||
+                 self.capabilities.has_extension("GL_ARB_pipeline_statistics_query"))
+            }
+        };
+
+        let gen_query = |enabled: bool| {
+            if !enabled {
+                return None;
+            }
+            let mut gl_query = 0;
+            unsafe {
+                gl::GenQueries(1, &mut gl_query); ck();
+            }
+            Some(gl_query)
+        };
+
+        GLPipelineStatsQuery {
+            primitives_generated: gen_query(count_primitives),
+            clipping_output_primitives: gen_query(count_clipping),
         }
-        self.reset_render_state(render_state);
     }
 
-    fn draw_elements_instanced(&self,
-                               index_count: u32,
-                               instance_count: u32,
-                               render_state: &RenderState<Self>) {
-        self.set_render_state(render_state);
+    fn begin_pipeline_stats_query(&self, query: &GLPipelineStatsQuery) {
         unsafe {
-            gl::DrawElementsInstanced(render_state.primitive.to_gl_primitive(),
-                                      index_count as GLsizei,
-                                      gl::UNSIGNED_INT,
-                                      ptr::null(),
-                                      instance_count as GLsizei); ck();
+            if let Some(gl_query) = query.primitives_generated {
+                gl::BeginQuery(gl::PRIMITIVES_GENERATED, gl_query); ck();
+            }
+            if let Some(gl_query) = query.clipping_output_primitives {
+                gl::BeginQuery(CLIPPING_OUTPUT_PRIMITIVES, gl_query); ck();
+            }
         }
-        self.reset_render_state(render_state);
     }
 
-    fn dispatch_compute(&self, dimensions: ComputeDimensions, compute_state: &ComputeState<Self>) {
-        self.set_compute_state(compute_state);
+    fn end_pipeline_stats_query(&self, query: &GLPipelineStatsQuery) {
         unsafe {
-            gl::DispatchCompute(dimensions.x, dimensions.y, dimensions.z); ck();
+            if query.primitives_generated.is_some() {
+                gl::EndQuery(gl::PRIMITIVES_GENERATED); ck();
+            }
+            if query.clipping_output_primitives.is_some() {
+                gl::EndQuery(CLIPPING_OUTPUT_PRIMITIVES); ck();
+            }
         }
-        self.reset_compute_state(compute_state);
     }
 
     #[inline]
-    fn create_timer_query(&self) -> GLTimerQuery {
-        let mut query = GLTimerQuery { gl_query: 0 };
+    fn create_occlusion_query(&self) -> GLOcclusionQuery {
+        let mut query = GLOcclusionQuery { gl_query: 0 };
         unsafe {
             gl::GenQueries(1, &mut query.gl_query); ck();
         }
@@ -842,37 +2048,95 @@ impl Device for GLDevice {
     }
 
     #[inline]
-    fn begin_timer_query(&self, query: &Self::TimerQuery) {
+    fn begin_occlusion_query(&self, query: &GLOcclusionQuery) {
         unsafe {
-            gl::BeginQuery(gl::TIME_ELAPSED, query.gl_query); ck();
+            gl::BeginQuery(gl::ANY_SAMPLES_PASSED, query.gl_query); ck();
         }
     }
 
     #[inline]
-    fn end_timer_query(&self, _: &Self::TimerQuery) {
+    fn end_occlusion_query(&self, _: &GLOcclusionQuery) {
         unsafe {
-            gl::EndQuery(gl::TIME_ELAPSED); ck();
+            gl::EndQuery(gl::ANY_SAMPLES_PASSED); ck();
         }
     }
 
-    fn try_recv_timer_query(&self, query: &Self::TimerQuery) -> Option<Duration> {
+    fn try_recv_occlusion_query(&self, query: &GLOcclusionQuery) -> Option<bool> {
         unsafe {
             let mut result = 0;
             gl::GetQueryObjectiv(query.gl_query, gl::QUERY_RESULT_AVAILABLE, &mut result); ck();
             if result == gl::FALSE as GLint {
                 None
             } else {
-                Some(self.recv_timer_query(query))
+                Some(self.recv_occlusion_query(query))
             }
         }
     }
 
-    fn recv_timer_query(&self, query: &Self::TimerQuery) -> Duration {
+    fn recv_occlusion_query(&self, query: &GLOcclusionQuery) -> bool {
         unsafe {
             let mut result = 0;
-            gl::GetQueryObjectui64v(query.gl_query, gl::QUERY_RESULT, &mut result); ck();
-            Duration::from_nanos(result)
+            gl::GetQueryObjectuiv(query.gl_query, gl::QUERY_RESULT, &mut result); ck();
+            result != gl::FALSE as GLuint
+        }
+    }
+
+    fn draw_elements_conditional(&self,
+                                 index_count: u32,
+                                 render_state: &RenderState<Self>,
+                                 query: &GLOcclusionQuery) {
+        match self.version {
+            GLVersion::GL3 | GLVersion::GL4 => {
+                unsafe {
+                    gl::BeginConditionalRender(query.gl_query, gl::QUERY_WAIT); ck();
+                }
+                self.draw_elements(index_count, render_state);
+                unsafe {
+                    gl::EndConditionalRender(); ck();
+                }
+            }
+            // OpenGL ES has no conditional rendering, so read the result back instead.
+            GLVersion::GLES3 => {
+                if self.recv_occlusion_query(query) {
+                    self.draw_elements(index_count, render_state);
+                }
+            }
+        }
+    }
+
+    fn gpu_timestamp_calibration(&self) -> Option<TimestampCalibration> {
+        // OpenGL ES needs `GL_EXT_disjoint_timer_query` to read timestamps.
+        if self.version == GLVersion::GLES3 &&
+                !self.capabilities.has_extension("GL_EXT_disjoint_timer_query") {
+            return None;
+        }
+
+        let mut gpu_timestamp = 0;
+        unsafe {
+            gl::GetInteger64v(gl::TIMESTAMP, &mut gpu_timestamp); ck();
         }
+        let cpu_time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+
+        // OpenGL timestamps are in nanoseconds.
+        Some(TimestampCalibration {
+            cpu_time_ns: cpu_time.as_nanos() as u64,
+            gpu_timestamp: gpu_timestamp as u64,
+            gpu_ticks_per_ns: 1.0,
+        })
+    }
+
+    fn recv_pipeline_stats_query(&self, query: &GLPipelineStatsQuery) -> Option<PipelineStats> {
+        let get_result = |gl_query| {
+            let mut result = 0;
+            unsafe {
+                gl::GetQueryObjectui64v(gl_query, gl::QUERY_RESULT, &mut result); ck();
+            }
+            result
+        };
+
+        let primitives_generated = get_result(query.primitives_generated?);
+        let clipping_output_primitives = query.clipping_output_primitives.map(get_result);
+        Some(PipelineStats { primitives_generated, clipping_output_primitives })
     }
 
     fn try_recv_texture_data(&self, receiver: &Self::TextureDataReceiver) -> Option<TextureData> {
@@ -927,6 +2191,9 @@ impl Device for GLDevice {
         unsafe {
             gl::BindBuffer(target.to_gl_target(), buffer.object.gl_buffer); ck();
         }
+        if let BufferTarget::Vertex = target {
+            vertex_array.array_buffer.set(buffer.object.gl_buffer);
+        }
         self.unbind_vertex_array();
     }
 
@@ -937,6 +2204,18 @@ impl Device for GLDevice {
             (GLVersion::GL3, ShaderKind::Compute) | (GLVersion::GLES3, ShaderKind::Compute) => {
                 panic!("Compute shaders are not supported on OpenGL versions prior to 4!")
             }
+            (GLVersion::GL3, ShaderKind::TessControl) |
+            (GLVersion::GL3, ShaderKind::TessEvaluation) |
+            (GLVersion::GLES3, ShaderKind::TessControl) |
+            (GLVersion::GLES3, ShaderKind::TessEvaluation) => {
+                panic!("Tessellation shaders are not supported on OpenGL versions prior to 4!")
+            }
+            (GLVersion::GL3, ShaderKind::Task) |
+            (GLVersion::GL3, ShaderKind::Mesh) |
+            (GLVersion::GLES3, ShaderKind::Task) |
+            (GLVersion::GLES3, ShaderKind::Mesh) => {
+                panic!("Mesh shaders are not supported on OpenGL versions prior to 4!")
+            }
             (GLVersion::GL3, ShaderKind::Vertex) |
             (GLVersion::GL3, ShaderKind::Fragment) |
             (GLVersion::GLES3, ShaderKind::Vertex) |
@@ -948,9 +2227,13 @@ impl Device for GLDevice {
             GLVersion::GL4 => "gl4",
         };
         let suffix = match kind {
-            ShaderKind::Vertex => 'v',
-            ShaderKind::Fragment => 'f',
-            ShaderKind::Compute => 'c',
+            ShaderKind::Vertex => "v",
+            ShaderKind::Fragment => "f",
+            ShaderKind::Compute => "c",
+            ShaderKind::TessControl => "tc",
+            ShaderKind::TessEvaluation => "te",
+            ShaderKind::Task => "t",
+            ShaderKind::Mesh => "m",
         };
         let path = format!("shaders/{}/{}.{}s.glsl", directory, name, suffix);
         self.create_shader_from_source(name, &resources.slurp(&path).unwrap(), kind)
@@ -965,17 +2248,61 @@ impl Device for GLDevice {
 
     fn wait_for_fence(&self, fence: &Self::Fence) {
         unsafe {
-            gl::ClientWaitSync(fence.gl_sync, gl::SYNC_FLUSH_COMMANDS_BIT, 0); ck();
+            loop {
+                let result = gl::ClientWaitSync(fence.gl_sync,
+                                                gl::SYNC_FLUSH_COMMANDS_BIT,
+                                                FENCE_WAIT_TIMEOUT_NS); ck();
+                match result {
+                    gl::ALREADY_SIGNALED | gl::CONDITION_SATISFIED => return,
+                    gl::TIMEOUT_EXPIRED => {}
+                    _ => panic!("Failed to wait for a fence: {:x}", result),
+                }
+            }
+        }
+    }
+
+    fn try_wait_for_fence(&self, fence: &Self::Fence) -> bool {
+        unsafe {
+            let result = gl::ClientWaitSync(fence.gl_sync, gl::SYNC_FLUSH_COMMANDS_BIT, 0); ck();
+            result != gl::TIMEOUT_EXPIRED && result != gl::WAIT_FAILED
         }
     }
 }
 
 impl GLDevice {
+    // Records the GPU time once every command before this point has completed.
+    fn create_timestamp_query(&self) -> GLTimerQuery {
+        let query = self.create_timer_query();
+        unsafe {
+            gl::QueryCounter(query.gl_query, gl::TIMESTAMP); ck();
+        }
+        query
+    }
+
     fn bind_render_target(&self, attachment: &RenderTarget<GLDevice>) {
         match *attachment {
             RenderTarget::Default => self.bind_default_framebuffer(),
             RenderTarget::Framebuffer(framebuffer) => self.bind_framebuffer(framebuffer),
         }
+
+        // OpenGL ES always encodes colors written to sRGB render targets, but desktop OpenGL only
+        // does so when asked.
+        if self.version != GLVersion::GLES3 {
+            unsafe {
+                if self.render_target_format(attachment).is_srgb() {
+                    gl::Enable(gl::FRAMEBUFFER_SRGB); ck();
+                } else {
+                    gl::Disable(gl::FRAMEBUFFER_SRGB); ck();
+                }
+            }
+        }
+    }
+
+    fn render_target_framebuffer(&self, attachment: &RenderTarget<GLDevice>) -> GLuint {
+        match *attachment {
+            RenderTarget::Default => self.default_framebuffer,
+            RenderTarget::Framebuffer(framebuffer) => framebuffer.gl_framebuffer,
+        }
     }
 
     fn bind_vertex_array(&self, vertex_array: &GLVertexArray) {
@@ -990,11 +2317,58 @@ impl GLDevice {
         }
     }
 
+    // Checks that an indexed draw stays within the index buffer of the bound vertex array.
+    fn debug_check_index_range(&self, index_count: u32, render_state: &RenderState<GLDevice>) {
+        if !cfg!(debug_assertions) {
+            return;
+        }
+        let mut index_buffer_size = 0;
+        unsafe {
+            gl::GetBufferParameteriv(gl::ELEMENT_ARRAY_BUFFER,
+                                     gl::BUFFER_SIZE,
+                                     &mut index_buffer_size); ck();
+        }
+        let index_end = render_state.first_index as usize + index_count as usize;
+        debug_assert!(index_end * mem::size_of::<u32>() <= index_buffer_size as usize,
+                      "Index range {}..{} is out of bounds of the index buffer!",
+                      render_state.first_index,
+                      index_end);
+    }
+
+    // Clamping to the border falls back to clamping to the edge where it's unsupported.
+    fn wrap_mode(&self, repeat: bool, clamp_to_border: bool) -> GLenum {
+        match (clamp_to_border, repeat) {
+            (true, _) if self.capabilities.supports_border_clamp() => gl::CLAMP_TO_BORDER,
+            (false, true) => gl::REPEAT,
+            _ => gl::CLAMP_TO_EDGE,
+        }
+    }
+
     fn bind_texture(&self, texture: &GLTexture, unit: u32) {
+        assert!(!texture.object.renderbuffer,
+                "Textures created with only `RENDER_TARGET` can't be sampled!");
         unsafe {
             gl::ActiveTexture(gl::TEXTURE0 + unit); ck();
-            gl::BindTexture(gl::TEXTURE_2D, texture.gl_texture); ck();
+            gl::BindTexture(texture.gl_target, texture.object.gl_texture); ck();
+        }
+    }
+
+    // Attaches `gl_target`, which is `GL_TEXTURE_2D`, `GL_RENDERBUFFER`, or one face of a cube
+    // map, to a new framebuffer.
+    fn create_framebuffer_for_target(&self, texture: GLTexture, gl_target: GLenum)
+                                     -> GLFramebuffer {
+        let mut gl_framebuffer = 0;
+        unsafe {
+            gl::GenFramebuffers(1, &mut gl_framebuffer); ck();
+            gl::BindFramebuffer(gl::FRAMEBUFFER, gl_framebuffer); ck();
+            if !texture.object.renderbuffer {
+                self.bind_texture(&texture, 0);
+            }
+            attach_color_target(gl::FRAMEBUFFER, &texture, gl_target);
+            assert_eq!(gl::CheckFramebufferStatus(gl::FRAMEBUFFER), gl::FRAMEBUFFER_COMPLETE);
         }
+
+        GLFramebuffer { gl_framebuffer, texture }
     }
 
     fn unbind_texture(&self, unit: u32) {
@@ -1007,7 +2381,7 @@ impl GLDevice {
     fn bind_image(&self, binding: &ImageBinding<GLImageParameter, GLTexture>) {
         unsafe {
             gl::BindImageTexture(binding.0.image_unit,
-                                 binding.1.gl_texture,
+                                 binding.1.object.gl_texture,
                                  0,
                                  gl::FALSE,
                                  0,
@@ -1068,7 +2442,7 @@ impl GLDevice {
         }
     }
 
-    fn clear(&self, ops: &ClearOps) {
+    fn clear_framebuffer(&self, ops: &ClearOps) {
         unsafe {
             let mut flags = 0;
             if let Some(color) = ops.color {
@@ -1092,6 +2466,41 @@ impl GLDevice {
         }
     }
 
+    // Discards the contents of all attachments that aren't about to be cleared.
+    fn invalidate_framebuffer(&self, render_target: &RenderTarget<GLDevice>, ops: &ClearOps) {
+        // `glInvalidateFramebuffer()` requires OpenGL 4.3 or OpenGL ES 3.0. Skipping it is
+        // harmless, since it's only a hint.
+        if let GLVersion::GL3 = self.version {
+            return;
+        }
+
+        let (color, depth, stencil) = match *render_target {
+            RenderTarget::Default => (gl::COLOR, gl::DEPTH, gl::STENCIL),
+            RenderTarget::Framebuffer(_) => {
+                (gl::COLOR_ATTACHMENT0, gl::DEPTH_ATTACHMENT, gl::STENCIL_ATTACHMENT)
+            }
+        };
+
+        let mut attachments = vec![];
+        if ops.color.is_none() {
+            attachments.push(color);
+        }
+        if ops.depth.is_none() {
+            attachments.push(depth);
+        }
+        if ops.stencil.is_none() {
+            attachments.push(stencil);
+        }
+
+        if !attachments.is_empty() {
+            unsafe {
+                gl::InvalidateFramebuffer(gl::FRAMEBUFFER,
+                                          attachments.len() as GLsizei,
+                                          attachments.as_ptr()); ck();
+            }
+        }
+    }
+
     fn render_target_format(&self, render_target: &RenderTarget<GLDevice>) -> TextureFormat {
         match *render_target {
             RenderTarget::Default => TextureFormat::RGBA8,
@@ -1107,14 +2516,25 @@ impl GLDevice {
             let channels = format.channels();
             let (mut texture_data, texture_data_ptr, texture_data_len);
             match format {
-                TextureFormat::R8 | TextureFormat::RGBA8 => {
+                TextureFormat::R8 |
+                TextureFormat::RG8 |
+                TextureFormat::RGBA8 |
+                TextureFormat::RGBA8Srgb |
+                TextureFormat::BGRA8 => {
                     let mut pixels: Vec<u8> =
                         vec![0; size.x() as usize * size.y() as usize * channels];
                     texture_data_ptr = pixels.as_mut_ptr();
                     texture_data_len = pixels.len() * mem::size_of::<u8>();
                     texture_data = TextureData::U8(pixels);
                 }
-                TextureFormat::R16F | TextureFormat::RGBA16F => {
+                TextureFormat::R16 | TextureFormat::RGBA16 => {
+                    let mut pixels: Vec<u16> =
+                        vec![0; size.x() as usize * size.y() as usize * channels];
+                    texture_data_ptr = pixels.as_mut_ptr() as *mut u8;
+                    texture_data_len = pixels.len() * mem::size_of::<u16>();
+                    texture_data = TextureData::U16(pixels);
+                }
+                TextureFormat::R16F | TextureFormat::RG16F | TextureFormat::RGBA16F => {
                     let mut pixels: Vec<f16> =
                         vec![f16::default(); size.x() as usize * size.y() as usize * channels];
                     texture_data_ptr = pixels.as_mut_ptr() as *mut u8;
@@ -1143,18 +2563,21 @@ impl GLDevice {
                 TextureData::F32(ref mut pixels) => flip_y(pixels, size, channels),
             }
 
+            if receiver.alpha_mode == AlphaMode::Straight {
+                texture_data.unpremultiply(format);
+            }
+
             texture_data
         }
     }
 
     fn get_buffer_data(&self, receiver: &GLBufferDataReceiver) -> Vec<u8> {
-        let mut dest = vec![0; receiver.range.end - receiver.range.start];
-        let gl_target = receiver.target.to_gl_target();
+        let mut dest = vec![0; receiver.byte_size];
         unsafe {
-            gl::BindBuffer(gl_target, receiver.object.gl_buffer); ck();
-            gl::GetBufferSubData(gl_target,
-                                 receiver.range.start as GLintptr,
-                                 (receiver.range.end - receiver.range.start) as GLsizeiptr,
+            gl::BindBuffer(gl::COPY_READ_BUFFER, receiver.gl_copy_buffer); ck();
+            gl::GetBufferSubData(gl::COPY_READ_BUFFER,
+                                 0,
+                                 receiver.byte_size as GLsizeiptr,
                                  dest.as_mut_ptr() as *mut GLvoid); ck();
         }
         dest
@@ -1163,6 +2586,16 @@ impl GLDevice {
 
 pub struct GLVertexArray {
     pub gl_vertex_array: GLuint,
+    // The buffer most recently bound to `GL_ARRAY_BUFFER` through this vertex array.
+    array_buffer: Cell<GLuint>,
+    instance_attrs: RefCell<Vec<GLInstanceAttr>>,
+}
+
+// A per-instance attribute, along with the buffer it was configured to read from.
+struct GLInstanceAttr {
+    attr: GLuint,
+    descriptor: VertexAttrDescriptor,
+    gl_buffer: GLuint,
 }
 
 impl Drop for GLVertexArray {
@@ -1248,6 +2681,16 @@ pub struct GLBuffer {
 
 pub struct GLBufferObject {
     pub gl_buffer: GLuint,
+    size: Cell<usize>,
+    mapping: Cell<Option<GLMappedRange>>,
+}
+
+// The byte range of a buffer that is currently mapped.
+#[derive(Clone, Copy, Debug)]
+struct GLMappedRange {
+    start: usize,
+    end: usize,
+    explicit_flush: bool,
 }
 
 impl Drop for GLBufferObject {
@@ -1258,6 +2701,38 @@ impl Drop for GLBufferObject {
     }
 }
 
+/// A mapped range of a buffer. The range is unmapped when this is dropped.
+pub struct GLBufferMapping<'a> {
+    buffer: &'a GLBuffer,
+    ptr: *mut u8,
+    len: usize,
+}
+
+impl<'a> Deref for GLBufferMapping<'a> {
+    type Target = [u8];
+    #[inline]
+    fn deref(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr, self.len) }
+    }
+}
+
+impl<'a> DerefMut for GLBufferMapping<'a> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.ptr, self.len) }
+    }
+}
+
+impl<'a> Drop for GLBufferMapping<'a> {
+    fn drop(&mut self) {
+        unsafe {
+            gl::BindBuffer(gl::COPY_WRITE_BUFFER, self.buffer.object.gl_buffer); ck();
+            gl::UnmapBuffer(gl::COPY_WRITE_BUFFER); ck();
+        }
+        self.buffer.object.mapping.set(None);
+    }
+}
+
 #[derive(Clone, PartialEq, Debug)]
 pub struct GLUniform {
     location: GLint,
@@ -1304,6 +2779,7 @@ pub struct GLProgramParameters {
 
 pub struct GLShader {
     gl_shader: GLuint,
+    name: String,
 }
 
 impl Drop for GLShader {
@@ -1314,20 +2790,133 @@ impl Drop for GLShader {
     }
 }
 
+/// A handle to a texture.
+///
+/// Handles can be cloned cheaply; all clones refer to the same OpenGL texture, which is deleted
+/// when the last handle is dropped.
+#[derive(Clone)]
 pub struct GLTexture {
-    gl_texture: GLuint,
+    object: Rc<GLTextureObject>,
+    // `GL_TEXTURE_2D`, `GL_TEXTURE_CUBE_MAP`, or `GL_RENDERBUFFER`.
+    gl_target: GLenum,
     pub size: Vector2I,
     pub format: TextureFormat,
 }
 
-impl Drop for GLTexture {
+impl GLTexture {
+    fn new(gl_target: GLenum, size: Vector2I, format: TextureFormat) -> GLTexture {
+        let mut gl_texture = 0;
+        unsafe {
+            gl::GenTextures(1, &mut gl_texture); ck();
+        }
+        let object = Rc::new(GLTextureObject {
+            gl_texture,
+            renderbuffer: false,
+            level_count: Cell::new(1),
+        });
+        GLTexture { object, gl_target, size, format }
+    }
+
+    fn new_renderbuffer(size: Vector2I, format: TextureFormat) -> GLTexture {
+        let mut gl_renderbuffer = 0;
+        unsafe {
+            gl::GenRenderbuffers(1, &mut gl_renderbuffer); ck();
+            gl::BindRenderbuffer(gl::RENDERBUFFER, gl_renderbuffer); ck();
+            gl::RenderbufferStorage(gl::RENDERBUFFER,
+                                    format.gl_internal_format() as GLenum,
+                                    size.x() as GLsizei,
+                                    size.y() as GLsizei); ck();
+            gl::BindRenderbuffer(gl::RENDERBUFFER, 0); ck();
+        }
+        let object = Rc::new(GLTextureObject {
+            gl_texture: gl_renderbuffer,
+            renderbuffer: true,
+            level_count: Cell::new(1),
+        });
+        GLTexture { object, gl_target: gl::RENDERBUFFER, size, format }
+    }
+}
+
+struct GLTextureObject {
+    // The name of a renderbuffer instead if `renderbuffer` is set.
+    gl_texture: GLuint,
+    renderbuffer: bool,
+    // Shared between handles, since `generate_mipmaps()` can be called through any of them.
+    level_count: Cell<u32>,
+}
+
+impl Drop for GLTextureObject {
     fn drop(&mut self) {
         unsafe {
-            gl::DeleteTextures(1, &mut self.gl_texture); ck();
+            if self.renderbuffer {
+                gl::DeleteRenderbuffers(1, &mut self.gl_texture); ck();
+            } else {
+                gl::DeleteTextures(1, &mut self.gl_texture); ck();
+            }
+        }
+    }
+}
+
+// Attaches a texture or renderbuffer as the color attachment of the bound framebuffer.
+unsafe fn attach_color_target(framebuffer_target: GLenum, texture: &GLTexture, gl_target: GLenum) {
+    if texture.object.renderbuffer {
+        gl::FramebufferRenderbuffer(framebuffer_target,
+                                    gl::COLOR_ATTACHMENT0,
+                                    gl::RENDERBUFFER,
+                                    texture.object.gl_texture); ck();
+    } else {
+        gl::FramebufferTexture2D(framebuffer_target,
+                                 gl::COLOR_ATTACHMENT0,
+                                 gl_target,
+                                 texture.object.gl_texture,
+                                 0); ck();
+    }
+}
+
+// Points `attr` at `offset` bytes into the buffer bound to `GL_ARRAY_BUFFER`, in the bound vertex
+// array.
+unsafe fn set_vertex_attr_pointer(attr: GLuint, descriptor: &VertexAttrDescriptor, offset: usize) {
+    let attr_type = descriptor.attr_type.to_gl_type();
+    match descriptor.class {
+        VertexAttrClass::Float | VertexAttrClass::FloatNorm => {
+            let normalized = if descriptor.class == VertexAttrClass::FloatNorm {
+                gl::TRUE
+            } else {
+                gl::FALSE
+            };
+            gl::VertexAttribPointer(attr,
+                                    descriptor.size as GLint,
+                                    attr_type,
+                                    normalized,
+                                    descriptor.stride as GLint,
+                                    offset as *const GLvoid); ck();
+        }
+        VertexAttrClass::Int => {
+            gl::VertexAttribIPointer(attr,
+                                     descriptor.size as GLint,
+                                     attr_type,
+                                     descriptor.stride as GLint,
+                                     offset as *const GLvoid); ck();
         }
     }
 }
 
+// Emulates a base instance by pointing each per-instance attribute `base_instance` instances
+// further into its buffer. Passing zero restores the configured offsets.
+unsafe fn offset_instance_attrs(instance_attrs: &[GLInstanceAttr], base_instance: u32) {
+    for instance_attr in instance_attrs {
+        let descriptor = &instance_attr.descriptor;
+        assert_eq!(base_instance % descriptor.divisor,
+                   0,
+                   "The base instance must be a multiple of the attribute divisor!");
+        let instance = (base_instance / descriptor.divisor) as usize;
+        gl::BindBuffer(gl::ARRAY_BUFFER, instance_attr.gl_buffer); ck();
+        set_vertex_attr_pointer(instance_attr.attr,
+                                descriptor,
+                                descriptor.offset + instance * descriptor.stride);
+    }
+}
+
 pub struct GLTimerQuery {
     gl_query: GLuint,
 }
@@ -1341,6 +2930,38 @@ impl Drop for GLTimerQuery {
     }
 }
 
+pub struct GLOcclusionQuery {
+    gl_query: GLuint,
+}
+
+impl Drop for GLOcclusionQuery {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteQueries(1, &mut self.gl_query); ck();
+        }
+    }
+}
+
+pub struct GLPipelineStatsQuery {
+    primitives_generated: Option<GLuint>,
+    clipping_output_primitives: Option<GLuint>,
+}
+
+impl Drop for GLPipelineStatsQuery {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe {
+            if let Some(ref mut gl_query) = self.primitives_generated {
+                gl::DeleteQueries(1, gl_query); ck();
+            }
+            if let Some(ref mut gl_query) = self.clipping_output_primitives {
+                gl::DeleteQueries(1, gl_query); ck();
+            }
+        }
+    }
+}
+
 trait BlendFactorExt {
     fn to_gl_blend_factor(self) -> GLenum;
 }
@@ -1356,6 +2977,8 @@ impl BlendFactorExt for BlendFactor {
             BlendFactor::DestAlpha => gl::DST_ALPHA,
             BlendFactor::OneMinusDestAlpha => gl::ONE_MINUS_DST_ALPHA,
             BlendFactor::DestColor => gl::DST_COLOR,
+            BlendFactor::ConstantColor => gl::CONSTANT_COLOR,
+            BlendFactor::OneMinusConstantColor => gl::ONE_MINUS_CONSTANT_COLOR,
         }
     }
 }
@@ -1404,6 +3027,23 @@ impl BufferUploadModeExt for BufferUploadMode {
     }
 }
 
+trait CubeFaceExt {
+    fn to_gl_target(self) -> GLenum;
+}
+
+impl CubeFaceExt for CubeFace {
+    fn to_gl_target(self) -> GLenum {
+        match self {
+            CubeFace::PositiveX => gl::TEXTURE_CUBE_MAP_POSITIVE_X,
+            CubeFace::NegativeX => gl::TEXTURE_CUBE_MAP_NEGATIVE_X,
+            CubeFace::PositiveY => gl::TEXTURE_CUBE_MAP_POSITIVE_Y,
+            CubeFace::NegativeY => gl::TEXTURE_CUBE_MAP_NEGATIVE_Y,
+            CubeFace::PositiveZ => gl::TEXTURE_CUBE_MAP_POSITIVE_Z,
+            CubeFace::NegativeZ => gl::TEXTURE_CUBE_MAP_NEGATIVE_Z,
+        }
+    }
+}
+
 trait DepthFuncExt {
     fn to_gl_depth_func(self) -> GLenum;
 }
@@ -1417,6 +3057,36 @@ impl DepthFuncExt for DepthFunc {
     }
 }
 
+trait SwizzleExt {
+    fn to_gl_swizzle(self) -> GLenum;
+    fn from_gl_swizzle(value: GLenum) -> Self;
+}
+
+impl SwizzleExt for Swizzle {
+    fn to_gl_swizzle(self) -> GLenum {
+        match self {
+            Swizzle::Zero => gl::ZERO,
+            Swizzle::One => gl::ONE,
+            Swizzle::Red => gl::RED,
+            Swizzle::Green => gl::GREEN,
+            Swizzle::Blue => gl::BLUE,
+            Swizzle::Alpha => gl::ALPHA,
+        }
+    }
+
+    fn from_gl_swizzle(value: GLenum) -> Swizzle {
+        match value {
+            gl::ZERO => Swizzle::Zero,
+            gl::ONE => Swizzle::One,
+            gl::RED => Swizzle::Red,
+            gl::GREEN => Swizzle::Green,
+            gl::BLUE => Swizzle::Blue,
+            gl::ALPHA => Swizzle::Alpha,
+            _ => panic!("Unexpected texture swizzle {:#x}!", value),
+        }
+    }
+}
+
 trait ImageAccessExt {
     fn to_gl_access(self) -> GLenum;
 }
@@ -1440,6 +3110,8 @@ impl PrimitiveExt for Primitive {
         match self {
             Primitive::Triangles => gl::TRIANGLES,
             Primitive::Lines => gl::LINES,
+            Primitive::Points => gl::POINTS,
+            Primitive::Patches(_) => gl::PATCHES,
         }
     }
 }
@@ -1467,8 +3139,13 @@ impl TextureFormatExt for TextureFormat {
     fn gl_internal_format(self) -> GLint {
         match self {
             TextureFormat::R8 => gl::R8 as GLint,
+            TextureFormat::R16 => gl::R16 as GLint,
             TextureFormat::R16F => gl::R16F as GLint,
-            TextureFormat::RGBA8 => gl::RGBA8 as GLint,
+            TextureFormat::RG8 => gl::RG8 as GLint,
+            TextureFormat::RG16F => gl::RG16F as GLint,
+            TextureFormat::RGBA8 | TextureFormat::BGRA8 => gl::RGBA8 as GLint,
+            TextureFormat::RGBA8Srgb => gl::SRGB8_ALPHA8 as GLint,
+            TextureFormat::RGBA16 => gl::RGBA16 as GLint,
             TextureFormat::RGBA16F => gl::RGBA16F as GLint,
             TextureFormat::RGBA32F => gl::RGBA32F as GLint,
         }
@@ -1476,15 +3153,28 @@ impl TextureFormatExt for TextureFormat {
 
     fn gl_format(self) -> GLuint {
         match self {
-            TextureFormat::R8 | TextureFormat::R16F => gl::RED,
-            TextureFormat::RGBA8 | TextureFormat::RGBA16F | TextureFormat::RGBA32F => gl::RGBA,
+            TextureFormat::R8 | TextureFormat::R16 | TextureFormat::R16F => gl::RED,
+            TextureFormat::RG8 | TextureFormat::RG16F => gl::RG,
+            TextureFormat::RGBA8 |
+            TextureFormat::RGBA8Srgb |
+            TextureFormat::RGBA16 |
+            TextureFormat::RGBA16F |
+            TextureFormat::RGBA32F => gl::RGBA,
+            // OpenGL ES needs `GL_EXT_texture_format_BGRA8888` for this, which
+            // `format_is_supported()` checks for.
+            TextureFormat::BGRA8 => gl::BGRA,
         }
     }
 
     fn gl_type(self) -> GLuint {
         match self {
-            TextureFormat::R8 | TextureFormat::RGBA8 => gl::UNSIGNED_BYTE,
-            TextureFormat::R16F | TextureFormat::RGBA16F => gl::HALF_FLOAT,
+            TextureFormat::R8 |
+            TextureFormat::RG8 |
+            TextureFormat::RGBA8 |
+            TextureFormat::RGBA8Srgb |
+            TextureFormat::BGRA8 => gl::UNSIGNED_BYTE,
+            TextureFormat::R16 | TextureFormat::RGBA16 => gl::UNSIGNED_SHORT,
+            TextureFormat::R16F | TextureFormat::RG16F | TextureFormat::RGBA16F => gl::HALF_FLOAT,
             TextureFormat::RGBA32F => gl::FLOAT,
         }
     }
@@ -1508,15 +3198,16 @@ impl VertexAttrTypeExt for VertexAttrType {
 }
 
 pub struct GLBufferDataReceiver {
-    object: Rc<GLBufferObject>,
+    // A copy of the requested range, taken when the readback was requested.
+    gl_copy_buffer: GLuint,
     gl_sync: GLsync,
-    range: Range<usize>,
-    target: BufferTarget,
+    byte_size: usize,
 }
 
 impl Drop for GLBufferDataReceiver {
     fn drop(&mut self) {
         unsafe {
+            gl::DeleteBuffers(1, &mut self.gl_copy_buffer); ck();
             gl::DeleteSync(self.gl_sync);
         }
     }
@@ -1527,6 +3218,7 @@ pub struct GLTextureDataReceiver {
     gl_sync: GLsync,
     size: Vector2I,
     format: TextureFormat,
+    alpha_mode: AlphaMode,
 }
 
 impl Drop for GLTextureDataReceiver {
@@ -1539,7 +3231,7 @@ impl Drop for GLTextureDataReceiver {
 }
 
 /// The version/dialect of OpenGL we should render with.
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 #[repr(u32)]
 pub enum GLVersion {
     /// OpenGL 3.0+, core profile.
@@ -1560,6 +3252,80 @@ impl GLVersion {
     }
 }
 
+/// Information about what the current OpenGL context actually supports.
+#[derive(Clone, Debug)]
+pub struct GLCapabilities {
+    /// The major and minor version of the context.
+    pub version: (u32, u32),
+    /// True if this is an OpenGL ES context.
+    pub es: bool,
+    /// The raw `GL_VERSION` string.
+    pub version_string: String,
+    /// The raw `GL_SHADING_LANGUAGE_VERSION` string.
+    pub shading_language_version_string: String,
+    /// The names of all supported extensions.
+    pub extensions: Vec<String>,
+}
+
+impl GLCapabilities {
+    fn query() -> GLCapabilities {
+        let version_string = get_gl_string(gl::VERSION);
+        let shading_language_version_string = get_gl_string(gl::SHADING_LANGUAGE_VERSION);
+
+        let es = version_string.starts_with("OpenGL ES");
+        let version = parse_gl_version(&version_string).unwrap_or_else(|| {
+            warn!("Couldn't parse OpenGL version string: {:?}", version_string);
+            (0, 0)
+        });
+
+        let mut extensions = vec![];
+        unsafe {
+            let mut extension_count = 0;
+            gl::GetIntegerv(gl::NUM_EXTENSIONS, &mut extension_count); ck();
+            for extension_index in 0..(extension_count.max(0) as GLuint) {
+                let extension = gl::GetStringi(gl::EXTENSIONS, extension_index); ck();
+                if !extension.is_null() {
+                    let extension = CStr::from_ptr(extension as *const c_char);
+                    extensions.push(extension.to_string_lossy().into_owned());
+                }
+            }
+        }
+
+        GLCapabilities { version, es, version_string, shading_language_version_string, extensions }
+    }
+
+    /// Returns true if the context is new enough to render with the given `GLVersion`.
+    pub fn supports(&self, version: GLVersion) -> bool {
+        match version {
+            GLVersion::GL3 => !self.es && self.version >= (3, 3),
+            GLVersion::GLES3 => self.es && self.version >= (3, 0),
+            GLVersion::GL4 => !self.es && self.version >= (4, 3),
+        }
+    }
+
+    /// Returns true if the context supports the named extension, e.g. `GL_ARB_debug_output`.
+    pub fn has_extension(&self, name: &str) -> bool {
+        self.extensions.iter().any(|extension| extension == name)
+    }
+
+    /// Returns true if textures can be filtered anisotropically, which is core in OpenGL 4.6 and
+    /// an extension everywhere else.
+    pub fn supports_anisotropic_filtering(&self) -> bool {
+        (!self.es && self.version >= (4, 6)) ||
+            self.has_extension("GL_ARB_texture_filter_anisotropic") ||
+            self.has_extension("GL_EXT_texture_filter_anisotropic")
+    }
+
+    /// Returns true if textures can clamp to a border color, which is core everywhere except in
+    /// OpenGL ES 3.0 and 3.1.
+    pub fn supports_border_clamp(&self) -> bool {
+        !self.es ||
+            self.version >= (3, 2) ||
+            self.has_extension("GL_OES_texture_border_clamp") ||
+            self.has_extension("GL_EXT_texture_border_clamp")
+    }
+}
+
 // Error checking
 
 #[cfg(debug_assertions)]
@@ -1588,6 +3354,40 @@ fn ck() {}
 
 // Utilities
 
+fn get_gl_string(name: GLenum) -> String {
+    unsafe {
+        let string = gl::GetString(name); ck();
+        if string.is_null() {
+            String::new()
+        } else {
+            CStr::from_ptr(string as *const c_char).to_string_lossy().into_owned()
+        }
+    }
+}
+
+// Extracts the major and minor version from a `GL_VERSION` string, which looks like
+// `4.6.0 NVIDIA 440.82` on desktop or `OpenGL ES 3.2 Mesa 20.0.8` on OpenGL ES.
+fn parse_gl_version(version_string: &str) -> Option<(u32, u32)> {
+    let version_string = version_string.trim_start_matches("OpenGL ES").trim_start();
+    let version_number = version_string.split_whitespace().next()?;
+    let mut components = version_number.split('.');
+    let major = components.next()?.parse().ok()?;
+    let minor = components.next()?.parse().ok()?;
+    Some((major, minor))
+}
+
+// The number of levels in a full mip chain, down to 1x1.
+fn mip_level_count(size: Vector2I) -> u32 {
+    let max_length = size.x().max(size.y()).max(1) as u32;
+    32 - max_length.leading_zeros()
+}
+
+// Returns the byte offset of the first index of an indexed draw, in the form `glDrawElements()`
+// expects.
+fn index_buffer_offset(render_state: &RenderState<GLDevice>) -> *const GLvoid {
+    (render_state.first_index as usize * mem::size_of::<u32>()) as *const GLvoid
+}
+
 // Flips a buffer of image data upside-down.
 fn flip_y<T>(pixels: &mut [T], size: Vector2I, channels: usize) {
     let stride = size.x() as usize * channels;
@@ -1598,3 +3398,183 @@ fn flip_y<T>(pixels: &mut [T], size: Vector2I, channels: usize) {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{GLDevice, GLVersion};
+    use euclid::default::Size2D;
+    use pathfinder_color::{ColorF, ColorU};
+    use pathfinder_geometry::rect::RectI;
+    use pathfinder_geometry::vector::{Vector2I, vec2i};
+    use pathfinder_gpu::{AlphaMode, Device, RenderTarget, TextureDataRef, TextureFormat};
+    use pathfinder_gpu::conformance;
+    use pathfinder_resources::embedded::EmbeddedResourceLoader;
+    use surfman::{ContextAttributeFlags, ContextAttributes, SurfaceAccess, SurfaceType};
+    use surfman::GLVersion as SurfmanGLVersion;
+
+    // Mesa's surfaceless platform needs no display server, which suits headless test machines.
+    #[cfg(target_os = "linux")]
+    use surfman::platform::unix::generic::connection::Connection;
+    #[cfg(not(target_os = "linux"))]
+    use surfman::Connection;
+
+    // Runs `f` between `begin_commands()` and `end_commands()` on a device with a headless OpenGL
+    // 3 context.
+    //
+    // Tests that use this are `#[ignore]`d, since not every machine can create a context. Run them
+    // with `cargo test -- --ignored`; they panic if no context can be created.
+    fn with_device<F>(f: F) where F: FnOnce(&GLDevice) {
+        let connection = Connection::new().expect("No OpenGL connection is available!");
+        let adapter = connection.create_adapter().unwrap();
+        let mut device = connection.create_device(&adapter).unwrap();
+
+        let context_attributes = ContextAttributes {
+            version: SurfmanGLVersion::new(3, 0),
+            flags: ContextAttributeFlags::empty(),
+        };
+        let context_descriptor = device.create_context_descriptor(&context_attributes).unwrap();
+        let mut context = device.create_context(&context_descriptor, None).unwrap();
+        let surface_type = SurfaceType::Generic { size: Size2D::new(1, 1) };
+        let surface = device.create_surface(&context, SurfaceAccess::GPUOnly, surface_type)
+                            .unwrap();
+        device.bind_surface_to_context(&mut context, surface).unwrap();
+        device.make_context_current(&context).unwrap();
+        gl::load_with(|symbol_name| device.get_proc_address(&context, symbol_name));
+
+        let default_framebuffer = device.context_surface_info(&context)
+                                        .unwrap()
+                                        .unwrap()
+                                        .framebuffer_object;
+        let gl_device = GLDevice::new(GLVersion::GL3, default_framebuffer);
+        gl_device.begin_commands();
+        f(&gl_device);
+        gl_device.end_commands();
+
+        drop(gl_device);
+        drop(device.destroy_context(&mut context));
+    }
+
+    #[test]
+    #[ignore]
+    fn test_format_conformance() {
+        with_device(conformance::run_format_conformance);
+    }
+
+    #[test]
+    #[ignore]
+    fn test_rg8_round_trip() {
+        with_device(|device| {
+            conformance::check_format_round_trip(device, TextureFormat::RG8).unwrap()
+        });
+    }
+
+    #[test]
+    #[ignore]
+    fn test_rg16f_round_trip() {
+        with_device(|device| {
+            conformance::check_format_round_trip(device, TextureFormat::RG16F).unwrap()
+        });
+    }
+
+    #[test]
+    #[ignore]
+    fn test_mipmap_generation() {
+        with_device(|device| {
+            let resources = EmbeddedResourceLoader::new();
+            conformance::check_mipmap_generation(device, &resources).unwrap()
+        });
+    }
+
+    #[test]
+    #[ignore]
+    fn test_srgb_sampling() {
+        with_device(|device| {
+            let resources = EmbeddedResourceLoader::new();
+            conformance::check_srgb_sampling(device, &resources).unwrap()
+        });
+    }
+
+    #[test]
+    #[ignore]
+    fn test_constant_blend_color() {
+        with_device(|device| {
+            let resources = EmbeddedResourceLoader::new();
+            conformance::check_constant_blend_color(device, &resources).unwrap()
+        });
+    }
+
+    #[test]
+    #[ignore]
+    fn test_scissored_clear() {
+        with_device(|device| {
+            let resources = EmbeddedResourceLoader::new();
+            conformance::check_scissored_clear(device, &resources).unwrap()
+        });
+    }
+
+    // The OpenGL 3 device emulates base instances by offsetting the instanced attributes.
+    #[test]
+    #[ignore]
+    fn test_base_instance() {
+        with_device(|device| {
+            let resources = EmbeddedResourceLoader::new();
+            conformance::check_base_instance(device, &resources).unwrap()
+        });
+    }
+
+    #[test]
+    #[ignore]
+    fn test_upload_to_data_texture() {
+        with_device(|device| {
+            let size = Vector2I::splat(4);
+            let texture = device.create_texture_from_fn(TextureFormat::RGBA8,
+                                                        size,
+                                                        |_| ColorU::black());
+            let upload_rect = RectI::new(Vector2I::splat(1), Vector2I::splat(2));
+            device.upload_to_texture(&texture, upload_rect, TextureDataRef::U8(&[255; 2 * 2 * 4]));
+
+            // The upload is centered vertically, so the orientation of the readback doesn't
+            // matter.
+            let framebuffer = device.create_framebuffer(texture);
+            let receiver = device.read_pixels(&RenderTarget::Framebuffer(&framebuffer),
+                                              RectI::new(Vector2I::zero(), size),
+                                              AlphaMode::Premultiplied);
+            let pixels = device.recv_texture_data(&receiver).pixels_as_colorf(size);
+            for (index, pixel) in pixels.into_iter().enumerate() {
+                let position = vec2i(index as i32 % size.x(), index as i32 / size.x());
+                let expected = if upload_rect.contains_point(position) {
+                    ColorF::white()
+                } else {
+                    ColorF::black()
+                };
+                assert_eq!(pixel, expected, "Texel at {:?} is wrong!", position);
+            }
+        });
+    }
+
+    #[test]
+    #[ignore]
+    fn test_frame_stats_count_outermost_scopes() {
+        with_device(|device| {
+            device.end_commands();
+
+            device.reset_frame_stats();
+            for _ in 0..2 {
+                device.begin_commands();
+                device.begin_commands();
+                device.end_commands();
+                device.end_commands();
+            }
+            let frame_stats = device.collect_frame_stats();
+            assert_eq!(frame_stats.scope_count, 2);
+
+            // Collecting stops the timing until the next reset.
+            device.begin_commands();
+            device.end_commands();
+            device.reset_frame_stats();
+            assert_eq!(device.collect_frame_stats().scope_count, 0);
+
+            device.begin_commands();
+        });
+    }
+}
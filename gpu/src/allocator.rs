@@ -318,7 +318,7 @@ impl<D> GPUMemoryAllocator<D> where D: Device {
 
         debug!("mapping framebuffer: {:?} {:?}", descriptor, tag);
 
-        let texture = device.create_texture(format, size);
+        let texture = device.create_render_target_texture(format, size);
         let framebuffer = device.create_framebuffer(texture);
         let id = self.next_framebuffer_id;
         self.next_framebuffer_id.0 += 1;
@@ -0,0 +1,516 @@
+// pathfinder/gpu/src/conformance.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Conformance checks that backends can run against a live device, to catch bugs such as swapped
+//! channels in format mappings or missing mip levels.
+
+use crate::{AlphaMode, BlendFactor, BlendOp, BlendState, BufferData, BufferTarget};
+use crate::{BufferUploadMode, ClearOps, Device, Primitive};
+use crate::{RenderOptions, RenderState, RenderTarget, TextureFormat, TextureSamplingFlags};
+use crate::{TextureUsage, UniformData, VertexAttrClass, VertexAttrDescriptor, VertexAttrType};
+use pathfinder_color::{ColorF, ColorU};
+use pathfinder_geometry::rect::RectI;
+use pathfinder_geometry::vector::{Vector2I, vec2i};
+use pathfinder_resources::ResourceLoader;
+use pathfinder_simd::default::{F32x2, F32x4};
+
+const TEXTURE_FORMATS: [TextureFormat; 11] = [
+    TextureFormat::R8,
+    TextureFormat::R16,
+    TextureFormat::R16F,
+    TextureFormat::RG8,
+    TextureFormat::RG16F,
+    TextureFormat::RGBA8,
+    TextureFormat::RGBA8Srgb,
+    TextureFormat::BGRA8,
+    TextureFormat::RGBA16,
+    TextureFormat::RGBA16F,
+    TextureFormat::RGBA32F,
+];
+
+const PATTERN_WIDTH: i32 = 16;
+const PATTERN_HEIGHT: i32 = 4;
+
+// Loose enough for half floats, which have 11 bits of precision.
+const TOLERANCE: f32 = 1.0 / 512.0;
+
+// The target is half the size of the texture, so that each target pixel samples one texel of
+// the second level.
+const MIPMAP_TEXTURE_LENGTH: i32 = 8;
+const MIPMAP_TARGET_LENGTH: i32 = MIPMAP_TEXTURE_LENGTH / 2;
+
+// Loose enough for drivers that round each level of an 8-bit mip chain.
+const MIPMAP_TOLERANCE: f32 = 1.0 / 32.0;
+
+// An sRGB-encoded value that decodes to roughly half intensity.
+const SRGB_TEXEL: u8 = 188;
+const SRGB_TARGET_LENGTH: i32 = 4;
+
+// Loose enough for the rounding of an 8-bit render target.
+const SRGB_TOLERANCE: f32 = 1.0 / 128.0;
+
+const BLEND_TARGET_LENGTH: i32 = 4;
+const BLEND_CONSTANT: f32 = 0.5;
+
+// Loose enough for the rounding of an 8-bit render target.
+const BLEND_TOLERANCE: f32 = 1.0 / 128.0;
+
+// The scissored square is centered, so that it's in the same place whether or not rows are read
+// back upside down.
+const SCISSOR_TARGET_LENGTH: i32 = 32;
+const SCISSOR_LENGTH: i32 = 10;
+const SCISSOR_ORIGIN: i32 = (SCISSOR_TARGET_LENGTH - SCISSOR_LENGTH) / 2;
+
+// One column per instance, each `BASE_INSTANCE_COLUMN_WIDTH` pixels wide.
+const BASE_INSTANCE_COLUMN_COUNT: i32 = 4;
+const BASE_INSTANCE_COLUMN_WIDTH: i32 = 4;
+
+// The offset and edge distance of each instance, in columns.
+static BASE_INSTANCE_ATTRS: [f32; 12] = [
+    0.0, 0.0, 0.0,
+    1.0, 0.0, 0.0,
+    2.0, 0.0, 0.0,
+    3.0, 0.0, 0.0,
+];
+
+static QUAD_VERTEX_POSITIONS: [u16; 8] = [0, 0, 1, 0, 1, 1, 0, 1];
+static QUAD_VERTEX_INDICES: [u32; 6] = [0, 1, 3, 1, 2, 3];
+
+/// Checks that every texture format that `device` can both sample and render to survives a round
+/// trip: `texture_format()` reports the format that the texture was created with, and
+/// `read_pixels()` returns the texels that were uploaded. Panics with a list of the formats that
+/// fail.
+///
+/// Backends should call this from tests that have a device available. Like `read_pixels()`, it
+/// must be called between `begin_commands()` and `end_commands()`.
+pub fn run_format_conformance<D>(device: &D) where D: Device {
+    let failures: Vec<String> = TEXTURE_FORMATS.iter().filter(|&&format| {
+        device.supports_texture_format(format, TextureUsage::Sampled) &&
+            device.supports_texture_format(format, TextureUsage::RenderTarget)
+    }).filter_map(|&format| check_format_round_trip(device, format).err()).collect();
+
+    assert!(failures.is_empty(), "Texture format conformance failed:\n{}", failures.join("\n"));
+}
+
+/// Uploads a pattern to a texture of the given format and reads it back, returning a
+/// description of the first mismatch if there is one.
+pub fn check_format_round_trip<D>(device: &D, format: TextureFormat) -> Result<(), String>
+                                  where D: Device {
+    let size = vec2i(PATTERN_WIDTH, PATTERN_HEIGHT);
+    let texture = device.create_texture_from_fn(format, size, |coord| pattern(coord.x()));
+    let reported_format = device.texture_format(&texture);
+    if reported_format != format {
+        return Err(format!("{:?} textures report their format as {:?}", format, reported_format));
+    }
+
+    let framebuffer = device.create_framebuffer(texture);
+    let viewport = RectI::new(Vector2I::zero(), size);
+    let receiver = device.read_pixels(&RenderTarget::Framebuffer(&framebuffer),
+                                      viewport,
+                                      AlphaMode::Premultiplied);
+    let pixels = device.recv_texture_data(&receiver).pixels_as_colorf(size);
+
+    // Backends differ in the vertical orientation of framebuffer readback, so every row of the
+    // pattern is the same and only the columns are compared.
+    for (index, pixel) in pixels.into_iter().enumerate() {
+        let x = index as i32 % PATTERN_WIDTH;
+        // `BGRA8` data is read back in memory order.
+        let mut actual = pixel;
+        if format == TextureFormat::BGRA8 {
+            actual = ColorF::new(actual.b(), actual.g(), actual.r(), actual.a());
+        }
+        let expected = expected_color(format, pattern(x));
+        if !(actual.0 - expected.0).abs().packed_le(F32x4::splat(TOLERANCE)).all_true() {
+            return Err(format!("{:?} texel at column {} read back as {:?} instead of {:?}",
+                               format,
+                               x,
+                               actual,
+                               expected));
+        }
+    }
+    Ok(())
+}
+
+/// Generates the mip chain of an 8x8 texture, checks that it has the full four levels, and reads
+/// back its second level, by drawing the texture into a 4x4 render target with the `blit` shader
+/// and nearest mip filtering. Returns a description of the first mismatch if there is one.
+///
+/// Every fourth column of the texture is white and the rest are black. Each texel of the second
+/// level averages two columns, so its columns alternate between half intensity and black, while
+/// sampling the base level instead would read back only black. Like `read_pixels()`, this must be
+/// called between `begin_commands()` and `end_commands()`.
+pub fn check_mipmap_generation<D>(device: &D, resources: &dyn ResourceLoader)
+                                  -> Result<(), String> where D: Device {
+    let texture_size = Vector2I::splat(MIPMAP_TEXTURE_LENGTH);
+    let texture = device.create_texture_from_fn(TextureFormat::RGBA8, texture_size, |coord| {
+        if coord.x() % 4 == 1 { ColorU::white() } else { ColorU::black() }
+    });
+    device.generate_mipmaps(&texture);
+    let level_count = device.texture_level_count(&texture);
+    if level_count != 4 {
+        return Err(format!("The mip chain has {} levels instead of 4", level_count));
+    }
+    let level_size = device.texture_level_size(&texture, 1);
+    if level_size != Vector2I::splat(MIPMAP_TARGET_LENGTH) {
+        return Err(format!("The second mip level is {:?} in size instead of {:?}",
+                           level_size,
+                           Vector2I::splat(MIPMAP_TARGET_LENGTH)));
+    }
+    device.set_texture_sampling_mode(&texture,
+                                     TextureSamplingFlags::MIPMAP |
+                                     TextureSamplingFlags::NEAREST_MIN);
+
+    let target_size = Vector2I::splat(MIPMAP_TARGET_LENGTH);
+    let pixels =
+        blit_and_read_back(device, resources, &texture, target_size, RenderOptions::default());
+    for (index, pixel) in pixels.into_iter().enumerate() {
+        let x = index as i32 % MIPMAP_TARGET_LENGTH;
+        let expected = if x % 2 == 0 { 0.5 } else { 0.0 };
+        if (pixel.r() - expected).abs() > MIPMAP_TOLERANCE {
+            return Err(format!("Second-level texel at column {} sampled as {:?} instead of {}",
+                               x,
+                               pixel,
+                               expected));
+        }
+    }
+    Ok(())
+}
+
+/// Checks that `TextureFormat::RGBA8Srgb` textures keep their raw bytes through an upload and
+/// `read_pixels()`, while shaders sample them converted to linear. Returns a description of the
+/// first mismatch if there is one.
+///
+/// Like `read_pixels()`, this must be called between `begin_commands()` and `end_commands()`.
+pub fn check_srgb_sampling<D>(device: &D, resources: &dyn ResourceLoader)
+                              -> Result<(), String> where D: Device {
+    let format = TextureFormat::RGBA8Srgb;
+    let texel = ColorU::new(SRGB_TEXEL, SRGB_TEXEL, SRGB_TEXEL, 255);
+    let target_size = Vector2I::splat(SRGB_TARGET_LENGTH);
+    let texture = device.create_texture_from_fn(format, target_size, |_| texel);
+
+    let framebuffer = device.create_framebuffer(texture);
+    let viewport = RectI::new(Vector2I::zero(), target_size);
+    let receiver = device.read_pixels(&RenderTarget::Framebuffer(&framebuffer),
+                                      viewport,
+                                      AlphaMode::Premultiplied);
+    let raw_pixels = device.recv_texture_data(&receiver).pixels_as_colorf(target_size);
+    let expected_raw = texel.to_f32();
+    for (index, pixel) in raw_pixels.into_iter().enumerate() {
+        if !(pixel.0 - expected_raw.0).abs().packed_le(F32x4::splat(TOLERANCE)).all_true() {
+            return Err(format!("sRGB texel {} read back as {:?} instead of {:?}",
+                               index,
+                               pixel,
+                               expected_raw));
+        }
+    }
+
+    let texture = device.destroy_framebuffer(framebuffer);
+    let expected_linear = texel.to_f32().from_srgb().r();
+    let pixels =
+        blit_and_read_back(device, resources, &texture, target_size, RenderOptions::default());
+    for (index, pixel) in pixels.into_iter().enumerate() {
+        if (pixel.r() - expected_linear).abs() > SRGB_TOLERANCE {
+            return Err(format!("sRGB texel {} sampled as {:?} instead of {}",
+                               index,
+                               pixel,
+                               expected_linear));
+        }
+    }
+    Ok(())
+}
+
+/// Checks that `BlendFactor::ConstantColor` and `BlendFactor::OneMinusConstantColor` blend with
+/// `RenderOptions::blend_color`, by blending a white quad halfway over a black target. Returns a
+/// description of the first mismatch if there is one.
+///
+/// Like `read_pixels()`, this must be called between `begin_commands()` and `end_commands()`.
+pub fn check_constant_blend_color<D>(device: &D, resources: &dyn ResourceLoader)
+                                     -> Result<(), String> where D: Device {
+    let target_size = Vector2I::splat(BLEND_TARGET_LENGTH);
+    let texture = device.create_texture_from_fn(TextureFormat::RGBA8,
+                                                target_size,
+                                                |_| ColorU::white());
+
+    let options = RenderOptions {
+        blend: Some(BlendState {
+            src_rgb_factor: BlendFactor::ConstantColor,
+            dest_rgb_factor: BlendFactor::OneMinusConstantColor,
+            src_alpha_factor: BlendFactor::One,
+            dest_alpha_factor: BlendFactor::Zero,
+            op: BlendOp::Add,
+        }),
+        blend_color: ColorF(F32x4::splat(BLEND_CONSTANT)),
+        clear_ops: ClearOps { color: Some(ColorF::black()), ..ClearOps::default() },
+        ..RenderOptions::default()
+    };
+    let pixels = blit_and_read_back(device, resources, &texture, target_size, options);
+    for (index, pixel) in pixels.into_iter().enumerate() {
+        if (pixel.r() - BLEND_CONSTANT).abs() > BLEND_TOLERANCE {
+            return Err(format!("Pixel {} blended to {:?} instead of {}",
+                               index,
+                               pixel,
+                               BLEND_CONSTANT));
+        }
+    }
+    Ok(())
+}
+
+/// Checks that clears in `RenderOptions::clear_ops` only affect the pixels inside
+/// `RenderOptions::scissor`, by clearing a 10x10 square in the middle of a black target to white.
+/// Returns a description of the first mismatch if there is one.
+///
+/// Like `read_pixels()`, this must be called between `begin_commands()` and `end_commands()`.
+/// Metal can't scissor clears, so this is unsupported there.
+pub fn check_scissored_clear<D>(device: &D, resources: &dyn ResourceLoader)
+                                -> Result<(), String> where D: Device {
+    let target_size = Vector2I::splat(SCISSOR_TARGET_LENGTH);
+    let framebuffer =
+        device.create_framebuffer(device.create_render_target_texture(TextureFormat::RGBA8,
+                                                                      target_size));
+    let target = RenderTarget::Framebuffer(&framebuffer);
+    let viewport = RectI::new(Vector2I::zero(), target_size);
+    device.clear(&target, &ClearOps { color: Some(ColorF::black()), ..ClearOps::default() });
+
+    // The draw itself is masked out, so only the clear writes any pixels.
+    let scissor = RectI::new(Vector2I::splat(SCISSOR_ORIGIN), Vector2I::splat(SCISSOR_LENGTH));
+    let texture = device.create_texture_from_fn(TextureFormat::RGBA8,
+                                                target_size,
+                                                |_| ColorU::black());
+    blit(device, resources, &texture, &target, viewport, RenderOptions {
+        clear_ops: ClearOps { color: Some(ColorF::white()), ..ClearOps::default() },
+        scissor: Some(scissor),
+        color_mask: false,
+        ..RenderOptions::default()
+    });
+
+    let receiver = device.read_pixels(&target, viewport, AlphaMode::Premultiplied);
+    let pixels = device.recv_texture_data(&receiver).pixels_as_colorf(target_size);
+    for (index, pixel) in pixels.into_iter().enumerate() {
+        let position = vec2i(index as i32 % SCISSOR_TARGET_LENGTH,
+                             index as i32 / SCISSOR_TARGET_LENGTH);
+        let expected = if scissor.contains_point(position) { 1.0 } else { 0.0 };
+        if (pixel.r() - expected).abs() > TOLERANCE {
+            return Err(format!("Pixel at {:?} read back as {:?} instead of {}",
+                               position,
+                               pixel,
+                               expected));
+        }
+    }
+    Ok(())
+}
+
+/// Checks that `draw_elements_instanced_base()` starts at the given instance, by drawing only the
+/// second column with one call and only the fourth with another. Returns a description of the
+/// first mismatch if there is one.
+///
+/// Like `read_pixels()`, this must be called between `begin_commands()` and `end_commands()`.
+pub fn check_base_instance<D>(device: &D, resources: &dyn ResourceLoader)
+                              -> Result<(), String> where D: Device {
+    let program = device.create_raster_program(resources, "debug/solid");
+    let framebuffer_size_uniform = device.get_uniform(&program, "FramebufferSize");
+    let transform_uniform = device.get_uniform(&program, "Transform");
+    let translation_uniform = device.get_uniform(&program, "Translation");
+    let color_uniform = device.get_uniform(&program, "Color");
+    let position_attr = device.get_vertex_attr(&program, "Position").unwrap();
+    let offset_attr = device.get_vertex_attr(&program, "Offset").unwrap();
+    let edge_distance_attr = device.get_vertex_attr(&program, "EdgeDistance").unwrap();
+
+    let quad_vertex_positions_buffer = device.create_buffer(BufferUploadMode::Static);
+    device.allocate_buffer(&quad_vertex_positions_buffer,
+                           BufferData::Memory(&QUAD_VERTEX_POSITIONS),
+                           BufferTarget::Vertex);
+    let instance_buffer = device.create_buffer(BufferUploadMode::Static);
+    device.allocate_buffer(&instance_buffer,
+                           BufferData::Memory(&BASE_INSTANCE_ATTRS),
+                           BufferTarget::Vertex);
+    let quad_vertex_indices_buffer = device.create_buffer(BufferUploadMode::Static);
+    device.allocate_buffer(&quad_vertex_indices_buffer,
+                           BufferData::Memory(&QUAD_VERTEX_INDICES),
+                           BufferTarget::Index);
+
+    let vertex_array = device.create_vertex_array();
+    device.bind_buffer(&vertex_array, &quad_vertex_positions_buffer, BufferTarget::Vertex);
+    device.configure_vertex_attr(&vertex_array, &position_attr, &VertexAttrDescriptor {
+        size: 2,
+        class: VertexAttrClass::Int,
+        attr_type: VertexAttrType::I16,
+        stride: 4,
+        offset: 0,
+        divisor: 0,
+        buffer_index: 0,
+    });
+    device.bind_buffer(&vertex_array, &instance_buffer, BufferTarget::Vertex);
+    device.configure_vertex_attr(&vertex_array, &offset_attr, &VertexAttrDescriptor {
+        size: 2,
+        class: VertexAttrClass::Float,
+        attr_type: VertexAttrType::F32,
+        stride: 12,
+        offset: 0,
+        divisor: 1,
+        buffer_index: 1,
+    });
+    device.configure_vertex_attr(&vertex_array, &edge_distance_attr, &VertexAttrDescriptor {
+        size: 1,
+        class: VertexAttrClass::Float,
+        attr_type: VertexAttrType::F32,
+        stride: 12,
+        offset: 8,
+        divisor: 1,
+        buffer_index: 1,
+    });
+    device.bind_buffer(&vertex_array, &quad_vertex_indices_buffer, BufferTarget::Index);
+
+    let target_size = vec2i(BASE_INSTANCE_COLUMN_COUNT * BASE_INSTANCE_COLUMN_WIDTH,
+                            BASE_INSTANCE_COLUMN_WIDTH);
+    let framebuffer =
+        device.create_framebuffer(device.create_render_target_texture(TextureFormat::RGBA8,
+                                                                      target_size));
+    let target = RenderTarget::Framebuffer(&framebuffer);
+    let viewport = RectI::new(Vector2I::zero(), target_size);
+
+    let column_width = BASE_INSTANCE_COLUMN_WIDTH as f32;
+    for &(base_instance, clear_ops) in &[
+        (1, ClearOps { color: Some(ColorF::black()), ..ClearOps::default() }),
+        (3, ClearOps::default()),
+    ] {
+        let render_state = RenderState {
+            target: &target,
+            program: &program,
+            vertex_array: &vertex_array,
+            primitive: Primitive::Triangles,
+            first_index: 0,
+            textures: &[],
+            images: &[],
+            storage_buffers: &[],
+            uniforms: &[
+                (&framebuffer_size_uniform, UniformData::Vec2(target_size.to_f32().0)),
+                (&transform_uniform,
+                 UniformData::Mat2(F32x4::new(column_width, 0.0, 0.0, column_width))),
+                (&translation_uniform, UniformData::Vec2(F32x2::default())),
+                (&color_uniform, UniformData::Vec4(ColorF::white().0)),
+            ],
+            viewport,
+            options: RenderOptions { clear_ops, ..RenderOptions::default() },
+        };
+        device.draw_elements_instanced_base(QUAD_VERTEX_INDICES.len() as u32,
+                                            1,
+                                            base_instance,
+                                            &render_state);
+    }
+
+    let receiver = device.read_pixels(&target, viewport, AlphaMode::Premultiplied);
+    let pixels = device.recv_texture_data(&receiver).pixels_as_colorf(target_size);
+    for (index, pixel) in pixels.into_iter().enumerate() {
+        let column = index as i32 % target_size.x() / BASE_INSTANCE_COLUMN_WIDTH;
+        let expected = if column % 2 == 1 { 1.0 } else { 0.0 };
+        if (pixel.r() - expected).abs() > TOLERANCE {
+            return Err(format!("Pixel {} in column {} read back as {:?} instead of {}",
+                               index,
+                               column,
+                               pixel,
+                               expected));
+        }
+    }
+    Ok(())
+}
+
+// Draws `texture` over a new `RGBA8` render target of the given size with the `blit` shader and
+// reads the result back.
+fn blit_and_read_back<D>(device: &D,
+                         resources: &dyn ResourceLoader,
+                         texture: &D::Texture,
+                         target_size: Vector2I,
+                         options: RenderOptions)
+                         -> Vec<ColorF>
+                         where D: Device {
+    let framebuffer =
+        device.create_framebuffer(device.create_render_target_texture(TextureFormat::RGBA8,
+                                                                      target_size));
+    let target = RenderTarget::Framebuffer(&framebuffer);
+    let viewport = RectI::new(Vector2I::zero(), target_size);
+    blit(device, resources, texture, &target, viewport, options);
+
+    let receiver = device.read_pixels(&target, viewport, AlphaMode::Premultiplied);
+    device.recv_texture_data(&receiver).pixels_as_colorf(target_size)
+}
+
+// Draws `texture` over all of `viewport` with the `blit` shader.
+fn blit<D>(device: &D,
+           resources: &dyn ResourceLoader,
+           texture: &D::Texture,
+           target: &RenderTarget<D>,
+           viewport: RectI,
+           options: RenderOptions)
+           where D: Device {
+    let program = device.create_raster_program(resources, "blit");
+    let dest_rect_uniform = device.get_uniform(&program, "DestRect");
+    let framebuffer_size_uniform = device.get_uniform(&program, "FramebufferSize");
+    let src_texture = device.get_texture_parameter(&program, "Src");
+    let position_attr = device.get_vertex_attr(&program, "Position").unwrap();
+
+    let quad_vertex_positions_buffer = device.create_buffer(BufferUploadMode::Static);
+    device.allocate_buffer(&quad_vertex_positions_buffer,
+                           BufferData::Memory(&QUAD_VERTEX_POSITIONS),
+                           BufferTarget::Vertex);
+    let quad_vertex_indices_buffer = device.create_buffer(BufferUploadMode::Static);
+    device.allocate_buffer(&quad_vertex_indices_buffer,
+                           BufferData::Memory(&QUAD_VERTEX_INDICES),
+                           BufferTarget::Index);
+
+    let vertex_array = device.create_vertex_array();
+    device.bind_buffer(&vertex_array, &quad_vertex_positions_buffer, BufferTarget::Vertex);
+    device.configure_vertex_attr(&vertex_array, &position_attr, &VertexAttrDescriptor {
+        size: 2,
+        class: VertexAttrClass::Int,
+        attr_type: VertexAttrType::I16,
+        stride: 4,
+        offset: 0,
+        divisor: 0,
+        buffer_index: 0,
+    });
+    device.bind_buffer(&vertex_array, &quad_vertex_indices_buffer, BufferTarget::Index);
+
+    let target_size_f32 = viewport.size().to_f32();
+    device.draw_elements(QUAD_VERTEX_INDICES.len() as u32, &RenderState {
+        target,
+        program: &program,
+        vertex_array: &vertex_array,
+        primitive: Primitive::Triangles,
+        first_index: 0,
+        textures: &[(&src_texture, texture)],
+        images: &[],
+        storage_buffers: &[],
+        uniforms: &[
+            (&framebuffer_size_uniform, UniformData::Vec2(target_size_f32.0)),
+            (&dest_rect_uniform, UniformData::Vec4(F32x4::new(0.0,
+                                                              0.0,
+                                                              target_size_f32.x(),
+                                                              target_size_f32.y()))),
+        ],
+        viewport,
+        options,
+    });
+}
+
+// Gives every column and channel a distinct value, so that swapped channels are detected.
+fn pattern(x: i32) -> ColorU {
+    let x = x as u32;
+    ColorU::new((x * 16) as u8, (255 - x * 16) as u8, (x * 16 + 128) as u8, (255 - x * 4) as u8)
+}
+
+// Missing channels read back as zero, except for alpha, which reads back as opaque.
+fn expected_color(format: TextureFormat, color: ColorU) -> ColorF {
+    let color = color.to_f32();
+    match format.channels() {
+        1 => ColorF::new(color.r(), 0.0, 0.0, 1.0),
+        2 => ColorF::new(color.r(), color.g(), 0.0, 1.0),
+        _ => color,
+    }
+}
@@ -0,0 +1,163 @@
+// pathfinder/gpu/src/fence.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Frame pacing with fences.
+
+use crate::Device;
+
+/// The fence operations that `FrameFenceRing` is built on. Every `Device` provides them.
+pub trait FenceDevice {
+    type Fence;
+    fn add_fence(&self) -> Self::Fence;
+    /// Blocks until the GPU has passed `fence`.
+    fn wait_for_fence(&self, fence: &Self::Fence);
+    /// Returns true if the GPU has passed `fence`, without blocking.
+    fn try_wait_for_fence(&self, fence: &Self::Fence) -> bool;
+}
+
+impl<D> FenceDevice for D where D: Device {
+    type Fence = D::Fence;
+
+    #[inline]
+    fn add_fence(&self) -> D::Fence {
+        Device::add_fence(self)
+    }
+
+    #[inline]
+    fn wait_for_fence(&self, fence: &D::Fence) {
+        Device::wait_for_fence(self, fence)
+    }
+
+    #[inline]
+    fn try_wait_for_fence(&self, fence: &D::Fence) -> bool {
+        Device::try_wait_for_fence(self, fence)
+    }
+}
+
+/// Keeps the CPU at most a fixed number of frames ahead of the GPU.
+///
+/// Call `begin_frame()` before recording each frame and `end_frame()` after submitting it. With
+/// three frames in flight, for example, this implements the usual triple-buffering pattern:
+/// resources used by a frame can be safely reused three frames later.
+pub struct FrameFenceRing<D> where D: FenceDevice {
+    fences: Vec<Option<D::Fence>>,
+    next_index: usize,
+}
+
+impl<D> FrameFenceRing<D> where D: FenceDevice {
+    pub fn new(frames_in_flight: usize) -> FrameFenceRing<D> {
+        assert!(frames_in_flight > 0, "There must be at least one frame in flight!");
+        let mut fences = Vec::with_capacity(frames_in_flight);
+        fences.resize_with(frames_in_flight, || None);
+        FrameFenceRing { fences, next_index: 0 }
+    }
+
+    #[inline]
+    pub fn frames_in_flight(&self) -> usize {
+        self.fences.len()
+    }
+
+    /// Blocks until the GPU has finished the frame that was submitted `frames_in_flight` frames
+    /// ago.
+    ///
+    /// During the first `frames_in_flight` frames, there is nothing to wait for, so this returns
+    /// immediately.
+    pub fn begin_frame(&mut self, device: &D) {
+        if let Some(fence) = self.fences[self.next_index].take() {
+            device.wait_for_fence(&fence);
+        }
+    }
+
+    /// Like `begin_frame()`, but returns false instead of blocking if the GPU hasn't finished that
+    /// frame yet. Callers can do other work and try again, calling `begin_frame()` only as a last
+    /// resort.
+    pub fn try_begin_frame(&mut self, device: &D) -> bool {
+        let fence = &mut self.fences[self.next_index];
+        if let Some(ref pending_fence) = *fence {
+            if !device.try_wait_for_fence(pending_fence) {
+                return false;
+            }
+        }
+        *fence = None;
+        true
+    }
+
+    /// Inserts a fence marking the end of the current frame.
+    pub fn end_frame(&mut self, device: &D) {
+        self.fences[self.next_index] = Some(device.add_fence());
+        self.next_index = (self.next_index + 1) % self.fences.len();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{FenceDevice, FrameFenceRing};
+    use std::cell::{Cell, RefCell};
+
+    // Hands out numbered fences, which the "GPU" passes when told to, and records every wait.
+    #[derive(Default)]
+    struct MockDevice {
+        next_fence: Cell<u32>,
+        passed_fence_count: Cell<u32>,
+        waited_fences: RefCell<Vec<u32>>,
+    }
+
+    impl FenceDevice for MockDevice {
+        type Fence = u32;
+
+        fn add_fence(&self) -> u32 {
+            let fence = self.next_fence.get();
+            self.next_fence.set(fence + 1);
+            fence
+        }
+
+        fn wait_for_fence(&self, fence: &u32) {
+            self.waited_fences.borrow_mut().push(*fence);
+            self.passed_fence_count.set(self.passed_fence_count.get().max(*fence + 1));
+        }
+
+        fn try_wait_for_fence(&self, fence: &u32) -> bool {
+            *fence < self.passed_fence_count.get()
+        }
+    }
+
+    #[test]
+    fn test_begin_frame_waits_for_frame_in_flight() {
+        let device = MockDevice::default();
+        let mut ring = FrameFenceRing::new(3);
+        for _ in 0..6 {
+            ring.begin_frame(&device);
+            ring.end_frame(&device);
+        }
+        // The first three frames have nothing to wait for. After that, each frame waits for the
+        // one three frames before it.
+        assert_eq!(*device.waited_fences.borrow(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_try_begin_frame() {
+        let device = MockDevice::default();
+        let mut ring = FrameFenceRing::new(2);
+        for _ in 0..2 {
+            assert!(ring.try_begin_frame(&device));
+            ring.end_frame(&device);
+        }
+
+        assert!(!ring.try_begin_frame(&device));
+        device.passed_fence_count.set(1);
+        assert!(ring.try_begin_frame(&device));
+        ring.end_frame(&device);
+
+        // The fence that was passed isn't waited on again.
+        assert!(!ring.try_begin_frame(&device));
+        ring.begin_frame(&device);
+        assert_eq!(*device.waited_fences.borrow(), vec![1]);
+    }
+}
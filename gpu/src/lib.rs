@@ -16,10 +16,14 @@ extern crate bitflags;
 extern crate log;
 
 pub mod allocator;
+pub mod conformance;
+pub mod fence;
+pub mod readback;
+pub mod reduce;
 
 use half::f16;
 use image::ImageFormat;
-use pathfinder_color::ColorF;
+use pathfinder_color::{ColorF, ColorU, color_slice_to_u8_slice};
 use pathfinder_geometry::rect::RectI;
 use pathfinder_geometry::transform3d::Transform4F;
 use pathfinder_geometry::vector::{Vector2I, vec2i};
@@ -35,6 +39,8 @@ pub trait Device: Sized {
     type Fence;
     type Framebuffer;
     type ImageParameter;
+    type OcclusionQuery;
+    type PipelineStatsQuery;
     type Program;
     type Shader;
     type StorageBuffer;
@@ -49,9 +55,91 @@ pub trait Device: Sized {
     fn backend_name(&self) -> &'static str;
     fn device_name(&self) -> String;
     fn feature_level(&self) -> FeatureLevel;
-    fn create_texture(&self, format: TextureFormat, size: Vector2I) -> Self::Texture;
+    fn supports_feature(&self, feature: Feature) -> bool;
+    fn storage_buffer_offset_alignment(&self) -> usize;
+    /// Returns the resource limits of this device, so that callers can clamp texture sizes,
+    /// buffer sizes, and dispatch dimensions instead of triggering driver errors.
+    fn device_limits(&self) -> DeviceLimits;
+    /// Returns true if textures of the given format can be sampled with linear filtering.
+    ///
+    /// Callers should use `TextureSamplingFlags::NEAREST_MIN` and `NEAREST_MAG` for textures of
+    /// other formats, since drivers may otherwise either fail or silently fall back to nearest.
+    fn format_is_filterable(&self, format: TextureFormat) -> bool;
+    /// Returns true if textures of the given format can be created on this device and used in
+    /// the given way.
+    ///
+    /// The texture creation methods panic or fail for unsupported formats, so callers that can
+    /// fall back to another format (for example, `RGBA8` instead of `RGBA16F`) should check first.
+    fn supports_texture_format(&self, format: TextureFormat, usage: TextureUsage) -> bool;
+    /// Returns true if `generate_mipmaps()` can be used on textures whose dimensions aren't
+    /// powers of two.
+    ///
+    /// Callers should pad textures to power-of-two sizes before mipmapping them otherwise.
+    fn supports_non_power_of_two_mipmaps(&self) -> bool;
+    /// Returns the sample counts that multisampled textures of the given format support on this
+    /// device, in increasing order. The list always includes 1, for single-sampled textures.
+    fn supported_sample_counts(&self, format: TextureFormat) -> Vec<u32>;
+    /// Returns the largest supported sample count for `format` that doesn't exceed
+    /// `sample_count`.
+    ///
+    /// Callers that need exactly `sample_count` should check `supported_sample_counts()` instead.
+    fn clamp_sample_count(&self, format: TextureFormat, sample_count: u32) -> u32 {
+        self.supported_sample_counts(format)
+            .into_iter()
+            .filter(|&supported_sample_count| supported_sample_count <= sample_count)
+            .max()
+            .unwrap_or(1)
+    }
+    /// Creates a texture that can be sampled and rendered to.
+    ///
+    /// This is `create_texture_with_usage()` with `SAMPLED | RENDER_TARGET`.
+    fn create_texture(&self, format: TextureFormat, size: Vector2I) -> Self::Texture {
+        self.create_texture_with_usage(format,
+                                       size,
+                                       TextureUsageFlags::SAMPLED |
+                                       TextureUsageFlags::RENDER_TARGET)
+    }
+    /// Creates a texture that will only be used in the ways that `usage` lists, so that the
+    /// backend can choose the fastest layout for it.
+    ///
+    /// Textures bound as images in a `ComputeState` need `STORAGE`. A texture created with only
+    /// `RENDER_TARGET` may be backed by something other than a texture, and can then only be
+    /// wrapped with `create_framebuffer()` and read back; it can't be sampled, uploaded to, or
+    /// mipmapped.
+    fn create_texture_with_usage(&self,
+                                 format: TextureFormat,
+                                 size: Vector2I,
+                                 usage: TextureUsageFlags)
+                                 -> Self::Texture;
+    /// Creates a texture suitable for wrapping with `create_framebuffer()`.
+    ///
+    /// The texture is sampled with clamping and linear filtering by default. Panics if this
+    /// device can't render to textures of the given format.
+    fn create_render_target_texture(&self, format: TextureFormat, size: Vector2I)
+                                    -> Self::Texture;
+    /// Creates a cube texture with six square faces of the given size, which are rendered to
+    /// through `create_framebuffer_for_cube_face()`. Panics if this device can't render to
+    /// textures of the given format.
+    ///
+    /// Shaders must declare the uniform that samples a cube texture as a `samplerCube` rather than
+    /// a `sampler2D`, and sample it with a direction vector instead of texture coordinates; it's
+    /// looked up with `get_texture_parameter()` as usual. Cube textures can't be uploaded to or
+    /// mipmapped.
+    fn create_cube_texture(&self, format: TextureFormat, size: Vector2I) -> Self::Texture;
     fn create_texture_from_data(&self, format: TextureFormat, size: Vector2I, data: TextureDataRef)
                                 -> Self::Texture;
+    /// Wraps an image allocated outside Pathfinder, such as a video decoder's output, in a
+    /// texture without copying it.
+    ///
+    /// Each backend accepts only the handle types native to it and panics on others.
+    ///
+    /// # Safety
+    ///
+    /// The handle must point to a valid image of its kind. That image must outlive every use of
+    /// the returned texture, including GPU commands that are still in flight when the texture
+    /// is dropped. For `ExternalImageHandle::EGLImage`, `format` and `size` must describe the
+    /// image exactly, since the backend can't check them.
+    unsafe fn import_external_texture(&self, handle: ExternalImageHandle) -> Self::Texture;
     fn create_shader(&self, resources: &dyn ResourceLoader, name: &str, kind: ShaderKind)
                      -> Self::Shader;
     fn create_shader_from_source(&self, name: &str, source: &[u8], kind: ShaderKind)
@@ -71,6 +159,16 @@ pub trait Device: Sized {
     fn get_image_parameter(&self, program: &Self::Program, name: &str) -> Self::ImageParameter;
     fn get_storage_buffer(&self, program: &Self::Program, name: &str, binding: u32)
                           -> Self::StorageBuffer;
+    /// Binds `size` bytes of `buffer`, starting at byte `offset`, to the given storage buffer.
+    ///
+    /// `offset` must be a multiple of `storage_buffer_offset_alignment()`. The binding applies to
+    /// subsequent draws and dispatches until the storage buffer is bound again, either by this
+    /// method or by listing it in a `RenderState` or `ComputeState`.
+    fn bind_storage_buffer_range(&self,
+                                 storage_buffer: &Self::StorageBuffer,
+                                 buffer: &Self::Buffer,
+                                 offset: usize,
+                                 size: usize);
     fn bind_buffer(&self,
                    vertex_array: &Self::VertexArray,
                    buffer: &Self::Buffer,
@@ -80,47 +178,331 @@ pub trait Device: Sized {
                              attr: &Self::VertexAttr,
                              descriptor: &VertexAttrDescriptor);
     fn create_framebuffer(&self, texture: Self::Texture) -> Self::Framebuffer;
+    /// Creates a framebuffer that renders to one face of a texture made by
+    /// `create_cube_texture()`. The framebuffer holds its own handle to the texture, so every face
+    /// can have a framebuffer at once.
+    fn create_framebuffer_for_cube_face(&self, texture: &Self::Texture, face: CubeFace)
+                                        -> Self::Framebuffer;
     fn create_buffer(&self, mode: BufferUploadMode) -> Self::Buffer;
     fn allocate_buffer<T>(&self,
                           buffer: &Self::Buffer,
                           data: BufferData<T>,
                           target: BufferTarget);
+    /// Replaces the storage of `buffer` with a fresh, uninitialized allocation of the same size,
+    /// so that overwriting it doesn't wait for the GPU to finish reading the old contents.
+    ///
+    /// Draws and dispatches that were already submitted keep reading the old storage. Uploads
+    /// that overwrite an entire `BufferUploadMode::Dynamic` buffer orphan it automatically.
+    fn orphan_buffer(&self, buffer: &Self::Buffer);
     fn upload_to_buffer<T>(&self,
                            buffer: &Self::Buffer,
                            position: usize,
                            data: &[T],
                            target: BufferTarget);
+    /// Makes CPU writes to the given byte range of a mapped buffer visible to the GPU.
+    ///
+    /// This is a no-op for coherent mappings. The range must lie within the region of the buffer
+    /// that is currently mapped.
+    fn flush_mapped_range(&self, buffer: &Self::Buffer, range: Range<usize>);
     fn framebuffer_texture<'f>(&self, framebuffer: &'f Self::Framebuffer) -> &'f Self::Texture;
     fn destroy_framebuffer(&self, framebuffer: Self::Framebuffer) -> Self::Texture;
+    /// Returns the format that `texture` was created with.
+    ///
+    /// Backends must report exactly the requested format, even where they store it differently
+    /// internally. `conformance::run_format_conformance()` checks this.
     fn texture_format(&self, texture: &Self::Texture) -> TextureFormat;
     fn texture_size(&self, texture: &Self::Texture) -> Vector2I;
+    /// Returns the number of mip levels that `texture` has. This is 1 until `generate_mipmaps()`
+    /// allocates a full chain.
+    fn texture_level_count(&self, texture: &Self::Texture) -> u32;
+    /// Returns the size of mip level `level` of `texture`. Each level is half the size of the one
+    /// before it, rounded down, but never smaller than 1x1.
+    ///
+    /// Panics if `texture` doesn't have that level.
+    fn texture_level_size(&self, texture: &Self::Texture, level: u32) -> Vector2I {
+        let level_count = self.texture_level_count(texture);
+        assert!(level < level_count,
+                "Mip level {} is out of range for a texture with {} levels!",
+                level,
+                level_count);
+        let size = self.texture_size(texture);
+        vec2i((size.x() >> level).max(1), (size.y() >> level).max(1))
+    }
+    /// Returns the size of `target` in pixels, for deriving a viewport that covers all of it.
+    fn render_target_size(&self, target: &RenderTarget<Self>) -> Vector2I;
     fn set_texture_sampling_mode(&self, texture: &Self::Texture, flags: TextureSamplingFlags);
+    /// Sets the color that `texture` reads as outside its bounds, along the axes for which its
+    /// sampling mode includes `TextureSamplingFlags::CLAMP_TO_BORDER_U` or `CLAMP_TO_BORDER_V`.
+    /// The default is transparent black.
+    ///
+    /// Metal only supports transparent black, opaque black, and opaque white borders, so other
+    /// colors are rounded to the nearest of those there. WebGL has no border colors at all and
+    /// clamps to the edge instead.
+    fn set_texture_border_color(&self, texture: &Self::Texture, color: ColorF);
+    /// Remaps the channels that shaders see when they sample `texture`. The red, green, blue, and
+    /// alpha channels take their values from the corresponding entries of `swizzle`.
+    ///
+    /// This is only available if `Feature::TextureSwizzle` is supported.
+    fn set_texture_swizzle(&self, texture: &Self::Texture, swizzle: [Swizzle; 4]);
+    /// Returns the swizzle last set with `set_texture_swizzle()`, or `Swizzle::IDENTITY` if
+    /// there is none.
+    fn texture_swizzle(&self, texture: &Self::Texture) -> [Swizzle; 4];
     fn upload_to_texture(&self, texture: &Self::Texture, rect: RectI, data: TextureDataRef);
-    fn read_pixels(&self, target: &RenderTarget<Self>, viewport: RectI)
+    /// Fills in the mip chain of `texture` from its base level, allocating the chain first if
+    /// the texture doesn't have one. Shaders only sample the chain if the texture's sampling mode
+    /// includes `TextureSamplingFlags::MIPMAP`.
+    ///
+    /// The chain isn't updated automatically, so this must be called again after uploading or
+    /// rendering to the texture. The texture's format must be both filterable and renderable.
+    fn generate_mipmaps(&self, texture: &Self::Texture);
+    fn copy_texture_to_buffer(&self,
+                              texture: &Self::Texture,
+                              rect: RectI,
+                              buffer: &Self::Buffer,
+                              buffer_offset: usize,
+                              bytes_per_row: usize);
+    /// Reads back the pixels of `target` within `viewport`.
+    ///
+    /// Pathfinder renders with premultiplied alpha. Pass `AlphaMode::Straight` to have the color
+    /// channels divided by alpha once the data arrives, as most image formats expect.
+    fn read_pixels(&self, target: &RenderTarget<Self>, viewport: RectI, alpha_mode: AlphaMode)
                    -> Self::TextureDataReceiver;
+    /// Reads back the bytes of `buffer` within `range`, as of the commands submitted so far, for
+    /// example to inspect a storage buffer after a compute pass. Later writes to the buffer don't
+    /// affect the result.
     fn read_buffer(&self, buffer: &Self::Buffer, target: BufferTarget, range: Range<usize>)
                    -> Self::BufferDataReceiver;
     fn begin_commands(&self);
     fn end_commands(&self);
+    /// Submits the commands recorded so far to the GPU without ending the scope that
+    /// `begin_commands` opened, so that the GPU can start on them while recording continues.
+    ///
+    /// Unlike `end_commands`, this doesn't mark the end of a frame. It must not be called
+    /// inside a render pass.
+    fn flush(&self);
+    /// Opens a named group of commands, which GPU debuggers such as RenderDoc and Xcode show
+    /// around the draws and dispatches inside it. Groups nest, and each must be closed with
+    /// `pop_debug_group()` on the same side of any render pass boundary, and before `flush()` or
+    /// `end_commands()`.
+    ///
+    /// Like the other debug annotations, this is a no-op in release builds.
+    fn push_debug_group(&self, name: &str);
+    /// Closes the group opened by the last unmatched `push_debug_group()`.
+    fn pop_debug_group(&self);
+    /// Names `texture` in GPU debuggers. This is a no-op in release builds.
+    fn set_texture_label(&self, texture: &Self::Texture, label: &str);
+    /// Names `buffer` in GPU debuggers. The name persists if the buffer is reallocated. This is
+    /// a no-op in release builds.
+    fn set_buffer_label(&self, buffer: &Self::Buffer, label: &str);
+    /// Clears the attachments of `target` that `ops` names, without drawing anything.
+    ///
+    /// Unlike `RenderOptions::clear_ops`, this doesn't need a program, vertex array, or any other
+    /// render state.
+    fn clear(&self, target: &RenderTarget<Self>, ops: &ClearOps);
+    /// Begins a render pass that draws to `target`.
+    ///
+    /// The target is bound, cleared as `clear_ops` describes, and given `viewport` once here
+    /// instead of by every draw call. Until `end_render_pass()`, only draws to `target` may be
+    /// recorded, and their `RenderOptions` must neither clear nor discard it. Compute dispatches
+    /// have to wait until the pass ends.
+    fn begin_render_pass(&self,
+                         target: &RenderTarget<Self>,
+                         clear_ops: &ClearOps,
+                         viewport: RectI);
+    /// Ends the render pass begun by `begin_render_pass()`.
+    fn end_render_pass(&self);
     fn draw_arrays(&self, index_count: u32, render_state: &RenderState<Self>);
     fn draw_elements(&self, index_count: u32, render_state: &RenderState<Self>);
     fn draw_elements_instanced(&self,
                                index_count: u32,
                                instance_count: u32,
                                render_state: &RenderState<Self>);
+    /// Like `draw_elements_instanced`, but per-instance vertex attributes start at instance
+    /// `base_instance` instead of zero.
+    ///
+    /// This is native on `FeatureLevel::D3D11` devices. Elsewhere it's emulated by offsetting the
+    /// per-instance attributes, so `base_instance` must be a multiple of every attribute divisor.
+    /// Whether the shader-visible instance ID includes `base_instance` differs between backends,
+    /// so shaders shouldn't rely on it.
+    fn draw_elements_instanced_base(&self,
+                                    index_count: u32,
+                                    instance_count: u32,
+                                    base_instance: u32,
+                                    render_state: &RenderState<Self>);
+    /// Like `draw_elements_instanced`, but reads the draw arguments from `indirect_buffer`,
+    /// which may have been written by a compute shader.
+    ///
+    /// The arguments are laid out as a `DrawElementsIndirectCommand` starting `offset` bytes into
+    /// the buffer, and `offset` must be a multiple of 4. `render_state.first_index` is ignored in
+    /// favor of the `first_index` argument. Requires `FeatureLevel::D3D11`.
+    fn draw_elements_indirect(&self,
+                              indirect_buffer: &Self::Buffer,
+                              offset: usize,
+                              render_state: &RenderState<Self>);
     fn dispatch_compute(&self, dimensions: ComputeDimensions, state: &ComputeState<Self>);
+    /// Launches `group_count` task shader workgroups, or mesh shader workgroups if the program
+    /// has no task shader, and rasterizes the primitives that the mesh shaders emit.
+    ///
+    /// Requires `Feature::MeshShaders` and a `ProgramKind::MeshRaster` program. The mesh shaders
+    /// generate all geometry, so the vertex array and primitive in `render_state` are ignored.
+    fn draw_mesh_tasks(&self, group_count: ComputeDimensions, render_state: &RenderState<Self>);
     fn add_fence(&self) -> Self::Fence;
+    /// Blocks until the GPU has passed `fence`.
     fn wait_for_fence(&self, fence: &Self::Fence);
+    /// Returns true if the GPU has passed `fence`, without blocking.
+    fn try_wait_for_fence(&self, fence: &Self::Fence) -> bool;
     fn create_timer_query(&self) -> Self::TimerQuery;
     fn begin_timer_query(&self, query: &Self::TimerQuery);
     fn end_timer_query(&self, query: &Self::TimerQuery);
     fn try_recv_timer_query(&self, query: &Self::TimerQuery) -> Option<Duration>;
     fn recv_timer_query(&self, query: &Self::TimerQuery) -> Duration;
+    /// Starts timing every `begin_commands()`/`end_commands()` scope on the GPU for
+    /// `collect_frame_stats()`, discarding anything accumulated so far.
+    ///
+    /// Must be called outside `begin_commands()`.
+    fn reset_frame_stats(&self);
+    /// Waits for the GPU to go idle, then returns the statistics accumulated since
+    /// `reset_frame_stats()` and stops accumulating them.
+    ///
+    /// This blocks, so it's meant for batch and headless profiling. Must be called outside
+    /// `begin_commands()`.
+    fn collect_frame_stats(&self) -> FrameStats;
+    fn create_pipeline_stats_query(&self) -> Self::PipelineStatsQuery;
+    fn begin_pipeline_stats_query(&self, query: &Self::PipelineStatsQuery);
+    fn end_pipeline_stats_query(&self, query: &Self::PipelineStatsQuery);
+    fn recv_pipeline_stats_query(&self, query: &Self::PipelineStatsQuery)
+                                 -> Option<PipelineStats>;
+    /// Creates a query that records whether any samples passed the depth and stencil tests
+    /// between `begin_occlusion_query` and `end_occlusion_query`.
+    fn create_occlusion_query(&self) -> Self::OcclusionQuery;
+    fn begin_occlusion_query(&self, query: &Self::OcclusionQuery);
+    fn end_occlusion_query(&self, query: &Self::OcclusionQuery);
+    /// Returns whether any samples passed, or `None` if the result isn't available yet.
+    ///
+    /// Backends that can't count samples conservatively report that samples passed.
+    fn try_recv_occlusion_query(&self, query: &Self::OcclusionQuery) -> Option<bool>;
+    fn recv_occlusion_query(&self, query: &Self::OcclusionQuery) -> bool;
+    /// Like `draw_elements`, but skips the draw if no samples passed during `query`.
+    ///
+    /// Where the backend supports conditional rendering, the GPU decides without stalling the
+    /// CPU. Otherwise the query result is read back first, or the draw is always performed.
+    /// Skipping is only an optimization, so callers must not depend on the draw being skipped.
+    fn draw_elements_conditional(&self,
+                                 index_count: u32,
+                                 render_state: &RenderState<Self>,
+                                 query: &Self::OcclusionQuery);
+    /// Samples the CPU and GPU clocks together so that GPU timestamps can be placed on the CPU
+    /// timeline, for example in a trace that combines CPU and GPU spans.
+    ///
+    /// Returns `None` if the backend can't read GPU timestamps.
+    fn gpu_timestamp_calibration(&self) -> Option<TimestampCalibration>;
     fn try_recv_texture_data(&self, receiver: &Self::TextureDataReceiver) -> Option<TextureData>;
     fn recv_texture_data(&self, receiver: &Self::TextureDataReceiver) -> TextureData;
     fn try_recv_buffer(&self, receiver: &Self::BufferDataReceiver) -> Option<Vec<u8>>;
     fn recv_buffer(&self, receiver: &Self::BufferDataReceiver) -> Vec<u8>;
 
+    /// Creates a texture by evaluating `f` at the coordinates of each texel.
+    ///
+    /// The colors are converted to `format`. Single-channel formats take the red channel.
+    fn create_texture_from_fn<F>(&self, format: TextureFormat, size: Vector2I, f: F)
+                                 -> Self::Texture where F: Fn(Vector2I) -> ColorU {
+        let mut colors = Vec::with_capacity(size.x() as usize * size.y() as usize);
+        for y in 0..size.y() {
+            for x in 0..size.x() {
+                colors.push(f(vec2i(x, y)));
+            }
+        }
+
+        match format {
+            TextureFormat::R8 => {
+                let texels: Vec<u8> = colors.iter().map(|color| color.r).collect();
+                self.create_texture_from_data(format, size, TextureDataRef::U8(&texels))
+            }
+            TextureFormat::RG8 => {
+                let texels: Vec<u8> = colors.iter().flat_map(|color| {
+                    vec![color.r, color.g]
+                }).collect();
+                self.create_texture_from_data(format, size, TextureDataRef::U8(&texels))
+            }
+            TextureFormat::RGBA8 | TextureFormat::RGBA8Srgb => {
+                let texels = color_slice_to_u8_slice(&colors);
+                self.create_texture_from_data(format, size, TextureDataRef::U8(texels))
+            }
+            TextureFormat::BGRA8 => {
+                let texels: Vec<u8> = colors.iter().flat_map(|color| {
+                    vec![color.b, color.g, color.r, color.a]
+                }).collect();
+                self.create_texture_from_data(format, size, TextureDataRef::U8(&texels))
+            }
+            TextureFormat::R16 => {
+                let texels: Vec<u16> = colors.iter().map(|color| color.r as u16 * 257).collect();
+                self.create_texture_from_data(format, size, TextureDataRef::U16(&texels))
+            }
+            TextureFormat::RGBA16 => {
+                let texels: Vec<u16> = colors.iter().flat_map(|color| {
+                    vec![color.r, color.g, color.b, color.a]
+                }).map(|channel| channel as u16 * 257).collect();
+                self.create_texture_from_data(format, size, TextureDataRef::U16(&texels))
+            }
+            TextureFormat::R16F => {
+                let texels: Vec<f16> = colors.iter().map(|color| {
+                    f16::from_f32(color.to_f32().r())
+                }).collect();
+                self.create_texture_from_data(format, size, TextureDataRef::F16(&texels))
+            }
+            TextureFormat::RG16F => {
+                let texels: Vec<f16> = colors.iter().flat_map(|color| {
+                    let color = color.to_f32();
+                    vec![color.r(), color.g()]
+                }).map(f16::from_f32).collect();
+                self.create_texture_from_data(format, size, TextureDataRef::F16(&texels))
+            }
+            TextureFormat::RGBA16F => {
+                let texels: Vec<f16> = colors.iter().flat_map(|color| {
+                    let color = color.to_f32();
+                    vec![color.r(), color.g(), color.b(), color.a()]
+                }).map(f16::from_f32).collect();
+                self.create_texture_from_data(format, size, TextureDataRef::F16(&texels))
+            }
+            TextureFormat::RGBA32F => {
+                let texels: Vec<f32> = colors.iter().flat_map(|color| {
+                    let color = color.to_f32();
+                    vec![color.r(), color.g(), color.b(), color.a()]
+                }).collect();
+                self.create_texture_from_data(format, size, TextureDataRef::F32(&texels))
+            }
+        }
+    }
+
+    /// Blocks until the GPU has finished every command submitted so far.
+    ///
+    /// Must be called outside `begin_commands()`.
+    fn wait_idle(&self) {
+        self.begin_commands();
+        let fence = self.add_fence();
+        self.end_commands();
+        self.wait_for_fence(&fence);
+    }
+
+    /// Creates a texture uniformly filled with `color`.
+    ///
+    /// Formats that this device can render to are cleared on the GPU, which avoids building the
+    /// texels on the CPU. Other formats are filled with `create_texture_from_fn()`. Either way,
+    /// the texture is sampled like one made with `create_texture()`.
+    fn create_texture_filled(&self, format: TextureFormat, size: Vector2I, color: ColorU)
+                             -> Self::Texture {
+        if !self.supports_texture_format(format, TextureUsage::RenderTarget) {
+            return self.create_texture_from_fn(format, size, |_| color);
+        }
+
+        let framebuffer = self.create_framebuffer(self.create_render_target_texture(format, size));
+        let clear_ops = ClearOps { color: Some(color.to_f32()), ..ClearOps::default() };
+        self.clear(&RenderTarget::Framebuffer(&framebuffer), &clear_ops);
+        let texture = self.destroy_framebuffer(framebuffer);
+        self.set_texture_sampling_mode(&texture, TextureSamplingFlags::empty());
+        texture
+    }
+
     fn create_texture_from_png(&self,
                                resources: &dyn ResourceLoader,
                                name: &str,
@@ -134,7 +516,7 @@ pub trait Device: Sized {
                 let size = vec2i(image.width() as i32, image.height() as i32);
                 self.create_texture_from_data(format, size, TextureDataRef::U8(&image))
             }
-            TextureFormat::RGBA8 => {
+            TextureFormat::RGBA8 | TextureFormat::RGBA8Srgb => {
                 let image = image.to_rgba8();
                 let size = vec2i(image.width() as i32, image.height() as i32);
                 self.create_texture_from_data(format, size, TextureDataRef::U8(&image))
@@ -157,7 +539,7 @@ pub trait Device: Sized {
                 let rect = RectI::new(Vector2I::default(), size);
                 self.upload_to_texture(&texture, rect, TextureDataRef::U8(&image))
             }
-            TextureFormat::RGBA8 => {
+            TextureFormat::RGBA8 | TextureFormat::RGBA8Srgb => {
                 let image = image.to_rgba8();
                 let size = vec2i(image.width() as i32, image.height() as i32);
                 let rect = RectI::new(Vector2I::default(), size);
@@ -180,6 +562,25 @@ pub trait Device: Sized {
                     fragment: self.create_shader(resources, fragment, ShaderKind::Fragment),
                 }
             }
+            ProgramKind::TessellatedRaster { vertex, tess_control, tess_evaluation, fragment } => {
+                ProgramKind::TessellatedRaster {
+                    vertex: self.create_shader(resources, vertex, ShaderKind::Vertex),
+                    tess_control: self.create_shader(resources,
+                                                     tess_control,
+                                                     ShaderKind::TessControl),
+                    tess_evaluation: self.create_shader(resources,
+                                                        tess_evaluation,
+                                                        ShaderKind::TessEvaluation),
+                    fragment: self.create_shader(resources, fragment, ShaderKind::Fragment),
+                }
+            }
+            ProgramKind::MeshRaster { task, mesh, fragment } => {
+                ProgramKind::MeshRaster {
+                    task: task.map(|task| self.create_shader(resources, task, ShaderKind::Task)),
+                    mesh: self.create_shader(resources, mesh, ShaderKind::Mesh),
+                    fragment: self.create_shader(resources, fragment, ShaderKind::Fragment),
+                }
+            }
             ProgramKind::Compute(compute) => {
                 ProgramKind::Compute(self.create_shader(resources, compute, ShaderKind::Compute))
             }
@@ -206,15 +607,98 @@ pub enum FeatureLevel {
     D3D11,
 }
 
+/// The resource limits of a device, returned by `Device::device_limits()`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DeviceLimits {
+    /// The largest width and height of a 2D texture.
+    pub max_texture_size: Vector2I,
+    /// The largest compute workgroup size in each dimension, or zero if compute shaders are
+    /// unsupported.
+    pub max_compute_work_group_size: [u32; 3],
+    /// The largest storage buffer that a shader can access, in bytes, or zero if storage buffers
+    /// are unsupported.
+    pub max_storage_buffer_size: u64,
+    /// The largest number of vertex attributes that a vertex array can have.
+    pub max_vertex_attributes: u32,
+}
+
+/// A simultaneous sample of the CPU and GPU clocks, returned by
+/// `Device::gpu_timestamp_calibration()`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimestampCalibration {
+    /// The CPU time, in nanoseconds since the Unix epoch.
+    pub cpu_time_ns: u64,
+    /// The GPU timestamp at the same moment, in backend-specific ticks.
+    pub gpu_timestamp: u64,
+    /// The rate at which GPU timestamps advance, in ticks per nanosecond.
+    pub gpu_ticks_per_ns: f64,
+}
+
+/// GPU statistics accumulated between `Device::reset_frame_stats()` and
+/// `Device::collect_frame_stats()`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameStats {
+    /// The number of `begin_commands()`/`end_commands()` scopes that were timed.
+    pub scope_count: usize,
+    /// The total GPU time of those scopes.
+    pub gpu_time: Duration,
+}
+
+/// Counts gathered by a pipeline statistics query.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PipelineStats {
+    /// The number of primitives that were sent to the rasterizer.
+    pub primitives_generated: u64,
+    /// The number of primitives that survived clipping, if the backend can count them.
+    pub clipping_output_primitives: Option<u64>,
+}
+
+/// Optional features that a device may or may not support.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Feature {
+    /// Tessellation control and evaluation shaders, and `Primitive::Patches`.
+    TessellationShaders,
+    /// Task and mesh shaders, and `Device::draw_mesh_tasks()`.
+    MeshShaders,
+    /// `Device::set_texture_swizzle()`.
+    TextureSwizzle,
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum TextureFormat {
     R8,
+    /// 16-bit normalized unsigned integer red.
+    R16,
     R16F,
+    /// 8-bit normalized unsigned integer red and green.
+    RG8,
+    /// 16-bit floating point red and green.
+    RG16F,
     RGBA8,
+    /// 8-bit RGBA with the color channels sRGB-encoded. Sampling decodes them to linear, and
+    /// rendering and blending encode them again, while uploads and `read_pixels()` see the raw
+    /// encoded bytes.
+    RGBA8Srgb,
+    /// 8-bit RGBA with the red and blue channels swapped in memory, as used by Core Animation
+    /// layers and IOSurfaces.
+    BGRA8,
+    /// 16-bit normalized unsigned integer RGBA.
+    RGBA16,
     RGBA16F,
     RGBA32F,
 }
 
+/// A way a texture can be used, for `Device::supports_texture_format()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TextureUsage {
+    /// Sampled in shaders with nearest filtering.
+    Sampled,
+    /// Sampled in shaders with linear filtering.
+    Filtered,
+    /// Rendered to as the color attachment of a framebuffer.
+    RenderTarget,
+}
+
 #[derive(Clone, Copy, Debug)]
 pub enum VertexAttrType {
     F32,
@@ -227,10 +711,17 @@ pub enum VertexAttrType {
 
 #[derive(Clone, Copy, Debug)]
 pub enum BufferData<'a, T> {
+    /// Allocates space for this many elements without initializing it.
+    ///
+    /// In debug builds, the contents are filled with `UNINITIALIZED_BUFFER_FILL_BYTE` instead, so
+    /// that reads before writes produce obviously wrong data on every backend.
     Uninitialized(usize),
     Memory(&'a [T]),
 }
 
+/// The byte that uninitialized buffers are filled with in debug builds.
+pub const UNINITIALIZED_BUFFER_FILL_BYTE: u8 = 0xcd;
+
 #[derive(Clone, Copy, Debug)]
 pub enum BufferTarget {
     Vertex,
@@ -249,6 +740,14 @@ pub enum ShaderKind {
     Vertex,
     Fragment,
     Compute,
+    /// Requires `Feature::TessellationShaders`.
+    TessControl,
+    /// Requires `Feature::TessellationShaders`.
+    TessEvaluation,
+    /// Requires `Feature::MeshShaders`.
+    Task,
+    /// Requires `Feature::MeshShaders`.
+    Mesh,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -257,6 +756,24 @@ pub enum ProgramKind<T> {
         vertex: T,
         fragment: T,
     },
+    /// A raster program with tessellation stages between the vertex and fragment shaders.
+    ///
+    /// Requires `Feature::TessellationShaders`. Draw these programs with `Primitive::Patches`.
+    TessellatedRaster {
+        vertex: T,
+        tess_control: T,
+        tess_evaluation: T,
+        fragment: T,
+    },
+    /// A raster program whose geometry is emitted by a mesh shader, optionally launched by a
+    /// task shader, instead of being fetched from vertex arrays.
+    ///
+    /// Requires `Feature::MeshShaders`. Draw these programs with `Device::draw_mesh_tasks()`.
+    MeshRaster {
+        task: Option<T>,
+        mesh: T,
+        fragment: T,
+    },
     Compute(T),
 }
 
@@ -267,6 +784,24 @@ pub struct ComputeDimensions {
     pub z: u32,
 }
 
+/// The arguments of `Device::draw_elements_indirect()`, as they must be laid out in the indirect
+/// buffer.
+///
+/// This matches both `DrawElementsIndirectCommand` in OpenGL and
+/// `MTLDrawIndexedPrimitivesIndirectArguments` in Metal.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[repr(C)]
+pub struct DrawElementsIndirectCommand {
+    /// The number of indices to draw.
+    pub index_count: u32,
+    pub instance_count: u32,
+    /// The first index to draw, in indices rather than bytes.
+    pub first_index: u32,
+    /// A value added to each index before fetching vertices.
+    pub base_vertex: i32,
+    pub base_instance: u32,
+}
+
 #[derive(Clone, Copy)]
 pub enum UniformData {
     Float(f32),
@@ -284,6 +819,9 @@ pub enum UniformData {
 pub enum Primitive {
     Triangles,
     Lines,
+    Points,
+    /// Patches with the given number of control points, for tessellation programs.
+    Patches(u32),
 }
 
 #[derive(Clone)]
@@ -292,6 +830,12 @@ pub struct RenderState<'a, D> where D: Device {
     pub program: &'a D::Program,
     pub vertex_array: &'a D::VertexArray,
     pub primitive: Primitive,
+    /// The position within the index buffer of the first index to draw, for `draw_elements()`
+    /// and `draw_elements_instanced()`. `draw_arrays()` ignores this.
+    ///
+    /// This allows one index buffer to hold several meshes that are drawn individually. The
+    /// drawn range must lie within the index buffer bound to `vertex_array`.
+    pub first_index: u32,
     pub uniforms: &'a [UniformBinding<'a, D::Uniform>],
     pub textures: &'a [TextureBinding<'a, D::TextureParameter, D::Texture>],
     pub images: &'a [ImageBinding<'a, D::ImageParameter, D::Texture>],
@@ -318,10 +862,52 @@ pub type ImageBinding<'a, IP, T> = (&'a IP, &'a T, ImageAccess);
 #[derive(Clone, Debug)]
 pub struct RenderOptions {
     pub blend: Option<BlendState>,
+    /// The color that `BlendFactor::ConstantColor` and `BlendFactor::OneMinusConstantColor`
+    /// refer to.
+    pub blend_color: ColorF,
     pub depth: Option<DepthState>,
+    /// An offset added to the depth of each fragment, to avoid z-fighting with coplanar
+    /// geometry.
+    pub depth_bias: Option<DepthBias>,
     pub stencil: Option<StencilState>,
     pub clear_ops: ClearOps,
+    /// If set, draws and the clears in `clear_ops` only affect pixels inside this rectangle, which
+    /// is in the same coordinate space as `viewport`.
+    ///
+    /// Metal can't restrict clears to a rectangle, so combining this with `clear_ops` or
+    /// `LoadAction::Clear` is unsupported there. `LoadAction::DontCare` still discards the whole
+    /// target.
+    pub scissor: Option<RectI>,
     pub color_mask: bool,
+    /// If set, an index with this value in an index buffer ends the current primitive and begins
+    /// a new one.
+    ///
+    /// Metal and WebGL only support a fixed restart index of `!0` (0xffffffff) for 32-bit
+    /// indices, so portable code should use that value.
+    pub primitive_restart: Option<u32>,
+    /// What happens to the previous contents of any attachment that `clear_ops` doesn't clear.
+    ///
+    /// Like `clear_ops`, this takes effect at the start of the draw call, so it should only be
+    /// set on the first draw call of a pass.
+    pub load_action: LoadAction,
+    /// How triangles are rasterized. Useful for debugging tessellation.
+    ///
+    /// OpenGL ES and WebGL have no polygon mode, so this is ignored there.
+    pub polygon_mode: PolygonMode,
+    /// The size of points in pixels, when drawing `Primitive::Points`.
+    ///
+    /// If `None`, the vertex shader must write the point size (`gl_PointSize` in GLSL,
+    /// `[[point_size]]` in Metal). A fixed size here is only supported on desktop OpenGL; the
+    /// other backends ignore it and always take the size from the shader, so portable code should
+    /// write it there.
+    pub point_size: Option<f32>,
+    /// Whether to antialias `Primitive::Lines` analytically, with `GL_LINE_SMOOTH`.
+    ///
+    /// Smoothed lines write their coverage to alpha, so if `blend` is `None`, standard alpha
+    /// blending is enabled for the draw. This is only supported on desktop OpenGL and is ignored
+    /// on the other backends; portable code should instead expand lines into quads and compute
+    /// coverage in the shader.
+    pub line_smooth: bool,
 }
 
 #[derive(Clone, Copy, Debug, Default)]
@@ -331,13 +917,34 @@ pub struct ClearOps {
     pub stencil: Option<u8>,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LoadAction {
+    /// The previous contents are preserved.
+    Load,
+    /// The contents are cleared to transparent black, a depth of 1.0, and a stencil value of 0.
+    Clear,
+    /// The previous contents are undefined.
+    ///
+    /// Use this when every pixel is about to be overwritten. On tiled GPUs, this saves the
+    /// bandwidth needed to load the old contents into tile memory.
+    DontCare,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PolygonMode {
+    /// Triangles are filled.
+    Fill,
+    /// Only the edges of triangles are drawn, as lines.
+    Line,
+}
+
 #[derive(Clone, Copy, Debug)]
 pub enum RenderTarget<'a, D> where D: Device {
     Default,
     Framebuffer(&'a D::Framebuffer),
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct BlendState {
     pub dest_rgb_factor: BlendFactor,
     pub dest_alpha_factor: BlendFactor,
@@ -346,7 +953,7 @@ pub struct BlendState {
     pub op: BlendOp,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum BlendFactor {
     Zero,
     One,
@@ -355,9 +962,12 @@ pub enum BlendFactor {
     DestAlpha,
     OneMinusDestAlpha,
     DestColor,
+    /// The corresponding channel of `RenderOptions::blend_color`.
+    ConstantColor,
+    OneMinusConstantColor,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum BlendOp {
     Add,
     Subtract,
@@ -366,19 +976,30 @@ pub enum BlendOp {
     Max,
 }
 
-#[derive(Clone, Copy, Default, Debug)]
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
 pub struct DepthState {
     pub func: DepthFunc,
     pub write: bool,
 }
 
-#[derive(Clone, Copy, Debug)]
+/// A depth offset, as in `glPolygonOffset()`.
+///
+/// The offset of each fragment is `constant` times the smallest resolvable depth difference,
+/// plus `slope` times the maximum depth slope of the polygon. Negative values move fragments
+/// towards the viewer.
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub struct DepthBias {
+    pub constant: f32,
+    pub slope: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum DepthFunc {
     Less,
     Always,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct StencilState {
     pub func: StencilFunc,
     pub reference: u32,
@@ -386,7 +1007,7 @@ pub struct StencilState {
     pub write: bool,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum StencilFunc {
     Always,
     Equal,
@@ -397,14 +1018,53 @@ impl Default for RenderOptions {
     fn default() -> RenderOptions {
         RenderOptions {
             blend: None,
+            blend_color: ColorF::transparent_black(),
             depth: None,
+            depth_bias: None,
             stencil: None,
             clear_ops: ClearOps::default(),
+            scissor: None,
             color_mask: true,
+            primitive_restart: None,
+            load_action: LoadAction::Load,
+            polygon_mode: PolygonMode::Fill,
+            point_size: None,
+            line_smooth: false,
+        }
+    }
+}
+
+impl RenderOptions {
+    /// Returns the clear operations to perform, including those implied by
+    /// `LoadAction::Clear`.
+    pub fn effective_clear_ops(&self) -> ClearOps {
+        match self.load_action {
+            LoadAction::Clear => {
+                ClearOps {
+                    color: Some(self.clear_ops.color.unwrap_or_else(ColorF::transparent_black)),
+                    depth: Some(self.clear_ops.depth.unwrap_or(1.0)),
+                    stencil: Some(self.clear_ops.stencil.unwrap_or(0)),
+                }
+            }
+            LoadAction::Load | LoadAction::DontCare => self.clear_ops,
         }
     }
 }
 
+impl Default for LoadAction {
+    #[inline]
+    fn default() -> LoadAction {
+        LoadAction::Load
+    }
+}
+
+impl Default for PolygonMode {
+    #[inline]
+    fn default() -> PolygonMode {
+        PolygonMode::Fill
+    }
+}
+
 impl Default for BlendOp {
     #[inline]
     fn default() -> BlendOp {
@@ -438,6 +1098,16 @@ impl Default for StencilFunc {
     }
 }
 
+/// How the color channels of read back pixels relate to their alpha.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AlphaMode {
+    /// Color channels are premultiplied by alpha, as Pathfinder renders them.
+    Premultiplied,
+    /// Color channels are independent of alpha. Fully transparent pixels become transparent
+    /// black.
+    Straight,
+}
+
 #[derive(Clone, Debug)]
 pub enum TextureData {
     U8(Vec<u8>),
@@ -446,17 +1116,40 @@ pub enum TextureData {
     F32(Vec<f32>),
 }
 
+/// A platform-specific handle to an externally-allocated image, for
+/// `Device::import_external_texture()`.
+#[derive(Clone, Copy, Debug)]
+pub enum ExternalImageHandle {
+    /// An `IOSurfaceRef`, importable by the Metal backend as a `TextureFormat::BGRA8` texture.
+    IOSurface(*const c_void),
+    /// An `EGLImage`, importable by the OpenGL backend with `GL_OES_EGL_image`.
+    ///
+    /// EGL images can't be queried for their size or format, so the caller supplies them.
+    /// Creating the image, for example from a DMABUF with `EGL_EXT_image_dma_buf_import`, is up
+    /// to the caller, which owns the EGL display.
+    EGLImage { image: *const c_void, format: TextureFormat, size: Vector2I },
+}
+
 #[derive(Clone, Copy, Debug)]
 pub enum TextureDataRef<'a> {
     U8(&'a [u8]),
+    U16(&'a [u16]),
     F16(&'a [f16]),
     F32(&'a [f32]),
 }
 
 impl UniformData {
+    /// Converts a 3D transform to a `mat4` uniform, in column-major order.
     #[inline]
     pub fn from_transform_3d(transform: &Transform4F) -> UniformData {
-        UniformData::Mat4([transform.c0, transform.c1, transform.c2, transform.c3])
+        UniformData::Mat4(transform.to_columns())
+    }
+}
+
+impl From<Transform4F> for UniformData {
+    #[inline]
+    fn from(transform: Transform4F) -> UniformData {
+        UniformData::from_transform_3d(&transform)
     }
 }
 
@@ -482,8 +1175,14 @@ impl TextureFormat {
     #[inline]
     pub fn channels(self) -> usize {
         match self {
-            TextureFormat::R8 | TextureFormat::R16F => 1,
-            TextureFormat::RGBA8 | TextureFormat::RGBA16F | TextureFormat::RGBA32F => 4,
+            TextureFormat::R8 | TextureFormat::R16 | TextureFormat::R16F => 1,
+            TextureFormat::RG8 | TextureFormat::RG16F => 2,
+            TextureFormat::RGBA8 |
+            TextureFormat::RGBA8Srgb |
+            TextureFormat::BGRA8 |
+            TextureFormat::RGBA16 |
+            TextureFormat::RGBA16F |
+            TextureFormat::RGBA32F => 4,
         }
     }
 
@@ -491,12 +1190,21 @@ impl TextureFormat {
     pub fn bytes_per_pixel(self) -> usize {
         match self {
             TextureFormat::R8 => 1,
-            TextureFormat::R16F => 2,
-            TextureFormat::RGBA8 => 4,
-            TextureFormat::RGBA16F => 8,
+            TextureFormat::R16 | TextureFormat::R16F | TextureFormat::RG8 => 2,
+            TextureFormat::RG16F |
+            TextureFormat::RGBA8 |
+            TextureFormat::RGBA8Srgb |
+            TextureFormat::BGRA8 => 4,
+            TextureFormat::RGBA16 | TextureFormat::RGBA16F => 8,
             TextureFormat::RGBA32F => 16,
         }
     }
+
+    /// Returns true if the color channels of this format are sRGB-encoded.
+    #[inline]
+    pub fn is_srgb(self) -> bool {
+        self == TextureFormat::RGBA8Srgb
+    }
 }
 
 impl ClearOps {
@@ -525,9 +1233,79 @@ bitflags! {
         const REPEAT_V    = 0x02;
         const NEAREST_MIN = 0x04;
         const NEAREST_MAG = 0x08;
+        /// Samples from the mip chain built by `Device::generate_mipmaps()`, blending between
+        /// levels unless `NEAREST_MIN` is also set.
+        const MIPMAP      = 0x10;
+        /// Filters anisotropically, up to 16 samples, to keep textures viewed at grazing angles
+        /// sharp. Ignored where anisotropic filtering is unsupported.
+        const ANISOTROPIC = 0x20;
+        /// Reads the color set by `Device::set_texture_border_color()` to the left and right of
+        /// the texture. Takes precedence over `REPEAT_U`.
+        const CLAMP_TO_BORDER_U = 0x40;
+        /// Reads the color set by `Device::set_texture_border_color()` above and below the
+        /// texture. Takes precedence over `REPEAT_V`.
+        const CLAMP_TO_BORDER_V = 0x80;
+    }
+}
+
+bitflags! {
+    /// The ways a texture will be used, for `Device::create_texture_with_usage()`.
+    pub struct TextureUsageFlags: u8 {
+        /// Sampled in shaders.
+        const SAMPLED       = 0x01;
+        /// Rendered to as the color attachment of a framebuffer.
+        const RENDER_TARGET = 0x02;
+        /// Read and written as an image by compute shaders.
+        const STORAGE       = 0x04;
     }
 }
 
+/// A face of a cube texture. See `Device::create_cube_texture()`.
+///
+/// Faces are listed in the order that OpenGL and Metal number them, so `face as u32` is the
+/// index of the face's slice.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CubeFace {
+    PositiveX,
+    NegativeX,
+    PositiveY,
+    NegativeY,
+    PositiveZ,
+    NegativeZ,
+}
+
+impl CubeFace {
+    pub const ALL: [CubeFace; 6] = [
+        CubeFace::PositiveX,
+        CubeFace::NegativeX,
+        CubeFace::PositiveY,
+        CubeFace::NegativeY,
+        CubeFace::PositiveZ,
+        CubeFace::NegativeZ,
+    ];
+}
+
+/// Where one channel of a sampled texture comes from. See `Device::set_texture_swizzle()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Swizzle {
+    Zero,
+    One,
+    Red,
+    Green,
+    Blue,
+    Alpha,
+}
+
+impl Swizzle {
+    /// The swizzle that leaves every channel in place.
+    pub const IDENTITY: [Swizzle; 4] = [
+        Swizzle::Red,
+        Swizzle::Green,
+        Swizzle::Blue,
+        Swizzle::Alpha,
+    ];
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum ImageAccess {
     Read,
@@ -535,33 +1313,252 @@ pub enum ImageAccess {
     ReadWrite,
 }
 
+impl TextureData {
+    /// Returns the color of the pixel at `coord` in image data of the given `size`.
+    ///
+    /// Unsigned normalized data is scaled to the [0, 1] range, while floating-point data is
+    /// returned as is. Channels missing from the data are filled in with zero, except for alpha,
+    /// which is set to 1.0.
+    pub fn get_pixel(&self, size: Vector2I, coord: Vector2I) -> ColorF {
+        assert!(coord.x() >= 0 && coord.y() >= 0 && coord.x() < size.x() && coord.y() < size.y(),
+                "Pixel coordinate {:?} is out of bounds for texture data of size {:?}!",
+                coord,
+                size);
+
+        let channels = self.channels(size);
+        let start_index = (coord.y() as usize * size.x() as usize + coord.x() as usize) * channels;
+        let mut components = [0.0, 0.0, 0.0, 1.0];
+        for (channel, component) in components.iter_mut().enumerate().take(channels) {
+            *component = self.normalized_component(start_index + channel);
+        }
+        ColorF::new(components[0], components[1], components[2], components[3])
+    }
+
+    /// Converts all pixels in image data of the given `size` to colors, in row-major order.
+    ///
+    /// See `get_pixel()` for details on how the data is normalized.
+    pub fn pixels_as_colorf(&self, size: Vector2I) -> Vec<ColorF> {
+        let mut pixels = Vec::with_capacity(size.x() as usize * size.y() as usize);
+        for y in 0..size.y() {
+            for x in 0..size.x() {
+                pixels.push(self.get_pixel(size, vec2i(x, y)));
+            }
+        }
+        pixels
+    }
+
+    /// Converts pixels of the given format from premultiplied to straight alpha in place.
+    ///
+    /// Pixels with zero alpha become transparent black. Formats without an alpha channel are
+    /// left unchanged.
+    pub fn unpremultiply(&mut self, format: TextureFormat) {
+        if format.channels() != 4 {
+            return;
+        }
+
+        match *self {
+            TextureData::U8(ref mut data) => {
+                unpremultiply_texels(data,
+                                     |component| component as f32 / 255.0,
+                                     |component| (component.min(1.0) * 255.0).round() as u8)
+            }
+            TextureData::U16(ref mut data) => {
+                unpremultiply_texels(data,
+                                     |component| component as f32 / 65535.0,
+                                     |component| (component.min(1.0) * 65535.0).round() as u16)
+            }
+            TextureData::F16(ref mut data) => {
+                unpremultiply_texels(data, |component| component.to_f32(), f16::from_f32)
+            }
+            TextureData::F32(ref mut data) => {
+                unpremultiply_texels(data, |component| component, |component| component)
+            }
+        }
+    }
+
+    fn len(&self) -> usize {
+        match *self {
+            TextureData::U8(ref data) => data.len(),
+            TextureData::U16(ref data) => data.len(),
+            TextureData::F16(ref data) => data.len(),
+            TextureData::F32(ref data) => data.len(),
+        }
+    }
+
+    fn channels(&self, size: Vector2I) -> usize {
+        let area = size.x() as usize * size.y() as usize;
+        let channels = self.len().checked_div(area).unwrap_or(0);
+        assert!((1..=4).contains(&channels) && channels * area == self.len(),
+                "Texture data length {} doesn't match size {:?}!",
+                self.len(),
+                size);
+        channels
+    }
+
+    fn normalized_component(&self, index: usize) -> f32 {
+        match *self {
+            TextureData::U8(ref data) => data[index] as f32 / 255.0,
+            TextureData::U16(ref data) => data[index] as f32 / 65535.0,
+            TextureData::F16(ref data) => data[index].to_f32(),
+            TextureData::F32(ref data) => data[index],
+        }
+    }
+}
+
 impl<'a> TextureDataRef<'a> {
     #[doc(hidden)]
     pub fn check_and_extract_data_ptr(self, minimum_size: Vector2I, format: TextureFormat)
                                       -> *const c_void {
+        assert!(minimum_size.x() >= 0 && minimum_size.y() >= 0,
+                "Texture region size {:?} is negative!",
+                minimum_size);
+
         let channels = match (format, self) {
             (TextureFormat::R8, TextureDataRef::U8(_)) => 1,
+            (TextureFormat::RG8, TextureDataRef::U8(_)) => 2,
             (TextureFormat::RGBA8, TextureDataRef::U8(_)) => 4,
+            (TextureFormat::RGBA8Srgb, TextureDataRef::U8(_)) => 4,
+            (TextureFormat::BGRA8, TextureDataRef::U8(_)) => 4,
+            (TextureFormat::R16, TextureDataRef::U16(_)) => 1,
+            (TextureFormat::RGBA16, TextureDataRef::U16(_)) => 4,
+            (TextureFormat::R16F, TextureDataRef::F16(_)) => 1,
+            (TextureFormat::RG16F, TextureDataRef::F16(_)) => 2,
             (TextureFormat::RGBA16F, TextureDataRef::F16(_)) => 4,
             (TextureFormat::RGBA32F, TextureDataRef::F32(_)) => 4,
-            _ => panic!("Unimplemented texture format!"),
+            _ => panic!("Unimplemented texture format or mismatched data type for {:?}!", format),
         };
 
         let area = minimum_size.x() as usize * minimum_size.y() as usize;
+        let (data_len, data_ptr) = match self {
+            TextureDataRef::U8(data) => (data.len(), data.as_ptr() as *const c_void),
+            TextureDataRef::U16(data) => (data.len(), data.as_ptr() as *const c_void),
+            TextureDataRef::F16(data) => (data.len(), data.as_ptr() as *const c_void),
+            TextureDataRef::F32(data) => (data.len(), data.as_ptr() as *const c_void),
+        };
 
-        match self {
-            TextureDataRef::U8(data) => {
-                assert!(data.len() >= area * channels);
-                data.as_ptr() as *const c_void
-            }
-            TextureDataRef::F16(data) => {
-                assert!(data.len() >= area * channels);
-                data.as_ptr() as *const c_void
-            }
-            TextureDataRef::F32(data) => {
-                assert!(data.len() >= area * channels);
-                data.as_ptr() as *const c_void
+        // Uploading from a slice that's too short would make the driver read past its end.
+        assert!(data_len >= area * channels,
+                "Texture data is too short: a {}x{} {:?} region needs {} elements, but only {} \
+                 were supplied!",
+                minimum_size.x(),
+                minimum_size.y(),
+                format,
+                area * channels,
+                data_len);
+        data_ptr
+    }
+}
+
+// Alpha is the last channel of every four-channel format, including `BGRA8`.
+fn unpremultiply_texels<T, F, G>(texels: &mut [T], to_f32: F, from_f32: G)
+                                 where T: Copy, F: Fn(T) -> f32, G: Fn(f32) -> T {
+    for pixel in texels.chunks_mut(4) {
+        let alpha = to_f32(pixel[3]);
+        for component in &mut pixel[0..3] {
+            *component = if alpha == 0.0 {
+                from_f32(0.0)
+            } else {
+                from_f32(to_f32(*component) / alpha)
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{DrawElementsIndirectCommand, TextureData, TextureDataRef, TextureFormat};
+    use half::f16;
+    use pathfinder_color::ColorF;
+    use pathfinder_geometry::vector::vec2i;
+
+    #[test]
+    fn test_check_texture_data_length() {
+        let data = [0u8; 4 * 4 * 4];
+        let data_ref = TextureDataRef::U8(&data);
+        assert!(!data_ref.check_and_extract_data_ptr(vec2i(4, 4), TextureFormat::RGBA8).is_null());
+        assert!(!data_ref.check_and_extract_data_ptr(vec2i(8, 8), TextureFormat::R8).is_null());
+    }
+
+    #[test]
+    fn test_check_u16_texture_data() {
+        let data = [0u16; 4 * 4 * 4];
+        let data_ref = TextureDataRef::U16(&data);
+        assert!(!data_ref.check_and_extract_data_ptr(vec2i(4, 4), TextureFormat::RGBA16).is_null());
+        assert!(!data_ref.check_and_extract_data_ptr(vec2i(8, 8), TextureFormat::R16).is_null());
+    }
+
+    #[test]
+    fn test_two_channel_texture_data() {
+        assert_eq!(TextureFormat::RG8.channels(), 2);
+        assert_eq!(TextureFormat::RG8.bytes_per_pixel(), 2);
+        assert_eq!(TextureFormat::RG16F.bytes_per_pixel(), 4);
+
+        let data = [0u8; 4 * 4 * 2];
+        let data_ref = TextureDataRef::U8(&data);
+        assert!(!data_ref.check_and_extract_data_ptr(vec2i(4, 4), TextureFormat::RG8).is_null());
+
+        let data = [f16::from_f32(0.25), f16::from_f32(0.75)];
+        let data_ref = TextureDataRef::F16(&data);
+        assert!(!data_ref.check_and_extract_data_ptr(vec2i(1, 1), TextureFormat::RG16F).is_null());
+        let pixel = TextureData::F16(data.to_vec()).get_pixel(vec2i(1, 1), vec2i(0, 0));
+        assert_eq!(pixel, ColorF::new(0.25, 0.75, 0.0, 1.0));
+    }
+
+    #[test]
+    fn test_srgb_texture_data() {
+        assert!(TextureFormat::RGBA8Srgb.is_srgb());
+        assert!(!TextureFormat::RGBA8.is_srgb());
+        assert_eq!(TextureFormat::RGBA8Srgb.bytes_per_pixel(), 4);
+
+        let data = [0u8; 4 * 4 * 4];
+        let data_ref = TextureDataRef::U8(&data);
+        assert!(!data_ref.check_and_extract_data_ptr(vec2i(4, 4), TextureFormat::RGBA8Srgb)
+                         .is_null());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_mismatched_texture_data_type_panics() {
+        let data = [0u8; 4 * 4 * 4 * 2];
+        TextureDataRef::U8(&data).check_and_extract_data_ptr(vec2i(4, 4), TextureFormat::RGBA16);
+    }
+
+    #[test]
+    #[should_panic(expected = "Texture data is too short")]
+    fn test_short_texture_data_panics() {
+        let data = [0u8; 4 * 4 * 4 - 1];
+        TextureDataRef::U8(&data).check_and_extract_data_ptr(vec2i(4, 4), TextureFormat::RGBA8);
+    }
+
+    #[test]
+    fn test_unpremultiply() {
+        let mut data = TextureData::U8(vec![64, 32, 0, 128, 200, 100, 50, 0, 10, 20, 30, 255]);
+        data.unpremultiply(TextureFormat::RGBA8);
+        match data {
+            TextureData::U8(data) => {
+                assert_eq!(data, [128, 64, 0, 128, 0, 0, 0, 0, 10, 20, 30, 255]);
             }
+            _ => unreachable!(),
+        }
+
+        let mut data = TextureData::U8(vec![64, 32, 0, 128]);
+        data.unpremultiply(TextureFormat::R8);
+        match data {
+            TextureData::U8(data) => assert_eq!(data, [64, 32, 0, 128]),
+            _ => unreachable!(),
         }
     }
+
+    #[test]
+    #[should_panic]
+    fn test_negative_texture_region_panics() {
+        let data = [0u8; 16];
+        TextureDataRef::U8(&data).check_and_extract_data_ptr(vec2i(-4, 4), TextureFormat::R8);
+    }
+
+    #[test]
+    fn test_draw_elements_indirect_command_layout() {
+        assert_eq!(std::mem::size_of::<DrawElementsIndirectCommand>(), 20);
+        assert_eq!(std::mem::align_of::<DrawElementsIndirectCommand>(), 4);
+    }
 }
@@ -0,0 +1,86 @@
+// pathfinder/gpu/src/readback.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Continuous pixel readback, for capturing every frame.
+
+use crate::{AlphaMode, Device, RenderTarget, TextureData};
+use pathfinder_geometry::rect::RectI;
+use std::collections::VecDeque;
+
+/// Keeps a bounded number of `read_pixels()` readbacks in flight and returns their results in
+/// the order they were issued.
+///
+/// Reading back every frame with `Device::read_pixels()` and waiting for the result right away
+/// stalls the CPU until the GPU catches up. Waiting only once `capacity` readbacks are pending
+/// lets the GPU run that many frames ahead, while bounding the memory used for staging.
+pub struct ReadbackRing<D> where D: Device {
+    pending: VecDeque<D::TextureDataReceiver>,
+    capacity: usize,
+}
+
+impl<D> ReadbackRing<D> where D: Device {
+    pub fn new(capacity: usize) -> ReadbackRing<D> {
+        assert!(capacity > 0, "A readback ring must have room for at least one readback!");
+        ReadbackRing { pending: VecDeque::with_capacity(capacity), capacity }
+    }
+
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of readbacks that have been issued but not yet dequeued.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Begins reading back `viewport` of `target`.
+    ///
+    /// If the ring is full, this first waits for the oldest readback to complete and returns its
+    /// pixels, so that no frame is dropped. Otherwise it returns `None` without blocking.
+    pub fn read_pixels(&mut self,
+                       device: &D,
+                       target: &RenderTarget<D>,
+                       viewport: RectI,
+                       alpha_mode: AlphaMode)
+                       -> Option<TextureData> {
+        let oldest = if self.pending.len() == self.capacity { self.dequeue(device) } else { None };
+        self.pending.push_back(device.read_pixels(target, viewport, alpha_mode));
+        oldest
+    }
+
+    /// Returns the pixels of the oldest pending readback if it has completed, without blocking.
+    ///
+    /// Readbacks are always dequeued in order, so this returns `None` while the oldest is still
+    /// pending even if newer ones have finished.
+    pub fn try_dequeue(&mut self, device: &D) -> Option<TextureData> {
+        let data = device.try_recv_texture_data(self.pending.front()?)?;
+        self.pending.pop_front();
+        Some(data)
+    }
+
+    /// Waits for the oldest pending readback and returns its pixels, or returns `None` if none
+    /// are pending.
+    pub fn dequeue(&mut self, device: &D) -> Option<TextureData> {
+        let receiver = self.pending.pop_front()?;
+        Some(device.recv_texture_data(&receiver))
+    }
+
+    /// Waits for every pending readback and returns their pixels, oldest first.
+    pub fn drain(&mut self, device: &D) -> Vec<TextureData> {
+        self.pending.drain(..).map(|receiver| device.recv_texture_data(&receiver)).collect()
+    }
+}
@@ -0,0 +1,216 @@
+// pathfinder/gpu/src/reduce.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Reductions of textures on the GPU, for statistics such as the luminance histograms that
+//! auto-exposure needs.
+
+use crate::{BufferData, BufferTarget, BufferUploadMode, ComputeDimensions, ComputeState, Device};
+use crate::{FeatureLevel, UniformData};
+use pathfinder_color::ColorF;
+use pathfinder_resources::ResourceLoader;
+use pathfinder_simd::default::{F32x2, F32x4};
+use std::convert::TryInto;
+
+const WORKGROUP_LENGTH: u32 = 16;
+
+const REDUCE_OP_MIN: i32 = 0;
+const REDUCE_OP_MAX: i32 = 1;
+const REDUCE_OP_SUM: i32 = 2;
+const REDUCE_OP_HISTOGRAM: i32 = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReduceOp {
+    /// The minimum of each channel.
+    Min,
+    /// The maximum of each channel.
+    Max,
+    /// The sum of each channel.
+    Sum,
+    /// A histogram of the Rec. 709 luminance of each texel, with `bin_count` equal bins spanning
+    /// `min..max`. Luminances outside that range are counted in the first or last bin.
+    Histogram { bin_count: u32, min: f32, max: f32 },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Reduction {
+    /// The minimum, maximum, or sum of each channel.
+    Channels(ColorF),
+    /// The number of texels in each bin of a histogram.
+    Histogram(Vec<u32>),
+}
+
+/// Reduces textures to a single value per channel or to a histogram, with a compute shader.
+///
+/// This requires `FeatureLevel::D3D11`. Results are read back asynchronously through
+/// `Device::read_buffer()`.
+pub struct TextureReducer<D> where D: Device {
+    program: D::Program,
+    texture_parameter: D::TextureParameter,
+    texture_size_uniform: D::Uniform,
+    op_uniform: D::Uniform,
+    histogram_range_uniform: D::Uniform,
+    histogram_bin_count_uniform: D::Uniform,
+    result_storage_buffer: D::StorageBuffer,
+}
+
+/// A reduction in flight, returned by `TextureReducer::reduce()`.
+pub struct ReductionReceiver<D> where D: Device {
+    op: ReduceOp,
+    receiver: D::BufferDataReceiver,
+    // Kept alive until the readback completes.
+    #[allow(dead_code)]
+    buffer: D::Buffer,
+}
+
+impl<D> TextureReducer<D> where D: Device {
+    pub fn new(device: &D, resources: &dyn ResourceLoader) -> TextureReducer<D> {
+        assert_eq!(device.feature_level(),
+                   FeatureLevel::D3D11,
+                   "Texture reductions need compute shaders!");
+
+        let mut program = device.create_compute_program(resources, "reduce");
+        let local_size = ComputeDimensions { x: WORKGROUP_LENGTH, y: WORKGROUP_LENGTH, z: 1 };
+        device.set_compute_program_local_size(&mut program, local_size);
+
+        let texture_parameter = device.get_texture_parameter(&program, "Texture");
+        let texture_size_uniform = device.get_uniform(&program, "TextureSize");
+        let op_uniform = device.get_uniform(&program, "Op");
+        let histogram_range_uniform = device.get_uniform(&program, "HistogramRange");
+        let histogram_bin_count_uniform = device.get_uniform(&program, "HistogramBinCount");
+        let result_storage_buffer = device.get_storage_buffer(&program, "Result", 0);
+
+        TextureReducer {
+            program,
+            texture_parameter,
+            texture_size_uniform,
+            op_uniform,
+            histogram_range_uniform,
+            histogram_bin_count_uniform,
+            result_storage_buffer,
+        }
+    }
+
+    /// Begins reducing all of `texture` with `op`.
+    ///
+    /// Float formats are reduced as is, while normalized formats are reduced in the [0, 1] range.
+    pub fn reduce(&self, device: &D, texture: &D::Texture, op: ReduceOp) -> ReductionReceiver<D> {
+        let size = device.texture_size(texture);
+        assert!(size.x() > 0 && size.y() > 0, "Can't reduce an empty texture!");
+
+        let workgroup_count = ComputeDimensions {
+            x: (size.x() as u32).div_ceil(WORKGROUP_LENGTH),
+            y: (size.y() as u32).div_ceil(WORKGROUP_LENGTH),
+            z: 1,
+        };
+
+        let (op_index, result_len, histogram_range, bin_count) = match op {
+            ReduceOp::Min | ReduceOp::Max | ReduceOp::Sum => {
+                let op_index = match op {
+                    ReduceOp::Min => REDUCE_OP_MIN,
+                    ReduceOp::Max => REDUCE_OP_MAX,
+                    _ => REDUCE_OP_SUM,
+                };
+                let partial_count = workgroup_count.x as usize * workgroup_count.y as usize;
+                (op_index, partial_count * 4, F32x2::default(), 0)
+            }
+            ReduceOp::Histogram { bin_count, min, max } => {
+                assert!(bin_count > 0, "A histogram needs at least one bin!");
+                assert!(max > min, "The histogram range {}..{} is empty!", min, max);
+                (REDUCE_OP_HISTOGRAM, bin_count as usize, F32x2::new(min, max), bin_count)
+            }
+        };
+
+        // Histogram bins are counted up from zero. Partial results are always overwritten.
+        let buffer = device.create_buffer(BufferUploadMode::Static);
+        device.allocate_buffer(&buffer,
+                               BufferData::Memory(&vec![0u32; result_len]),
+                               BufferTarget::Storage);
+
+        device.dispatch_compute(workgroup_count, &ComputeState {
+            program: &self.program,
+            textures: &[(&self.texture_parameter, texture)],
+            images: &[],
+            uniforms: &[
+                (&self.texture_size_uniform, UniformData::IVec2(size.0)),
+                (&self.op_uniform, UniformData::Int(op_index)),
+                (&self.histogram_range_uniform, UniformData::Vec2(histogram_range)),
+                (&self.histogram_bin_count_uniform, UniformData::Int(bin_count as i32)),
+            ],
+            storage_buffers: &[(&self.result_storage_buffer, &buffer)],
+        });
+
+        let byte_len = result_len * 4;
+        let receiver = device.read_buffer(&buffer, BufferTarget::Storage, 0..byte_len);
+        ReductionReceiver { op, receiver, buffer }
+    }
+}
+
+impl<D> ReductionReceiver<D> where D: Device {
+    /// Returns the result if the reduction has finished, without blocking.
+    pub fn try_recv(&self, device: &D) -> Option<Reduction> {
+        device.try_recv_buffer(&self.receiver).map(|data| decode_reduction(self.op, &data))
+    }
+
+    /// Waits for the reduction to finish and returns the result.
+    pub fn recv(&self, device: &D) -> Reduction {
+        decode_reduction(self.op, &device.recv_buffer(&self.receiver))
+    }
+}
+
+// Combines the partial results that each workgroup wrote, or unpacks the histogram bins.
+fn decode_reduction(op: ReduceOp, data: &[u8]) -> Reduction {
+    let words = data.chunks_exact(4).map(|bytes| u32::from_ne_bytes(bytes.try_into().unwrap()));
+    if let ReduceOp::Histogram { .. } = op {
+        return Reduction::Histogram(words.collect());
+    }
+
+    let components: Vec<f32> = words.map(f32::from_bits).collect();
+    let mut partials = components.chunks_exact(4).map(|partial| {
+        F32x4::new(partial[0], partial[1], partial[2], partial[3])
+    });
+    let first = partials.next().expect("No partial results were read back!");
+    let result = partials.fold(first, |accumulator, partial| {
+        match op {
+            ReduceOp::Min => accumulator.min(partial),
+            ReduceOp::Max => accumulator.max(partial),
+            _ => accumulator + partial,
+        }
+    });
+    Reduction::Channels(ColorF(result))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ReduceOp, Reduction, decode_reduction};
+    use pathfinder_color::ColorF;
+
+    fn encode(values: &[f32]) -> Vec<u8> {
+        values.iter().flat_map(|value| value.to_bits().to_ne_bytes().to_vec()).collect()
+    }
+
+    #[test]
+    fn test_decode_partials() {
+        let data = encode(&[1.0, -2.0, 0.5, 1.0, 3.0, 4.0, -0.5, 0.0]);
+        assert_eq!(decode_reduction(ReduceOp::Min, &data),
+                   Reduction::Channels(ColorF::new(1.0, -2.0, -0.5, 0.0)));
+        assert_eq!(decode_reduction(ReduceOp::Max, &data),
+                   Reduction::Channels(ColorF::new(3.0, 4.0, 0.5, 1.0)));
+        assert_eq!(decode_reduction(ReduceOp::Sum, &data),
+                   Reduction::Channels(ColorF::new(4.0, 2.0, 0.0, 1.0)));
+    }
+
+    #[test]
+    fn test_decode_histogram() {
+        let bins = [3u32, 0, 7];
+        let data: Vec<u8> = bins.iter().flat_map(|bin| bin.to_ne_bytes().to_vec()).collect();
+        let op = ReduceOp::Histogram { bin_count: 3, min: 0.0, max: 1.0 };
+        assert_eq!(decode_reduction(op, &data), Reduction::Histogram(vec![3, 0, 7]));
+    }
+}
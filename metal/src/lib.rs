@@ -33,58 +33,98 @@ use metal::{self, Argument, ArgumentEncoder, BlitCommandEncoder, Buffer, Command
 use metal::{CommandQueue, CompileOptions, ComputeCommandEncoder, ComputePipelineDescriptor};
 use metal::{ComputePipelineState, CoreAnimationDrawable, CoreAnimationDrawableRef};
 use metal::{CoreAnimationLayer, CoreAnimationLayerRef, DepthStencilDescriptor, Device as NativeMetalDevice, DeviceRef, Function, Library};
+use metal::DepthStencilState;
 use metal::{MTLArgument, MTLArgumentEncoder, MTLArgumentType, MTLBlendFactor, MTLBlendOperation};
 use metal::{MTLBlitOption, MTLClearColor, MTLColorWriteMask, MTLCompareFunction, MTLComputePipelineState};
 use metal::{MTLDataType, MTLDevice, MTLIndexType, MTLLoadAction, MTLOrigin, MTLPixelFormat};
 use metal::{MTLPrimitiveType, MTLRegion, MTLRenderPipelineReflection, MTLRenderPipelineState};
 use metal::{MTLResourceOptions, MTLResourceUsage, MTLSamplerAddressMode, MTLSamplerMinMagFilter};
+use metal::{MTLSamplerBorderColor, MTLSamplerMipFilter};
 use metal::{MTLSize, MTLStencilOperation, MTLStorageMode, MTLStoreAction, MTLTextureType};
-use metal::{MTLTextureUsage, MTLVertexFormat, MTLVertexStepFunction, MTLViewport};
+use metal::{MTLTextureUsage, MTLTriangleFillMode, MTLVertexFormat, MTLVertexStepFunction};
+use metal::{MTLScissorRect, MTLViewport};
 use metal::{RenderCommandEncoder, RenderCommandEncoderRef, RenderPassDescriptor};
 use metal::{RenderPassDescriptorRef, RenderPipelineColorAttachmentDescriptorRef};
 use metal::{RenderPipelineDescriptor, RenderPipelineReflection, RenderPipelineReflectionRef};
 use metal::{RenderPipelineState, SamplerDescriptor, SamplerState, StencilDescriptor};
 use metal::{StructMemberRef, StructType, StructTypeRef, TextureDescriptor, Texture, TextureRef};
 use metal::{VertexAttribute, VertexAttributeRef, VertexDescriptor, VertexDescriptorRef};
-use objc::runtime::{Class, Object};
+use objc::runtime::{BOOL, Class, NO, Object};
+use pathfinder_color::{ColorF, ColorU};
 use pathfinder_geometry::rect::RectI;
 use pathfinder_geometry::vector::{Vector2I, vec2i};
-use pathfinder_gpu::{BlendFactor, BlendOp, BufferData, BufferTarget, BufferUploadMode};
-use pathfinder_gpu::{ComputeDimensions, ComputeState, DepthFunc, Device, FeatureLevel};
-use pathfinder_gpu::{ImageAccess, Primitive, ProgramKind, RenderState, RenderTarget, ShaderKind};
-use pathfinder_gpu::{StencilFunc, TextureData, TextureDataRef, TextureFormat};
-use pathfinder_gpu::{TextureSamplingFlags, UniformData, VertexAttrClass};
-use pathfinder_gpu::{VertexAttrDescriptor, VertexAttrType};
+use pathfinder_gpu::{AlphaMode, BlendState};
+use pathfinder_gpu::{BlendFactor, BlendOp, BufferData, BufferTarget, BufferUploadMode, ClearOps};
+use pathfinder_gpu::{ComputeDimensions, ComputeState, CubeFace, DepthFunc, DepthState, Device};
+use pathfinder_gpu::ExternalImageHandle;
+use pathfinder_gpu::{DeviceLimits, Feature, FeatureLevel, FrameStats};
+use pathfinder_gpu::{ImageAccess, PipelineStats, PolygonMode, Primitive, ProgramKind};
+use pathfinder_gpu::{RenderOptions, RenderState, RenderTarget, ShaderKind, Swizzle};
+use pathfinder_gpu::{LoadAction, StencilFunc, StencilState, TextureData, TextureDataRef};
+use pathfinder_gpu::TextureFormat;
+use pathfinder_gpu::{TextureSamplingFlags, TextureUsage, TextureUsageFlags};
+use pathfinder_gpu::{TimestampCalibration, UniformData};
+use pathfinder_gpu::VertexAttrClass;
+use pathfinder_gpu::{UNINITIALIZED_BUFFER_FILL_BYTE, VertexAttrDescriptor, VertexAttrType};
 use pathfinder_resources::ResourceLoader;
 use pathfinder_simd::default::{F32x2, F32x4, I32x2};
 use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap};
 use std::convert::TryInto;
+use std::hash::{Hash, Hasher};
 use std::mem;
-use std::ops::Range;
+use std::ops::{Deref, DerefMut, Range};
 use std::ptr;
 use std::rc::Rc;
 use std::slice;
 use std::sync::{Arc, Condvar, Mutex, MutexGuard};
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 const FIRST_VERTEX_BUFFER_INDEX: u64 = 16;
 
+const MAX_ANISOTROPY: u64 = 16;
+
 pub struct MetalDevice {
     device: NativeMetalDevice,
     main_color_texture: Texture,
     main_depth_stencil_texture: Texture,
     command_queue: CommandQueue,
     scopes: RefCell<Vec<Scope>>,
-    samplers: Vec<SamplerState>,
+    // Samplers for every combination of `TextureSamplingFlags` and border color in use, created
+    // on demand.
+    samplers: RefCell<HashMap<(TextureSamplingFlags, u64), SamplerState>>,
+    // Render pipeline states, created on the first draw that needs each one.
+    render_pipeline_states: RefCell<HashMap<PipelineKey, RenderPipelineState>>,
+    // Depth-stencil states, created on the first draw that needs each one. Only a handful of
+    // depth and stencil configurations are ever used, so these are searched linearly.
+    depth_stencil_states: RefCell<Vec<(DepthStencilKey, DepthStencilState)>>,
     #[allow(dead_code)]
     dispatch_queue: Queue,
     timer_query_shared_event: SharedEvent,
     buffer_upload_shared_event: SharedEvent,
     shared_event_listener: SharedEventListener,
     compute_fence: RefCell<Option<Fence>>,
+    storage_buffer_ranges: RefCell<Vec<MetalStorageBufferRange>>,
     next_timer_query_event_value: Cell<u64>,
     next_buffer_upload_event_value: Cell<u64>,
     buffer_upload_event_data: Arc<BufferUploadEventData>,
+    has_unified_memory: bool,
+    // The CPU and GPU timestamps sampled at creation, used to measure the GPU clock rate.
+    initial_timestamps: Option<(u64, u64)>,
+    cpu_clock: CpuClock,
+    // The encoder for the render pass begun by `begin_render_pass()`, if any.
+    render_pass_encoder: RefCell<Option<RenderCommandEncoder>>,
+    // The color texture and slice that the render pass draws to.
+    render_pass_target: RefCell<Option<(Texture, u64)>>,
+    // The timings being collected for `collect_frame_stats()`, if `reset_frame_stats()` has been
+    // called.
+    frame_stats: RefCell<Option<MetalFrameStats>>,
+}
+
+struct MetalFrameStats {
+    stats: FrameStats,
+    // Timers for the command buffers of ended scopes, which the GPU may not have finished yet.
+    pending_scopes: Vec<MetalTimerQuery>,
 }
 
 pub enum MetalProgram {
@@ -93,11 +133,13 @@ pub enum MetalProgram {
 }
 
 pub struct MetalRasterProgram {
+    name: String,
     vertex_shader: MetalShader,
     fragment_shader: MetalShader,
 }
 
 pub struct MetalComputeProgram {
+    name: String,
     shader: MetalShader,
     local_size: MTLSize,
 }
@@ -106,12 +148,17 @@ pub struct MetalComputeProgram {
 pub struct MetalBuffer {
     allocations: Rc<RefCell<BufferAllocations>>,
     mode: BufferUploadMode,
+    // The name set by `set_buffer_label()`, which is reapplied whenever the buffer is reallocated.
+    label: Rc<RefCell<Option<String>>>,
 }
 
 struct BufferAllocations {
     private: Option<Buffer>,
     shared: Option<StagingBuffer>,
     byte_size: u64,
+    // If true, `private` actually lives in shared memory and can be written to directly by the
+    // CPU, without going through a staging buffer.
+    cpu_writable: bool,
 }
 
 struct StagingBuffer {
@@ -122,6 +169,76 @@ struct StagingBuffer {
 struct Scope {
     autorelease_pool: id,
     command_buffer: CommandBuffer,
+    // Times the command buffer for `collect_frame_stats()`.
+    timer_query: Option<MetalTimerQuery>,
+}
+
+// Everything a draw's render pipeline state depends on.
+//
+// The functions are compared by address. The key retains them, so that their addresses can't be
+// reused by new objects while the pipeline state is cached. The vertex layout is compared by
+// value, so that vertex arrays with the same layout share pipeline states and dropping a vertex
+// array leaves no stale entries behind.
+struct PipelineKey {
+    vertex_function: Function,
+    fragment_function: Function,
+    vertex_layout: Rc<VertexLayout>,
+    color_pixel_format: MTLPixelFormat,
+    blend: Option<BlendState>,
+    color_mask: bool,
+    has_depth: bool,
+}
+
+impl PartialEq for PipelineKey {
+    fn eq(&self, other: &PipelineKey) -> bool {
+        self.vertex_function.as_ptr() == other.vertex_function.as_ptr() &&
+            self.fragment_function.as_ptr() == other.fragment_function.as_ptr() &&
+            self.vertex_layout == other.vertex_layout &&
+            self.color_pixel_format == other.color_pixel_format &&
+            self.blend == other.blend &&
+            self.color_mask == other.color_mask &&
+            self.has_depth == other.has_depth
+    }
+}
+
+impl Eq for PipelineKey {}
+
+impl Hash for PipelineKey {
+    fn hash<H>(&self, state: &mut H) where H: Hasher {
+        self.vertex_function.as_ptr().hash(state);
+        self.fragment_function.as_ptr().hash(state);
+        self.vertex_layout.hash(state);
+        self.color_pixel_format.hash(state);
+        self.blend.hash(state);
+        self.color_mask.hash(state);
+        self.has_depth.hash(state);
+    }
+}
+
+// The layout that a vertex array's descriptor describes, mirrored so that it can be hashed.
+#[derive(Clone, Default, PartialEq, Eq, Hash)]
+struct VertexLayout {
+    // Maps each attribute index to the attribute's format, offset and buffer index.
+    attributes: BTreeMap<u64, (u64, u64, u64)>,
+    // Maps each buffer index to its stride and instance step rate, which is zero for per-vertex
+    // data.
+    buffers: BTreeMap<u64, (u64, u64)>,
+}
+
+// Everything a draw's depth-stencil state depends on. The stencil reference value is set on the
+// encoder instead, so it's always zero here.
+#[derive(Clone, Copy, PartialEq)]
+struct DepthStencilKey {
+    depth: Option<DepthState>,
+    stencil: Option<StencilState>,
+}
+
+// A storage buffer binding made with `bind_storage_buffer_range()`. Metal encoders don't retain
+// bindings, so these are replayed on every subsequent draw and dispatch.
+struct MetalStorageBufferRange {
+    name: String,
+    buffer: Buffer,
+    offset: u64,
 }
 
 impl MetalDevice {
@@ -131,38 +248,6 @@ impl MetalDevice {
         let device = device.into_metal_device();
         let command_queue = device.new_command_queue();
 
-        let samplers = (0..16).map(|sampling_flags_value| {
-            let sampling_flags = TextureSamplingFlags::from_bits(sampling_flags_value).unwrap();
-            let sampler_descriptor = SamplerDescriptor::new();
-            sampler_descriptor.set_support_argument_buffers(true);
-            sampler_descriptor.set_normalized_coordinates(true);
-            sampler_descriptor.set_min_filter(
-                if sampling_flags.contains(TextureSamplingFlags::NEAREST_MIN) {
-                    MTLSamplerMinMagFilter::Nearest
-                } else {
-                    MTLSamplerMinMagFilter::Linear
-                });
-            sampler_descriptor.set_mag_filter(
-                if sampling_flags.contains(TextureSamplingFlags::NEAREST_MAG) {
-                    MTLSamplerMinMagFilter::Nearest
-                } else {
-                    MTLSamplerMinMagFilter::Linear
-                });
-            sampler_descriptor.set_address_mode_s(
-                if sampling_flags.contains(TextureSamplingFlags::REPEAT_U) {
-                    MTLSamplerAddressMode::Repeat
-                } else {
-                    MTLSamplerAddressMode::ClampToEdge
-                });
-            sampler_descriptor.set_address_mode_t(
-                if sampling_flags.contains(TextureSamplingFlags::REPEAT_V) {
-                    MTLSamplerAddressMode::Repeat
-                } else {
-                    MTLSamplerAddressMode::ClampToEdge
-                });
-            device.new_sampler(&sampler_descriptor)
-        }).collect();
-
         let texture = texture.into_texture(&device);
         let framebuffer_size = vec2i(texture.width() as i32, texture.height() as i32);
         let main_depth_stencil_texture = device.create_depth_stencil_texture(framebuffer_size);
@@ -179,21 +264,37 @@ impl MetalDevice {
             cond: Condvar::new(),
         });
 
+        // Low-power (integrated) GPUs on macOS and all GPUs on iOS share memory with the CPU, so
+        // frequently-updated buffers are cheaper to write directly than to stage and blit.
+        let has_unified_memory = cfg!(target_os = "ios") || device.is_low_power();
+
+        let initial_timestamps = device.sample_timestamps();
+        let cpu_clock = CpuClock::new();
+
         MetalDevice {
             device,
             main_color_texture: texture,
             main_depth_stencil_texture,
             command_queue,
             scopes: RefCell::new(vec![]),
-            samplers,
+            samplers: RefCell::new(HashMap::new()),
+            render_pipeline_states: RefCell::new(HashMap::new()),
+            depth_stencil_states: RefCell::new(vec![]),
             dispatch_queue,
             timer_query_shared_event,
             buffer_upload_shared_event,
             shared_event_listener,
             compute_fence: RefCell::new(None),
+            storage_buffer_ranges: RefCell::new(vec![]),
             next_timer_query_event_value: Cell::new(1),
             next_buffer_upload_event_value: Cell::new(1),
             buffer_upload_event_data,
+            has_unified_memory,
+            initial_timestamps,
+            cpu_clock,
+            render_pass_encoder: RefCell::new(None),
+            render_pass_target: RefCell::new(None),
+            frame_stats: RefCell::new(None),
         }
     }
 
@@ -210,6 +311,15 @@ impl MetalDevice {
         self.device.clone()
     }
 
+    /// Returns the format of a native Metal texture, or `None` if Pathfinder has no equivalent.
+    ///
+    /// This is useful for checking textures that come from outside Pathfinder, such as Core
+    /// Animation drawables, before wrapping them.
+    #[inline]
+    pub fn try_texture_format(&self, texture: &Texture) -> Option<TextureFormat> {
+        TextureFormat::from_metal_pixel_format(texture.pixel_format())
+    }
+
     /// A convenience function to present a Core Animation drawable.
     pub fn present_drawable(&self, drawable: &CoreAnimationDrawableRef) {
         self.begin_commands();
@@ -220,9 +330,111 @@ impl MetalDevice {
         }
         self.end_commands();
     }
+
+    /// Presents a Core Animation drawable on the current command buffer and commits it.
+    ///
+    /// Unlike `present_drawable()`, which uses a command buffer of its own, this guarantees that
+    /// the present is ordered after all work encoded so far. As with `add_fence()`, a new command
+    /// buffer is begun afterward, so this must be called between `begin_commands()` and
+    /// `end_commands()`. The returned fence is signaled when the frame's work has completed.
+    pub fn submit_and_present(&self, drawable: &CoreAnimationDrawableRef) -> MetalFence {
+        {
+            let scopes = self.scopes.borrow();
+            let scope = scopes.last().expect("Must call `begin_commands()` first!");
+            scope.command_buffer.present_drawable(drawable);
+        }
+        self.add_fence()
+    }
+
+    /// Maps the given byte range of a buffer for writing only, to refill it.
+    ///
+    /// If `invalidate` is true, the old contents of the range are discarded. Any bytes of the
+    /// range that aren't written are then undefined. When the whole buffer is invalidated, it is
+    /// given fresh storage, like `orphan_buffer()`, so the write doesn't wait for in-flight
+    /// commands. On unified memory, the returned slice then points straight at that storage.
+    /// Otherwise the writes are staged and uploaded when the returned guard is dropped.
+    pub fn map_buffer_write<'a>(&'a self,
+                                buffer: &'a MetalBuffer,
+                                range: Range<usize>,
+                                invalidate: bool)
+                                -> MetalBufferWriteMapping<'a> {
+        assert!(range.start <= range.end);
+        let byte_size = buffer.allocations.borrow().byte_size as usize;
+        assert!(range.end <= byte_size,
+                "Map range {:?} is out of bounds of a buffer of {} bytes!",
+                range,
+                byte_size);
+
+        let len = range.end - range.start;
+        if invalidate && range.start == 0 && range.end == byte_size {
+            self.orphan_buffer(buffer);
+            let allocations = buffer.allocations.borrow();
+            if allocations.cpu_writable {
+                let ptr = allocations.private.as_ref().unwrap().contents() as *mut u8;
+                return MetalBufferWriteMapping {
+                    device: self,
+                    buffer,
+                    start: 0,
+                    ptr,
+                    len,
+                    staging: None,
+                };
+            }
+        }
+
+        let mut staging = vec![0; len];
+        let ptr = staging.as_mut_ptr();
+        MetalBufferWriteMapping {
+            device: self,
+            buffer,
+            start: range.start,
+            ptr,
+            len,
+            staging: Some(staging),
+        }
+    }
 }
 
-pub struct MetalFramebuffer(MetalTexture);
+/// A range of a buffer mapped for writing by `MetalDevice::map_buffer_write()`.
+///
+/// Staged writes are uploaded when this is dropped.
+pub struct MetalBufferWriteMapping<'a> {
+    device: &'a MetalDevice,
+    buffer: &'a MetalBuffer,
+    start: usize,
+    ptr: *mut u8,
+    len: usize,
+    staging: Option<Vec<u8>>,
+}
+
+impl<'a> Deref for MetalBufferWriteMapping<'a> {
+    type Target = [u8];
+    #[inline]
+    fn deref(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr, self.len) }
+    }
+}
+
+impl<'a> DerefMut for MetalBufferWriteMapping<'a> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.ptr, self.len) }
+    }
+}
+
+impl<'a> Drop for MetalBufferWriteMapping<'a> {
+    fn drop(&mut self) {
+        if let Some(staging) = self.staging.take() {
+            self.device.upload_to_buffer(self.buffer, self.start, &staging, BufferTarget::Vertex);
+        }
+    }
+}
+
+pub struct MetalFramebuffer {
+    texture: MetalTexture,
+    // The face rendered to, if `texture` is a cube texture.
+    cube_face: Option<CubeFace>,
+}
 
 pub struct MetalShader {
     #[allow(dead_code)]
@@ -233,10 +445,24 @@ pub struct MetalShader {
     arguments: RefCell<Option<ArgumentArray>>,
 }
 
+/// A handle to a texture.
+///
+/// Handles can be cloned cheaply; all clones refer to the same Metal texture and share its
+/// sampling mode.
+#[derive(Clone)]
 pub struct MetalTexture {
     private_texture: Texture,
-    shared_buffer: RefCell<Option<Buffer>>,
-    sampling_flags: Cell<TextureSamplingFlags>,
+    shared_buffer: Rc<RefCell<Option<Buffer>>>,
+    sampling_flags: Rc<Cell<TextureSamplingFlags>>,
+    swizzle: Rc<Cell<[Swizzle; 4]>>,
+    // A view of `private_texture` with `swizzle` applied, which shaders sample instead of it. This
+    // is `None` if the swizzle is the identity.
+    swizzled_view: Rc<RefCell<Option<Texture>>>,
+    // A copy of `private_texture` with a full mip chain, which shaders sample instead of it when
+    // mipmapping is enabled. Metal textures can't gain levels after they're created, so this is
+    // allocated by the first call to `generate_mipmaps()`.
+    mipmapped_texture: Rc<RefCell<Option<Texture>>>,
+    border_color: Rc<Cell<MTLSamplerBorderColor>>,
 }
 
 #[derive(Clone)]
@@ -247,6 +473,7 @@ struct MetalTextureDataReceiverInfo {
     cond: Condvar,
     texture: Texture,
     viewport: RectI,
+    alpha_mode: AlphaMode,
 }
 
 #[derive(Clone)]
@@ -347,6 +574,8 @@ enum MetalFenceStatus {
 
 pub struct MetalVertexArray {
     descriptor: VertexDescriptor,
+    // Copied on write, so that pipeline keys holding the old layout keep it.
+    layout: RefCell<Rc<VertexLayout>>,
     vertex_buffers: RefCell<Vec<MetalBuffer>>,
     index_buffer: RefCell<Option<MetalBuffer>>,
 }
@@ -357,6 +586,8 @@ impl Device for MetalDevice {
     type Fence = MetalFence;
     type Framebuffer = MetalFramebuffer;
     type ImageParameter = MetalImageParameter;
+    type OcclusionQuery = ();
+    type PipelineStatsQuery = ();
     type Program = MetalProgram;
     type Shader = MetalShader;
     type StorageBuffer = MetalStorageBuffer;
@@ -383,14 +614,136 @@ impl Device for MetalDevice {
         FeatureLevel::D3D11
     }
 
-    // TODO: Add texture usage hint.
-    fn create_texture(&self, format: TextureFormat, size: Vector2I) -> MetalTexture {
+    fn supports_feature(&self, feature: Feature) -> bool {
+        match feature {
+            // TODO: Metal tessellation works quite differently from OpenGL's, with tessellation
+            // factors computed in a compute kernel and a post-tessellation vertex function.
+            Feature::TessellationShaders => false,
+            // TODO: Metal 3 object and mesh functions need `MTLMeshRenderPipelineDescriptor`,
+            // which metal-rs doesn't expose yet.
+            Feature::MeshShaders => false,
+            // Swizzled texture views need macOS 10.15 or iOS 13.
+            Feature::TextureSwizzle => self.main_color_texture.supports_swizzled_views(),
+        }
+    }
+
+    fn device_limits(&self) -> DeviceLimits {
+        let max_threads_per_threadgroup = self.device.max_threads_per_threadgroup();
+        DeviceLimits {
+            // Every Mac GPU supports 16384x16384 textures, but older iOS GPUs only support
+            // 8192x8192.
+            max_texture_size: Vector2I::splat(if cfg!(target_os = "macos") { 16384 } else { 8192 }),
+            max_compute_work_group_size: [
+                max_threads_per_threadgroup.width as u32,
+                max_threads_per_threadgroup.height as u32,
+                max_threads_per_threadgroup.depth as u32,
+            ],
+            max_storage_buffer_size: self.device.max_buffer_length(),
+            max_vertex_attributes: 31,
+        }
+    }
+
+    #[inline]
+    fn storage_buffer_offset_alignment(&self) -> usize {
+        // Offsets into buffers must be 256-byte aligned on macOS, but only 16-byte aligned on
+        // iOS.
+        if cfg!(target_os = "macos") { 256 } else { 16 }
+    }
+
+    fn format_is_filterable(&self, format: TextureFormat) -> bool {
+        match format {
+            // TODO: Newer iOS GPUs can filter these; query `supports32BitFloatFiltering` once
+            // metal-rs exposes it.
+            TextureFormat::RGBA32F => cfg!(target_os = "macos"),
+            TextureFormat::R8 |
+            TextureFormat::R16 |
+            TextureFormat::R16F |
+            TextureFormat::RG8 |
+            TextureFormat::RG16F |
+            TextureFormat::RGBA8 |
+            TextureFormat::RGBA8Srgb |
+            TextureFormat::BGRA8 |
+            TextureFormat::RGBA16 |
+            TextureFormat::RGBA16F => true,
+        }
+    }
+
+    fn supports_texture_format(&self, format: TextureFormat, usage: TextureUsage) -> bool {
+        // Every format can be sampled and rendered to on all Metal feature sets.
+        match usage {
+            TextureUsage::Sampled | TextureUsage::RenderTarget => true,
+            TextureUsage::Filtered => self.format_is_filterable(format),
+        }
+    }
+
+    fn supports_non_power_of_two_mipmaps(&self) -> bool {
+        true
+    }
+
+    fn supported_sample_counts(&self, _: TextureFormat) -> Vec<u32> {
+        // TODO: Some older iOS GPUs can't multisample 32-bit float formats. Metal has no per-format
+        // query, so this reports the device's limits.
+        [1, 2, 4, 8].iter().cloned().filter(|&sample_count| {
+            self.device.supports_texture_sample_count(sample_count as u64)
+        }).collect()
+    }
+
+    fn create_texture_with_usage(&self,
+                                 format: TextureFormat,
+                                 size: Vector2I,
+                                 usage: TextureUsageFlags)
+                                 -> MetalTexture {
         let descriptor = create_texture_descriptor(format, size);
         descriptor.set_storage_mode(MTLStorageMode::Private);
+        descriptor.set_usage(usage.to_metal_texture_usage());
         MetalTexture {
             private_texture: self.device.new_texture(&descriptor),
-            shared_buffer: RefCell::new(None),
-            sampling_flags: Cell::new(TextureSamplingFlags::empty()),
+            shared_buffer: Rc::new(RefCell::new(None)),
+            sampling_flags: Rc::new(Cell::new(TextureSamplingFlags::empty())),
+            swizzle: Rc::new(Cell::new(Swizzle::IDENTITY)),
+            swizzled_view: Rc::new(RefCell::new(None)),
+            mipmapped_texture: Rc::new(RefCell::new(None)),
+            border_color: Rc::new(Cell::new(MTLSamplerBorderColor::TransparentBlack)),
+        }
+    }
+
+    unsafe fn import_external_texture(&self, handle: ExternalImageHandle) -> MetalTexture {
+        let io_surface = match handle {
+            ExternalImageHandle::IOSurface(io_surface) => io_surface as IOSurfaceRef,
+            ExternalImageHandle::EGLImage { .. } => {
+                panic!("EGL images can't be imported into Metal; use the OpenGL backend!")
+            }
+        };
+        MetalTexture {
+            private_texture: io_surface.into_texture(&self.device),
+            shared_buffer: Rc::new(RefCell::new(None)),
+            sampling_flags: Rc::new(Cell::new(TextureSamplingFlags::empty())),
+            swizzle: Rc::new(Cell::new(Swizzle::IDENTITY)),
+            swizzled_view: Rc::new(RefCell::new(None)),
+            mipmapped_texture: Rc::new(RefCell::new(None)),
+            border_color: Rc::new(Cell::new(MTLSamplerBorderColor::TransparentBlack)),
+        }
+    }
+
+    fn create_render_target_texture(&self, format: TextureFormat, size: Vector2I)
+                                    -> MetalTexture {
+        self.create_texture(format, size)
+    }
+
+    fn create_cube_texture(&self, format: TextureFormat, size: Vector2I) -> MetalTexture {
+        assert_eq!(size.x(), size.y(), "Cube texture faces must be square!");
+        let descriptor = create_texture_descriptor(format, size);
+        descriptor.set_texture_type(MTLTextureType::Cube);
+        descriptor.set_storage_mode(MTLStorageMode::Private);
+        descriptor.set_usage(MTLTextureUsage::RenderTarget | MTLTextureUsage::ShaderRead);
+        MetalTexture {
+            private_texture: self.device.new_texture(&descriptor),
+            shared_buffer: Rc::new(RefCell::new(None)),
+            sampling_flags: Rc::new(Cell::new(TextureSamplingFlags::empty())),
+            swizzle: Rc::new(Cell::new(Swizzle::IDENTITY)),
+            swizzled_view: Rc::new(RefCell::new(None)),
+            mipmapped_texture: Rc::new(RefCell::new(None)),
+            border_color: Rc::new(Cell::new(MTLSamplerBorderColor::TransparentBlack)),
         }
     }
 
@@ -401,12 +754,33 @@ impl Device for MetalDevice {
         texture
     }
 
-    fn create_shader_from_source(&self, name: &str, source: &[u8], _: ShaderKind) -> MetalShader {
-        let source = String::from_utf8(source.to_vec()).expect("Source wasn't valid UTF-8!");
+    fn create_texture_filled(&self, format: TextureFormat, size: Vector2I, color: ColorU)
+                             -> MetalTexture {
+        // Clear in a command buffer of our own, so that this works outside `begin_commands()`.
+        self.begin_commands();
+        let framebuffer = self.create_framebuffer(self.create_render_target_texture(format, size));
+        let clear_ops = ClearOps { color: Some(color.to_f32()), ..ClearOps::default() };
+        self.clear(&RenderTarget::Framebuffer(&framebuffer), &clear_ops);
+        self.end_commands();
+        self.destroy_framebuffer(framebuffer)
+    }
+
+    fn create_shader_from_source(&self, name: &str, source: &[u8], kind: ShaderKind)
+                                 -> MetalShader {
+        let source = String::from_utf8(source.to_vec()).unwrap_or_else(|_| {
+            panic!("Source of {:?} shader '{}' wasn't valid UTF-8!", kind, name)
+        });
 
         let compile_options = CompileOptions::new();
-        let library = self.device.new_library_with_source(&source, &compile_options).unwrap();
-        let function = library.get_function("main0", None).unwrap();
+        let library = self.device
+                          .new_library_with_source(&source, &compile_options)
+                          .unwrap_or_else(|error| {
+            panic!("{:?} shader '{}' compilation failed: {}", kind, name, error)
+        });
+        library.set_label(name);
+        let function = library.get_function("main0", None).unwrap_or_else(|error| {
+            panic!("{:?} shader '{}' has no `main0` function: {}", kind, name, error)
+        });
 
         MetalShader {
             library,
@@ -419,6 +793,7 @@ impl Device for MetalDevice {
     fn create_vertex_array(&self) -> MetalVertexArray {
         MetalVertexArray {
             descriptor: VertexDescriptor::new().retain(),
+            layout: RefCell::new(Rc::new(VertexLayout::default())),
             vertex_buffers: RefCell::new(vec![]),
             index_buffer: RefCell::new(None),
         }
@@ -441,16 +816,23 @@ impl Device for MetalDevice {
 
     fn create_program_from_shaders(&self,
                                    _: &dyn ResourceLoader,
-                                   _: &str,
+                                   name: &str,
                                    shaders: ProgramKind<MetalShader>)
                                    -> MetalProgram {
+        let name = name.to_owned();
         match shaders {
             ProgramKind::Raster { vertex: vertex_shader, fragment: fragment_shader } => {
-                MetalProgram::Raster(MetalRasterProgram { vertex_shader, fragment_shader })
+                MetalProgram::Raster(MetalRasterProgram { name, vertex_shader, fragment_shader })
+            }
+            ProgramKind::TessellatedRaster { .. } => {
+                panic!("Tessellation shaders are not yet supported on Metal!")
+            }
+            ProgramKind::MeshRaster { .. } => {
+                panic!("Mesh shaders are not yet supported on Metal!")
             }
             ProgramKind::Compute(shader) => {
                 let local_size = MTLSize { width: 0, height: 0, depth: 0 };
-                MetalProgram::Compute(MetalComputeProgram { shader, local_size })
+                MetalProgram::Compute(MetalComputeProgram { name, shader, local_size })
             }
         }
     }
@@ -502,6 +884,29 @@ impl Device for MetalDevice {
         MetalStorageBuffer { indices: RefCell::new(None), name: name.to_owned() }
     }
 
+    fn bind_storage_buffer_range(&self,
+                                 storage_buffer: &MetalStorageBuffer,
+                                 buffer: &MetalBuffer,
+                                 offset: usize,
+                                 size: usize) {
+        self.debug_check_storage_buffer_offset(offset as u64);
+        let allocations = buffer.allocations.borrow();
+        assert!((offset + size) as u64 <= allocations.byte_size,
+                "Storage buffer range {:?} is out of bounds of a buffer of {} bytes!",
+                offset..(offset + size),
+                allocations.byte_size);
+        let private_buffer = allocations.private.as_ref().expect("Buffer not allocated!");
+
+        // Metal buffer bindings have no size; the shader sees everything from `offset` onward.
+        let mut ranges = self.storage_buffer_ranges.borrow_mut();
+        ranges.retain(|range| range.name != storage_buffer.name);
+        ranges.push(MetalStorageBufferRange {
+            name: storage_buffer.name.clone(),
+            buffer: private_buffer.clone(),
+            offset: offset as u64,
+        });
+    }
+
     fn configure_vertex_attr(&self,
                              vertex_array: &MetalVertexArray,
                              attr: &VertexAttribute,
@@ -602,10 +1007,27 @@ impl Device for MetalDevice {
             layout.set_step_rate(descriptor.divisor as u64);
         }
         layout.set_stride(descriptor.stride as u64);
+
+        let mut vertex_layout = vertex_array.layout.borrow_mut();
+        let vertex_layout = Rc::make_mut(&mut *vertex_layout);
+        vertex_layout.attributes.insert(attribute_index,
+                                        (format as u64, descriptor.offset as u64, buffer_index));
+        vertex_layout.buffers.insert(buffer_index,
+                                     (descriptor.stride as u64, descriptor.divisor as u64));
     }
 
     fn create_framebuffer(&self, texture: MetalTexture) -> MetalFramebuffer {
-        MetalFramebuffer(texture)
+        assert!(texture.private_texture.texture_type() != MTLTextureType::Cube,
+                "Use `create_framebuffer_for_cube_face()` for cube textures!");
+        MetalFramebuffer { texture, cube_face: None }
+    }
+
+    fn create_framebuffer_for_cube_face(&self, texture: &MetalTexture, face: CubeFace)
+                                        -> MetalFramebuffer {
+        assert_eq!(texture.private_texture.texture_type(),
+                   MTLTextureType::Cube,
+                   "The texture isn't a cube texture!");
+        MetalFramebuffer { texture: texture.clone(), cube_face: Some(face) }
     }
 
     fn create_buffer(&self, mode: BufferUploadMode) -> MetalBuffer {
@@ -614,8 +1036,10 @@ impl Device for MetalDevice {
                 private: None,
                 shared: None,
                 byte_size: 0,
+                cpu_writable: false,
             })),
             mode,
+            label: Rc::new(RefCell::new(None)),
         }
     }
 
@@ -623,26 +1047,31 @@ impl Device for MetalDevice {
                           buffer: &MetalBuffer,
                           data: BufferData<T>,
                           target: BufferTarget) {
-        let options = buffer.mode.to_metal_resource_options();
         let length = match data {
             BufferData::Uninitialized(size) => size,
             BufferData::Memory(slice) => slice.len(),
         };
         let byte_size = (length * mem::size_of::<T>()) as u64;
-        let new_buffer = self.device.new_buffer(byte_size, options);
-
-        *buffer.allocations.borrow_mut() = BufferAllocations {
-             private: Some(new_buffer),
-             shared: None,
-             byte_size,
-        };
+        *buffer.allocations.borrow_mut() = self.create_buffer_allocations(buffer, byte_size);
 
         match data {
+            BufferData::Uninitialized(_) if cfg!(debug_assertions) => {
+                let debug_fill = vec![UNINITIALIZED_BUFFER_FILL_BYTE; byte_size as usize];
+                self.upload_to_buffer(buffer, 0, &debug_fill, target)
+            }
             BufferData::Uninitialized(_) => {}
             BufferData::Memory(slice) => self.upload_to_buffer(buffer, 0, slice, target),
         }
     }
 
+    fn orphan_buffer(&self, buffer: &MetalBuffer) {
+        // Command buffers retain the buffers they use, so the old allocation stays alive until
+        // the GPU is done with it.
+        let mut allocations = buffer.allocations.borrow_mut();
+        let byte_size = allocations.byte_size;
+        *allocations = self.create_buffer_allocations(buffer, byte_size);
+    }
+
     fn upload_to_buffer<T>(&self,
                            dest_buffer: &MetalBuffer,
                            start: usize,
@@ -652,12 +1081,38 @@ impl Device for MetalDevice {
             return;
         }
 
+        let byte_start = (start * mem::size_of::<T>()) as u64;
+        let byte_size = (data.len() * mem::size_of::<T>()) as u64;
+
         let mut dest_allocations = dest_buffer.allocations.borrow_mut();
+        let mut freshly_allocated = false;
+        if dest_allocations.byte_size == 0 {
+            // Empty buffers grow to fit their first upload.
+            *dest_allocations = self.create_buffer_allocations(dest_buffer, byte_start + byte_size);
+            freshly_allocated = true;
+        } else if dest_allocations.cpu_writable && byte_start == 0 &&
+                byte_size == dest_allocations.byte_size {
+            // Orphan the old buffer when overwriting the whole thing, so that in-flight commands
+            // can keep reading it.
+            *dest_allocations = self.create_buffer_allocations(dest_buffer, byte_size);
+            freshly_allocated = true;
+        }
         let dest_allocations = &mut *dest_allocations;
         let dest_private_buffer = dest_allocations.private.as_mut().unwrap();
 
-        let byte_start = (start * mem::size_of::<T>()) as u64;
-        let byte_size = (data.len() * mem::size_of::<T>()) as u64;
+        // Fast path: on unified memory, write straight into the buffer the GPU reads from. This is
+        // only safe if no command can have referenced the buffer yet. Partial writes into a buffer
+        // that may be in flight go through the staging buffer below, since the blit is ordered
+        // after the commands already encoded.
+        if dest_allocations.cpu_writable && freshly_allocated {
+            unsafe {
+                ptr::copy_nonoverlapping(
+                    data.as_ptr() as *const u8,
+                    (dest_private_buffer.contents() as *mut u8).offset(byte_start as isize),
+                    byte_size as usize)
+            }
+            return;
+        }
 
         if dest_allocations.shared.is_none() {
             let resource_options = MTLResourceOptions::CPUCacheModeWriteCombined |
@@ -717,24 +1172,31 @@ impl Device for MetalDevice {
         self.begin_commands();
     }
 
+    fn flush_mapped_range(&self, buffer: &MetalBuffer, range: Range<usize>) {
+        let allocations = buffer.allocations.borrow();
+        assert!(range.start <= range.end && range.end as u64 <= allocations.byte_size,
+                "Flush range {:?} is out of bounds of a buffer of {} bytes!",
+                range,
+                allocations.byte_size);
+        // All CPU-visible buffers use shared storage, which is coherent, so there's nothing to
+        // do. A managed buffer would need `didModifyRange:` here.
+    }
+
     #[inline]
     fn framebuffer_texture<'f>(&self, framebuffer: &'f MetalFramebuffer) -> &'f MetalTexture {
-        &framebuffer.0
+        &framebuffer.texture
     }
 
     #[inline]
     fn destroy_framebuffer(&self, framebuffer: MetalFramebuffer) -> MetalTexture {
-        framebuffer.0
+        framebuffer.texture
     }
 
     fn texture_format(&self, texture: &MetalTexture) -> TextureFormat {
-        match texture.private_texture.pixel_format() {
-            MTLPixelFormat::R8Unorm => TextureFormat::R8,
-            MTLPixelFormat::R16Float => TextureFormat::R16F,
-            MTLPixelFormat::RGBA8Unorm => TextureFormat::RGBA8,
-            MTLPixelFormat::RGBA16Float => TextureFormat::RGBA16F,
-            MTLPixelFormat::RGBA32Float => TextureFormat::RGBA32F,
-            _ => panic!("Unexpected Metal texture format!"),
+        let pixel_format = texture.private_texture.pixel_format();
+        match TextureFormat::from_metal_pixel_format(pixel_format) {
+            Some(format) => format,
+            None => panic!("Unsupported Metal pixel format {:?}!", pixel_format),
         }
     }
 
@@ -742,19 +1204,59 @@ impl Device for MetalDevice {
         vec2i(texture.private_texture.width() as i32, texture.private_texture.height() as i32)
     }
 
+    fn texture_level_count(&self, texture: &MetalTexture) -> u32 {
+        match *texture.mipmapped_texture.borrow() {
+            Some(ref mipmapped_texture) => mipmapped_texture.mipmap_level_count() as u32,
+            None => 1,
+        }
+    }
+
+    fn render_target_size(&self, target: &RenderTarget<MetalDevice>) -> Vector2I {
+        let texture = self.render_target_color_texture(target);
+        vec2i(texture.width() as i32, texture.height() as i32)
+    }
+
     fn set_texture_sampling_mode(&self, texture: &MetalTexture, flags: TextureSamplingFlags) {
         texture.sampling_flags.set(flags)
     }
 
+    fn set_texture_border_color(&self, texture: &MetalTexture, color: ColorF) {
+        texture.border_color.set(nearest_border_color(color))
+    }
+
+    fn set_texture_swizzle(&self, texture: &MetalTexture, swizzle: [Swizzle; 4]) {
+        let view = if swizzle == Swizzle::IDENTITY {
+            None
+        } else {
+            assert!(texture.private_texture.supports_swizzled_views(),
+                    "Texture swizzle requires macOS 10.15 or iOS 13!");
+            Some(texture.private_texture.new_swizzled_view(swizzle))
+        };
+        texture.swizzle.set(swizzle);
+        *texture.swizzled_view.borrow_mut() = view;
+    }
+
+    #[inline]
+    fn texture_swizzle(&self, texture: &MetalTexture) -> [Swizzle; 4] {
+        texture.swizzle.get()
+    }
+
     fn upload_to_texture(&self, dest_texture: &MetalTexture, rect: RectI, data: TextureDataRef) {
+        assert!(dest_texture.private_texture.texture_type() != MTLTextureType::Cube,
+                "Cube textures can't be uploaded to!");
         let scopes = self.scopes.borrow();
         let command_buffer = &scopes.last()
                                     .expect("Must call `begin_commands()` first!")
                                     .command_buffer;
 
         let texture_size = self.texture_size(dest_texture);
-        let texture_format = self.texture_format(&dest_texture.private_texture)
-                                 .expect("Unexpected texture format!");
+        assert!(rect.min_x() >= 0 && rect.min_y() >= 0 &&
+                rect.max_x() <= texture_size.x() && rect.max_y() <= texture_size.y(),
+                "Upload rect {:?} is out of bounds of a texture of size {:?}!",
+                rect,
+                texture_size);
+
+        let texture_format = self.texture_format(dest_texture);
         let bytes_per_pixel = texture_format.bytes_per_pixel() as u64;
         let texture_byte_size = texture_size.area() as u64 * bytes_per_pixel;
 
@@ -807,15 +1309,115 @@ impl Device for MetalDevice {
         blit_command_encoder.end_encoding();
     }
 
-    fn read_pixels(&self, target: &RenderTarget<MetalDevice>, viewport: RectI)
+    fn generate_mipmaps(&self, texture: &MetalTexture) {
+        assert!(texture.private_texture.texture_type() != MTLTextureType::Cube,
+                "Cube textures can't be mipmapped!");
+        let scopes = self.scopes.borrow();
+        let command_buffer = &scopes.last()
+                                    .expect("Must call `begin_commands()` first!")
+                                    .command_buffer;
+
+        let size = self.texture_size(texture);
+        let mut mipmapped_texture = texture.mipmapped_texture.borrow_mut();
+        if mipmapped_texture.is_none() {
+            let descriptor = create_texture_descriptor(self.texture_format(texture), size);
+            descriptor.set_storage_mode(MTLStorageMode::Private);
+            descriptor.set_mipmap_level_count(mip_level_count(size));
+            *mipmapped_texture = Some(self.device.new_texture(&descriptor));
+        }
+        let mipmapped_texture = mipmapped_texture.as_ref().unwrap();
+
+        let origin = MTLOrigin { x: 0, y: 0, z: 0 };
+        let metal_size = MTLSize { width: size.x() as u64, height: size.y() as u64, depth: 1 };
+        let blit_command_encoder = command_buffer.real_new_blit_command_encoder();
+        blit_command_encoder.copy_from_texture(&texture.private_texture,
+                                               0,
+                                               0,
+                                               origin,
+                                               metal_size,
+                                               mipmapped_texture,
+                                               0,
+                                               0,
+                                               origin);
+        blit_command_encoder.generate_mipmaps(mipmapped_texture);
+        blit_command_encoder.end_encoding();
+    }
+
+    fn copy_texture_to_buffer(&self,
+                              texture: &MetalTexture,
+                              rect: RectI,
+                              buffer: &MetalBuffer,
+                              buffer_offset: usize,
+                              bytes_per_row: usize) {
+        let scopes = self.scopes.borrow();
+        let command_buffer = &scopes.last()
+                                    .expect("Must call `begin_commands()` first!")
+                                    .command_buffer;
+
+        let texture_size = self.texture_size(texture);
+        assert!(rect.min_x() >= 0 && rect.min_y() >= 0 &&
+                rect.max_x() <= texture_size.x() && rect.max_y() <= texture_size.y(),
+                "Copy rect {:?} is out of bounds of a texture of size {:?}!",
+                rect,
+                texture_size);
+
+        let texture_format = self.texture_format(texture);
+        let bytes_per_pixel = texture_format.bytes_per_pixel() as usize;
+        assert!(bytes_per_row >= rect.width() as usize * bytes_per_pixel,
+                "Row pitch {} is too small for {} pixels of {:?}!",
+                bytes_per_row,
+                rect.width(),
+                texture_format);
+
+        let allocations = buffer.allocations.borrow();
+        let byte_size = bytes_per_row * rect.height() as usize;
+        assert!((buffer_offset + byte_size) as u64 <= allocations.byte_size,
+                "Copying {} bytes at offset {} would overflow a buffer of {} bytes!",
+                byte_size,
+                buffer_offset,
+                allocations.byte_size);
+        let dest_buffer = allocations.private
+                                     .as_ref()
+                                     .expect("Must allocate the buffer before copying to it!");
+
+        let src_origin = MTLOrigin { x: rect.origin_x() as u64, y: rect.origin_y() as u64, z: 0 };
+        let src_size = MTLSize {
+            width: rect.width() as u64,
+            height: rect.height() as u64,
+            depth: 1,
+        };
+
+        let blit_command_encoder = command_buffer.real_new_blit_command_encoder();
+        blit_command_encoder.copy_from_texture_to_buffer(&texture.private_texture,
+                                                         0,
+                                                         0,
+                                                         src_origin,
+                                                         src_size,
+                                                         dest_buffer,
+                                                         buffer_offset as u64,
+                                                         bytes_per_row as u64,
+                                                         byte_size as u64,
+                                                         MTLBlitOption::empty());
+        blit_command_encoder.end_encoding();
+    }
+
+    fn read_pixels(&self,
+                   target: &RenderTarget<MetalDevice>,
+                   viewport: RectI,
+                   alpha_mode: AlphaMode)
                    -> MetalTextureDataReceiver {
-        let texture = self.render_target_color_texture(target);
+        // Cube faces are read back through a 2D view, so the download needn't know about slices.
+        let texture = match self.render_target_slice(target) {
+            0 => self.render_target_color_texture(target),
+            slice => self.render_target_color_texture(target).new_face_view(slice),
+        };
         let texture_data_receiver =
             MetalTextureDataReceiver(Arc::new(MetalTextureDataReceiverInfo {
                 mutex: Mutex::new(MetalDataReceiverState::Pending),
                 cond: Condvar::new(),
                 texture,
                 viewport,
+                alpha_mode,
             }));
 
         let texture_data_receiver_for_block = texture_data_receiver.clone();
@@ -901,11 +1503,26 @@ impl Device for MetalDevice {
         unsafe {
             let autorelease_pool = NSAutoreleasePool::new(nil);
             let command_buffer = self.command_queue.new_command_buffer_retained();
-            self.scopes.borrow_mut().push(Scope { autorelease_pool, command_buffer })
+            let scope = Scope { autorelease_pool, command_buffer, timer_query: None };
+            self.scopes.borrow_mut().push(scope);
+        }
+
+        if self.frame_stats.borrow().is_some() {
+            let timer_query = self.create_timer_query();
+            self.begin_timer_query(&timer_query);
+            self.scopes.borrow_mut().last_mut().unwrap().timer_query = Some(timer_query);
         }
     }
 
     fn end_commands(&self) {
+        let timer_query = self.scopes.borrow_mut().last_mut().unwrap().timer_query.take();
+        if let Some(timer_query) = timer_query {
+            self.end_timer_query(&timer_query);
+            if let Some(ref mut frame_stats) = *self.frame_stats.borrow_mut() {
+                frame_stats.pending_scopes.push(timer_query);
+            }
+        }
+
         let scope = self.scopes.borrow_mut().pop().unwrap();
         scope.command_buffer.commit();
         unsafe {
@@ -913,11 +1530,94 @@ impl Device for MetalDevice {
         }
     }
 
+    fn flush(&self) {
+        assert!(self.render_pass_encoder.borrow().is_none(), "Can't flush in a render pass!");
+
+        // Commit the current command buffer and keep recording into a fresh one. The autorelease
+        // pool stays open, since the scope hasn't ended.
+        let mut scopes = self.scopes.borrow_mut();
+        let scope = scopes.last_mut().expect("Must call `begin_commands()` first!");
+        let command_buffer = self.command_queue.new_command_buffer_retained();
+        mem::replace(&mut scope.command_buffer, command_buffer).commit();
+    }
+
+    fn push_debug_group(&self, name: &str) {
+        if !cfg!(debug_assertions) {
+            return;
+        }
+        match *self.render_pass_encoder.borrow() {
+            Some(ref encoder) => encoder.push_debug_group(name),
+            None => {
+                let scopes = self.scopes.borrow();
+                let scope = scopes.last().expect("Must call `begin_commands()` first!");
+                scope.command_buffer.push_debug_group(name);
+            }
+        }
+    }
+
+    fn pop_debug_group(&self) {
+        if !cfg!(debug_assertions) {
+            return;
+        }
+        match *self.render_pass_encoder.borrow() {
+            Some(ref encoder) => encoder.pop_debug_group(),
+            None => {
+                let scopes = self.scopes.borrow();
+                let scope = scopes.last().expect("Must call `begin_commands()` first!");
+                scope.command_buffer.pop_debug_group();
+            }
+        }
+    }
+
+    fn set_texture_label(&self, texture: &MetalTexture, label: &str) {
+        if cfg!(debug_assertions) {
+            texture.private_texture.set_label(label);
+        }
+    }
+
+    fn set_buffer_label(&self, buffer: &MetalBuffer, label: &str) {
+        if !cfg!(debug_assertions) {
+            return;
+        }
+        if let Some(ref private_buffer) = buffer.allocations.borrow().private {
+            private_buffer.set_label(label);
+        }
+        *buffer.label.borrow_mut() = Some(label.to_owned());
+    }
+
+    fn clear(&self, target: &RenderTarget<MetalDevice>, ops: &ClearOps) {
+        if !ops.has_ops() {
+            return;
+        }
+
+        // Record a render pass that clears on load and draws nothing.
+        let options = RenderOptions { clear_ops: *ops, ..RenderOptions::default() };
+        self.new_render_command_encoder(target, &options).end_encoding();
+    }
+
+    fn begin_render_pass(&self,
+                         target: &RenderTarget<MetalDevice>,
+                         clear_ops: &ClearOps,
+                         viewport: RectI) {
+        let options = RenderOptions { clear_ops: *clear_ops, ..RenderOptions::default() };
+        let encoder = self.new_render_command_encoder(target, &options);
+        self.set_viewport(&encoder, &viewport);
+        *self.render_pass_encoder.borrow_mut() = Some(encoder);
+        *self.render_pass_target.borrow_mut() =
+            Some((self.render_target_color_texture(target), self.render_target_slice(target)));
+    }
+
+    fn end_render_pass(&self) {
+        let encoder = self.render_pass_encoder.borrow_mut().take();
+        encoder.expect("No render pass is active!").end_encoding();
+        *self.render_pass_target.borrow_mut() = None;
+    }
+
     fn draw_arrays(&self, index_count: u32, render_state: &RenderState<MetalDevice>) {
         let encoder = self.prepare_to_draw(render_state);
         let primitive = render_state.primitive.to_metal_primitive();
         encoder.draw_primitives(primitive, 0, index_count as u64);
-        encoder.end_encoding();
+        self.finish_draw(&encoder);
     }
 
     fn draw_elements(&self, index_count: u32, render_state: &RenderState<MetalDevice>) {
@@ -931,8 +1631,13 @@ impl Device for MetalDevice {
         let index_buffer = index_buffer.as_ref().expect("No index buffer bound to VAO!");
         let index_buffer = index_buffer.allocations.borrow();
         let index_buffer = index_buffer.private.as_ref().expect("Index buffer not allocated!");
-        encoder.draw_indexed_primitives(primitive, index_count, index_type, index_buffer, 0);
-        encoder.end_encoding();
+        let index_buffer_offset = index_buffer_offset(index_buffer, index_count, render_state);
+        encoder.draw_indexed_primitives(primitive,
+                                        index_count,
+                                        index_type,
+                                        index_buffer,
+                                        index_buffer_offset);
+        self.finish_draw(&encoder);
     }
 
     fn draw_elements_instanced(&self,
@@ -949,19 +1654,85 @@ impl Device for MetalDevice {
         let index_buffer = index_buffer.as_ref().expect("No index buffer bound to VAO!");
         let index_buffer = index_buffer.allocations.borrow();
         let index_buffer = index_buffer.private.as_ref().expect("Index buffer not allocated!");
+        let index_buffer_offset =
+            index_buffer_offset(index_buffer, index_count as u64, render_state);
 
         encoder.draw_indexed_primitives_instanced(primitive,
                                                   index_count as u64,
                                                   index_type,
                                                   index_buffer,
-                                                  0,
+                                                  index_buffer_offset,
                                                   instance_count as u64);
-        encoder.end_encoding();
+        self.finish_draw(&encoder);
+    }
+
+    fn draw_elements_instanced_base(&self,
+                                    index_count: u32,
+                                    instance_count: u32,
+                                    base_instance: u32,
+                                    render_state: &RenderState<MetalDevice>) {
+        let encoder = self.prepare_to_draw(render_state);
+        let primitive = render_state.primitive.to_metal_primitive();
+
+        let index_type = MTLIndexType::UInt32;
+        let index_buffer = render_state.vertex_array
+                                       .index_buffer
+                                       .borrow();
+        let index_buffer = index_buffer.as_ref().expect("No index buffer bound to VAO!");
+        let index_buffer = index_buffer.allocations.borrow();
+        let index_buffer = index_buffer.private.as_ref().expect("Index buffer not allocated!");
+        let index_buffer_offset =
+            index_buffer_offset(index_buffer, index_count as u64, render_state);
+
+        encoder.draw_indexed_primitives_instanced_base_instance(primitive,
+                                                                index_count as u64,
+                                                                index_type,
+                                                                index_buffer,
+                                                                index_buffer_offset,
+                                                                instance_count as u64,
+                                                                0,
+                                                                base_instance as u64);
+        self.finish_draw(&encoder);
+    }
+
+    fn draw_elements_indirect(&self,
+                              indirect_buffer: &MetalBuffer,
+                              offset: usize,
+                              render_state: &RenderState<MetalDevice>) {
+        assert_eq!(offset % 4, 0, "Indirect draw arguments must be 4-byte aligned!");
+
+        let encoder = self.prepare_to_draw(render_state);
+        let primitive = render_state.primitive.to_metal_primitive();
+
+        let index_type = MTLIndexType::UInt32;
+        let index_buffer = render_state.vertex_array
+                                       .index_buffer
+                                       .borrow();
+        let index_buffer = index_buffer.as_ref().expect("No index buffer bound to VAO!");
+        let index_buffer = index_buffer.allocations.borrow();
+        let index_buffer = index_buffer.private.as_ref().expect("Index buffer not allocated!");
+
+        let indirect_buffer = indirect_buffer.allocations.borrow();
+        let indirect_buffer = indirect_buffer.private
+                                             .as_ref()
+                                             .expect("Indirect buffer not allocated!");
+
+        // The first index comes from the indirect arguments, so the index buffer offset is zero.
+        encoder.draw_indexed_primitives_indirect(primitive,
+                                                 index_type,
+                                                 index_buffer,
+                                                 0,
+                                                 indirect_buffer,
+                                                 offset as u64);
+        self.finish_draw(&encoder);
     }
 
     fn dispatch_compute(&self,
                         size: ComputeDimensions,
                         compute_state: &ComputeState<MetalDevice>) {
+        assert!(self.render_pass_encoder.borrow().is_none(),
+                "Can't dispatch compute in a render pass!");
+
         let scopes = self.scopes.borrow();
         let command_buffer = &scopes.last().unwrap().command_buffer;
 
@@ -974,6 +1745,7 @@ impl Device for MetalDevice {
 
         let compute_pipeline_descriptor = ComputePipelineDescriptor::new();
         compute_pipeline_descriptor.set_compute_function(Some(&program.shader.function));
+        compute_pipeline_descriptor.set_label(&program.name);
 
         let compute_pipeline_state = unsafe {
             if program.shader.arguments.borrow().is_none() {
@@ -988,6 +1760,12 @@ impl Device for MetalDevice {
                                                 options:reflection_options
                                              reflection:&mut reflection
                                                   error:&mut error];
+                if raw_compute_pipeline_state.is_null() {
+                    let description: CFStringRef = msg_send![error, description];
+                    panic!("Compute pipeline state construction failed for program '{}': {}",
+                           program.name,
+                           CFString::wrap_under_get_rule(description).to_string());
+                }
                 let compute_pipeline_state =
                     ComputePipelineState::from_ptr(raw_compute_pipeline_state);
                 *program.shader.arguments.borrow_mut() =
@@ -996,7 +1774,11 @@ impl Device for MetalDevice {
             } else {
                 self.device
                     .new_compute_pipeline_state(&compute_pipeline_descriptor)
-                    .expect("Failed to create compute pipeline state!")
+                    .unwrap_or_else(|error| {
+                        panic!("Failed to create compute pipeline state for program '{}': {}",
+                               program.name,
+                               error)
+                    })
             }
         };
 
@@ -1017,6 +1799,10 @@ impl Device for MetalDevice {
         encoder.end_encoding();
     }
 
+    fn draw_mesh_tasks(&self, _: ComputeDimensions, _: &RenderState<MetalDevice>) {
+        panic!("Mesh shaders are not yet supported on Metal!")
+    }
+
     fn create_timer_query(&self) -> MetalTimerQuery {
         let query = MetalTimerQuery(Arc::new(MetalTimerQueryInfo {
             mutex: Mutex::new(MetalTimerQueryData {
@@ -1085,15 +1871,93 @@ impl Device for MetalDevice {
         try_recv_timer_query_with_guard(&mut query.0.mutex.lock().unwrap())
     }
 
-    fn recv_timer_query(&self, query: &MetalTimerQuery) -> Duration {
-        let mut guard = query.0.mutex.lock().unwrap();
-        loop {
-            let duration = try_recv_timer_query_with_guard(&mut guard);
-            if let Some(duration) = duration {
-                return duration
-            }
-            guard = query.0.cond.wait(guard).unwrap();
-        }
+    fn recv_timer_query(&self, query: &MetalTimerQuery) -> Duration {
+        let mut guard = query.0.mutex.lock().unwrap();
+        loop {
+            let duration = try_recv_timer_query_with_guard(&mut guard);
+            if let Some(duration) = duration {
+                return duration
+            }
+            guard = query.0.cond.wait(guard).unwrap();
+        }
+    }
+
+    fn reset_frame_stats(&self) {
+        *self.frame_stats.borrow_mut() =
+            Some(MetalFrameStats { stats: FrameStats::default(), pending_scopes: vec![] });
+    }
+
+    fn collect_frame_stats(&self) -> FrameStats {
+        let frame_stats = self.frame_stats.borrow_mut().take();
+        let MetalFrameStats { mut stats, pending_scopes } =
+            frame_stats.expect("Must call `reset_frame_stats()` first!");
+        assert!(self.scopes.borrow().is_empty(),
+                "Can't collect frame stats inside `begin_commands()`!");
+
+        // Command buffers on one queue run one after another, so their times add up.
+        self.wait_idle();
+        for timer_query in pending_scopes {
+            stats.scope_count += 1;
+            stats.gpu_time += self.recv_timer_query(&timer_query);
+        }
+        stats
+    }
+
+    // TODO: Use counter sample buffers where the hardware supports them.
+    fn create_pipeline_stats_query(&self) {}
+
+    fn begin_pipeline_stats_query(&self, _: &()) {}
+
+    fn end_pipeline_stats_query(&self, _: &()) {}
+
+    fn recv_pipeline_stats_query(&self, _: &()) -> Option<PipelineStats> {
+        None
+    }
+
+    // TODO: Use visibility result buffers. Metal has no conditional rendering, so this would
+    // still need a CPU readback (or an indirect draw) to skip occluded draws. Until then, report
+    // that samples always pass, which is conservative.
+    fn create_occlusion_query(&self) {}
+
+    fn begin_occlusion_query(&self, _: &()) {}
+
+    fn end_occlusion_query(&self, _: &()) {}
+
+    fn try_recv_occlusion_query(&self, _: &()) -> Option<bool> {
+        Some(true)
+    }
+
+    fn recv_occlusion_query(&self, _: &()) -> bool {
+        true
+    }
+
+    fn draw_elements_conditional(&self,
+                                 index_count: u32,
+                                 render_state: &RenderState<MetalDevice>,
+                                 _: &()) {
+        self.draw_elements(index_count, render_state)
+    }
+
+    fn gpu_timestamp_calibration(&self) -> Option<TimestampCalibration> {
+        let (initial_cpu_timestamp, initial_gpu_timestamp) = self.initial_timestamps?;
+        let (cpu_timestamp, gpu_timestamp) = self.device.sample_timestamps()?;
+        let cpu_time_ns = self.cpu_clock.ticks_to_ns(cpu_timestamp);
+
+        // GPU timestamps are in GPU-specific ticks, so measure the rate against the timestamps
+        // sampled at creation.
+        let elapsed_cpu_ns =
+            cpu_time_ns.saturating_sub(self.cpu_clock.ticks_to_ns(initial_cpu_timestamp));
+        let gpu_ticks_per_ns = if elapsed_cpu_ns == 0 {
+            1.0
+        } else {
+            gpu_timestamp.saturating_sub(initial_gpu_timestamp) as f64 / elapsed_cpu_ns as f64
+        };
+
+        Some(TimestampCalibration {
+            cpu_time_ns: self.cpu_clock.epoch_offset_ns + cpu_time_ns,
+            gpu_timestamp,
+            gpu_ticks_per_ns,
+        })
     }
 
     fn try_recv_texture_data(&self, receiver: &MetalTextureDataReceiver) -> Option<TextureData> {
@@ -1122,6 +1986,12 @@ impl Device for MetalDevice {
             ShaderKind::Vertex => 'v',
             ShaderKind::Fragment => 'f',
             ShaderKind::Compute => 'c',
+            ShaderKind::TessControl | ShaderKind::TessEvaluation => {
+                panic!("Tessellation shaders are not yet supported on Metal!")
+            }
+            ShaderKind::Task | ShaderKind::Mesh => {
+                panic!("Mesh shaders are not yet supported on Metal!")
+            }
         };
         let path = format!("shaders/metal/{}.{}s.metal", name, suffix);
         self.create_shader_from_source(name, &resources.slurp(&path).unwrap(), kind)
@@ -1154,9 +2024,44 @@ impl Device for MetalDevice {
             guard = fence.0.cond.wait(guard).unwrap();
         }
     }
+
+    fn try_wait_for_fence(&self, fence: &MetalFence) -> bool {
+        // If the lock is held, the completion handler is resolving the fence right now. Treat it
+        // as pending rather than blocking.
+        match fence.0.mutex.try_lock() {
+            Ok(guard) => *guard == MetalFenceStatus::Resolved,
+            Err(_) => false,
+        }
+    }
 }
 
 impl MetalDevice {
+    // Metal rejects zero-length buffers, so empty buffers are backed by a single byte. Their
+    // reported size is still zero.
+    fn create_buffer_allocations(&self, buffer: &MetalBuffer, byte_size: u64)
+                                 -> BufferAllocations {
+        let mode = buffer.mode;
+        let cpu_writable = match mode {
+            BufferUploadMode::Dynamic => self.has_unified_memory,
+            BufferUploadMode::Static => false,
+        };
+        let options = if cpu_writable {
+            MTLResourceOptions::CPUCacheModeWriteCombined | MTLResourceOptions::StorageModeShared
+        } else {
+            mode.to_metal_resource_options()
+        };
+        let private_buffer = self.device.new_buffer(byte_size.max(1), options);
+        if let Some(ref label) = *buffer.label.borrow() {
+            private_buffer.set_label(label);
+        }
+        BufferAllocations {
+            private: Some(private_buffer),
+            shared: None,
+            byte_size,
+            cpu_writable,
+        }
+    }
+
     fn get_uniform_index(&self, shader: &MetalShader, name: &str) -> Option<MetalUniformIndex> {
         let uniforms = shader.arguments.borrow();
         let arguments = match *uniforms {
@@ -1214,6 +2119,13 @@ impl MetalDevice {
         None
     }
 
+    fn debug_check_storage_buffer_offset(&self, offset: u64) {
+        debug_assert_eq!(offset % self.storage_buffer_offset_alignment() as u64,
+                         0,
+                         "Storage buffer offset {} is misaligned!",
+                         offset);
+    }
+
     fn get_storage_buffer_index(&self, shader: &MetalShader, name: &str)
                                 -> Option<MetalStorageBufferIndex> {
         let uniforms = shader.arguments.borrow();
@@ -1253,6 +2165,7 @@ impl MetalDevice {
             MetalProgram::Raster(MetalRasterProgram {
                 ref vertex_shader,
                 ref fragment_shader,
+                ..
             }) => {
                 Some(MetalUniformIndices(ProgramKind::Raster {
                     vertex: self.get_uniform_index(vertex_shader, &uniform.name),
@@ -1278,6 +2191,7 @@ impl MetalDevice {
             MetalProgram::Raster(MetalRasterProgram {
                 ref vertex_shader,
                 ref fragment_shader,
+                ..
             }) => {
                 Some(MetalTextureIndices(ProgramKind::Raster {
                     vertex: self.get_texture_index(vertex_shader, &texture_parameter.name),
@@ -1303,6 +2217,7 @@ impl MetalDevice {
             MetalProgram::Raster(MetalRasterProgram {
                 ref vertex_shader,
                 ref fragment_shader,
+                ..
             }) => {
                 Some(MetalImageIndices(ProgramKind::Raster {
                     vertex: self.get_image_index(vertex_shader, &image_parameter.name),
@@ -1328,6 +2243,7 @@ impl MetalDevice {
             MetalProgram::Raster(MetalRasterProgram {
                 ref vertex_shader,
                 ref fragment_shader,
+                ..
             }) => {
                 Some(MetalStorageBufferIndices(ProgramKind::Raster {
                     vertex: self.get_storage_buffer_index(vertex_shader, &storage_buffer.name),
@@ -1346,7 +2262,29 @@ impl MetalDevice {
                                    -> Texture {
         match *render_target {
             RenderTarget::Default {..} => self.main_color_texture.retain(),
-            RenderTarget::Framebuffer(framebuffer) => framebuffer.0.private_texture.retain(),
+            RenderTarget::Framebuffer(framebuffer) => framebuffer.texture.private_texture.retain(),
+        }
+    }
+
+    // Returns the slice of the color texture that `render_target` renders to, which is nonzero
+    // only for the faces of cube textures.
+    fn render_target_slice(&self, render_target: &RenderTarget<MetalDevice>) -> u64 {
+        match *render_target {
+            RenderTarget::Framebuffer(&MetalFramebuffer { cube_face: Some(face), .. }) => {
+                face as u64
+            }
+            RenderTarget::Default {..} | RenderTarget::Framebuffer(_) => 0,
+        }
+    }
+
+    // Returns true if `render_target` is the target of the active render pass.
+    fn is_render_pass_target(&self, render_target: &RenderTarget<MetalDevice>) -> bool {
+        match *self.render_pass_target.borrow() {
+            None => false,
+            Some((ref texture, slice)) => {
+                self.render_target_color_texture(render_target).as_ptr() == texture.as_ptr() &&
+                    self.render_target_slice(render_target) == slice
+            }
         }
     }
 
@@ -1365,32 +2303,127 @@ impl MetalDevice {
         }
     }
 
-    fn prepare_to_draw(&self, render_state: &RenderState<MetalDevice>) -> RenderCommandEncoder {
+    // Starts a render pass on the current command buffer. Only one encoder can be active at a
+    // time, so this can't be called inside a pass begun by `begin_render_pass()`.
+    fn new_render_command_encoder(&self,
+                                  target: &RenderTarget<MetalDevice>,
+                                  options: &RenderOptions)
+                                  -> RenderCommandEncoder {
+        assert!(self.render_pass_encoder.borrow().is_none(),
+                "Render passes can't be nested!");
+
         let scopes = self.scopes.borrow();
         let command_buffer = &scopes.last().unwrap().command_buffer;
 
-        let render_pass_descriptor = self.create_render_pass_descriptor(render_state);
-
+        let render_pass_descriptor = self.create_render_pass_descriptor(target, options);
         let encoder = command_buffer.new_render_command_encoder_retained(&render_pass_descriptor);
 
         // Wait on the previous compute command, if any.
-        let compute_fence = self.compute_fence.borrow();
-        if let Some(ref compute_fence) = *compute_fence {
+        if let Some(ref compute_fence) = *self.compute_fence.borrow() {
             encoder.wait_for_fence_before_stages(compute_fence, MTLRenderStage::Vertex);
         }
 
+        encoder
+    }
+
+    // Ends the encoder used for a draw, unless it belongs to a render pass that's still active.
+    fn finish_draw(&self, encoder: &RenderCommandEncoderRef) {
+        if self.render_pass_encoder.borrow().is_none() {
+            encoder.end_encoding();
+        }
+    }
+
+    fn prepare_to_draw(&self, render_state: &RenderState<MetalDevice>) -> RenderCommandEncoder {
+        // Metal always restarts primitives at index 0xffffffff for 32-bit indices, and this can't
+        // be changed.
+        if let Some(restart_index) = render_state.options.primitive_restart {
+            assert_eq!(restart_index, !0, "Metal only supports a primitive restart index of !0!");
+        }
+
+        // Clears happen through load actions, which always affect the whole attachment.
+        // TODO: Clear with a draw instead, so that clears can be scissored.
+        assert!(render_state.options.scissor.is_none() ||
+                !render_state.options.effective_clear_ops().has_ops(),
+                "Metal can't scissor clears!");
+
+        let encoder = match *self.render_pass_encoder.borrow() {
+            Some(ref encoder) => {
+                assert!(self.is_render_pass_target(render_state.target),
+                        "Draws inside a render pass must use its target!");
+                assert!(render_state.options.load_action == LoadAction::Load &&
+                        !render_state.options.clear_ops.has_ops(),
+                        "Draws inside a render pass can't clear or discard its target!");
+                encoder.clone()
+            }
+            None => self.new_render_command_encoder(render_state.target, &render_state.options),
+        };
+
         self.set_viewport(&encoder, &render_state.viewport);
+        self.set_scissor_rect(&encoder, render_state);
+
+        match render_state.options.polygon_mode {
+            PolygonMode::Fill => encoder.set_triangle_fill_mode(MTLTriangleFillMode::Fill),
+            PolygonMode::Line => encoder.set_triangle_fill_mode(MTLTriangleFillMode::Lines),
+        }
+
+        // Always set the bias and blend color, since a render pass reuses the encoder across draws.
+        let bias = render_state.options.depth_bias.unwrap_or_default();
+        encoder.set_depth_bias(bias.constant, bias.slope, 0.0);
+        let blend_color = render_state.options.blend_color;
+        encoder.set_blend_color(blend_color.r(), blend_color.g(), blend_color.b(), blend_color.a());
 
         let program = match render_state.program {
             MetalProgram::Raster(ref raster_program) => raster_program,
             _ => panic!("Raster render command must use a raster program!"),
         };
+        let render_pipeline_state = self.render_pipeline_state(program, render_state);
+
+        for (vertex_buffer_index, vertex_buffer) in render_state.vertex_array
+                                                                .vertex_buffers
+                                                                .borrow()
+                                                                .iter()
+                                                                .enumerate() {
+            let real_index = vertex_buffer_index as u64 + FIRST_VERTEX_BUFFER_INDEX;
+            let buffer = vertex_buffer.allocations.borrow();
+            let buffer = buffer.private
+                               .as_ref()
+                               .map(|buffer| buffer.as_ref())
+                               .expect("Where's the private vertex buffer?");
+            encoder.set_vertex_buffer(real_index, Some(buffer), 0);
+        }
+
+        self.set_raster_uniforms(&encoder, render_state);
+        encoder.set_render_pipeline_state(&render_pipeline_state);
+        self.set_depth_stencil_state(&encoder, render_state);
+
+        encoder
+    }
+
+    // Returns the render pipeline state for a draw, creating it on the first draw that needs it.
+    fn render_pipeline_state(&self,
+                             program: &MetalRasterProgram,
+                             render_state: &RenderState<MetalDevice>)
+                             -> RenderPipelineState {
+        let key = PipelineKey {
+            vertex_function: program.vertex_shader.function.clone(),
+            fragment_function: program.fragment_shader.function.clone(),
+            vertex_layout: render_state.vertex_array.layout.borrow().clone(),
+            color_pixel_format: self.render_target_color_texture(&render_state.target)
+                                    .pixel_format(),
+            blend: render_state.options.blend,
+            color_mask: render_state.options.color_mask,
+            has_depth: self.render_target_has_depth(render_state.target),
+        };
+        if let Some(render_pipeline_state) = self.render_pipeline_states.borrow().get(&key) {
+            return render_pipeline_state.clone();
+        }
 
         let render_pipeline_descriptor = RenderPipelineDescriptor::new();
         render_pipeline_descriptor.set_vertex_function(Some(&program.vertex_shader.function));
         render_pipeline_descriptor.set_fragment_function(Some(&program.fragment_shader.function));
         render_pipeline_descriptor.set_vertex_descriptor(Some(&render_state.vertex_array
                                                                            .descriptor));
+        render_pipeline_descriptor.set_label(&program.name);
 
         // Create render pipeline state.
         let pipeline_color_attachment =
@@ -1426,28 +2459,15 @@ impl MetalDevice {
         } else {
             self.device
                 .new_render_pipeline_state(&render_pipeline_descriptor)
-                .expect("Failed to create render pipeline state!")
+                .unwrap_or_else(|error| {
+                    panic!("Failed to create render pipeline state for program '{}': {}",
+                           program.name,
+                           error)
+                })
         };
 
-        for (vertex_buffer_index, vertex_buffer) in render_state.vertex_array
-                                                                .vertex_buffers
-                                                                .borrow()
-                                                                .iter()
-                                                                .enumerate() {
-            let real_index = vertex_buffer_index as u64 + FIRST_VERTEX_BUFFER_INDEX;
-            let buffer = vertex_buffer.allocations.borrow();
-            let buffer = buffer.private
-                               .as_ref()
-                               .map(|buffer| buffer.as_ref())
-                               .expect("Where's the private vertex buffer?");
-            encoder.set_vertex_buffer(real_index, Some(buffer), 0);
-        }
-
-        self.set_raster_uniforms(&encoder, render_state);
-        encoder.set_render_pipeline_state(&render_pipeline_state);
-        self.set_depth_stencil_state(&encoder, render_state);
-
-        encoder
+        self.render_pipeline_states.borrow_mut().insert(key, render_pipeline_state.clone());
+        render_pipeline_state
     }
 
     fn set_raster_uniforms(&self,
@@ -1535,6 +2555,29 @@ impl MetalDevice {
             }
         }
 
+        // Set storage buffer ranges. Binding a storage buffer in the render state replaces them.
+        let mut storage_buffer_ranges = self.storage_buffer_ranges.borrow_mut();
+        storage_buffer_ranges.retain(|range| {
+            render_state.storage_buffers.iter().all(|&(storage_buffer, _)| {
+                storage_buffer.name != range.name
+            })
+        });
+        for range in storage_buffer_ranges.iter() {
+            self.debug_check_storage_buffer_offset(range.offset);
+            let vertex_shader = &program.vertex_shader;
+            if let Some(index) = self.get_storage_buffer_index(vertex_shader, &range.name) {
+                render_command_encoder.set_vertex_buffer(index.0,
+                                                         Some(&range.buffer),
+                                                         range.offset);
+            }
+            let fragment_shader = &program.fragment_shader;
+            if let Some(index) = self.get_storage_buffer_index(fragment_shader, &range.name) {
+                render_command_encoder.set_fragment_buffer(index.0,
+                                                           Some(&range.buffer),
+                                                           range.offset);
+            }
+        }
+
         // Set storage buffers.
         for &(storage_buffer_id, storage_buffer_binding) in render_state.storage_buffers {
             self.populate_storage_buffer_indices_if_necessary(storage_buffer_id,
@@ -1616,6 +2659,24 @@ impl MetalDevice {
             }
         }
 
+        // Set storage buffer ranges. Binding a storage buffer in the compute state replaces them.
+        let shader = match compute_state.program {
+            MetalProgram::Compute(MetalComputeProgram { ref shader, .. }) => shader,
+            _ => unreachable!(),
+        };
+        let mut storage_buffer_ranges = self.storage_buffer_ranges.borrow_mut();
+        storage_buffer_ranges.retain(|range| {
+            compute_state.storage_buffers.iter().all(|&(storage_buffer, _)| {
+                storage_buffer.name != range.name
+            })
+        });
+        for range in storage_buffer_ranges.iter() {
+            self.debug_check_storage_buffer_offset(range.offset);
+            if let Some(index) = self.get_storage_buffer_index(shader, &range.name) {
+                compute_command_encoder.set_buffer(index.0, Some(&range.buffer), range.offset);
+            }
+        }
+
         // Set storage buffers.
         for &(storage_buffer_id, storage_buffer_binding) in compute_state.storage_buffers {
             self.populate_storage_buffer_indices_if_necessary(storage_buffer_id,
@@ -1732,14 +2793,31 @@ impl MetalDevice {
             &buffer[buffer_range.start as usize] as *const u8 as *const _)
     }
 
+    // Returns the sampler for the sampling mode and border color of `texture`, creating it if
+    // this is the first texture to use that combination.
+    fn sampler(&self, texture: &MetalTexture) -> SamplerState {
+        let sampling_flags = texture.sampling_flags.get();
+        // The border color is irrelevant unless the texture clamps to it.
+        let border_color = if sampling_flags.intersects(TextureSamplingFlags::CLAMP_TO_BORDER_U |
+                                                        TextureSamplingFlags::CLAMP_TO_BORDER_V) {
+            texture.border_color.get()
+        } else {
+            MTLSamplerBorderColor::TransparentBlack
+        };
+        let mut samplers = self.samplers.borrow_mut();
+        samplers.entry((sampling_flags, border_color as u64)).or_insert_with(|| {
+            create_sampler(&self.device, sampling_flags, border_color)
+        }).clone()
+    }
+
     fn encode_vertex_texture_parameter(&self,
                                        argument_index: MetalTextureIndex,
                                        render_command_encoder: &RenderCommandEncoderRef,
                                        texture: &MetalTexture) {
         render_command_encoder.set_vertex_texture(argument_index.main,
-                                                  Some(&texture.private_texture));
-        let sampler = &self.samplers[texture.sampling_flags.get().bits() as usize];
-        render_command_encoder.set_vertex_sampler_state(argument_index.sampler, Some(sampler));
+                                                  Some(&texture.sampled_texture()));
+        let sampler = self.sampler(texture);
+        render_command_encoder.set_vertex_sampler_state(argument_index.sampler, Some(&sampler));
     }
 
     fn encode_fragment_texture_parameter(&self,
@@ -1747,18 +2825,18 @@ impl MetalDevice {
                                          render_command_encoder: &RenderCommandEncoderRef,
                                          texture: &MetalTexture) {
         render_command_encoder.set_fragment_texture(argument_index.main,
-                                                    Some(&texture.private_texture));
-        let sampler = &self.samplers[texture.sampling_flags.get().bits() as usize];
-        render_command_encoder.set_fragment_sampler_state(argument_index.sampler, Some(sampler));
+                                                    Some(&texture.sampled_texture()));
+        let sampler = self.sampler(texture);
+        render_command_encoder.set_fragment_sampler_state(argument_index.sampler, Some(&sampler));
     }
 
     fn encode_compute_texture_parameter(&self,
                                         argument_index: MetalTextureIndex,
                                         compute_command_encoder: &ComputeCommandEncoder,
                                         texture: &MetalTexture) {
-        compute_command_encoder.set_texture(argument_index.main, Some(&texture.private_texture));
-        let sampler = &self.samplers[texture.sampling_flags.get().bits() as usize];
-        compute_command_encoder.set_sampler_state(argument_index.sampler, Some(sampler));
+        compute_command_encoder.set_texture(argument_index.main, Some(&texture.sampled_texture()));
+        let sampler = self.sampler(texture);
+        compute_command_encoder.set_sampler_state(argument_index.sampler, Some(&sampler));
     }
 
     fn prepare_pipeline_color_attachment_for_render(
@@ -1795,13 +2873,22 @@ impl MetalDevice {
         }
     }
 
-    fn create_render_pass_descriptor(&self, render_state: &RenderState<MetalDevice>)
+    fn create_render_pass_descriptor(&self,
+                                     target: &RenderTarget<MetalDevice>,
+                                     options: &RenderOptions)
                                      -> RenderPassDescriptor {
         let render_pass_descriptor = RenderPassDescriptor::new_retained();
         let color_attachment = render_pass_descriptor.color_attachments().object_at(0).unwrap();
-        color_attachment.set_texture(Some(&self.render_target_color_texture(render_state.target)));
+        color_attachment.set_texture(Some(&self.render_target_color_texture(target)));
+        color_attachment.set_slice(self.render_target_slice(target));
 
-        match render_state.options.clear_ops.color {
+        let clear_ops = options.effective_clear_ops();
+        let load_action = match options.load_action {
+            LoadAction::DontCare => MTLLoadAction::DontCare,
+            LoadAction::Load | LoadAction::Clear => MTLLoadAction::Load,
+        };
+
+        match clear_ops.color {
             Some(color) => {
                 let color = MTLClearColor::new(color.r() as f64,
                                                color.g() as f64,
@@ -1810,32 +2897,32 @@ impl MetalDevice {
                 color_attachment.set_clear_color(color);
                 color_attachment.set_load_action(MTLLoadAction::Clear);
             }
-            None => color_attachment.set_load_action(MTLLoadAction::Load),
+            None => color_attachment.set_load_action(load_action),
         }
         color_attachment.set_store_action(MTLStoreAction::Store);
 
-        let depth_stencil_texture = self.render_target_depth_texture(render_state.target);
+        let depth_stencil_texture = self.render_target_depth_texture(target);
         if let Some(depth_stencil_texture) = depth_stencil_texture {
             let depth_attachment = render_pass_descriptor.depth_attachment().unwrap();
             let stencil_attachment = render_pass_descriptor.stencil_attachment().unwrap();
             depth_attachment.set_texture(Some(&depth_stencil_texture));
             stencil_attachment.set_texture(Some(&depth_stencil_texture));
 
-            match render_state.options.clear_ops.depth {
+            match clear_ops.depth {
                 Some(depth) => {
                     depth_attachment.set_clear_depth(depth as f64);
                     depth_attachment.set_load_action(MTLLoadAction::Clear);
                 }
-                None => depth_attachment.set_load_action(MTLLoadAction::Load),
+                None => depth_attachment.set_load_action(load_action),
             }
             depth_attachment.set_store_action(MTLStoreAction::Store);
 
-            match render_state.options.clear_ops.stencil {
+            match clear_ops.stencil {
                 Some(value) => {
                     stencil_attachment.set_clear_stencil(value as u32);
                     stencil_attachment.set_load_action(MTLLoadAction::Clear);
                 }
-                None => stencil_attachment.set_load_action(MTLLoadAction::Load),
+                None => stencil_attachment.set_load_action(load_action),
             }
             stencil_attachment.set_store_action(MTLStoreAction::Store);
         }
@@ -1846,50 +2933,48 @@ impl MetalDevice {
     fn set_depth_stencil_state(&self,
                                encoder: &RenderCommandEncoderRef,
                                render_state: &RenderState<MetalDevice>) {
-        let depth_stencil_descriptor = DepthStencilDescriptor::new();
-
-        match render_state.options.depth {
-            Some(depth_state) => {
-                let compare_function = depth_state.func.to_metal_compare_function();
-                depth_stencil_descriptor.set_depth_compare_function(compare_function);
-                depth_stencil_descriptor.set_depth_write_enabled(depth_state.write);
-            }
-            None => {
-                depth_stencil_descriptor.set_depth_compare_function(MTLCompareFunction::Always);
-                depth_stencil_descriptor.set_depth_write_enabled(false);
-            }
-        }
-
-        match render_state.options.stencil {
-            Some(stencil_state) => {
-                let stencil_descriptor = StencilDescriptor::new();
-                let compare_function = stencil_state.func.to_metal_compare_function();
-                let (pass_operation, write_mask) = if stencil_state.write {
-                    (MTLStencilOperation::Replace, stencil_state.mask)
-                } else {
-                    (MTLStencilOperation::Keep, 0)
-                };
-                stencil_descriptor.set_stencil_compare_function(compare_function);
-                stencil_descriptor.set_stencil_failure_operation(MTLStencilOperation::Keep);
-                stencil_descriptor.set_depth_failure_operation(MTLStencilOperation::Keep);
-                stencil_descriptor.set_depth_stencil_pass_operation(pass_operation);
-                stencil_descriptor.set_write_mask(write_mask);
-                depth_stencil_descriptor.set_front_face_stencil(Some(&stencil_descriptor));
-                depth_stencil_descriptor.set_back_face_stencil(Some(&stencil_descriptor));
-                encoder.set_stencil_reference_value(stencil_state.reference);
-            }
-            None => {
-                depth_stencil_descriptor.set_front_face_stencil(None);
-                depth_stencil_descriptor.set_back_face_stencil(None);
-            }
+        let stencil = render_state.options.stencil;
+        if let Some(stencil_state) = stencil {
+            encoder.set_stencil_reference_value(stencil_state.reference);
         }
 
-        let depth_stencil_state = self.device.new_depth_stencil_state(&depth_stencil_descriptor);
-        encoder.set_depth_stencil_state(&depth_stencil_state);
+        let key = DepthStencilKey {
+            depth: render_state.options.depth,
+            stencil: stencil.map(|stencil_state| StencilState { reference: 0, ..stencil_state }),
+        };
+        encoder.set_depth_stencil_state(&self.depth_stencil_state(key));
     }
 
-    fn texture_format(&self, texture: &Texture) -> Option<TextureFormat> {
-        TextureFormat::from_metal_pixel_format(texture.pixel_format())
+    fn depth_stencil_state(&self, key: DepthStencilKey) -> DepthStencilState {
+        let mut depth_stencil_states = self.depth_stencil_states.borrow_mut();
+        if let Some(&(_, ref depth_stencil_state)) =
+                depth_stencil_states.iter().find(|&&(cached_key, _)| cached_key == key) {
+            return depth_stencil_state.clone();
+        }
+
+        let depth_stencil_state = create_depth_stencil_state(&self.device, key);
+        depth_stencil_states.push((key, depth_stencil_state.clone()));
+        depth_stencil_state
+    }
+
+    // Always sets a scissor rect, since a render pass reuses the encoder across draws.
+    fn set_scissor_rect(&self,
+                        encoder: &RenderCommandEncoderRef,
+                        render_state: &RenderState<MetalDevice>) {
+        let texture = self.render_target_color_texture(render_state.target);
+        let target_rect = RectI::new(Vector2I::zero(),
+                                     vec2i(texture.width() as i32, texture.height() as i32));
+        // Metal requires the scissor rect to lie within the render target.
+        let scissor = match render_state.options.scissor {
+            None => target_rect,
+            Some(scissor) => scissor.intersection(target_rect).unwrap_or_default(),
+        };
+        encoder.set_scissor_rect(MTLScissorRect {
+            x: scissor.origin_x() as u64,
+            y: scissor.origin_y() as u64,
+            width: scissor.width() as u64,
+            height: scissor.height() as u64,
+        });
     }
 
     fn set_viewport(&self, encoder: &RenderCommandEncoderRef, viewport: &RectI) {
@@ -1920,6 +3005,7 @@ impl MetalDevice {
 
 trait DeviceExtra {
     fn create_depth_stencil_texture(&self, size: Vector2I) -> Texture;
+    fn sample_timestamps(&self) -> Option<(u64, u64)>;
 }
 
 impl DeviceExtra for NativeMetalDevice {
@@ -1933,10 +3019,57 @@ impl DeviceExtra for NativeMetalDevice {
         descriptor.set_usage(MTLTextureUsage::Unknown);
         self.new_texture(&descriptor)
     }
+
+    // Returns the CPU and GPU timestamps, sampled together. The CPU timestamp is in Mach absolute
+    // time units. `sampleTimestamps:gpuTimestamp:` needs macOS 10.15 or iOS 14.
+    fn sample_timestamps(&self) -> Option<(u64, u64)> {
+        unsafe {
+            let supported: BOOL =
+                msg_send![*self, respondsToSelector:sel!(sampleTimestamps:gpuTimestamp:)];
+            if supported == NO {
+                return None;
+            }
+            let (mut cpu_timestamp, mut gpu_timestamp): (u64, u64) = (0, 0);
+            let () = msg_send![*self, sampleTimestamps:&mut cpu_timestamp
+                                          gpuTimestamp:&mut gpu_timestamp];
+            Some((cpu_timestamp, gpu_timestamp))
+        }
+    }
 }
 
 // Helper types
 
+// Converts Mach absolute time, which Metal reports CPU timestamps in, to nanoseconds.
+struct CpuClock {
+    numer: u32,
+    denom: u32,
+    // Nanoseconds from the Unix epoch to Mach absolute time zero.
+    epoch_offset_ns: u64,
+}
+
+impl CpuClock {
+    fn new() -> CpuClock {
+        let mut timebase = libc::mach_timebase_info { numer: 0, denom: 0 };
+        unsafe {
+            libc::mach_timebase_info(&mut timebase);
+        }
+        let mut clock = CpuClock {
+            numer: timebase.numer,
+            denom: timebase.denom,
+            epoch_offset_ns: 0,
+        };
+
+        let now_ns = clock.ticks_to_ns(unsafe { libc::mach_absolute_time() });
+        let unix_time_ns = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64;
+        clock.epoch_offset_ns = unix_time_ns.saturating_sub(now_ns);
+        clock
+    }
+
+    fn ticks_to_ns(&self, ticks: u64) -> u64 {
+        (ticks as u128 * self.numer as u128 / self.denom as u128) as u64
+    }
+}
+
 struct UniformBuffer {
     data: Vec<u8>,
     ranges: Vec<Range<usize>>,
@@ -1949,6 +3082,25 @@ impl MetalTexture {
     pub fn metal_texture(&self) -> Texture {
         self.private_texture.clone()
     }
+
+    // Returns the texture that shaders should sample, which has the swizzle applied.
+    fn sampled_texture(&self) -> Texture {
+        if self.sampling_flags.get().contains(TextureSamplingFlags::MIPMAP) {
+            if let Some(ref mipmapped_texture) = *self.mipmapped_texture.borrow() {
+                // Swizzled, mipmapped textures are rare enough that their views aren't cached.
+                let swizzle = self.swizzle.get();
+                if swizzle == Swizzle::IDENTITY {
+                    return mipmapped_texture.clone();
+                }
+                return mipmapped_texture.new_swizzled_view(swizzle);
+            }
+        }
+
+        match *self.swizzled_view.borrow() {
+            Some(ref view) => view.clone(),
+            None => self.private_texture.clone(),
+        }
+    }
 }
 
 pub trait IntoMetalDevice {
@@ -2026,6 +3178,8 @@ impl BlendFactorExt for BlendFactor {
             BlendFactor::DestAlpha => MTLBlendFactor::DestinationAlpha,
             BlendFactor::OneMinusDestAlpha => MTLBlendFactor::OneMinusDestinationAlpha,
             BlendFactor::DestColor => MTLBlendFactor::DestinationColor,
+            BlendFactor::ConstantColor => MTLBlendFactor::BlendColor,
+            BlendFactor::OneMinusConstantColor => MTLBlendFactor::OneMinusBlendColor,
         }
     }
 }
@@ -2110,6 +3264,8 @@ impl PrimitiveExt for Primitive {
         match self {
             Primitive::Triangles => MTLPrimitiveType::Triangle,
             Primitive::Lines => MTLPrimitiveType::Line,
+            Primitive::Points => MTLPrimitiveType::Point,
+            Primitive::Patches(_) => panic!("Tessellation is not yet supported on Metal!"),
         }
     }
 }
@@ -2167,6 +3323,23 @@ impl UniformDataExt for UniformData {
     }
 }
 
+trait SwizzleExt {
+    fn to_metal_swizzle(self) -> MTLTextureSwizzle;
+}
+
+impl SwizzleExt for Swizzle {
+    fn to_metal_swizzle(self) -> MTLTextureSwizzle {
+        match self {
+            Swizzle::Zero => MTLTextureSwizzle::Zero,
+            Swizzle::One => MTLTextureSwizzle::One,
+            Swizzle::Red => MTLTextureSwizzle::Red,
+            Swizzle::Green => MTLTextureSwizzle::Green,
+            Swizzle::Blue => MTLTextureSwizzle::Blue,
+            Swizzle::Alpha => MTLTextureSwizzle::Alpha,
+        }
+    }
+}
+
 trait TextureFormatExt: Sized {
     fn from_metal_pixel_format(metal_pixel_format: MTLPixelFormat) -> Option<Self>;
 }
@@ -2175,12 +3348,14 @@ impl TextureFormatExt for TextureFormat {
     fn from_metal_pixel_format(metal_pixel_format: MTLPixelFormat) -> Option<TextureFormat> {
         match metal_pixel_format {
             MTLPixelFormat::R8Unorm => Some(TextureFormat::R8),
+            MTLPixelFormat::R16Unorm => Some(TextureFormat::R16),
             MTLPixelFormat::R16Float => Some(TextureFormat::R16F),
+            MTLPixelFormat::RG8Unorm => Some(TextureFormat::RG8),
+            MTLPixelFormat::RG16Float => Some(TextureFormat::RG16F),
             MTLPixelFormat::RGBA8Unorm => Some(TextureFormat::RGBA8),
-            MTLPixelFormat::BGRA8Unorm => {
-                // FIXME(pcwalton): This is wrong! But it prevents a crash for now.
-                Some(TextureFormat::RGBA8)
-            }
+            MTLPixelFormat::RGBA8Unorm_sRGB => Some(TextureFormat::RGBA8Srgb),
+            MTLPixelFormat::BGRA8Unorm => Some(TextureFormat::BGRA8),
+            MTLPixelFormat::RGBA16Unorm => Some(TextureFormat::RGBA16),
             MTLPixelFormat::RGBA16Float => Some(TextureFormat::RGBA16F),
             MTLPixelFormat::RGBA32Float => Some(TextureFormat::RGBA32F),
             _ => None,
@@ -2188,6 +3363,27 @@ impl TextureFormatExt for TextureFormat {
     }
 }
 
+trait TextureUsageFlagsExt {
+    fn to_metal_texture_usage(self) -> MTLTextureUsage;
+}
+
+impl TextureUsageFlagsExt for TextureUsageFlags {
+    fn to_metal_texture_usage(self) -> MTLTextureUsage {
+        let mut metal_usage = MTLTextureUsage::empty();
+        if self.contains(TextureUsageFlags::SAMPLED) {
+            metal_usage |= MTLTextureUsage::ShaderRead;
+        }
+        if self.contains(TextureUsageFlags::RENDER_TARGET) {
+            metal_usage |= MTLTextureUsage::RenderTarget;
+        }
+        if self.contains(TextureUsageFlags::STORAGE) {
+            // Images can be bound for reading as well as writing.
+            metal_usage |= MTLTextureUsage::ShaderRead | MTLTextureUsage::ShaderWrite;
+        }
+        metal_usage
+    }
+}
+
 // Synchronization helpers
 
 fn try_recv_timer_query_with_guard(guard: &mut MutexGuard<MetalTimerQueryData>)
@@ -2208,8 +3404,12 @@ impl MetalTextureDataReceiver {
         let format = TextureFormat::from_metal_pixel_format(self.0.texture.pixel_format());
         let format = format.expect("Unexpected framebuffer texture format!");
 
-        let texture_data = match format {
-            TextureFormat::R8 | TextureFormat::RGBA8 => {
+        let mut texture_data = match format {
+            TextureFormat::R8 |
+            TextureFormat::RG8 |
+            TextureFormat::RGBA8 |
+            TextureFormat::RGBA8Srgb |
+            TextureFormat::BGRA8 => {
                 let channels = format.channels();
                 let stride = size.x() as usize * channels;
                 let mut pixels = vec![0; stride * size.y() as usize];
@@ -2219,7 +3419,17 @@ impl MetalTextureDataReceiver {
                                          stride as u64);
                 TextureData::U8(pixels)
             }
-            TextureFormat::R16F | TextureFormat::RGBA16F => {
+            TextureFormat::R16 | TextureFormat::RGBA16 => {
+                let channels = format.channels();
+                let stride = size.x() as usize * channels;
+                let mut pixels = vec![0; stride * size.y() as usize];
+                self.0.texture.get_bytes(pixels.as_mut_ptr() as *mut _,
+                                         metal_region,
+                                         0,
+                                         stride as u64 * 2);
+                TextureData::U16(pixels)
+            }
+            TextureFormat::R16F | TextureFormat::RG16F | TextureFormat::RGBA16F => {
                 let channels = format.channels();
                 let stride = size.x() as usize * channels;
                 let mut pixels = vec![f16::default(); stride * size.y() as usize];
@@ -2241,6 +3451,10 @@ impl MetalTextureDataReceiver {
             }
         };
 
+        if self.0.alpha_mode == AlphaMode::Straight {
+            texture_data.unpremultiply(format);
+        }
+
         let mut guard = self.0.mutex.lock().unwrap();
         *guard = MetalDataReceiverState::Downloaded(texture_data);
         self.0.cond.notify_all();
@@ -2283,6 +3497,26 @@ bitflags! {
     }
 }
 
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct MTLTextureSwizzleChannels {
+    red: MTLTextureSwizzle,
+    green: MTLTextureSwizzle,
+    blue: MTLTextureSwizzle,
+    alpha: MTLTextureSwizzle,
+}
+
+#[repr(u8)]
+#[derive(Clone, Copy)]
+enum MTLTextureSwizzle {
+    Zero = 0,
+    One = 1,
+    Red = 2,
+    Green = 3,
+    Blue = 4,
+    Alpha = 5,
+}
+
 // Extra objects missing from `metal-rs`
 
 struct ArgumentArray(*mut Object);
@@ -2486,6 +3720,9 @@ trait DeviceExt {
                                                           RenderPipelineReflection);
     fn new_shared_event(&self) -> SharedEvent;
     fn new_fence(&self) -> Fence;
+    // Returns the largest buffer that can be allocated. `maxBufferLength` needs macOS 10.14 or
+    // iOS 12; before that, Metal guarantees 256 MB.
+    fn max_buffer_length(&self) -> u64;
 }
 
 impl DeviceExt for metal::Device {
@@ -2505,7 +3742,8 @@ impl DeviceExt for metal::Device {
                                                          error:&mut error_ptr];
             if !error_ptr.is_null() {
                 let description: CFStringRef = msg_send![error_ptr, description];
-                panic!("Render pipeline state construction failed: {}",
+                panic!("Render pipeline state construction failed for program '{}': {}",
+                       descriptor.label(),
                        CFString::wrap_under_get_rule(description).to_string());
             }
             assert!(!render_pipeline_state_ptr.is_null());
@@ -2522,6 +3760,17 @@ impl DeviceExt for metal::Device {
     fn new_fence(&self) -> Fence {
         unsafe { Fence(msg_send![self.as_ptr(), newFence]) }
     }
+
+    fn max_buffer_length(&self) -> u64 {
+        unsafe {
+            let supported: BOOL =
+                msg_send![self.as_ptr(), respondsToSelector:sel!(maxBufferLength)];
+            if supported == NO {
+                return 256 * 1024 * 1024;
+            }
+            msg_send![self.as_ptr(), maxBufferLength]
+        }
+    }
 }
 
 trait FunctionExt {
@@ -2601,6 +3850,13 @@ impl ComputeCommandEncoderExt for ComputeCommandEncoder {
 trait RenderCommandEncoderExt {
     fn update_fence_before_stages(&self, fence: &Fence, stages: MTLRenderStage);
     fn wait_for_fence_before_stages(&self, fence: &Fence, stages: MTLRenderStage);
+    fn draw_indexed_primitives_indirect(&self,
+                                        primitive: MTLPrimitiveType,
+                                        index_type: MTLIndexType,
+                                        index_buffer: &Buffer,
+                                        index_buffer_offset: u64,
+                                        indirect_buffer: &Buffer,
+                                        indirect_buffer_offset: u64);
 }
 
 impl RenderCommandEncoderExt for RenderCommandEncoderRef {
@@ -2613,6 +3869,74 @@ impl RenderCommandEncoderExt for RenderCommandEncoderRef {
             msg_send![self.as_ptr(), waitForFence:fence.0 beforeStages:stages]
         }
     }
+
+    fn draw_indexed_primitives_indirect(&self,
+                                        primitive: MTLPrimitiveType,
+                                        index_type: MTLIndexType,
+                                        index_buffer: &Buffer,
+                                        index_buffer_offset: u64,
+                                        indirect_buffer: &Buffer,
+                                        indirect_buffer_offset: u64) {
+        unsafe {
+            msg_send![self.as_ptr(), drawIndexedPrimitives:primitive
+                                                  indexType:index_type
+                                                indexBuffer:index_buffer.as_ptr()
+                                          indexBufferOffset:index_buffer_offset
+                                             indirectBuffer:indirect_buffer.as_ptr()
+                                       indirectBufferOffset:indirect_buffer_offset]
+        }
+    }
+}
+
+trait TextureExt {
+    fn supports_swizzled_views(&self) -> bool;
+    fn new_swizzled_view(&self, swizzle: [Swizzle; 4]) -> Texture;
+    // Returns a 2D view of one face of a cube texture.
+    fn new_face_view(&self, slice: u64) -> Texture;
+}
+
+impl TextureExt for TextureRef {
+    fn supports_swizzled_views(&self) -> bool {
+        unsafe {
+            let responds: BOOL = msg_send![
+                self.as_ptr(),
+                respondsToSelector:sel!(newTextureViewWithPixelFormat:textureType:levels:slices:
+                                        swizzle:)];
+            responds != NO
+        }
+    }
+
+    fn new_swizzled_view(&self, swizzle: [Swizzle; 4]) -> Texture {
+        let channels = MTLTextureSwizzleChannels {
+            red: swizzle[0].to_metal_swizzle(),
+            green: swizzle[1].to_metal_swizzle(),
+            blue: swizzle[2].to_metal_swizzle(),
+            alpha: swizzle[3].to_metal_swizzle(),
+        };
+        let levels = metal::NSRange::new(0, self.mipmap_level_count());
+        let slice_count = if self.texture_type() == MTLTextureType::Cube { 6 } else { 1 };
+        let slices = metal::NSRange::new(0, slice_count);
+        unsafe {
+            Texture::from_ptr(msg_send![self.as_ptr(),
+                                        newTextureViewWithPixelFormat:self.pixel_format()
+                                                          textureType:self.texture_type()
+                                                               levels:levels
+                                                               slices:slices
+                                                              swizzle:channels])
+        }
+    }
+
+    fn new_face_view(&self, slice: u64) -> Texture {
+        let levels = metal::NSRange::new(0, 1);
+        let slices = metal::NSRange::new(slice, 1);
+        unsafe {
+            Texture::from_ptr(msg_send![self.as_ptr(),
+                                        newTextureViewWithPixelFormat:self.pixel_format()
+                                                          textureType:MTLTextureType::D2
+                                                               levels:levels
+                                                               slices:slices])
+        }
+    }
 }
 
 trait RenderPassDescriptorExt {
@@ -2719,13 +4043,133 @@ extern {
 
 // Helper functions
 
+// The number of levels in a full mip chain, down to 1x1.
+fn mip_level_count(size: Vector2I) -> u64 {
+    let max_length = size.x().max(size.y()).max(1) as u32;
+    (32 - max_length.leading_zeros()) as u64
+}
+
+fn create_sampler(device: &DeviceRef,
+                  sampling_flags: TextureSamplingFlags,
+                  border_color: MTLSamplerBorderColor)
+                  -> SamplerState {
+    let sampler_descriptor = SamplerDescriptor::new();
+    sampler_descriptor.set_support_argument_buffers(true);
+    sampler_descriptor.set_normalized_coordinates(true);
+    sampler_descriptor.set_min_filter(
+        if sampling_flags.contains(TextureSamplingFlags::NEAREST_MIN) {
+            MTLSamplerMinMagFilter::Nearest
+        } else {
+            MTLSamplerMinMagFilter::Linear
+        });
+    sampler_descriptor.set_mag_filter(
+        if sampling_flags.contains(TextureSamplingFlags::NEAREST_MAG) {
+            MTLSamplerMinMagFilter::Nearest
+        } else {
+            MTLSamplerMinMagFilter::Linear
+        });
+    sampler_descriptor.set_mip_filter(
+        match (sampling_flags.contains(TextureSamplingFlags::MIPMAP),
+               sampling_flags.contains(TextureSamplingFlags::NEAREST_MIN)) {
+            (false, _) => MTLSamplerMipFilter::NotMipmapped,
+            (true, false) => MTLSamplerMipFilter::Linear,
+            (true, true) => MTLSamplerMipFilter::Nearest,
+        });
+    sampler_descriptor.set_max_anisotropy(
+        if sampling_flags.contains(TextureSamplingFlags::ANISOTROPIC) {
+            MAX_ANISOTROPY
+        } else {
+            1
+        });
+    sampler_descriptor.set_address_mode_s(
+        address_mode(sampling_flags.contains(TextureSamplingFlags::REPEAT_U),
+                     sampling_flags.contains(TextureSamplingFlags::CLAMP_TO_BORDER_U)));
+    sampler_descriptor.set_address_mode_t(
+        address_mode(sampling_flags.contains(TextureSamplingFlags::REPEAT_V),
+                     sampling_flags.contains(TextureSamplingFlags::CLAMP_TO_BORDER_V)));
+    // Border colors are unavailable before iOS 14, so they're only set when they're used.
+    if sampling_flags.intersects(TextureSamplingFlags::CLAMP_TO_BORDER_U |
+                                 TextureSamplingFlags::CLAMP_TO_BORDER_V) {
+        sampler_descriptor.set_border_color(border_color);
+    }
+    device.new_sampler(&sampler_descriptor)
+}
+
+fn create_depth_stencil_state(device: &DeviceRef, key: DepthStencilKey) -> DepthStencilState {
+    let depth_stencil_descriptor = DepthStencilDescriptor::new();
+
+    match key.depth {
+        Some(depth_state) => {
+            let compare_function = depth_state.func.to_metal_compare_function();
+            depth_stencil_descriptor.set_depth_compare_function(compare_function);
+            depth_stencil_descriptor.set_depth_write_enabled(depth_state.write);
+        }
+        None => {
+            depth_stencil_descriptor.set_depth_compare_function(MTLCompareFunction::Always);
+            depth_stencil_descriptor.set_depth_write_enabled(false);
+        }
+    }
+
+    match key.stencil {
+        Some(stencil_state) => {
+            let stencil_descriptor = StencilDescriptor::new();
+            let compare_function = stencil_state.func.to_metal_compare_function();
+            let (pass_operation, write_mask) = if stencil_state.write {
+                (MTLStencilOperation::Replace, stencil_state.mask)
+            } else {
+                (MTLStencilOperation::Keep, 0)
+            };
+            stencil_descriptor.set_stencil_compare_function(compare_function);
+            stencil_descriptor.set_stencil_failure_operation(MTLStencilOperation::Keep);
+            stencil_descriptor.set_depth_failure_operation(MTLStencilOperation::Keep);
+            stencil_descriptor.set_depth_stencil_pass_operation(pass_operation);
+            stencil_descriptor.set_write_mask(write_mask);
+            depth_stencil_descriptor.set_front_face_stencil(Some(&stencil_descriptor));
+            depth_stencil_descriptor.set_back_face_stencil(Some(&stencil_descriptor));
+        }
+        None => {
+            depth_stencil_descriptor.set_front_face_stencil(None);
+            depth_stencil_descriptor.set_back_face_stencil(None);
+        }
+    }
+
+    device.new_depth_stencil_state(&depth_stencil_descriptor)
+}
+
+fn address_mode(repeat: bool, clamp_to_border: bool) -> MTLSamplerAddressMode {
+    match (clamp_to_border, repeat) {
+        (true, _) => MTLSamplerAddressMode::ClampToBorderColor,
+        (false, true) => MTLSamplerAddressMode::Repeat,
+        (false, false) => MTLSamplerAddressMode::ClampToEdge,
+    }
+}
+
+// Metal only has three border colors, so this picks the closest one.
+fn nearest_border_color(color: ColorF) -> MTLSamplerBorderColor {
+    if color.a() < 0.5 {
+        MTLSamplerBorderColor::TransparentBlack
+    } else if color.r() + color.g() + color.b() < 1.5 {
+        MTLSamplerBorderColor::OpaqueBlack
+    } else {
+        MTLSamplerBorderColor::OpaqueWhite
+    }
+}
+
 fn create_texture_descriptor(format: TextureFormat, size: Vector2I) -> TextureDescriptor {
     let descriptor = TextureDescriptor::new();
     descriptor.set_texture_type(MTLTextureType::D2);
     match format {
         TextureFormat::R8 => descriptor.set_pixel_format(MTLPixelFormat::R8Unorm),
+        TextureFormat::R16 => descriptor.set_pixel_format(MTLPixelFormat::R16Unorm),
         TextureFormat::R16F => descriptor.set_pixel_format(MTLPixelFormat::R16Float),
+        TextureFormat::RG8 => descriptor.set_pixel_format(MTLPixelFormat::RG8Unorm),
+        TextureFormat::RG16F => descriptor.set_pixel_format(MTLPixelFormat::RG16Float),
         TextureFormat::RGBA8 => descriptor.set_pixel_format(MTLPixelFormat::RGBA8Unorm),
+        TextureFormat::RGBA8Srgb => {
+            descriptor.set_pixel_format(MTLPixelFormat::RGBA8Unorm_sRGB)
+        }
+        TextureFormat::BGRA8 => descriptor.set_pixel_format(MTLPixelFormat::BGRA8Unorm),
+        TextureFormat::RGBA16 => descriptor.set_pixel_format(MTLPixelFormat::RGBA16Unorm),
         TextureFormat::RGBA16F => descriptor.set_pixel_format(MTLPixelFormat::RGBA16Float),
         TextureFormat::RGBA32F => descriptor.set_pixel_format(MTLPixelFormat::RGBA32Float),
     }
@@ -2735,7 +4179,153 @@ fn create_texture_descriptor(format: TextureFormat, size: Vector2I) -> TextureDe
     descriptor
 }
 
+// Returns the byte offset of the first index of an indexed draw, checking in debug builds that
+// the drawn indices lie within the index buffer.
+fn index_buffer_offset(index_buffer: &Buffer,
+                       index_count: u64,
+                       render_state: &RenderState<MetalDevice>)
+                       -> u64 {
+    let index_size = mem::size_of::<u32>() as u64;
+    let index_end = render_state.first_index as u64 + index_count;
+    debug_assert!(index_end * index_size <= index_buffer.length(),
+                  "Index range {}..{} is out of bounds of the index buffer!",
+                  render_state.first_index,
+                  index_end);
+    render_state.first_index as u64 * index_size
+}
+
 struct BufferUploadEventData {
     mutex: Mutex<u64>,
     cond: Condvar,
 }
+
+#[cfg(test)]
+mod test {
+    use super::{DepthStencilKey, MetalDevice};
+    use foreign_types::ForeignType;
+    use metal::{self, MTLPixelFormat, MTLStorageMode, MTLTextureUsage, TextureDescriptor};
+    use pathfinder_gpu::{DepthFunc, DepthState, Device, StencilFunc, StencilState};
+    use pathfinder_gpu::{TextureFormat, VertexAttrClass, VertexAttrDescriptor, VertexAttrType};
+    use pathfinder_gpu::conformance;
+    use pathfinder_resources::embedded::EmbeddedResourceLoader;
+
+    fn create_device() -> MetalDevice {
+        let native_device = metal::Device::system_default().expect("No Metal device found!");
+        let descriptor = TextureDescriptor::new();
+        descriptor.set_pixel_format(MTLPixelFormat::BGRA8Unorm);
+        descriptor.set_width(1);
+        descriptor.set_height(1);
+        descriptor.set_storage_mode(MTLStorageMode::Private);
+        descriptor.set_usage(MTLTextureUsage::RenderTarget);
+        let texture = native_device.new_texture(&descriptor);
+        unsafe { MetalDevice::new(native_device, texture) }
+    }
+
+    #[test]
+    fn test_depth_stencil_state_reuse() {
+        let device = create_device();
+        let key = DepthStencilKey {
+            depth: Some(DepthState { func: DepthFunc::Less, write: true }),
+            stencil: None,
+        };
+        let depth_stencil_state = device.depth_stencil_state(key);
+        assert_eq!(device.depth_stencil_state(key).as_ptr(), depth_stencil_state.as_ptr());
+
+        let other_key = DepthStencilKey {
+            depth: None,
+            stencil: Some(StencilState {
+                func: StencilFunc::Equal,
+                reference: 0,
+                mask: 1,
+                write: false,
+            }),
+        };
+        assert_ne!(device.depth_stencil_state(other_key).as_ptr(), depth_stencil_state.as_ptr());
+        assert_eq!(device.depth_stencil_states.borrow().len(), 2);
+    }
+
+    #[test]
+    fn test_vertex_layout_sharing() {
+        let device = create_device();
+        let program = device.create_raster_program(&EmbeddedResourceLoader::new(), "debug/solid");
+        let position_attr = device.get_vertex_attr(&program, "Position").unwrap();
+        let descriptor = VertexAttrDescriptor {
+            size: 2,
+            class: VertexAttrClass::Int,
+            attr_type: VertexAttrType::I16,
+            stride: 4,
+            offset: 0,
+            divisor: 0,
+            buffer_index: 0,
+        };
+        let vertex_array = device.create_vertex_array();
+        let other_vertex_array = device.create_vertex_array();
+        device.configure_vertex_attr(&vertex_array, &position_attr, &descriptor);
+        device.configure_vertex_attr(&other_vertex_array, &position_attr, &descriptor);
+        let layout = vertex_array.layout.borrow().clone();
+        assert!(layout == *other_vertex_array.layout.borrow());
+
+        // Reconfiguring a vertex array mustn't change the layout that a pipeline key holds.
+        device.configure_vertex_attr(&vertex_array,
+                                     &position_attr,
+                                     &VertexAttrDescriptor { stride: 8, ..descriptor });
+        assert!(*vertex_array.layout.borrow() != layout);
+        assert!(layout == *other_vertex_array.layout.borrow());
+    }
+
+    #[test]
+    fn test_format_conformance() {
+        let device = create_device();
+        device.begin_commands();
+        conformance::run_format_conformance(&device);
+        device.end_commands();
+    }
+
+    #[test]
+    fn test_mipmap_generation() {
+        let device = create_device();
+        device.begin_commands();
+        conformance::check_mipmap_generation(&device, &EmbeddedResourceLoader::new()).unwrap();
+        device.end_commands();
+    }
+
+    #[test]
+    fn test_srgb_sampling() {
+        let device = create_device();
+        device.begin_commands();
+        conformance::check_srgb_sampling(&device, &EmbeddedResourceLoader::new()).unwrap();
+        device.end_commands();
+    }
+
+    #[test]
+    fn test_constant_blend_color() {
+        let device = create_device();
+        device.begin_commands();
+        conformance::check_constant_blend_color(&device, &EmbeddedResourceLoader::new()).unwrap();
+        device.end_commands();
+    }
+
+    #[test]
+    fn test_base_instance() {
+        let device = create_device();
+        device.begin_commands();
+        conformance::check_base_instance(&device, &EmbeddedResourceLoader::new()).unwrap();
+        device.end_commands();
+    }
+
+    #[test]
+    fn test_rg8_round_trip() {
+        let device = create_device();
+        device.begin_commands();
+        conformance::check_format_round_trip(&device, TextureFormat::RG8).unwrap();
+        device.end_commands();
+    }
+
+    #[test]
+    fn test_rg16f_round_trip() {
+        let device = create_device();
+        device.begin_commands();
+        conformance::check_format_round_trip(&device, TextureFormat::RG16F).unwrap();
+        device.end_commands();
+    }
+}
@@ -14,3 +14,8 @@
 
 pub mod renderer;
 pub mod shaders;
+
+// The GPU-side structs that `Renderer::read_d3d11_tiles()` and `Renderer::read_d3d11_fills()`
+// decode, for debugging.
+pub use crate::gpu_data::{Fill, FillId, ShaderBufferElement, TileBatchId, TileD3D11, TileId};
+pub use crate::gpu_data::decode_shader_buffer;
@@ -19,7 +19,7 @@ use crate::gpu::renderer::{FramebufferFlags, RendererCore};
 use crate::gpu_data::{AlphaTileD3D11, BackdropInfoD3D11, DiceMetadataD3D11, DrawTileBatchD3D11};
 use crate::gpu_data::{Fill, FirstTileD3D11, MicrolineD3D11, PathSource, PropagateMetadataD3D11};
 use crate::gpu_data::{SegmentIndicesD3D11, SegmentsD3D11, TileD3D11, TileBatchDataD3D11};
-use crate::gpu_data::{TileBatchTexture, TilePathInfoD3D11};
+use crate::gpu_data::{TileBatchId, TileBatchTexture, TilePathInfoD3D11};
 use byte_slice_cast::AsSliceOf;
 use pathfinder_geometry::transform2d::Transform2F;
 use pathfinder_geometry::vector::Vector2F;
@@ -230,7 +230,7 @@ impl<D> RendererD3D11<D> where D: Device {
 
         core.stats.fill_count += needed_fill_count as usize;
 
-        Some(FillBufferInfoD3D11 { fill_vertex_buffer_id })
+        Some(FillBufferInfoD3D11 { fill_vertex_buffer_id, fill_count: needed_fill_count })
     }
 
     pub(crate) fn upload_scene(&mut self,
@@ -361,7 +361,7 @@ impl<D> RendererD3D11<D> where D: Device {
                   tiles_d3d11_buffer_id: GeneralBufferID,
                   alpha_tiles_buffer_id: GeneralBufferID,
                   propagate_tiles_info: &PropagateTilesInfoD3D11) {
-        let &FillBufferInfoD3D11 { fill_vertex_buffer_id } = fill_storage_info;
+        let &FillBufferInfoD3D11 { fill_vertex_buffer_id, .. } = fill_storage_info;
         let &PropagateTilesInfoD3D11 { ref alpha_tile_range } = propagate_tiles_info;
 
         let fill_program = &self.programs.fill_program;
@@ -525,7 +525,6 @@ impl<D> RendererD3D11<D> where D: Device {
                         alpha_tiles_buffer_id,
                         &propagate_tiles_info);
 
-        core.allocator.free_general_buffer(fill_buffer_info.fill_vertex_buffer_id);
         core.allocator.free_general_buffer(alpha_tiles_buffer_id);
 
         // FIXME(pcwalton): This seems like the wrong place to do this...
@@ -538,6 +537,8 @@ impl<D> RendererD3D11<D> where D: Device {
             tiles_d3d11_buffer_id,
             propagate_metadata_buffer_id: propagate_metadata_buffer_ids.propagate_metadata,
             first_tile_map_buffer_id,
+            fill_vertex_buffer_id: fill_buffer_info.fill_vertex_buffer_id,
+            fill_count: fill_buffer_info.fill_count,
         });
     }
 
@@ -781,7 +782,24 @@ impl<D> RendererD3D11<D> where D: Device {
         core.preserve_draw_framebuffer();
     }
 
-    pub(crate) fn end_frame(&mut self, core: &mut RendererCore<D>) {
+    // Returns the tile buffer of the given batch and the number of tiles in it, if the batch was
+    // prepared in the current or most recent frame.
+    pub(crate) fn tiles_buffer(&self, batch_id: TileBatchId) -> Option<(GeneralBufferID, u32)> {
+        self.tile_batch_info.get(batch_id.0 as usize).map(|tile_batch_info| {
+            (tile_batch_info.tiles_d3d11_buffer_id, tile_batch_info.tile_count)
+        })
+    }
+
+    // Like `tiles_buffer()`, but for the fills binned into the batch's tiles.
+    pub(crate) fn fills_buffer(&self, batch_id: TileBatchId) -> Option<(GeneralBufferID, u32)> {
+        self.tile_batch_info.get(batch_id.0 as usize).map(|tile_batch_info| {
+            (tile_batch_info.fill_vertex_buffer_id, tile_batch_info.fill_count)
+        })
+    }
+
+    // The previous frame's tile batch buffers are kept until now so that they can be read back
+    // after `end_scene()`.
+    pub(crate) fn begin_frame(&mut self, core: &mut RendererCore<D>) {
         self.free_tile_batch_buffers(core);
     }
 
@@ -791,6 +809,7 @@ impl<D> RendererD3D11<D> where D: Device {
             core.allocator.free_general_buffer(tile_batch_info.tiles_d3d11_buffer_id);
             core.allocator.free_general_buffer(tile_batch_info.propagate_metadata_buffer_id);
             core.allocator.free_general_buffer(tile_batch_info.first_tile_map_buffer_id);
+            core.allocator.free_general_buffer(tile_batch_info.fill_vertex_buffer_id);
         }
     }
 }
@@ -804,11 +823,14 @@ struct TileBatchInfoD3D11 {
     tiles_d3d11_buffer_id: GeneralBufferID,
     propagate_metadata_buffer_id: GeneralBufferID,
     first_tile_map_buffer_id: GeneralBufferID,
+    fill_vertex_buffer_id: GeneralBufferID,
+    fill_count: u32,
 }
 
 #[derive(Clone)]
 struct FillBufferInfoD3D11 {
     fill_vertex_buffer_id: GeneralBufferID,
+    fill_count: u32,
 }
 
 #[derive(Debug)]
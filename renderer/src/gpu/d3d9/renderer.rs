@@ -239,6 +239,7 @@ impl<D> RendererD3D9<D> where D: Device {
             program: &fill_raster_program.program,
             vertex_array: &fill_vertex_array.vertex_array,
             primitive: Primitive::Triangles,
+            first_index: 0,
             textures: &[(&fill_raster_program.area_lut_texture, area_lut_texture)],
             uniforms: &[
                 (&fill_raster_program.framebuffer_size_uniform,
@@ -315,6 +316,7 @@ impl<D> RendererD3D9<D> where D: Device {
             program: &self.programs.tile_clip_copy_program.program,
             vertex_array: &tile_clip_copy_vertex_array.vertex_array,
             primitive: Primitive::Triangles,
+            first_index: 0,
             textures: &[
                 (&self.programs.tile_clip_copy_program.src_texture,
                  core.device.framebuffer_texture(mask_framebuffer)),
@@ -341,6 +343,7 @@ impl<D> RendererD3D9<D> where D: Device {
             program: &self.programs.tile_clip_combine_program.program,
             vertex_array: &tile_clip_combine_vertex_array.vertex_array,
             primitive: Primitive::Triangles,
+            first_index: 0,
             textures: &[
                 (&self.programs.tile_clip_combine_program.src_texture,
                  core.device.framebuffer_texture(&mask_temp_framebuffer)),
@@ -451,6 +454,7 @@ impl<D> RendererD3D9<D> where D: Device {
             program: &tile_raster_program.common.program,
             vertex_array: &tile_vertex_array.vertex_array,
             primitive: Primitive::Triangles,
+            first_index: 0,
             textures: &textures,
             images: &[],
             storage_buffers: &[],
@@ -514,6 +518,7 @@ impl<D> RendererD3D9<D> where D: Device {
             program: &self.programs.tile_copy_program.program,
             vertex_array: &tile_copy_vertex_array.vertex_array,
             primitive: Primitive::Triangles,
+            first_index: 0,
             textures: &textures,
             images: &[],
             storage_buffers: &[],
@@ -75,6 +75,23 @@ impl Div<usize> for RenderStats {
     }
 }
 
+/// Statistics accumulated over a batch of frames, for profiling.
+///
+/// See `Renderer::collect_frame_stats()`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FrameStats {
+    /// The number of scenes rendered since the last reset.
+    pub frame_count: usize,
+    /// The sum of the statistics of every frame.
+    pub render_stats: RenderStats,
+    /// The total GPU time of every frame, as measured by `Device::collect_frame_stats()`.
+    pub gpu_time: Duration,
+    /// The most VRAM Pathfinder had allocated at the end of any frame.
+    pub peak_gpu_bytes_allocated: u64,
+    /// The most VRAM any single frame used.
+    pub peak_gpu_bytes_committed: u64,
+}
+
 pub(crate) struct TimerQueryCache<D> where D: Device {
     free_queries: Vec<D::TimerQuery>,
 }
@@ -18,12 +18,16 @@ use crate::gpu::d3d11::renderer::RendererD3D11;
 #[cfg(feature="ui")]
 use crate::gpu::debug::DebugUIPresenter;
 use crate::gpu::options::{DestFramebuffer, RendererLevel, RendererMode, RendererOptions};
+#[cfg(feature="debug")]
+use crate::gpu::perf::FrameStats;
 use crate::gpu::perf::{PendingTimer, RenderStats, RenderTime, TimeCategory, TimerQueryCache};
 use crate::gpu::shaders::{BlitProgram, BlitVertexArray, ClearProgram, ClearVertexArray};
 use crate::gpu::shaders::{ProgramsCore, ReprojectionProgram, ReprojectionVertexArray};
 use crate::gpu::shaders::{StencilProgram, StencilVertexArray, TileProgramCommon, VertexArraysCore};
 use crate::gpu_data::{ColorCombineMode, RenderCommand, TextureLocation, TextureMetadataEntry};
 use crate::gpu_data::{TexturePageDescriptor, TexturePageId, TileBatchTexture};
+#[cfg(feature="d3d11")]
+use crate::gpu_data::{Fill, ShaderBufferElement, TileBatchId, TileD3D11, decode_shader_buffer};
 use crate::options::BoundingQuad;
 use crate::tiles::{TILE_HEIGHT, TILE_WIDTH};
 use half::f16;
@@ -38,7 +42,8 @@ use pathfinder_gpu::allocator::{BufferTag, FramebufferID, FramebufferTag, Genera
 use pathfinder_gpu::allocator::{GPUMemoryAllocator, IndexBufferID, TextureID, TextureTag};
 use pathfinder_gpu::{BufferData, BufferTarget, ClearOps, DepthFunc, DepthState, Device, Primitive};
 use pathfinder_gpu::{RenderOptions, RenderState, RenderTarget, StencilFunc, StencilState};
-use pathfinder_gpu::{TextureBinding, TextureDataRef, TextureFormat, UniformBinding, UniformData};
+use pathfinder_gpu::{TextureBinding, TextureDataRef, TextureFormat, TextureSamplingFlags};
+use pathfinder_gpu::{UniformBinding, UniformData};
 use pathfinder_resources::ResourceLoader;
 use pathfinder_simd::default::{F32x2, F32x4, I32x2};
 use std::collections::VecDeque;
@@ -100,6 +105,9 @@ pub struct Renderer<D> where D: Device {
 
     #[cfg(feature="debug")]
     last_rendering_time: Option<RenderTime>,
+    // Statistics accumulated since `reset_frame_stats()`, if it has been called.
+    #[cfg(feature="debug")]
+    frame_stats: Option<FrameStats>,
 }
 
 enum RendererLevelImpl<D> where D: Device {
@@ -336,6 +344,8 @@ impl<D> Renderer<D> where D: Device {
 
             #[cfg(feature="debug")]
             last_rendering_time: None,
+            #[cfg(feature="debug")]
+            frame_stats: None,
         }
     }
 
@@ -354,6 +364,15 @@ impl<D> Renderer<D> where D: Device {
         self.core.current_timer = Some(PendingTimer::new());
         self.core.stats = RenderStats::default();
 
+        match self.level_impl {
+            #[cfg(feature="d3d9")]
+            RendererLevelImpl::D3D9(_) => {}
+            #[cfg(feature="d3d11")]
+            RendererLevelImpl::D3D11(ref mut d3d11_renderer) => {
+                d3d11_renderer.begin_frame(&mut self.core)
+            }
+        }
+
         self.core.alpha_tile_count = 0;
     }
 
@@ -430,21 +449,22 @@ impl<D> Renderer<D> where D: Device {
         self.core.stats.gpu_bytes_allocated = self.core.allocator.bytes_allocated();
         self.core.stats.gpu_bytes_committed = self.core.allocator.bytes_committed();
 
-        match self.level_impl {
-            #[cfg(feature="d3d9")]
-            RendererLevelImpl::D3D9(_) => {}
-            #[cfg(feature="d3d11")]
-            RendererLevelImpl::D3D11(ref mut d3d11_renderer) => {
-                d3d11_renderer.end_frame(&mut self.core)
-            }
-        }
-
         #[cfg(feature="debug")]
         {
             if let Some(timer) = self.core.current_timer.take() {
                 self.pending_timers.push_back(timer);
             }
             self.current_cpu_build_time = None;
+
+            if let Some(ref mut frame_stats) = self.frame_stats {
+                let stats = self.core.stats;
+                frame_stats.frame_count += 1;
+                frame_stats.render_stats = frame_stats.render_stats + stats;
+                frame_stats.peak_gpu_bytes_allocated =
+                    frame_stats.peak_gpu_bytes_allocated.max(stats.gpu_bytes_allocated);
+                frame_stats.peak_gpu_bytes_committed =
+                    frame_stats.peak_gpu_bytes_committed.max(stats.gpu_bytes_committed);
+            }
         }
 
         #[cfg(feature="ui")] {
@@ -538,6 +558,29 @@ impl<D> Renderer<D> where D: Device {
         self.last_rendering_time
     }
 
+    /// Starts accumulating statistics for `collect_frame_stats()`, discarding any accumulated so
+    /// far.
+    ///
+    /// This must not be called between `begin_scene()` and `end_scene()`.
+    #[cfg(feature="debug")]
+    pub fn reset_frame_stats(&mut self) {
+        self.frame_stats = Some(FrameStats::default());
+        self.core.device.reset_frame_stats();
+    }
+
+    /// Waits for the GPU to finish all submitted work and returns the statistics accumulated
+    /// over every scene ended since `reset_frame_stats()`, then stops accumulating them.
+    ///
+    /// This blocks, so it's intended for batch and headless profiling rather than interactive
+    /// use. It must not be called between `begin_scene()` and `end_scene()`. Returns `None` if
+    /// `reset_frame_stats()` hasn't been called since the last collection.
+    #[cfg(feature="debug")]
+    pub fn collect_frame_stats(&mut self) -> Option<FrameStats> {
+        let mut frame_stats = self.frame_stats.take()?;
+        frame_stats.gpu_time = self.core.device.collect_frame_stats().gpu_time;
+        Some(frame_stats)
+    }
+
     /// Returns a reference to the GPU device.
     /// 
     /// This can be useful to issue GPU commands manually via the low-level `pathfinder_gpu`
@@ -644,6 +687,36 @@ impl<D> Renderer<D> where D: Device {
         self.core.allocator.get_index_buffer(self.core.quad_vertex_indices_buffer_id)
     }
 
+    /// Reads back the tiles that the D3D11 tiler wrote for the given batch, for debugging.
+    ///
+    /// The tiles of the most recent scene stay readable until the next `begin_scene()`. Returns
+    /// `None` if this isn't a D3D11 renderer or that scene had no such batch. This stalls until
+    /// the GPU has finished writing the tiles.
+    #[cfg(feature="d3d11")]
+    pub fn read_d3d11_tiles(&self, batch_id: TileBatchId) -> Option<Vec<TileD3D11>> {
+        let (buffer_id, tile_count) = match self.level_impl {
+            RendererLevelImpl::D3D11(ref d3d11_renderer) => d3d11_renderer.tiles_buffer(batch_id)?,
+            #[cfg(feature="d3d9")]
+            RendererLevelImpl::D3D9(_) => return None,
+        };
+        Some(self.core.read_shader_buffer(buffer_id, tile_count as usize))
+    }
+
+    /// Reads back the fills that the D3D11 tiler binned for the given batch, for debugging.
+    ///
+    /// The `link` of each fill is the index of the next fill in its tile, starting from the
+    /// tile's `first_fill_id`. Like `read_d3d11_tiles()`, this works until the next
+    /// `begin_scene()`.
+    #[cfg(feature="d3d11")]
+    pub fn read_d3d11_fills(&self, batch_id: TileBatchId) -> Option<Vec<Fill>> {
+        let (buffer_id, fill_count) = match self.level_impl {
+            RendererLevelImpl::D3D11(ref d3d11_renderer) => d3d11_renderer.fills_buffer(batch_id)?,
+            #[cfg(feature="d3d9")]
+            RendererLevelImpl::D3D9(_) => return None,
+        };
+        Some(self.core.read_shader_buffer(buffer_id, fill_count as usize))
+    }
+
     fn allocate_pattern_texture_page(&mut self,
                                      page_id: TexturePageId,
                                      descriptor: &TexturePageDescriptor) {
@@ -794,6 +867,7 @@ impl<D> Renderer<D> where D: Device {
             program: &self.stencil_program.program,
             vertex_array: &self.frame.stencil_vertex_array.vertex_array,
             primitive: Primitive::Triangles,
+            first_index: 0,
             textures: &[],
             images: &[],
             storage_buffers: &[],
@@ -832,6 +906,7 @@ impl<D> Renderer<D> where D: Device {
             program: &self.reprojection_program.program,
             vertex_array: &self.frame.reprojection_vertex_array.vertex_array,
             primitive: Primitive::Triangles,
+            first_index: 0,
             textures: &[(&self.reprojection_program.texture, texture)],
             images: &[],
             storage_buffers: &[],
@@ -886,6 +961,7 @@ impl<D> Renderer<D> where D: Device {
             program: &self.clear_program.program,
             vertex_array: &self.frame.clear_vertex_array.vertex_array,
             primitive: Primitive::Triangles,
+            first_index: 0,
             textures: &[],
             images: &[],
             storage_buffers: &[],
@@ -929,6 +1005,7 @@ impl<D> Renderer<D> where D: Device {
             program: &self.blit_program.program,
             vertex_array: &self.frame.blit_vertex_array.vertex_array,
             primitive: Primitive::Triangles,
+            first_index: 0,
             textures: &textures[..],
             images: &[],
             storage_buffers: &[],
@@ -1050,6 +1127,18 @@ impl<D> Renderer<D> where D: Device {
 }
 
 impl<D> RendererCore<D> where D: Device {
+    /// Reads back the first `count` elements of a storage buffer, such as the tiles or fills of
+    /// the D3D11 tiler, for debugging. This stalls until the GPU has finished writing them.
+    #[cfg(feature="d3d11")]
+    pub(crate) fn read_shader_buffer<T>(&self, buffer_id: GeneralBufferID, count: usize)
+                                        -> Vec<T>
+                                        where T: ShaderBufferElement {
+        let buffer = self.allocator.get_general_buffer(buffer_id);
+        let byte_range = 0..(count * T::SHADER_STRIDE);
+        let receiver = self.device.read_buffer(buffer, BufferTarget::Storage, byte_range);
+        decode_shader_buffer(&self.device.recv_buffer(&receiver))
+    }
+
     pub(crate) fn mask_texture_format(&self) -> TextureFormat {
         match self.mode.level {
             RendererLevel::D3D9 => TextureFormat::RGBA16F,
@@ -1097,6 +1186,7 @@ impl<D> RendererCore<D> where D: Device {
             program: &self.programs.blit_program.program,
             vertex_array: &self.vertex_arrays.blit_vertex_array.vertex_array,
             primitive: Primitive::Triangles,
+            first_index: 0,
             textures: &[(&self.programs.blit_program.src_texture, old_mask_texture)],
             images: &[],
             storage_buffers: &[],
@@ -1158,8 +1248,13 @@ impl<D> RendererCore<D> where D: Device {
             Some(color_texture) => {
                 let color_texture_page = self.texture_page(color_texture.page);
                 let color_texture_size = self.device.texture_size(color_texture_page).to_f32();
-                self.device.set_texture_sampling_mode(color_texture_page,
-                                                      color_texture.sampling_flags);
+                let mut sampling_flags = color_texture.sampling_flags;
+                let color_texture_format = self.device.texture_format(color_texture_page);
+                if !self.device.format_is_filterable(color_texture_format) {
+                    sampling_flags.insert(TextureSamplingFlags::NEAREST_MIN |
+                                          TextureSamplingFlags::NEAREST_MAG);
+                }
+                self.device.set_texture_sampling_mode(color_texture_page, sampling_flags);
                 textures.push((&tile_program.color_texture_0, color_texture_page));
                 uniforms.push((&tile_program.color_texture_size_0_uniform,
                                UniformData::Vec2(color_texture_size.0)));
@@ -1228,8 +1323,7 @@ impl<D> RendererCore<D> where D: Device {
         match self.options.dest {
             DestFramebuffer::Default { viewport, .. } => viewport,
             DestFramebuffer::Other(ref framebuffer) => {
-                let texture = self.device.framebuffer_texture(framebuffer);
-                let size = self.device.texture_size(texture);
+                let size = self.device.render_target_size(&RenderTarget::Framebuffer(framebuffer));
                 RectI::new(Vector2I::default(), size)
             }
         }
@@ -24,6 +24,10 @@ use pathfinder_geometry::transform2d::Transform2F;
 use pathfinder_geometry::vector::{Vector2F, Vector2I};
 use pathfinder_gpu::TextureSamplingFlags;
 use std::fmt::{Debug, Formatter, Result as DebugResult};
+#[cfg(feature="d3d11")]
+use std::mem;
+#[cfg(feature="d3d11")]
+use std::ptr;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Duration;
@@ -252,10 +256,12 @@ pub struct TileBatchTexture {
     pub(crate) composite_op: PaintCompositeOp,
 }
 
+/// The index of a tile in the D3D11 tiler's tile buffer, or -1 for none.
 #[derive(Clone, Copy, PartialEq, Debug)]
 #[repr(C)]
 pub struct TileId(pub i32);
 
+/// The index of a fill in the D3D11 tiler's fill buffer, or -1 for none.
 #[derive(Clone, Copy, PartialEq, Debug)]
 #[repr(C)]
 pub struct FillId(pub i32);
@@ -274,16 +280,26 @@ pub struct TileObjectPrimitive {
     pub backdrop: i8,
 }
 
+/// A tile as the D3D11 tiler's compute shaders lay it out.
 #[derive(Clone, Copy, Debug)]
 #[repr(C)]
 pub struct TileD3D11 {
+    /// The next tile at the same position, in the order that they're composited.
     pub next_tile_id: TileId,
+    /// The first fill binned into this tile. The rest follow the fills' `link`s.
     pub first_fill_id: FillId,
+    /// The low 16 bits of the tile's 24-bit alpha tile index, which is negative if the tile
+    /// doesn't need one.
     pub alpha_tile_id_lo: i16,
+    /// The high 8 bits of the alpha tile index.
     pub alpha_tile_id_hi: i8,
+    /// The change in winding number that binning found along the tile's edge.
     pub backdrop_delta: i8,
+    /// The index of the tile's paint in the color texture metadata.
     pub color: u16,
+    /// The tile's control flags.
     pub ctrl: u8,
+    /// The winding number at the tile's left edge.
     pub backdrop: i8,
 }
 
@@ -351,14 +367,16 @@ pub enum ColorCombineMode {
     DestIn,
 }
 
+/// A line segment to be rasterized into an alpha tile.
 #[derive(Clone, Copy, Debug, Default)]
 #[repr(C)]
 pub struct Fill {
+    /// The segment, in 8.8 fixed point relative to the tile.
     pub line_segment: LineSegmentU16,
-    // The meaning of this field depends on whether fills are being done with the GPU rasterizer or
-    // GPU compute. If raster, this field names the index of the alpha tile that this fill belongs
-    // to. If compute, this field names the index of the next fill in the singly-linked list of
-    // fills belonging to this alpha tile.
+    /// The meaning of this field depends on whether fills are being done with the GPU rasterizer
+    /// or GPU compute. If raster, this field names the index of the alpha tile that this fill
+    /// belongs to. If compute, this field names the index of the next fill in the singly-linked
+    /// list of fills belonging to this alpha tile.
     pub link: u32,
 }
 
@@ -437,6 +455,52 @@ pub(crate) struct FirstTileD3D11 {
 #[repr(C)]
 pub struct AlphaTileId(pub u32);
 
+#[cfg(feature="d3d11")]
+/// Structs that mirror an element of a shader storage buffer, so that buffers read back from the
+/// GPU can be decoded for debugging.
+///
+/// # Safety
+///
+/// `decode_shader_buffer()` reinterprets buffer bytes as `Self`, so implementors must be
+/// `#[repr(C)]`, valid for every bit pattern, and laid out exactly like the shader's element.
+pub unsafe trait ShaderBufferElement: Copy {
+    /// The stride between elements in the shader's storage buffer, in bytes.
+    const SHADER_STRIDE: usize;
+}
+
+#[cfg(feature="d3d11")]
+// `uint iTiles[]`, four words per tile.
+unsafe impl ShaderBufferElement for TileD3D11 {
+    const SHADER_STRIDE: usize = 16;
+}
+
+#[cfg(feature="d3d11")]
+// `uint iFills[]`, three words per fill.
+unsafe impl ShaderBufferElement for Fill {
+    const SHADER_STRIDE: usize = 12;
+}
+
+#[cfg(feature="d3d11")]
+// `uint iAlphaTiles[]`, two words per alpha tile.
+unsafe impl ShaderBufferElement for AlphaTileD3D11 {
+    const SHADER_STRIDE: usize = 8;
+}
+
+#[cfg(feature="d3d11")]
+/// Decodes the contents of a storage buffer, as returned by `Device::recv_buffer()`.
+pub fn decode_shader_buffer<T>(bytes: &[u8]) -> Vec<T> where T: ShaderBufferElement {
+    let stride = mem::size_of::<T>();
+    assert_eq!(stride, T::SHADER_STRIDE, "Rust and shader layouts of the element differ!");
+    assert_eq!(bytes.len() % stride,
+               0,
+               "Buffer length {} isn't a multiple of the {}-byte element size!",
+               bytes.len(),
+               stride);
+    bytes.chunks_exact(stride).map(|element| {
+        unsafe { ptr::read_unaligned(element.as_ptr() as *const T) }
+    }).collect()
+}
+
 impl PathBatchIndex {
     #[inline]
     pub fn none() -> PathBatchIndex {
@@ -544,3 +608,29 @@ impl Default for FirstTileD3D11 {
         FirstTileD3D11 { first_tile: -1 }
     }
 }
+
+#[cfg(all(test, feature="d3d11"))]
+mod test {
+    use super::{AlphaTileD3D11, Fill, ShaderBufferElement, TileD3D11, decode_shader_buffer};
+    use std::mem;
+
+    #[test]
+    fn test_shader_buffer_element_layouts() {
+        assert_eq!(mem::size_of::<TileD3D11>(), TileD3D11::SHADER_STRIDE);
+        assert_eq!(mem::size_of::<Fill>(), Fill::SHADER_STRIDE);
+        assert_eq!(mem::size_of::<AlphaTileD3D11>(), AlphaTileD3D11::SHADER_STRIDE);
+    }
+
+    #[test]
+    fn test_decode_shader_buffer() {
+        let words: [u32; 6] = [0x00020001, 0x00040003, 7, 0x00060005, 0x00080007, !0];
+        let bytes: Vec<u8> = words.iter().flat_map(|word| word.to_le_bytes().to_vec()).collect();
+        let fills: Vec<Fill> = decode_shader_buffer(&bytes);
+        assert_eq!(fills.len(), 2);
+        assert_eq!((fills[0].line_segment.from_x, fills[0].line_segment.from_y), (1, 2));
+        assert_eq!((fills[0].line_segment.to_x, fills[0].line_segment.to_y), (3, 4));
+        assert_eq!(fills[0].link, 7);
+        assert_eq!((fills[1].line_segment.from_x, fills[1].line_segment.to_y), (5, 8));
+        assert_eq!(fills[1].link, !0);
+    }
+}
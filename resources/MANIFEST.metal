@@ -23,12 +23,14 @@ shaders/metal/d3d9/tile_clip_copy.fs.metal
 shaders/metal/d3d9/tile_clip_copy.vs.metal
 shaders/metal/d3d9/tile_copy.fs.metal
 shaders/metal/d3d9/tile_copy.vs.metal
+shaders/metal/debug/inspect.fs.metal
 shaders/metal/debug/solid.fs.metal
 shaders/metal/debug/solid.vs.metal
 shaders/metal/debug/texture.fs.metal
 shaders/metal/debug/texture.vs.metal
 shaders/metal/demo_ground.fs.metal
 shaders/metal/demo_ground.vs.metal
+shaders/metal/reduce.cs.metal
 shaders/metal/reproject.fs.metal
 shaders/metal/reproject.vs.metal
 shaders/metal/stencil.fs.metal
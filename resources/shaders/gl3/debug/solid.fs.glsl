@@ -20,9 +20,15 @@ precision highp float;
 
 uniform vec4 uColor;
 
+in float vEdgeDistance;
+
 out vec4 oFragColor;
 
 void main(){
-    oFragColor = vec4(uColor . rgb, 1.0)* uColor . a;
+
+
+
+    float coverage = 1.0 - smoothstep(0.0, 1.0, abs(vEdgeDistance));
+    oFragColor = vec4(uColor . rgb, 1.0)* uColor . a * coverage;
 }
 
@@ -19,6 +19,8 @@ precision highp float;
 
 
 uniform vec2 uFramebufferSize;
+uniform mat2 uTransform;
+uniform vec2 uTranslation;
 uniform vec2 uTextureSize;
 
 in ivec2 aPosition;
@@ -28,7 +30,8 @@ out vec2 vTexCoord;
 
 void main(){
     vTexCoord = vec2(aTexCoord)/ uTextureSize;
-    vec2 position = vec2(aPosition)/ uFramebufferSize * 2.0 - 1.0;
+    vec2 position = uTransform * vec2(aPosition)+ uTranslation;
+    position = position / uFramebufferSize * 2.0 - 1.0;
     gl_Position = vec4(position . x, - position . y, 0.0, 1.0);
 }
 
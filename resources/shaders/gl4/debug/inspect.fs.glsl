@@ -0,0 +1,46 @@
+#version {{version}}
+// Automatically generated from files in pathfinder/shaders/. Do not edit!
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+precision highp float;
+
+
+
+
+
+uniform sampler2D uTexture;
+uniform vec4 uChannelMask;
+uniform float uScale;
+
+in vec2 vTexCoord;
+
+out vec4 oFragColor;
+
+void main(){
+    vec4 texel = texture(uTexture, vTexCoord);
+
+    vec4 color;
+    if(dot(uChannelMask, vec4(1.0)) == 1.0)
+        color = vec4(vec3(dot(texel, uChannelMask)), 1.0);
+    else
+        color = vec4(texel . rgb, mix(1.0, texel . a, uChannelMask . a));
+
+    oFragColor = vec4(clamp(color . rgb * uScale, 0.0, 1.0) * color . a, color . a);
+}
+
@@ -27,6 +27,12 @@ out vec4 oFragColor;
 
 void main(){
     float alpha = texture(uTexture, vTexCoord). r * uColor . a;
+
+
+
+    if(alpha == 0.0)
+        discard;
+
     oFragColor = alpha * vec4(uColor . rgb, 1.0);
 }
 
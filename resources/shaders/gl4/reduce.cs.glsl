@@ -0,0 +1,99 @@
+#version {{version}}
+// Automatically generated from files in pathfinder/shaders/. Do not edit!
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+
+#extension GL_GOOGLE_include_directive : enable
+
+precision highp float;
+
+
+
+
+
+
+
+
+
+
+
+
+layout(local_size_x = 16, local_size_y = 16)in;
+
+uniform sampler2D uTexture;
+uniform ivec2 uTextureSize;
+uniform int uOp;
+uniform vec2 uHistogramRange;
+uniform int uHistogramBinCount;
+
+layout(std430, binding = 0)buffer bResult {
+
+
+    restrict uint iResult[];
+};
+
+shared vec4 sPartials[256];
+
+vec4 combine(vec4 a, vec4 b){
+    if(uOp == 0)
+        return min(a, b);
+    if(uOp == 1)
+        return max(a, b);
+    return a + b;
+}
+
+void main(){
+    ivec2 texelCoord = ivec2(gl_GlobalInvocationID . xy);
+    bool inBounds = all(lessThan(texelCoord, uTextureSize));
+    vec4 texel = texelFetch(uTexture, min(texelCoord, uTextureSize - 1), 0);
+
+    if(uOp == 3){
+        if(! inBounds)
+            return;
+        float luminance = dot(texel . rgb, vec3(0.2126, 0.7152, 0.0722));
+        float t =(luminance - uHistogramRange . x)/(uHistogramRange . y - uHistogramRange . x);
+        int bin = clamp(int(floor(t * float(uHistogramBinCount))), 0, uHistogramBinCount - 1);
+        atomicAdd(iResult[bin], 1u);
+        return;
+    }
+
+
+
+    if(! inBounds && uOp == 2)
+        texel = vec4(0.0);
+
+    uint localIndex = gl_LocalInvocationIndex;
+    sPartials[localIndex]= texel;
+    memoryBarrierShared();
+    barrier();
+
+    for(uint stride = 256 / 2;stride > 0u;stride /= 2u){
+        if(localIndex < stride)
+            sPartials[localIndex]= combine(sPartials[localIndex], sPartials[localIndex + stride]);
+        memoryBarrierShared();
+        barrier();
+    }
+
+    if(localIndex != 0u)
+        return;
+
+    uint workgroupIndex = gl_WorkGroupID . y * gl_NumWorkGroups . x + gl_WorkGroupID . x;
+    for(uint channel = 0u;channel < 4u;channel ++)
+        iResult[workgroupIndex * 4u + channel]= floatBitsToUint(sPartials[0][channel]);
+}
+
@@ -0,0 +1,33 @@
+// Automatically generated from files in pathfinder/shaders/. Do not edit!
+#include <metal_stdlib>
+#include <simd/simd.h>
+
+using namespace metal;
+
+struct main0_out
+{
+    float4 oFragColor [[color(0)]];
+};
+
+struct main0_in
+{
+    float2 vTexCoord [[user(locn0)]];
+};
+
+fragment main0_out main0(main0_in in [[stage_in]], constant float4& uChannelMask [[buffer(0)]], constant float& uScale [[buffer(1)]], texture2d<float> uTexture [[texture(0)]], sampler uTextureSmplr [[sampler(0)]])
+{
+    main0_out out = {};
+    float4 texel = uTexture.sample(uTextureSmplr, in.vTexCoord);
+    float4 color;
+    if (dot(uChannelMask, float4(1.0)) == 1.0)
+    {
+        color = float4(float3(dot(texel, uChannelMask)), 1.0);
+    }
+    else
+    {
+        color = float4(texel.xyz, mix(1.0, texel.w, uChannelMask.w));
+    }
+    out.oFragColor = float4(fast::clamp(color.xyz * uScale, float3(0.0), float3(1.0)) * color.w, color.w);
+    return out;
+}
+
@@ -9,10 +9,16 @@ struct main0_out
     float4 oFragColor [[color(0)]];
 };
 
-fragment main0_out main0(constant float4& uColor [[buffer(0)]])
+struct main0_in
+{
+    float vEdgeDistance [[user(locn0)]];
+};
+
+fragment main0_out main0(main0_in in [[stage_in]], constant float4& uColor [[buffer(0)]])
 {
     main0_out out = {};
-    out.oFragColor = float4(uColor.xyz, 1.0) * uColor.w;
+    float coverage = 1.0 - smoothstep(0.0, 1.0, abs(in.vEdgeDistance));
+    out.oFragColor = (float4(uColor.xyz, 1.0) * uColor.w) * coverage;
     return out;
 }
 
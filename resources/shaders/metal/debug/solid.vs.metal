@@ -6,18 +6,23 @@ using namespace metal;
 
 struct main0_out
 {
+    float vEdgeDistance [[user(locn0)]];
     float4 gl_Position [[position]];
 };
 
 struct main0_in
 {
     int2 aPosition [[attribute(0)]];
+    float2 aOffset [[attribute(1)]];
+    float aEdgeDistance [[attribute(2)]];
 };
 
-vertex main0_out main0(main0_in in [[stage_in]], constant float2& uFramebufferSize [[buffer(0)]])
+vertex main0_out main0(main0_in in [[stage_in]], constant float2x2& uTransform [[buffer(0)]], constant float2& uTranslation [[buffer(1)]], constant float2& uFramebufferSize [[buffer(2)]])
 {
     main0_out out = {};
-    float2 position = ((float2(in.aPosition) / uFramebufferSize) * 2.0) - float2(1.0);
+    float2 position = (uTransform * (float2(in.aPosition) + in.aOffset)) + uTranslation;
+    position = ((position / uFramebufferSize) * 2.0) - float2(1.0);
+    out.vEdgeDistance = in.aEdgeDistance;
     out.gl_Position = float4(position.x, -position.y, 0.0, 1.0);
     return out;
 }
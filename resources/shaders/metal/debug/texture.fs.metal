@@ -18,6 +18,10 @@ fragment main0_out main0(main0_in in [[stage_in]], constant float4& uColor [[buf
 {
     main0_out out = {};
     float alpha = uTexture.sample(uTextureSmplr, in.vTexCoord).x * uColor.w;
+    if (alpha == 0.0)
+    {
+        discard_fragment();
+    }
     out.oFragColor = float4(uColor.xyz, 1.0) * alpha;
     return out;
 }
@@ -16,11 +16,12 @@ struct main0_in
     int2 aTexCoord [[attribute(1)]];
 };
 
-vertex main0_out main0(main0_in in [[stage_in]], constant float2& uTextureSize [[buffer(0)]], constant float2& uFramebufferSize [[buffer(1)]])
+vertex main0_out main0(main0_in in [[stage_in]], constant float2& uTextureSize [[buffer(0)]], constant float2x2& uTransform [[buffer(1)]], constant float2& uTranslation [[buffer(2)]], constant float2& uFramebufferSize [[buffer(3)]])
 {
     main0_out out = {};
     out.vTexCoord = float2(in.aTexCoord) / uTextureSize;
-    float2 position = ((float2(in.aPosition) / uFramebufferSize) * 2.0) - float2(1.0);
+    float2 position = (uTransform * float2(in.aPosition)) + uTranslation;
+    position = ((position / uFramebufferSize) * 2.0) - float2(1.0);
     out.gl_Position = float4(position.x, -position.y, 0.0, 1.0);
     return out;
 }
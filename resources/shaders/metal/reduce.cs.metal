@@ -0,0 +1,88 @@
+// Automatically generated from files in pathfinder/shaders/. Do not edit!
+#pragma clang diagnostic ignored "-Wmissing-prototypes"
+
+#include <metal_stdlib>
+#include <simd/simd.h>
+#include <metal_atomic>
+
+using namespace metal;
+
+struct bResult
+{
+    uint iResult[1];
+};
+
+constant uint3 gl_WorkGroupSize [[maybe_unused]] = uint3(16u, 16u, 1u);
+
+static inline __attribute__((always_inline))
+float4 combine(thread const float4& a, thread const float4& b, constant int& uOp)
+{
+    if (uOp == 0)
+    {
+        return fast::min(a, b);
+    }
+    if (uOp == 1)
+    {
+        return fast::max(a, b);
+    }
+    return a + b;
+}
+
+kernel void main0(constant int2& uTextureSize [[buffer(0)]], constant int& uOp [[buffer(1)]], constant float2& uHistogramRange [[buffer(2)]], constant int& uHistogramBinCount [[buffer(3)]], device bResult& _108 [[buffer(4)]], texture2d<float> uTexture [[texture(0)]], sampler uTextureSmplr [[sampler(0)]], uint3 gl_GlobalInvocationID [[thread_position_in_grid]], uint gl_LocalInvocationIndex [[thread_index_in_threadgroup]], uint3 gl_WorkGroupID [[threadgroup_position_in_grid]], uint3 gl_NumWorkGroups [[threadgroups_per_grid]])
+{
+    threadgroup float4 sPartials[256];
+    int2 texelCoord = int2(gl_GlobalInvocationID.xy);
+    bool inBounds = all(texelCoord < uTextureSize);
+    float4 texel = uTexture.read(uint2(min(texelCoord, uTextureSize - int2(1))), 0);
+    if (uOp == 3)
+    {
+        if (!inBounds)
+        {
+            return;
+        }
+        float luminance = dot(texel.xyz, float3(0.2125999927520751953125, 0.715200006961822509765625, 0.072200000286102294921875));
+        float t = (luminance - uHistogramRange.x) / (uHistogramRange.y - uHistogramRange.x);
+        int bin = clamp(int(floor(t * float(uHistogramBinCount))), 0, uHistogramBinCount - 1);
+        uint _113 = atomic_fetch_add_explicit((device atomic_uint*)&_108.iResult[bin], 1u, memory_order_relaxed);
+        return;
+    }
+    bool _123 = !inBounds;
+    bool _130;
+    if (_123)
+    {
+        _130 = uOp == 2;
+    }
+    else
+    {
+        _130 = _123;
+    }
+    if (_130)
+    {
+        texel = float4(0.0);
+    }
+    uint localIndex = gl_LocalInvocationIndex;
+    sPartials[localIndex] = texel;
+    threadgroup_barrier(mem_flags::mem_threadgroup);
+    threadgroup_barrier(mem_flags::mem_threadgroup);
+    for (uint stride = 128u; stride > 0u; stride /= 2u)
+    {
+        if (localIndex < stride)
+        {
+            float4 param = sPartials[localIndex];
+            float4 param_1 = sPartials[localIndex + stride];
+            sPartials[localIndex] = combine(param, param_1, uOp);
+        }
+        threadgroup_barrier(mem_flags::mem_threadgroup);
+        threadgroup_barrier(mem_flags::mem_threadgroup);
+    }
+    if (localIndex != 0u)
+    {
+        return;
+    }
+    uint workgroupIndex = (gl_WorkGroupID.y * gl_NumWorkGroups.x) + gl_WorkGroupID.x;
+    for (uint channel = 0u; channel < 4u; channel++)
+    {
+        _108.iResult[(workgroupIndex * 4u) + channel] = as_type<uint>(sPartials[0][channel]);
+    }
+}
+
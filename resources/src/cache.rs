@@ -0,0 +1,127 @@
+// pathfinder/resources/src/cache.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Memoizes resources so that repeated requests don't reread them.
+
+use crate::ResourceLoader;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Error as IOError;
+use std::sync::Arc;
+
+/// Wraps another resource loader, remembering every resource it successfully loads.
+///
+/// Errors aren't cached, so a missing resource is looked up again on the next request. Nothing is
+/// ever evicted automatically; call `clear_cache()` once the resources needed at startup have been
+/// loaded if memory use is a concern.
+pub struct CachingResourceLoader<L> where L: ResourceLoader {
+    inner: L,
+    cache: RefCell<HashMap<String, Arc<Vec<u8>>>>,
+}
+
+impl<L> CachingResourceLoader<L> where L: ResourceLoader {
+    #[inline]
+    pub fn new(inner: L) -> CachingResourceLoader<L> {
+        CachingResourceLoader { inner, cache: RefCell::new(HashMap::new()) }
+    }
+
+    /// Like `slurp()`, but returns the cached data itself instead of copying it.
+    pub fn slurp_shared(&self, virtual_path: &str) -> Result<Arc<Vec<u8>>, IOError> {
+        if let Some(data) = self.cache.borrow().get(virtual_path) {
+            return Ok(data.clone());
+        }
+
+        let data = Arc::new(self.inner.slurp(virtual_path)?);
+        self.cache.borrow_mut().insert(virtual_path.to_owned(), data.clone());
+        Ok(data)
+    }
+
+    /// Returns the total size of all cached resources, in bytes.
+    pub fn cached_size(&self) -> usize {
+        self.cache.borrow().values().map(|data| data.len()).sum()
+    }
+
+    /// Frees all cached resources. Data already returned by `slurp_shared()` stays valid.
+    #[inline]
+    pub fn clear_cache(&self) {
+        self.cache.borrow_mut().clear();
+    }
+}
+
+impl<L> ResourceLoader for CachingResourceLoader<L> where L: ResourceLoader {
+    #[inline]
+    fn slurp(&self, virtual_path: &str) -> Result<Vec<u8>, IOError> {
+        self.slurp_shared(virtual_path).map(|data| (*data).clone())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ResourceLoader;
+    use super::CachingResourceLoader;
+    use std::cell::Cell;
+    use std::io::{Error as IOError, ErrorKind};
+    use std::rc::Rc;
+    use std::sync::Arc;
+
+    // Returns each path's own bytes, counting loads and failing the first `failures` of them.
+    struct CountingLoader {
+        loads: Rc<Cell<u32>>,
+        failures: Cell<u32>,
+    }
+
+    impl ResourceLoader for CountingLoader {
+        fn slurp(&self, virtual_path: &str) -> Result<Vec<u8>, IOError> {
+            self.loads.set(self.loads.get() + 1);
+            if self.failures.get() > 0 {
+                self.failures.set(self.failures.get() - 1);
+                return Err(IOError::new(ErrorKind::Other, "transient failure"));
+            }
+            Ok(virtual_path.as_bytes().to_vec())
+        }
+    }
+
+    fn caching_loader(failures: u32) -> (CachingResourceLoader<CountingLoader>, Rc<Cell<u32>>) {
+        let loads = Rc::new(Cell::new(0));
+        let inner = CountingLoader { loads: loads.clone(), failures: Cell::new(failures) };
+        (CachingResourceLoader::new(inner), loads)
+    }
+
+    #[test]
+    fn test_cache_miss() {
+        let (loader, loads) = caching_loader(0);
+        assert_eq!(loader.slurp("shaders/a").unwrap(), b"shaders/a");
+        assert_eq!(loader.slurp("shaders/b").unwrap(), b"shaders/b");
+        assert_eq!(loads.get(), 2);
+        assert_eq!(loader.cached_size(), 18);
+    }
+
+    #[test]
+    fn test_cache_hit() {
+        let (loader, loads) = caching_loader(0);
+        let first = loader.slurp_shared("shaders/a").unwrap();
+        let second = loader.slurp_shared("shaders/a").unwrap();
+        assert_eq!(loads.get(), 1);
+        assert!(Arc::ptr_eq(&first, &second));
+
+        loader.clear_cache();
+        assert_eq!(loader.slurp("shaders/a").unwrap(), b"shaders/a");
+        assert_eq!(loads.get(), 2);
+    }
+
+    #[test]
+    fn test_errors_are_not_cached() {
+        let (loader, loads) = caching_loader(1);
+        assert_eq!(loader.slurp("shaders/a").unwrap_err().kind(), ErrorKind::Other);
+        assert_eq!(loader.cached_size(), 0);
+        assert_eq!(loader.slurp("shaders/a").unwrap(), b"shaders/a");
+        assert_eq!(loads.get(), 2);
+    }
+}
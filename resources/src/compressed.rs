@@ -0,0 +1,125 @@
+// pathfinder/resources/src/compressed.rs
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Transparently decompresses gzipped resources.
+
+use crate::ResourceLoader;
+use crate::cache::CachingResourceLoader;
+use flate2::read::GzDecoder;
+use std::io::{Error as IOError, ErrorKind, Read};
+
+/// Wraps another resource loader, decompressing resources that are stored gzipped.
+///
+/// When `path` is requested, this first looks for `path.gz` in the wrapped loader and gunzips it
+/// if present, falling back to `path` itself otherwise. Resources are cached with a
+/// `CachingResourceLoader`, since decompression is much slower than reading embedded bytes.
+pub struct CompressedResourceLoader<L> where L: ResourceLoader {
+    cache: CachingResourceLoader<GzipResourceLoader<L>>,
+}
+
+// Does the actual decompression, without caching.
+struct GzipResourceLoader<L>(L) where L: ResourceLoader;
+
+impl<L> CompressedResourceLoader<L> where L: ResourceLoader {
+    #[inline]
+    pub fn new(inner: L) -> CompressedResourceLoader<L> {
+        CompressedResourceLoader { cache: CachingResourceLoader::new(GzipResourceLoader(inner)) }
+    }
+
+    /// Frees all cached decompressed resources.
+    #[inline]
+    pub fn clear_cache(&self) {
+        self.cache.clear_cache();
+    }
+}
+
+impl<L> ResourceLoader for CompressedResourceLoader<L> where L: ResourceLoader {
+    #[inline]
+    fn slurp(&self, virtual_path: &str) -> Result<Vec<u8>, IOError> {
+        self.cache.slurp(virtual_path)
+    }
+}
+
+impl<L> ResourceLoader for GzipResourceLoader<L> where L: ResourceLoader {
+    fn slurp(&self, virtual_path: &str) -> Result<Vec<u8>, IOError> {
+        let compressed_data = match self.0.slurp(&format!("{}.gz", virtual_path)) {
+            Ok(compressed_data) => compressed_data,
+            Err(ref error) if error.kind() == ErrorKind::NotFound => {
+                return self.0.slurp(virtual_path);
+            }
+            Err(error) => return Err(error),
+        };
+
+        let mut data = vec![];
+        GzDecoder::new(&compressed_data[..]).read_to_end(&mut data)?;
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ResourceLoader;
+    use super::CompressedResourceLoader;
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use std::collections::HashMap;
+    use std::io::{Error as IOError, ErrorKind, Write};
+
+    // Serves a fixed set of paths, reporting any others as not found.
+    struct MapLoader(HashMap<String, Vec<u8>>);
+
+    impl ResourceLoader for MapLoader {
+        fn slurp(&self, virtual_path: &str) -> Result<Vec<u8>, IOError> {
+            match self.0.get(virtual_path) {
+                Some(data) => Ok(data.clone()),
+                None => Err(IOError::new(ErrorKind::NotFound, virtual_path.to_owned())),
+            }
+        }
+    }
+
+    fn compressed_loader(files: &[(&str, Vec<u8>)]) -> CompressedResourceLoader<MapLoader> {
+        let files = files.iter().map(|(path, data)| (path.to_string(), data.clone())).collect();
+        CompressedResourceLoader::new(MapLoader(files))
+    }
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn test_decompresses_gzipped_resource() {
+        let loader = compressed_loader(&[
+            ("shaders/a", b"uncompressed".to_vec()),
+            ("shaders/a.gz", gzip(b"compressed")),
+        ]);
+        assert_eq!(loader.slurp("shaders/a").unwrap(), b"compressed");
+    }
+
+    #[test]
+    fn test_falls_back_to_uncompressed_resource() {
+        let loader = compressed_loader(&[("shaders/a", b"uncompressed".to_vec())]);
+        assert_eq!(loader.slurp("shaders/a").unwrap(), b"uncompressed");
+        assert_eq!(loader.slurp("shaders/b").unwrap_err().kind(), ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_corrupt_gzip_stream_is_an_error() {
+        let mut truncated = gzip(b"compressed");
+        truncated.truncate(truncated.len() / 2);
+        let loader = compressed_loader(&[
+            ("shaders/a.gz", b"not gzip".to_vec()),
+            ("shaders/b.gz", truncated),
+        ]);
+        assert!(loader.slurp("shaders/a").is_err());
+        assert!(loader.slurp("shaders/b").is_err());
+    }
+}
@@ -19,9 +19,13 @@
 
 use std::io::Error as IOError;
 
+pub mod cache;
 pub mod embedded;
 pub mod fs;
 
+#[cfg(feature = "compression")]
+pub mod compressed;
+
 pub trait ResourceLoader {
     /// This is deliberately not a `Path`, because these are virtual paths
     /// that do not necessarily correspond to real paths on a filesystem.
@@ -0,0 +1,43 @@
+#version 330
+
+// pathfinder/shaders/debug/inspect.fs.glsl
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Displays the contents of an arbitrary texture for debugging.
+//
+// `uChannelMask` selects the channels to show. A single channel is shown as grayscale. Otherwise
+// the color channels are shown, using alpha as coverage if it's selected. `uScale` brightens or
+// darkens the result, for floating-point textures.
+
+precision highp float;
+
+#ifdef GL_ES
+precision highp sampler2D;
+#endif
+
+uniform sampler2D uTexture;
+uniform vec4 uChannelMask;
+uniform float uScale;
+
+in vec2 vTexCoord;
+
+out vec4 oFragColor;
+
+void main() {
+    vec4 texel = texture(uTexture, vTexCoord);
+
+    vec4 color;
+    if (dot(uChannelMask, vec4(1.0)) == 1.0)
+        color = vec4(vec3(dot(texel, uChannelMask)), 1.0);
+    else
+        color = vec4(texel.rgb, mix(1.0, texel.a, uChannelMask.a));
+
+    oFragColor = vec4(clamp(color.rgb * uScale, 0.0, 1.0) * color.a, color.a);
+}
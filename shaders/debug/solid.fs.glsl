@@ -18,8 +18,14 @@ precision highp sampler2D;
 
 uniform vec4 uColor;
 
+in float vEdgeDistance;
+
 out vec4 oFragColor;
 
 void main() {
-    oFragColor = vec4(uColor.rgb, 1.0) * uColor.a;
+    // Lines are drawn as quads extending one pixel to either side of their center, which yields
+    // one-pixel-wide lines with antialiased edges. Filled shapes have an edge distance of zero
+    // everywhere and so are fully covered.
+    float coverage = 1.0 - smoothstep(0.0, 1.0, abs(vEdgeDistance));
+    oFragColor = vec4(uColor.rgb, 1.0) * uColor.a * coverage;
 }
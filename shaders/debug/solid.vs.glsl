@@ -17,10 +17,18 @@ precision highp sampler2D;
 #endif
 
 uniform vec2 uFramebufferSize;
+uniform mat2 uTransform;
+uniform vec2 uTranslation;
 
 in ivec2 aPosition;
+in vec2 aOffset;
+in float aEdgeDistance;
+
+out float vEdgeDistance;
 
 void main() {
-    vec2 position = vec2(aPosition) / uFramebufferSize * 2.0 - 1.0;
+    vec2 position = uTransform * (vec2(aPosition) + aOffset) + uTranslation;
+    position = position / uFramebufferSize * 2.0 - 1.0;
+    vEdgeDistance = aEdgeDistance;
     gl_Position = vec4(position.x, -position.y, 0.0, 1.0);
 }
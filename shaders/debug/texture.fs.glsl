@@ -25,5 +25,11 @@ out vec4 oFragColor;
 
 void main() {
     float alpha = texture(uTexture, vTexCoord).r * uColor.a;
+
+    // Discard fully transparent fragments so that they don't write to the stencil buffer when
+    // this shader draws a clip mask.
+    if (alpha == 0.0)
+        discard;
+
     oFragColor = alpha * vec4(uColor.rgb, 1.0);
 }
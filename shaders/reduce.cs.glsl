@@ -0,0 +1,96 @@
+#version 430
+
+// pathfinder/shaders/reduce.cs.glsl
+//
+// Copyright © 2020 The Pathfinder Project Developers.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Reduces a texture to the minimum, maximum, or sum of each channel, or to a histogram of its
+// luminance.
+//
+// For minima, maxima, and sums, each workgroup writes one partial result, and the CPU combines
+// them after readback.
+
+#extension GL_GOOGLE_include_directive : enable
+
+precision highp float;
+
+#ifdef GL_ES
+precision highp sampler2D;
+#endif
+
+#define REDUCE_OP_MIN           0
+#define REDUCE_OP_MAX           1
+#define REDUCE_OP_SUM           2
+#define REDUCE_OP_HISTOGRAM     3
+
+#define WORKGROUP_SIZE          256
+
+layout(local_size_x = 16, local_size_y = 16) in;
+
+uniform sampler2D uTexture;
+uniform ivec2 uTextureSize;
+uniform int uOp;
+uniform vec2 uHistogramRange;
+uniform int uHistogramBinCount;
+
+layout(std430, binding = 0) buffer bResult {
+    // Minimum, maximum, or sum: four floats per workgroup, in row-major workgroup order.
+    // Histogram: one count per bin, initialized to zero.
+    restrict uint iResult[];
+};
+
+shared vec4 sPartials[WORKGROUP_SIZE];
+
+vec4 combine(vec4 a, vec4 b) {
+    if (uOp == REDUCE_OP_MIN)
+        return min(a, b);
+    if (uOp == REDUCE_OP_MAX)
+        return max(a, b);
+    return a + b;
+}
+
+void main() {
+    ivec2 texelCoord = ivec2(gl_GlobalInvocationID.xy);
+    bool inBounds = all(lessThan(texelCoord, uTextureSize));
+    vec4 texel = texelFetch(uTexture, min(texelCoord, uTextureSize - 1), 0);
+
+    if (uOp == REDUCE_OP_HISTOGRAM) {
+        if (!inBounds)
+            return;
+        float luminance = dot(texel.rgb, vec3(0.2126, 0.7152, 0.0722));
+        float t = (luminance - uHistogramRange.x) / (uHistogramRange.y - uHistogramRange.x);
+        int bin = clamp(int(floor(t * float(uHistogramBinCount))), 0, uHistogramBinCount - 1);
+        atomicAdd(iResult[bin], 1u);
+        return;
+    }
+
+    // Invocations past the edge of the texture repeat the edge texel, which leaves minima and
+    // maxima unchanged. For sums, they contribute nothing.
+    if (!inBounds && uOp == REDUCE_OP_SUM)
+        texel = vec4(0.0);
+
+    uint localIndex = gl_LocalInvocationIndex;
+    sPartials[localIndex] = texel;
+    memoryBarrierShared();
+    barrier();
+
+    for (uint stride = WORKGROUP_SIZE / 2; stride > 0u; stride /= 2u) {
+        if (localIndex < stride)
+            sPartials[localIndex] = combine(sPartials[localIndex], sPartials[localIndex + stride]);
+        memoryBarrierShared();
+        barrier();
+    }
+
+    if (localIndex != 0u)
+        return;
+
+    uint workgroupIndex = gl_WorkGroupID.y * gl_NumWorkGroups.x + gl_WorkGroupID.x;
+    for (uint channel = 0u; channel < 4u; channel++)
+        iResult[workgroupIndex * 4u + channel] = floatBitsToUint(sPartials[0][channel]);
+}
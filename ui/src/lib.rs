@@ -17,17 +17,23 @@
 extern crate serde_derive;
 
 use hashbrown::HashMap;
+use instant::Instant;
 use pathfinder_color::ColorU;
 use pathfinder_geometry::rect::RectI;
-use pathfinder_geometry::vector::{Vector2F, Vector2I, vec2i};
+use pathfinder_geometry::transform2d::Transform2F;
+use pathfinder_geometry::vector::{Vector2F, Vector2I, vec2f, vec2i};
 use pathfinder_gpu::allocator::{BufferTag, GPUMemoryAllocator};
-use pathfinder_gpu::{BlendFactor, BlendState, BufferTarget, Device, Primitive, RenderOptions};
-use pathfinder_gpu::{RenderState, RenderTarget, TextureFormat, UniformData, VertexAttrClass};
+use pathfinder_gpu::{BlendFactor, BlendState, BufferTarget, ClearOps, Device, Primitive};
+use pathfinder_gpu::{ProgramKind, RenderOptions, RenderState, RenderTarget, StencilFunc};
+use pathfinder_gpu::{StencilState, TextureFormat, UniformData, VertexAttrClass};
 use pathfinder_gpu::{VertexAttrDescriptor, VertexAttrType};
 use pathfinder_resources::ResourceLoader;
 use pathfinder_simd::default::F32x4;
 use serde_json;
+use std::cell::Cell;
+use std::f32::consts::PI;
 use std::mem;
+use std::ops::Range;
 
 pub const PADDING: i32 = 12;
 
@@ -40,13 +46,26 @@ pub const BUTTON_TEXT_OFFSET: i32 = PADDING + 36;
 
 pub const TOOLTIP_HEIGHT: i32 = FONT_ASCENT + PADDING * 2;
 
+pub const PERF_GRAPH_WIDTH: i32 = PADDING * 24;
+pub const PERF_GRAPH_HEIGHT: i32 = PADDING * 12;
+
 const DEBUG_TEXTURE_VERTEX_SIZE: usize = 8;
-const DEBUG_SOLID_VERTEX_SIZE:   usize = 4;
+const DEBUG_SOLID_VERTEX_SIZE:   usize = 16;
 
 const ICON_SIZE: i32 = 48;
 
 const SEGMENT_SIZE: i32 = 96;
 
+// Rounded rects can't be any thinner than two corners.
+const SLIDER_TRACK_HEIGHT: i32 = 32;
+const SLIDER_KNOB_WIDTH: i32 = 32;
+
+const CHECKBOX_SIZE: i32 = 32;
+
+const CARET_BLINK_INTERVAL: u128 = 500;
+
+const MIN_ARC_SEGMENT_COUNT: u32 = 8;
+
 pub static TEXT_COLOR:   ColorU = ColorU { r: 255, g: 255, b: 255, a: 255      };
 pub static WINDOW_COLOR: ColorU = ColorU { r: 0,   g: 0,   b: 0,   a: 255 - 90 };
 
@@ -55,29 +74,41 @@ static OUTLINE_COLOR:     ColorU = ColorU { r: 255, g: 255, b: 255, a: 192 };
 
 static INVERTED_TEXT_COLOR: ColorU = ColorU { r: 0,   g: 0,   b: 0,   a: 255      };
 
+static PERF_GRAPH_FILL_COLOR:   ColorU = ColorU { r: 0,   g: 192, b: 255, a: 192 };
+static PERF_GRAPH_BUDGET_COLOR: ColorU = ColorU { r: 255, g: 64,  b: 64,  a: 255 };
+
+// The frame time at 60 FPS, in milliseconds.
+const FRAME_BUDGET: f32 = 1000.0 / 60.0;
+
 static FONT_JSON_VIRTUAL_PATH: &'static str = "debug-fonts/regular.json";
 static FONT_PNG_NAME: &'static str = "debug-font";
 
 static CORNER_FILL_PNG_NAME: &'static str = "debug-corner-fill";
 static CORNER_OUTLINE_PNG_NAME: &'static str = "debug-corner-outline";
 
-static QUAD_INDICES:              [u32; 6] = [0, 1, 3, 1, 2, 3];
-static RECT_LINE_INDICES:         [u32; 8] = [0, 1, 1, 2, 2, 3, 3, 0];
-static OUTLINE_RECT_LINE_INDICES: [u32; 8] = [0, 1, 2, 3, 4, 5, 6, 7];
+static QUAD_INDICES: [u32; 6] = [0, 1, 3, 1, 2, 3];
 
 pub struct UIPresenter<D> where D: Device {
     pub event_queue: UIEventQueue,
     pub mouse_position: Vector2F,
 
     framebuffer_size: Vector2I,
+    transform: Transform2F,
 
     texture_program: DebugTextureProgram<D>,
     solid_program: DebugSolidProgram<D>,
+    inspector_program: DebugInspectorProgram<D>,
     font: DebugFont,
 
     font_texture: D::Texture,
     corner_fill_texture: D::Texture,
     corner_outline_texture: D::Texture,
+
+    clip_mode: ClipMode,
+    clip_mask_needs_clear: Cell<bool>,
+
+    // Caret blinking is timed from here.
+    start_time: Instant,
 }
 
 impl<D> UIPresenter<D> where D: Device {
@@ -87,6 +118,7 @@ impl<D> UIPresenter<D> where D: Device {
         let font = DebugFont::load(resources);
 
         let solid_program = DebugSolidProgram::new(device, resources);
+        let inspector_program = DebugInspectorProgram::new(device, resources);
 
         let font_texture = device.create_texture_from_png(resources,
                                                           FONT_PNG_NAME,
@@ -103,14 +135,21 @@ impl<D> UIPresenter<D> where D: Device {
             mouse_position: Vector2F::zero(),
 
             framebuffer_size,
+            transform: Transform2F::default(),
 
             texture_program,
             font,
             solid_program,
+            inspector_program,
 
             font_texture,
             corner_fill_texture,
             corner_outline_texture,
+
+            clip_mode: ClipMode::None,
+            clip_mask_needs_clear: Cell::new(false),
+
+            start_time: Instant::now(),
         }
     }
 
@@ -122,6 +161,81 @@ impl<D> UIPresenter<D> where D: Device {
         self.framebuffer_size = window_size;
     }
 
+    #[inline]
+    pub fn transform(&self) -> Transform2F {
+        self.transform
+    }
+
+    /// Sets the transform from UI coordinates to framebuffer pixels, which is identity by
+    /// default.
+    ///
+    /// This allows the UI to be embedded in part of a larger surface, for example by offsetting
+    /// and scaling it into a panel. Mouse events and the mouse position are in framebuffer pixels
+    /// and are mapped back into UI coordinates for hit testing.
+    pub fn set_transform(&mut self, transform: Transform2F) {
+        self.transform = transform;
+        self.event_queue.inverse_transform = transform.inverse();
+    }
+
+    /// Begins a group of draws that are clipped to the given rounded rectangle.
+    ///
+    /// The rounded shape is written into the stencil buffer of the default framebuffer, which
+    /// must therefore have one. Clip groups don't nest.
+    pub fn begin_clip_group(&mut self,
+                            device: &D,
+                            allocator: &mut GPUMemoryAllocator<D>,
+                            rect: RectI) {
+        assert_eq!(self.clip_mode, ClipMode::None, "Clip groups can't be nested!");
+        self.clip_mode = ClipMode::WritingMask;
+        self.clip_mask_needs_clear.set(true);
+        self.draw_solid_rounded_rect(device, allocator, rect, ColorU::white());
+        self.clip_mode = ClipMode::Clipping;
+    }
+
+    /// Ends the current clip group. Subsequent draws are no longer clipped.
+    pub fn end_clip_group(&mut self) {
+        assert_eq!(self.clip_mode, ClipMode::Clipping, "No clip group is active!");
+        self.clip_mode = ClipMode::None;
+    }
+
+    fn render_options(&self) -> RenderOptions {
+        match self.clip_mode {
+            ClipMode::None => {
+                RenderOptions { blend: Some(alpha_blend_state()), ..RenderOptions::default() }
+            }
+            ClipMode::WritingMask => {
+                // Only the first draw of the mask clears the stencil buffer.
+                let clear_stencil = self.clip_mask_needs_clear.replace(false);
+                RenderOptions {
+                    stencil: Some(StencilState {
+                        func: StencilFunc::Always,
+                        reference: 1,
+                        mask: 1,
+                        write: true,
+                    }),
+                    clear_ops: ClearOps {
+                        stencil: if clear_stencil { Some(0) } else { None },
+                        ..ClearOps::default()
+                    },
+                    color_mask: false,
+                    ..RenderOptions::default()
+                }
+            }
+            ClipMode::Clipping => {
+                RenderOptions {
+                    blend: Some(alpha_blend_state()),
+                    stencil: Some(StencilState {
+                        func: StencilFunc::Equal,
+                        reference: 1,
+                        mask: 1,
+                        write: false,
+                    }),
+                    ..RenderOptions::default()
+                }
+            }
+        }
+    }
+
 
     pub fn draw_solid_rect(&self,
                            device: &D,
@@ -145,37 +259,34 @@ impl<D> UIPresenter<D> where D: Device {
                  rect: RectI,
                  color: ColorU,
                  filled: bool) {
-        let vertex_data = [
-            DebugSolidVertex::new(rect.origin()),
-            DebugSolidVertex::new(rect.upper_right()),
-            DebugSolidVertex::new(rect.lower_right()),
-            DebugSolidVertex::new(rect.lower_left()),
-        ];
-
         if filled {
-            self.draw_solid_rects_with_vertex_data(device,
-                                                   allocator,
-                                                   &vertex_data,
-                                                   &QUAD_INDICES,
-                                                   color,
-                                                   true);
+            let vertex_data = [
+                DebugSolidVertex::new(rect.origin()),
+                DebugSolidVertex::new(rect.upper_right()),
+                DebugSolidVertex::new(rect.lower_right()),
+                DebugSolidVertex::new(rect.lower_left()),
+            ];
+            self.draw_solid_triangles_with_vertex_data(device,
+                                                       allocator,
+                                                       &vertex_data,
+                                                       &QUAD_INDICES,
+                                                       color);
         } else {
-            self.draw_solid_rects_with_vertex_data(device,
-                                                   allocator,
-                                                   &vertex_data,
-                                                   &RECT_LINE_INDICES,
-                                                   color,
-                                                   false);
+            self.draw_lines(device, allocator, &[
+                (rect.origin(),      rect.upper_right()),
+                (rect.upper_right(), rect.lower_right()),
+                (rect.lower_right(), rect.lower_left()),
+                (rect.lower_left(),  rect.origin()),
+            ], color);
         }
     }
 
-    fn draw_solid_rects_with_vertex_data(&self,
-                                         device: &D,
-                                         allocator: &mut GPUMemoryAllocator<D>,
-                                         vertex_data: &[DebugSolidVertex],
-                                         index_data: &[u32],
-                                         color: ColorU,
-                                         filled: bool) {
+    fn draw_solid_triangles_with_vertex_data(&self,
+                                             device: &D,
+                                             allocator: &mut GPUMemoryAllocator<D>,
+                                             vertex_data: &[DebugSolidVertex],
+                                             index_data: &[u32],
+                                             color: ColorU) {
         let vertex_buffer_id =
             allocator.allocate_general_buffer::<DebugSolidVertex>(device,
                                                                   vertex_data.len() as u64,
@@ -186,32 +297,33 @@ impl<D> UIPresenter<D> where D: Device {
         {
             let vertex_buffer = allocator.get_general_buffer(vertex_buffer_id);
             let index_buffer = allocator.get_index_buffer(index_buffer_id);
-            device.upload_to_buffer(&vertex_buffer, 0, vertex_data, BufferTarget::Vertex);
-            device.upload_to_buffer(&index_buffer, 0, index_data, BufferTarget::Index);
+            device.upload_to_buffer(vertex_buffer, 0, vertex_data, BufferTarget::Vertex);
+            device.upload_to_buffer(index_buffer, 0, index_data, BufferTarget::Index);
             let solid_vertex_array = DebugSolidVertexArray::new(device,
                                                                 &self.solid_program,
                                                                 vertex_buffer,
                                                                 index_buffer);
 
-            let primitive = if filled { Primitive::Triangles } else { Primitive::Lines };
             device.draw_elements(index_data.len() as u32, &RenderState {
                 target: &RenderTarget::Default,
                 program: &self.solid_program.program,
                 vertex_array: &solid_vertex_array.vertex_array,
-                primitive,
+                primitive: Primitive::Triangles,
+                first_index: 0,
                 uniforms: &[
                     (&self.solid_program.framebuffer_size_uniform,
                     UniformData::Vec2(self.framebuffer_size.0.to_f32x2())),
+                    (&self.solid_program.transform_uniform,
+                     UniformData::Mat2(self.transform.matrix.0)),
+                    (&self.solid_program.translation_uniform,
+                     UniformData::Vec2(self.transform.vector.0)),
                     (&self.solid_program.color_uniform, get_color_uniform(color)),
                 ],
                 textures: &[],
                 images: &[],
                 storage_buffers: &[],
                 viewport: RectI::new(Vector2I::default(), self.framebuffer_size),
-                options: RenderOptions {
-                    blend: Some(alpha_blend_state()),
-                    ..RenderOptions::default()
-                },
+                options: self.render_options(),
             });
         }
 
@@ -225,6 +337,48 @@ impl<D> UIPresenter<D> where D: Device {
                      string: &str,
                      origin: Vector2I,
                      invert: bool) {
+        let color = if invert { INVERTED_TEXT_COLOR } else { TEXT_COLOR };
+        self.draw_text_with_color(device, allocator, string, origin, color);
+    }
+
+    /// Draws text broken into lines no wider than `max_width`, `LINE_HEIGHT` apart. Lines break at
+    /// `\n` and at spaces, and words wider than `max_width` are broken wherever they overflow.
+    /// `origin` is the start of the first baseline, as in `draw_text()`.
+    ///
+    /// Returns the height of all the lines drawn.
+    pub fn draw_text_wrapped(&self,
+                             device: &D,
+                             allocator: &mut GPUMemoryAllocator<D>,
+                             string: &str,
+                             origin: Vector2I,
+                             max_width: i32)
+                             -> i32 {
+        let lines = self.wrap_text(string, max_width);
+        for (line_index, line) in lines.iter().enumerate() {
+            let line_origin = origin + vec2i(0, line_index as i32 * LINE_HEIGHT);
+            self.draw_text(device, allocator, line, line_origin, false);
+        }
+        lines.len() as i32 * LINE_HEIGHT
+    }
+
+    /// Draws text in whichever of the normal and inverted text colors is more legible over
+    /// `background`.
+    pub fn draw_text_over(&self,
+                          device: &D,
+                          allocator: &mut GPUMemoryAllocator<D>,
+                          string: &str,
+                          origin: Vector2I,
+                          background: ColorU) {
+        let color = text_color_for_background(background);
+        self.draw_text_with_color(device, allocator, string, origin, color);
+    }
+
+    fn draw_text_with_color(&self,
+                            device: &D,
+                            allocator: &mut GPUMemoryAllocator<D>,
+                            string: &str,
+                            origin: Vector2I,
+                            color: ColorU) {
         let mut next = origin;
         let char_count = string.chars().count();
         let mut vertex_data = Vec::with_capacity(char_count * 4);
@@ -252,7 +406,6 @@ impl<D> UIPresenter<D> where D: Device {
             next.set_x(next_x);
         }
 
-        let color = if invert { INVERTED_TEXT_COLOR } else { TEXT_COLOR };
         self.draw_texture_with_vertex_data(device,
                                            allocator,
                                            &vertex_data,
@@ -284,6 +437,112 @@ impl<D> UIPresenter<D> where D: Device {
                                            color);
     }
 
+    /// Draws the portion of `texture` covered by `src_rect`, scaled to fill a rectangle of size
+    /// `dest_size` at `origin`.
+    ///
+    /// `src_rect` is in texels.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_image(&self,
+                      device: &D,
+                      allocator: &mut GPUMemoryAllocator<D>,
+                      origin: Vector2I,
+                      texture: &D::Texture,
+                      src_rect: RectI,
+                      dest_size: Vector2I,
+                      color: ColorU) {
+        let texture_rect = RectI::new(Vector2I::default(), device.texture_size(texture));
+        debug_assert!(texture_rect.to_f32().contains_rect(src_rect.to_f32()),
+                      "Source rect {:?} lies outside the texture!",
+                      src_rect);
+
+        // The texture shader normalizes texel coordinates against the texture size for us.
+        let position_rect = RectI::new(origin, dest_size);
+        let vertex_data = [
+            DebugTextureVertex::new(position_rect.origin(),      src_rect.origin()),
+            DebugTextureVertex::new(position_rect.upper_right(), src_rect.upper_right()),
+            DebugTextureVertex::new(position_rect.lower_right(), src_rect.lower_right()),
+            DebugTextureVertex::new(position_rect.lower_left(),  src_rect.lower_left()),
+        ];
+
+        self.draw_texture_with_vertex_data(device,
+                                           allocator,
+                                           &vertex_data,
+                                           &QUAD_INDICES,
+                                           texture,
+                                           color);
+    }
+
+    /// Draws all of `texture`, scaled to fill a rectangle of size `size` at `origin`, showing only
+    /// the channels in `channels`.
+    ///
+    /// The displayed color is multiplied by `scale`, which is useful for floating-point textures
+    /// whose values lie outside the 0 to 1 range.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_texture_inspector(&self,
+                                  device: &D,
+                                  allocator: &mut GPUMemoryAllocator<D>,
+                                  origin: Vector2I,
+                                  size: Vector2I,
+                                  texture: &D::Texture,
+                                  channels: ChannelMask,
+                                  scale: f32) {
+        let position_rect = RectI::new(origin, size);
+        let tex_coord_rect = RectI::new(Vector2I::default(), device.texture_size(texture));
+        let vertex_data = [
+            DebugTextureVertex::new(position_rect.origin(),      tex_coord_rect.origin()),
+            DebugTextureVertex::new(position_rect.upper_right(), tex_coord_rect.upper_right()),
+            DebugTextureVertex::new(position_rect.lower_right(), tex_coord_rect.lower_right()),
+            DebugTextureVertex::new(position_rect.lower_left(),  tex_coord_rect.lower_left()),
+        ];
+
+        let vertex_buffer_id = allocator.allocate_general_buffer::<DebugTextureVertex>(
+            device,
+            vertex_data.len() as u64,
+            BufferTag("InspectorVertexDebug"));
+        let index_buffer_id =
+            allocator.allocate_index_buffer::<u32>(device,
+                                                   QUAD_INDICES.len() as u64,
+                                                   BufferTag("InspectorIndexDebug"));
+        {
+            let vertex_buffer = allocator.get_general_buffer(vertex_buffer_id);
+            let index_buffer = allocator.get_index_buffer(index_buffer_id);
+            device.upload_to_buffer(vertex_buffer, 0, &vertex_data, BufferTarget::Vertex);
+            device.upload_to_buffer(index_buffer, 0, &QUAD_INDICES, BufferTarget::Index);
+
+            let vertex_array = DebugTextureVertexArray::new(device,
+                                                            &self.inspector_program.program,
+                                                            vertex_buffer,
+                                                            index_buffer);
+
+            let program = &self.inspector_program;
+            device.draw_elements(QUAD_INDICES.len() as u32, &RenderState {
+                target: &RenderTarget::Default,
+                program: &program.program,
+                vertex_array: &vertex_array.vertex_array,
+                primitive: Primitive::Triangles,
+                first_index: 0,
+                textures: &[(&program.texture, texture)],
+                images: &[],
+                storage_buffers: &[],
+                uniforms: &[
+                    (&program.framebuffer_size_uniform,
+                     UniformData::Vec2(self.framebuffer_size.0.to_f32x2())),
+                    (&program.transform_uniform, UniformData::Mat2(self.transform.matrix.0)),
+                    (&program.translation_uniform, UniformData::Vec2(self.transform.vector.0)),
+                    (&program.texture_size_uniform,
+                     UniformData::Vec2(tex_coord_rect.size().0.to_f32x2())),
+                    (&program.channel_mask_uniform, UniformData::Vec4(channels.to_f32x4())),
+                    (&program.scale_uniform, UniformData::Float(scale)),
+                ],
+                viewport: RectI::new(Vector2I::default(), self.framebuffer_size),
+                options: self.render_options(),
+            });
+        }
+
+        allocator.free_index_buffer(index_buffer_id);
+        allocator.free_general_buffer(vertex_buffer_id);
+    }
+
     pub fn measure_text(&self, string: &str) -> i32 {
         let mut next = 0;
         for mut character in string.chars() {
@@ -297,6 +556,40 @@ impl<D> UIPresenter<D> where D: Device {
         next
     }
 
+    fn wrap_text(&self, string: &str, max_width: i32) -> Vec<String> {
+        let mut lines = vec![];
+        for paragraph in string.split('\n') {
+            let mut line = String::new();
+            for word in paragraph.split(' ') {
+                let candidate = if line.is_empty() {
+                    word.to_owned()
+                } else {
+                    format!("{} {}", line, word)
+                };
+                if self.measure_text(&candidate) <= max_width {
+                    line = candidate;
+                    continue;
+                }
+
+                if !line.is_empty() {
+                    lines.push(mem::take(&mut line));
+                }
+
+                // Start the word on a new line, breaking it wherever it overflows. Each line keeps
+                // at least one character, even if that character is too wide.
+                for character in word.chars() {
+                    line.push(character);
+                    if line.chars().count() > 1 && self.measure_text(&line) > max_width {
+                        line.pop();
+                        lines.push(mem::replace(&mut line, character.to_string()));
+                    }
+                }
+            }
+            lines.push(line);
+        }
+        lines
+    }
+
     #[inline]
     pub fn measure_segmented_control(&self, segment_count: u8) -> i32 {
         SEGMENT_SIZE * segment_count as i32 + (segment_count - 1) as i32
@@ -339,12 +632,11 @@ impl<D> UIPresenter<D> where D: Device {
         index_data.extend(QUAD_INDICES.iter().map(|&index| index + 4));
         index_data.extend(QUAD_INDICES.iter().map(|&index| index + 8));
 
-        self.draw_solid_rects_with_vertex_data(device,
-                                               allocator,
-                                               &vertex_data,
-                                               &index_data[0..18],
-                                               color,
-                                               true);
+        self.draw_solid_triangles_with_vertex_data(device,
+                                                   allocator,
+                                                   &vertex_data,
+                                                   &index_data[0..18],
+                                                   color);
     }
 
     pub fn draw_rounded_rect_outline(&self,
@@ -356,24 +648,91 @@ impl<D> UIPresenter<D> where D: Device {
         let corner_rects = CornerRects::new(device, rect, corner_texture);
         self.draw_rounded_rect_corners(device, allocator, color, corner_texture, &corner_rects);
 
-        let vertex_data = vec![
-            DebugSolidVertex::new(corner_rects.upper_left.upper_right()),
-            DebugSolidVertex::new(corner_rects.upper_right.origin()),
-            DebugSolidVertex::new(corner_rects.upper_right.lower_right()),
-            DebugSolidVertex::new(corner_rects.lower_right.upper_right()),
-            DebugSolidVertex::new(corner_rects.lower_left.lower_right()),
-            DebugSolidVertex::new(corner_rects.lower_right.lower_left()),
-            DebugSolidVertex::new(corner_rects.upper_left.lower_left()),
-            DebugSolidVertex::new(corner_rects.lower_left.origin()),
-        ];
+        self.draw_lines(device, allocator, &[
+            (corner_rects.upper_left.upper_right(), corner_rects.upper_right.origin()),
+            (corner_rects.upper_right.lower_right(), corner_rects.lower_right.upper_right()),
+            (corner_rects.lower_left.lower_right(), corner_rects.lower_right.lower_left()),
+            (corner_rects.upper_left.lower_left(), corner_rects.lower_left.origin()),
+        ], color);
+    }
+
+    /// Draws a filled convex polygon with the given vertices, which may wind either way.
+    ///
+    /// The polygon is triangulated as a fan, so concave polygons render incorrectly; debug builds
+    /// assert that the polygon is convex.
+    pub fn draw_convex_polygon(&self,
+                               device: &D,
+                               allocator: &mut GPUMemoryAllocator<D>,
+                               points: &[Vector2I],
+                               color: ColorU) {
+        if points.len() < 3 {
+            return;
+        }
+        debug_assert!(is_convex_polygon(points), "Polygon {:?} is not convex!", points);
+        self.draw_solid_triangle_fan(device, allocator, points, color);
+    }
+
+    /// Draws a filled circle, with more segments the larger it is.
+    pub fn draw_solid_circle(&self,
+                             device: &D,
+                             allocator: &mut GPUMemoryAllocator<D>,
+                             center: Vector2I,
+                             radius: i32,
+                             color: ColorU) {
+        self.draw_arc(device, allocator, center, radius, 0.0, PI * 2.0, color);
+    }
+
+    /// Draws a filled wedge of a circle, like a slice of a pie chart, from `start_angle` to
+    /// `end_angle` in radians. Angles are measured from the positive x axis and, since y points
+    /// down, increase clockwise.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_arc(&self,
+                    device: &D,
+                    allocator: &mut GPUMemoryAllocator<D>,
+                    center: Vector2I,
+                    radius: i32,
+                    start_angle: f32,
+                    end_angle: f32,
+                    color: ColorU) {
+        let sweep = end_angle - start_angle;
+        if radius <= 0 || sweep == 0.0 {
+            return;
+        }
 
-        let index_data = &OUTLINE_RECT_LINE_INDICES;
-        self.draw_solid_rects_with_vertex_data(device,
-                                               allocator,
-                                               &vertex_data,
-                                               index_data,
-                                               color,
-                                               false);
+        // One segment per pixel of radius around a full circle keeps large circles smooth.
+        let segment_count =
+            ((radius as f32 * sweep.abs() / (PI * 2.0)).ceil() as u32).max(MIN_ARC_SEGMENT_COUNT);
+
+        let mut points = Vec::with_capacity(segment_count as usize + 2);
+        points.push(center);
+        for segment_index in 0..(segment_count + 1) {
+            let angle = start_angle + sweep * segment_index as f32 / segment_count as f32;
+            let offset = vec2f(angle.cos(), angle.sin()) * radius as f32;
+            points.push(center + offset.round().to_i32());
+        }
+
+        self.draw_solid_triangle_fan(device, allocator, &points, color);
+    }
+
+    // Draws a filled triangle fan around `points[0]`.
+    fn draw_solid_triangle_fan(&self,
+                               device: &D,
+                               allocator: &mut GPUMemoryAllocator<D>,
+                               points: &[Vector2I],
+                               color: ColorU) {
+        let vertex_data: Vec<_> = points.iter()
+                                        .map(|&point| DebugSolidVertex::new(point))
+                                        .collect();
+        let mut index_data = Vec::with_capacity((points.len() - 2) * 3);
+        for index in 1..(points.len() as u32 - 1) {
+            index_data.extend_from_slice(&[0, index, index + 1]);
+        }
+
+        self.draw_solid_triangles_with_vertex_data(device,
+                                                   allocator,
+                                                   &vertex_data,
+                                                   &index_data,
+                                                   color);
     }
 
     // TODO(pcwalton): `LineSegment2I`.
@@ -383,14 +742,50 @@ impl<D> UIPresenter<D> where D: Device {
                  from: Vector2I,
                  to: Vector2I,
                  color: ColorU) {
-        let vertex_data = vec![DebugSolidVertex::new(from), DebugSolidVertex::new(to)];
-        self.draw_solid_rects_with_vertex_data(device,
-                                               allocator,
-                                               &vertex_data,
-                                               &[0, 1],
-                                               color,
-                                               false);
+        self.draw_lines(device, allocator, &[(from, to)], color);
+    }
+
+    // Draws one-pixel-wide antialiased lines.
+    //
+    // Each line is expanded into a quad extending one pixel to either side of its center, across
+    // which the solid shader fades coverage out. The quads also extend half a pixel past each
+    // endpoint so that outline corners are covered. Lines run through pixel centers so that
+    // axis-aligned ones stay crisp.
+    fn draw_lines(&self,
+                  device: &D,
+                  allocator: &mut GPUMemoryAllocator<D>,
+                  lines: &[(Vector2I, Vector2I)],
+                  color: ColorU) {
+        let mut vertex_data = Vec::with_capacity(lines.len() * 4);
+        let mut index_data = Vec::with_capacity(lines.len() * 6);
+        for &(from, to) in lines {
+            let vector = (to - from).to_f32();
+            if vector.is_zero() {
+                continue;
+            }
+
+            let tangent = vector.normalize();
+            let (normal, tangent) = (vec2f(-tangent.y(), tangent.x()), tangent * 0.5);
+            let center = vec2f(0.5, 0.5);
+
+            let first_index = vertex_data.len() as u32;
+            vertex_data.extend_from_slice(&[
+                DebugSolidVertex::line(from, center - tangent - normal, -1.0),
+                DebugSolidVertex::line(to,   center + tangent - normal, -1.0),
+                DebugSolidVertex::line(to,   center + tangent + normal,  1.0),
+                DebugSolidVertex::line(from, center - tangent + normal,  1.0),
+            ]);
+            index_data.extend(QUAD_INDICES.iter().map(|&index| index + first_index));
+        }
 
+        if index_data.is_empty() {
+            return;
+        }
+        self.draw_solid_triangles_with_vertex_data(device,
+                                                   allocator,
+                                                   &vertex_data,
+                                                   &index_data,
+                                                   color);
     }
 
     fn draw_rounded_rect_corners(&self,
@@ -476,11 +871,11 @@ impl<D> UIPresenter<D> where D: Device {
         {
             let vertex_buffer = allocator.get_general_buffer(vertex_buffer_id);
             let index_buffer = allocator.get_index_buffer(index_buffer_id);
-            device.upload_to_buffer(&vertex_buffer, 0, vertex_data, BufferTarget::Vertex);
-            device.upload_to_buffer(&index_buffer, 0, index_data, BufferTarget::Index);
+            device.upload_to_buffer(vertex_buffer, 0, vertex_data, BufferTarget::Vertex);
+            device.upload_to_buffer(index_buffer, 0, index_data, BufferTarget::Index);
 
             let texture_vertex_array = DebugTextureVertexArray::new(device,
-                                                                    &self.texture_program,
+                                                                    &self.texture_program.program,
                                                                     vertex_buffer,
                                                                     index_buffer);
 
@@ -489,21 +884,23 @@ impl<D> UIPresenter<D> where D: Device {
                 program: &self.texture_program.program,
                 vertex_array: &texture_vertex_array.vertex_array,
                 primitive: Primitive::Triangles,
+                first_index: 0,
                 textures: &[(&self.texture_program.texture, &texture)],
                 images: &[],
                 storage_buffers: &[],
                 uniforms: &[
                     (&self.texture_program.framebuffer_size_uniform,
                     UniformData::Vec2(self.framebuffer_size.0.to_f32x2())),
+                    (&self.texture_program.transform_uniform,
+                     UniformData::Mat2(self.transform.matrix.0)),
+                    (&self.texture_program.translation_uniform,
+                     UniformData::Vec2(self.transform.vector.0)),
                     (&self.texture_program.color_uniform, get_color_uniform(color)),
                     (&self.texture_program.texture_size_uniform,
                     UniformData::Vec2(device.texture_size(&texture).0.to_f32x2()))
                 ],
                 viewport: RectI::new(Vector2I::default(), self.framebuffer_size),
-                options: RenderOptions {
-                    blend: Some(alpha_blend_state()),
-                    ..RenderOptions::default()
-                },
+                options: self.render_options(),
             });
         }
 
@@ -521,7 +918,7 @@ impl<D> UIPresenter<D> where D: Device {
         self.draw_rounded_rect_outline(device, allocator, button_rect, OUTLINE_COLOR);
         self.draw_texture(device,
                           allocator,
-                          origin + vec2i(PADDING, PADDING),
+                          button_rect.inset(vec2i(PADDING, PADDING)).origin(),
                           texture,
                           BUTTON_ICON_COLOR);
         self.event_queue.handle_mouse_down_in_rect(button_rect).is_some()
@@ -642,34 +1039,263 @@ impl<D> UIPresenter<D> where D: Device {
         clicked_segment
     }
 
+    /// Draws a horizontal slider `width` pixels wide whose knob the user can drag to pick a
+    /// value in `range`. The value is clamped to `range`. Returns true if it changed.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_slider(&mut self,
+                       device: &D,
+                       allocator: &mut GPUMemoryAllocator<D>,
+                       origin: Vector2I,
+                       width: i32,
+                       value: &mut f32,
+                       range: Range<f32>)
+                       -> bool {
+        debug_assert!(range.start < range.end, "The slider range is empty!");
+        debug_assert!(width > SLIDER_KNOB_WIDTH, "The slider is too narrow for its knob!");
+
+        let widget_rect = RectI::new(origin, vec2i(width, BUTTON_HEIGHT));
+        let knob_travel = (width - SLIDER_KNOB_WIDTH) as f32;
+
+        let old_value = *value;
+        if let Some(position) = self.event_queue.handle_mouse_down_or_dragged_in_rect(widget_rect) {
+            // Center the knob on the mouse.
+            let knob_x = (position.x() - SLIDER_KNOB_WIDTH / 2) as f32;
+            *value = range.start + knob_x / knob_travel * (range.end - range.start);
+        }
+        *value = value.max(range.start).min(range.end);
+
+        let track_origin = origin + vec2i(0, (BUTTON_HEIGHT - SLIDER_TRACK_HEIGHT) / 2);
+        let track_rect = RectI::new(track_origin, vec2i(width, SLIDER_TRACK_HEIGHT));
+        self.draw_solid_rounded_rect(device, allocator, track_rect, WINDOW_COLOR);
+        self.draw_rounded_rect_outline(device, allocator, track_rect, OUTLINE_COLOR);
+
+        let knob_x = ((*value - range.start) / (range.end - range.start) * knob_travel).round();
+        let knob_rect = RectI::new(origin + vec2i(knob_x as i32, 0),
+                                   vec2i(SLIDER_KNOB_WIDTH, BUTTON_HEIGHT));
+        self.draw_solid_rounded_rect(device, allocator, knob_rect, TEXT_COLOR);
+
+        *value != old_value
+    }
+
+    /// Draws a checkbox with `label` to its right. Clicking the box or the label toggles `value`.
+    /// Returns true if it changed.
+    pub fn draw_checkbox(&mut self,
+                         device: &D,
+                         allocator: &mut GPUMemoryAllocator<D>,
+                         origin: Vector2I,
+                         label: &str,
+                         value: &mut bool)
+                         -> bool {
+        let label_offset = CHECKBOX_SIZE + PADDING;
+        let widget_size = vec2i(label_offset + self.measure_text(label), CHECKBOX_SIZE);
+        let clicked = self.event_queue
+                          .handle_mouse_down_in_rect(RectI::new(origin, widget_size))
+                          .is_some();
+        if clicked {
+            *value = !*value;
+        }
+
+        let box_rect = RectI::new(origin, Vector2I::splat(CHECKBOX_SIZE));
+        self.draw_solid_rounded_rect(device, allocator, box_rect, WINDOW_COLOR);
+        self.draw_rounded_rect_outline(device, allocator, box_rect, OUTLINE_COLOR);
+        if *value {
+            let (left, bottom, right) = (vec2i(8, 16), vec2i(14, 22), vec2i(24, 10));
+            self.draw_lines(device, allocator, &[
+                (origin + left, origin + bottom),
+                (origin + bottom, origin + right),
+            ], TEXT_COLOR);
+        }
+
+        // Center the text vertically on the box.
+        let baseline = FONT_ASCENT + (CHECKBOX_SIZE - FONT_ASCENT) / 2;
+        self.draw_text(device, allocator, label, origin + vec2i(label_offset, baseline), false);
+
+        clicked
+    }
+
+    /// Draws a single-line text field `width` pixels wide. Clicking the field focuses it. While
+    /// it's focused, typed text is appended to `text`, Backspace deletes the last character, and
+    /// Enter or Escape unfocuses it. Returns true if `text` changed.
+    ///
+    /// If `text` is too long for the field, only as much of the end as fits is shown, so that the
+    /// caret stays visible.
+    pub fn draw_text_field(&mut self,
+                           device: &D,
+                           allocator: &mut GPUMemoryAllocator<D>,
+                           origin: Vector2I,
+                           width: i32,
+                           text: &mut String,
+                           focused: &mut bool)
+                           -> bool {
+        let field_rect = RectI::new(origin, vec2i(width, BUTTON_HEIGHT));
+        if self.event_queue.handle_mouse_down_in_rect(field_rect).is_some() {
+            *focused = true;
+        } else if self.event_queue.has_mouse_down_outside_rect(field_rect) {
+            *focused = false;
+        }
+        let changed = *focused && self.event_queue.handle_text_editing(text, focused);
+
+        let outline_color = if *focused { TEXT_COLOR } else { OUTLINE_COLOR };
+        self.draw_solid_rounded_rect(device, allocator, field_rect, WINDOW_COLOR);
+        self.draw_rounded_rect_outline(device, allocator, field_rect, outline_color);
+
+        // Scroll by dropping characters from the start until the rest fits beside the caret.
+        let text_width = width - PADDING * 2 - 1;
+        let mut visible_text = &text[..];
+        while self.measure_text(visible_text) > text_width {
+            let mut characters = visible_text.chars();
+            characters.next();
+            visible_text = characters.as_str();
+        }
+
+        let text_origin = origin + vec2i(PADDING, BUTTON_TEXT_OFFSET);
+        self.draw_text(device, allocator, visible_text, text_origin, false);
+
+        let caret_visible =
+            (self.start_time.elapsed().as_millis() / CARET_BLINK_INTERVAL).is_multiple_of(2);
+        if *focused && caret_visible {
+            let caret_bottom = text_origin + vec2i(self.measure_text(visible_text) + 1, 0);
+            self.draw_line(device,
+                           allocator,
+                           caret_bottom - vec2i(0, FONT_ASCENT),
+                           caret_bottom,
+                           TEXT_COLOR);
+        }
+
+        changed
+    }
+
     pub fn draw_tooltip(&self,
                         device: &D,
                         allocator: &mut GPUMemoryAllocator<D>,
                         string: &str,
                         rect: RectI) {
-        if !rect.to_f32().contains_point(self.mouse_position) {
+        let mouse_position = self.transform.inverse() * self.mouse_position;
+        if !rect.to_f32().contains_point(mouse_position) {
             return;
         }
 
-        let text_size = self.measure_text(string);
-        let window_size = vec2i(text_size + PADDING * 2, TOOLTIP_HEIGHT);
+        let text_size = vec2i(self.measure_text(string), FONT_ASCENT);
+        let window_size = text_size + vec2i(PADDING * 2, PADDING * 2);
         let origin = rect.origin() - vec2i(0, window_size.y() + PADDING);
+        let window_rect = RectI::new(origin, window_size);
 
-        self.draw_solid_rounded_rect(device,
-                                     allocator,
-                                     RectI::new(origin, window_size),
-                                     WINDOW_COLOR);
+        self.draw_solid_rounded_rect(device, allocator, window_rect, WINDOW_COLOR);
         self.draw_text(device,
                        allocator,
                        string,
+                       window_rect.inset(vec2i(PADDING, PADDING)).lower_left(),
+                       false);
+    }
+
+    /// Draws a `PERF_GRAPH_WIDTH` by `PERF_GRAPH_HEIGHT` graph of frame times in milliseconds,
+    /// oldest first, labeled with the latest and average times. The graph is scaled so that the
+    /// worst frame fills its height, with a line marking the 60 FPS budget if it's in range.
+    pub fn draw_perf_graph(&self,
+                           device: &D,
+                           allocator: &mut GPUMemoryAllocator<D>,
+                           origin: Vector2I,
+                           samples: &[f32]) {
+        let window_rect = RectI::new(origin, vec2i(PERF_GRAPH_WIDTH, PERF_GRAPH_HEIGHT));
+        self.draw_solid_rounded_rect(device, allocator, window_rect, WINDOW_COLOR);
+
+        let current = match samples.last() {
+            None => return,
+            Some(&current) => current,
+        };
+        let average = samples.iter().sum::<f32>() / samples.len() as f32;
+        self.draw_text(device,
+                       allocator,
+                       &format!("{:.1} ms (avg {:.1} ms)", current, average),
                        origin + vec2i(PADDING, PADDING + FONT_ASCENT),
                        false);
+
+        let worst = samples.iter().cloned().fold(0.0, f32::max);
+        if samples.len() < 2 || worst <= 0.0 {
+            return;
+        }
+
+        let graph_rect = RectI::from_points(origin + vec2i(PADDING, PADDING * 2 + FONT_ASCENT),
+                                            window_rect.lower_right() - vec2i(PADDING, PADDING));
+        let to_y = |time: f32| {
+            graph_rect.max_y() - (time / worst * graph_rect.height() as f32).round() as i32
+        };
+        let to_point = |sample_index: usize| {
+            let x = sample_index as i32 * graph_rect.width() / (samples.len() as i32 - 1);
+            vec2i(graph_rect.min_x() + x, to_y(samples[sample_index]))
+        };
+
+        // Fill a trapezoid down to the baseline between each pair of neighboring samples.
+        let mut vertex_data = Vec::with_capacity((samples.len() - 1) * 4);
+        let mut index_data = Vec::with_capacity((samples.len() - 1) * 6);
+        for sample_index in 0..(samples.len() - 1) {
+            let (left, right) = (to_point(sample_index), to_point(sample_index + 1));
+            let first_vertex_index = vertex_data.len() as u32;
+            vertex_data.extend_from_slice(&[
+                DebugSolidVertex::new(left),
+                DebugSolidVertex::new(right),
+                DebugSolidVertex::new(vec2i(right.x(), graph_rect.max_y())),
+                DebugSolidVertex::new(vec2i(left.x(), graph_rect.max_y())),
+            ]);
+            index_data.extend(QUAD_INDICES.iter().map(|&index| index + first_vertex_index));
+        }
+        self.draw_solid_triangles_with_vertex_data(device,
+                                                   allocator,
+                                                   &vertex_data,
+                                                   &index_data,
+                                                   PERF_GRAPH_FILL_COLOR);
+
+        if FRAME_BUDGET <= worst {
+            let budget_y = to_y(FRAME_BUDGET);
+            self.draw_line(device,
+                           allocator,
+                           vec2i(graph_rect.min_x(), budget_y),
+                           vec2i(graph_rect.max_x(), budget_y),
+                           PERF_GRAPH_BUDGET_COLOR);
+        }
     }
 }
 
+/// The most recent frame times, for `UIPresenter::draw_perf_graph()`.
+pub struct PerfGraph {
+    samples: Vec<f32>,
+    sample_count: usize,
+}
+
+impl PerfGraph {
+    /// Creates a graph that keeps the latest `sample_count` frame times.
+    pub fn new(sample_count: usize) -> PerfGraph {
+        assert!(sample_count > 0, "A perf graph needs room for at least one sample!");
+        PerfGraph { samples: Vec::with_capacity(sample_count), sample_count }
+    }
+
+    /// Records a frame time in milliseconds, dropping the oldest if the graph is full.
+    pub fn push(&mut self, frame_time: f32) {
+        if self.samples.len() == self.sample_count {
+            self.samples.remove(0);
+        }
+        self.samples.push(frame_time);
+    }
+
+    /// The recorded frame times, oldest first.
+    #[inline]
+    pub fn samples(&self) -> &[f32] {
+        &self.samples
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum ClipMode {
+    None,
+    WritingMask,
+    Clipping,
+}
+
 struct DebugTextureProgram<D> where D: Device {
     program: D::Program,
     framebuffer_size_uniform: D::Uniform,
+    transform_uniform: D::Uniform,
+    translation_uniform: D::Uniform,
     texture_size_uniform: D::Uniform,
     color_uniform: D::Uniform,
     texture: D::TextureParameter,
@@ -679,12 +1305,16 @@ impl<D> DebugTextureProgram<D> where D: Device {
     fn new(device: &D, resources: &dyn ResourceLoader) -> DebugTextureProgram<D> {
         let program = device.create_raster_program(resources, "debug/texture");
         let framebuffer_size_uniform = device.get_uniform(&program, "FramebufferSize");
+        let transform_uniform = device.get_uniform(&program, "Transform");
+        let translation_uniform = device.get_uniform(&program, "Translation");
         let texture_size_uniform = device.get_uniform(&program, "TextureSize");
         let color_uniform = device.get_uniform(&program, "Color");
         let texture = device.get_texture_parameter(&program, "Texture");
         DebugTextureProgram {
             program,
             framebuffer_size_uniform,
+            transform_uniform,
+            translation_uniform,
             texture_size_uniform,
             color_uniform,
             texture,
@@ -692,22 +1322,60 @@ impl<D> DebugTextureProgram<D> where D: Device {
     }
 }
 
+struct DebugInspectorProgram<D> where D: Device {
+    program: D::Program,
+    framebuffer_size_uniform: D::Uniform,
+    transform_uniform: D::Uniform,
+    translation_uniform: D::Uniform,
+    texture_size_uniform: D::Uniform,
+    channel_mask_uniform: D::Uniform,
+    scale_uniform: D::Uniform,
+    texture: D::TextureParameter,
+}
+
+impl<D> DebugInspectorProgram<D> where D: Device {
+    fn new(device: &D, resources: &dyn ResourceLoader) -> DebugInspectorProgram<D> {
+        let program = device.create_program_from_shader_names(resources,
+                                                              "debug/inspect",
+                                                              ProgramKind::Raster {
+                                                                  vertex: "debug/texture",
+                                                                  fragment: "debug/inspect",
+                                                              });
+        let framebuffer_size_uniform = device.get_uniform(&program, "FramebufferSize");
+        let transform_uniform = device.get_uniform(&program, "Transform");
+        let translation_uniform = device.get_uniform(&program, "Translation");
+        let texture_size_uniform = device.get_uniform(&program, "TextureSize");
+        let channel_mask_uniform = device.get_uniform(&program, "ChannelMask");
+        let scale_uniform = device.get_uniform(&program, "Scale");
+        let texture = device.get_texture_parameter(&program, "Texture");
+        DebugInspectorProgram {
+            program,
+            framebuffer_size_uniform,
+            transform_uniform,
+            translation_uniform,
+            texture_size_uniform,
+            channel_mask_uniform,
+            scale_uniform,
+            texture,
+        }
+    }
+}
+
 struct DebugTextureVertexArray<D> where D: Device {
     vertex_array: D::VertexArray,
 }
 
 impl<D> DebugTextureVertexArray<D> where D: Device {
+    // `program` is any program that uses the `debug/texture` vertex shader.
     fn new(device: &D,
-           debug_texture_program: &DebugTextureProgram<D>,
+           program: &D::Program,
            vertex_buffer: &D::Buffer,
            index_buffer: &D::Buffer)
            -> DebugTextureVertexArray<D> {
         let vertex_array = device.create_vertex_array();
 
-        let position_attr = device.get_vertex_attr(&debug_texture_program.program, "Position")
-                                  .unwrap();
-        let tex_coord_attr = device.get_vertex_attr(&debug_texture_program.program, "TexCoord")
-                                   .unwrap();
+        let position_attr = device.get_vertex_attr(program, "Position").unwrap();
+        let tex_coord_attr = device.get_vertex_attr(program, "TexCoord").unwrap();
 
         device.bind_buffer(&vertex_array, vertex_buffer, BufferTarget::Vertex);
         device.bind_buffer(&vertex_array, index_buffer, BufferTarget::Index);
@@ -748,6 +1416,9 @@ impl<D> DebugSolidVertexArray<D> where D: Device {
 
         let position_attr =
             device.get_vertex_attr(&debug_solid_program.program, "Position").unwrap();
+        let offset_attr = device.get_vertex_attr(&debug_solid_program.program, "Offset").unwrap();
+        let edge_distance_attr =
+            device.get_vertex_attr(&debug_solid_program.program, "EdgeDistance").unwrap();
         device.bind_buffer(&vertex_array, &vertex_buffer, BufferTarget::Vertex);
         device.bind_buffer(&vertex_array, &index_buffer, BufferTarget::Index);
         device.configure_vertex_attr(&vertex_array, &position_attr, &VertexAttrDescriptor {
@@ -759,6 +1430,24 @@ impl<D> DebugSolidVertexArray<D> where D: Device {
             divisor: 0,
             buffer_index: 0,
         });
+        device.configure_vertex_attr(&vertex_array, &offset_attr, &VertexAttrDescriptor {
+            size: 2,
+            class: VertexAttrClass::Float,
+            attr_type: VertexAttrType::F32,
+            stride: DEBUG_SOLID_VERTEX_SIZE,
+            offset: 4,
+            divisor: 0,
+            buffer_index: 0,
+        });
+        device.configure_vertex_attr(&vertex_array, &edge_distance_attr, &VertexAttrDescriptor {
+            size: 1,
+            class: VertexAttrClass::Float,
+            attr_type: VertexAttrType::F32,
+            stride: DEBUG_SOLID_VERTEX_SIZE,
+            offset: 12,
+            divisor: 0,
+            buffer_index: 0,
+        });
 
         DebugSolidVertexArray { vertex_array }
     }
@@ -767,6 +1456,8 @@ impl<D> DebugSolidVertexArray<D> where D: Device {
 struct DebugSolidProgram<D> where D: Device {
     program: D::Program,
     framebuffer_size_uniform: D::Uniform,
+    transform_uniform: D::Uniform,
+    translation_uniform: D::Uniform,
     color_uniform: D::Uniform,
 }
 
@@ -774,8 +1465,16 @@ impl<D> DebugSolidProgram<D> where D: Device {
     fn new(device: &D, resources: &dyn ResourceLoader) -> DebugSolidProgram<D> {
         let program = device.create_raster_program(resources, "debug/solid");
         let framebuffer_size_uniform = device.get_uniform(&program, "FramebufferSize");
+        let transform_uniform = device.get_uniform(&program, "Transform");
+        let translation_uniform = device.get_uniform(&program, "Translation");
         let color_uniform = device.get_uniform(&program, "Color");
-        DebugSolidProgram { program, framebuffer_size_uniform, color_uniform }
+        DebugSolidProgram {
+            program,
+            framebuffer_size_uniform,
+            transform_uniform,
+            translation_uniform,
+            color_uniform,
+        }
     }
 }
 
@@ -806,11 +1505,27 @@ impl DebugTextureVertex {
 struct DebugSolidVertex {
     position_x: i16,
     position_y: i16,
+    offset_x: f32,
+    offset_y: f32,
+    edge_distance: f32,
 }
 
 impl DebugSolidVertex {
+    // Creates a vertex of a filled shape, which is fully covered.
     fn new(position: Vector2I) -> DebugSolidVertex {
-        DebugSolidVertex { position_x: position.x() as i16, position_y: position.y() as i16 }
+        DebugSolidVertex::line(position, Vector2F::zero(), 0.0)
+    }
+
+    // Creates a vertex of a line quad. `edge_distance` is the distance in pixels from the center
+    // of the line, which the fragment shader uses to compute coverage.
+    fn line(position: Vector2I, offset: Vector2F, edge_distance: f32) -> DebugSolidVertex {
+        DebugSolidVertex {
+            position_x: position.x() as i16,
+            position_y: position.y() as i16,
+            offset_x: offset.x(),
+            offset_y: offset.y(),
+            edge_distance,
+        }
     }
 }
 
@@ -833,24 +1548,107 @@ impl CornerRects {
     }
 }
 
+// Returns true if the polygon turns the same way at every vertex, ignoring collinear vertices,
+// and winds around exactly once. The second check rejects star polygons like pentagrams, whose
+// vertices all turn the same way but whose turns add up to a multiple of 2π other than ±2π.
+fn is_convex_polygon(points: &[Vector2I]) -> bool {
+    let (mut turns_left, mut turns_right) = (false, false);
+    let mut total_turn = 0.0;
+    for index in 0..points.len() {
+        let point = points[index];
+        let next_point = points[(index + 1) % points.len()];
+        let next_next_point = points[(index + 2) % points.len()];
+        let (edge, next_edge) = (next_point - point, next_next_point - next_point);
+        let cross = edge.x() as i64 * next_edge.y() as i64 - edge.y() as i64 * next_edge.x() as i64;
+        let dot = edge.x() as i64 * next_edge.x() as i64 + edge.y() as i64 * next_edge.y() as i64;
+        turns_left |= cross > 0;
+        turns_right |= cross < 0;
+        total_turn += (cross as f32).atan2(dot as f32);
+    }
+    // The total turn of a closed polygon is always a multiple of 2π, so this tolerance is loose.
+    !(turns_left && turns_right) && (total_turn.abs() - 2.0 * PI).abs() < PI
+}
+
+/// Returns the text color, normal or inverted, that contrasts most with `background`.
+pub fn text_color_for_background(background: ColorU) -> ColorU {
+    // Black and white text have equal contrast ratios against a luminance of about 0.179.
+    if background.luminance() > 0.179 { INVERTED_TEXT_COLOR } else { TEXT_COLOR }
+}
+
 fn get_color_uniform(color: ColorU) -> UniformData {
-    let color = F32x4::new(color.r as f32, color.g as f32, color.b as f32, color.a as f32);
-    UniformData::Vec4(color * F32x4::splat(1.0 / 255.0))
+    UniformData::Vec4(color.to_f32().0)
 }
 
+/// The channels of a texture that `UIPresenter::draw_texture_inspector()` shows.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChannelMask {
+    /// The red channel, as grayscale.
+    Red,
+    /// The green channel, as grayscale.
+    Green,
+    /// The blue channel, as grayscale.
+    Blue,
+    /// The alpha channel, as grayscale.
+    Alpha,
+    /// The color channels, ignoring alpha.
+    All,
+    /// The color channels, blended over the background using alpha as coverage.
+    Coverage,
+}
+
+impl ChannelMask {
+    // The inspector shader shows a single selected channel as grayscale and otherwise uses the
+    // alpha lane to decide whether to blend.
+    fn to_f32x4(self) -> F32x4 {
+        match self {
+            ChannelMask::Red      => F32x4::new(1.0, 0.0, 0.0, 0.0),
+            ChannelMask::Green    => F32x4::new(0.0, 1.0, 0.0, 0.0),
+            ChannelMask::Blue     => F32x4::new(0.0, 0.0, 1.0, 0.0),
+            ChannelMask::Alpha    => F32x4::new(0.0, 0.0, 0.0, 1.0),
+            ChannelMask::All      => F32x4::new(1.0, 1.0, 1.0, 0.0),
+            ChannelMask::Coverage => F32x4::new(1.0, 1.0, 1.0, 1.0),
+        }
+    }
+}
+
+/// Input for the UI, which the host windowing code pushes onto `UIPresenter::event_queue` as it
+/// receives it. Widgets consume the events that they handle, and the host can drain the rest.
+///
+/// When a key is pressed, the host should push `KeyDown` if the key has a `Keycode`. Separately,
+/// it should push a `TextInput` for each character that the key press produces after the
+/// platform has applied the keyboard layout and any input method, such as from a
+/// `ReceivedCharacter` or `TextInput` window event. Control characters shouldn't be pushed as
+/// text input.
 #[derive(Clone, Copy)]
 pub enum UIEvent {
     MouseDown(MousePosition),
     MouseDragged(MousePosition),
+    KeyDown(Keycode),
+    TextInput(char),
+}
+
+/// The keys that UI widgets respond to. Keys that only produce text don't need a keycode.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Keycode {
+    Left,
+    Right,
+    Up,
+    Down,
+    Enter,
+    Backspace,
+    Escape,
+    Tab,
 }
 
 pub struct UIEventQueue {
     events: Vec<UIEvent>,
+    // Maps mouse positions from framebuffer pixels back into UI coordinates.
+    inverse_transform: Transform2F,
 }
 
 impl UIEventQueue {
     fn new() -> UIEventQueue {
-        UIEventQueue { events: vec![] }
+        UIEventQueue { events: vec![], inverse_transform: Transform2F::default() }
     }
 
     pub fn push(&mut self, event: UIEvent) {
@@ -863,10 +1661,11 @@ impl UIEventQueue {
 
     pub fn handle_mouse_down_in_rect(&mut self, rect: RectI) -> Option<Vector2I> {
         let (mut remaining_events, mut result) = (vec![], None);
-        for event in self.events.drain(..) {
+        for event in self.drain() {
             match event {
-                UIEvent::MouseDown(position) if rect.contains_point(position.absolute) => {
-                    result = Some(position.absolute - rect.origin());
+                UIEvent::MouseDown(position) if
+                        rect.contains_point(self.to_ui_space(position.absolute)) => {
+                    result = Some(self.to_ui_space(position.absolute) - rect.origin());
                 }
                 event => remaining_events.push(event),
             }
@@ -877,18 +1676,72 @@ impl UIEventQueue {
 
     pub fn handle_mouse_down_or_dragged_in_rect(&mut self, rect: RectI) -> Option<Vector2I> {
         let (mut remaining_events, mut result) = (vec![], None);
-        for event in self.events.drain(..) {
+        for event in self.drain() {
             match event {
                 UIEvent::MouseDown(position) | UIEvent::MouseDragged(position) if
-                        rect.contains_point(position.absolute) => {
-                    result = Some(position.absolute - rect.origin());
+                        rect.contains_point(self.to_ui_space(position.absolute)) => {
+                    result = Some(self.to_ui_space(position.absolute) - rect.origin());
+                }
+                event => remaining_events.push(event),
+            }
+        }
+        self.events = remaining_events;
+        result
+    }
+
+    /// Returns true if the mouse was pressed anywhere outside `rect`. The events are left in the
+    /// queue for the widgets under the mouse.
+    pub fn has_mouse_down_outside_rect(&self, rect: RectI) -> bool {
+        self.events.iter().any(|event| {
+            match *event {
+                UIEvent::MouseDown(position) => {
+                    !rect.contains_point(self.to_ui_space(position.absolute))
+                }
+                _ => false,
+            }
+        })
+    }
+
+    /// Applies typed text and editing keys to `text`, in the order they arrived: text input is
+    /// appended, Backspace deletes the last character, and Enter or Escape clear `focused`.
+    /// Events after the focus is lost are left in the queue. Returns true if `text` changed.
+    pub fn handle_text_editing(&mut self, text: &mut String, focused: &mut bool) -> bool {
+        let (mut remaining_events, mut changed) = (vec![], false);
+        for event in self.drain() {
+            match event {
+                UIEvent::TextInput(character) if *focused => {
+                    text.push(character);
+                    changed = true;
+                }
+                UIEvent::KeyDown(Keycode::Backspace) if *focused => {
+                    changed |= text.pop().is_some();
                 }
+                UIEvent::KeyDown(Keycode::Enter) |
+                UIEvent::KeyDown(Keycode::Escape) if *focused => *focused = false,
+                event => remaining_events.push(event),
+            }
+        }
+        self.events = remaining_events;
+        changed
+    }
+
+    /// Consumes presses of `keycode`, returning true if there were any.
+    pub fn handle_key_down(&mut self, keycode: Keycode) -> bool {
+        let (mut remaining_events, mut result) = (vec![], false);
+        for event in self.drain() {
+            match event {
+                UIEvent::KeyDown(event_keycode) if event_keycode == keycode => result = true,
                 event => remaining_events.push(event),
             }
         }
         self.events = remaining_events;
         result
     }
+
+    #[inline]
+    fn to_ui_space(&self, position: Vector2I) -> Vector2I {
+        (self.inverse_transform * position.to_f32()).floor().to_i32()
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -938,3 +1791,116 @@ fn alpha_blend_state() -> BlendState {
         ..BlendState::default()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{Keycode, MousePosition, UIEvent, UIEventQueue, is_convex_polygon};
+    use pathfinder_geometry::rect::RectI;
+    use pathfinder_geometry::vector::vec2i;
+
+    fn event_queue(events: &[UIEvent]) -> UIEventQueue {
+        let mut event_queue = UIEventQueue::new();
+        for &event in events {
+            event_queue.push(event);
+        }
+        event_queue
+    }
+
+    #[test]
+    fn test_convex_polygons() {
+        let square = [vec2i(0, 0), vec2i(10, 0), vec2i(10, 10), vec2i(0, 10)];
+        assert!(is_convex_polygon(&square));
+        let reversed_square: Vec<_> = square.iter().rev().cloned().collect();
+        assert!(is_convex_polygon(&reversed_square));
+        // Collinear vertices don't make a polygon concave.
+        let triangle = [vec2i(0, 0), vec2i(5, 0), vec2i(10, 0), vec2i(5, 8)];
+        assert!(is_convex_polygon(&triangle));
+    }
+
+    #[test]
+    fn test_concave_polygons() {
+        let arrowhead = [vec2i(0, 0), vec2i(10, 5), vec2i(0, 10), vec2i(3, 5)];
+        assert!(!is_convex_polygon(&arrowhead));
+    }
+
+    #[test]
+    fn test_self_intersecting_polygons() {
+        // Every vertex of a pentagram turns the same way, but it winds around twice.
+        let pentagram = [vec2i(0, 10), vec2i(6, -8), vec2i(-10, 3), vec2i(10, 3), vec2i(-6, -8)];
+        assert!(!is_convex_polygon(&pentagram));
+        let bowtie = [vec2i(0, 0), vec2i(10, 10), vec2i(10, 0), vec2i(0, 10)];
+        assert!(!is_convex_polygon(&bowtie));
+    }
+
+    #[test]
+    fn test_text_editing_order() {
+        let mut event_queue = event_queue(&[
+            UIEvent::TextInput('a'),
+            UIEvent::TextInput('b'),
+            UIEvent::KeyDown(Keycode::Backspace),
+            UIEvent::TextInput('c'),
+        ]);
+        let (mut text, mut focused) = (String::new(), true);
+        assert!(event_queue.handle_text_editing(&mut text, &mut focused));
+        assert_eq!(text, "ac");
+        assert!(focused);
+        assert!(event_queue.drain().is_empty());
+    }
+
+    #[test]
+    fn test_text_editing_backspace_on_empty_text() {
+        let mut event_queue = event_queue(&[UIEvent::KeyDown(Keycode::Backspace)]);
+        let (mut text, mut focused) = (String::new(), true);
+        assert!(!event_queue.handle_text_editing(&mut text, &mut focused));
+        assert_eq!(text, "");
+        assert!(focused);
+    }
+
+    #[test]
+    fn test_text_editing_enter_and_escape_unfocus() {
+        for &keycode in &[Keycode::Enter, Keycode::Escape] {
+            let mut event_queue = event_queue(&[UIEvent::KeyDown(keycode)]);
+            let (mut text, mut focused) = (String::from("text"), true);
+            assert!(!event_queue.handle_text_editing(&mut text, &mut focused));
+            assert_eq!(text, "text");
+            assert!(!focused);
+            assert!(event_queue.drain().is_empty());
+        }
+    }
+
+    #[test]
+    fn test_text_editing_leaves_events_after_unfocus() {
+        let mut event_queue = event_queue(&[
+            UIEvent::TextInput('a'),
+            UIEvent::KeyDown(Keycode::Enter),
+            UIEvent::TextInput('b'),
+            UIEvent::KeyDown(Keycode::Backspace),
+        ]);
+        let (mut text, mut focused) = (String::new(), true);
+        assert!(event_queue.handle_text_editing(&mut text, &mut focused));
+        assert_eq!(text, "a");
+        assert!(!focused);
+
+        let remaining_events = event_queue.drain();
+        assert_eq!(remaining_events.len(), 2);
+        match (remaining_events[0], remaining_events[1]) {
+            (UIEvent::TextInput('b'), UIEvent::KeyDown(Keycode::Backspace)) => {}
+            _ => panic!("Events after the focus was lost weren't left in the queue!"),
+        }
+    }
+
+    #[test]
+    fn test_mouse_down_outside_rect() {
+        let rect = RectI::new(vec2i(10, 10), vec2i(20, 20));
+        let mouse_down = |position| {
+            UIEvent::MouseDown(MousePosition { absolute: position, relative: position })
+        };
+
+        let inside_queue = event_queue(&[mouse_down(vec2i(15, 15))]);
+        assert!(!inside_queue.has_mouse_down_outside_rect(rect));
+
+        let mut outside_queue = event_queue(&[mouse_down(vec2i(5, 15)), UIEvent::TextInput('a')]);
+        assert!(outside_queue.has_mouse_down_outside_rect(rect));
+        assert_eq!(outside_queue.drain().len(), 2);
+    }
+}
@@ -13,32 +13,58 @@
 #[macro_use]
 extern crate log;
 
-use js_sys::{Uint8Array, Uint16Array, Float32Array, Object};
+use half::f16;
+use js_sys::{Array, Uint8Array, Uint16Array, Float32Array, Object};
+use pathfinder_color::ColorF;
 use pathfinder_geometry::rect::RectI;
-use pathfinder_geometry::vector::Vector2I;
+use pathfinder_geometry::vector::{Vector2I, vec2i};
+use pathfinder_gpu::AlphaMode;
 use pathfinder_gpu::{BlendFactor, BlendOp, BufferData, BufferTarget, BufferUploadMode, ClearOps};
-use pathfinder_gpu::{ComputeDimensions, ComputeState, DepthFunc, Device, FeatureLevel};
-use pathfinder_gpu::{ImageBinding, Primitive, ProgramKind, RenderOptions, RenderState};
+use pathfinder_gpu::{ComputeDimensions, ComputeState, CubeFace, DepthFunc, Device};
+use pathfinder_gpu::ExternalImageHandle;
+use pathfinder_gpu::{DeviceLimits, Feature, FeatureLevel, FrameStats};
+use pathfinder_gpu::{ImageBinding, LoadAction, PipelineStats, Primitive, ProgramKind};
+use pathfinder_gpu::RenderOptions;
+use pathfinder_gpu::{RenderState, Swizzle};
 use pathfinder_gpu::{RenderTarget, ShaderKind, StencilFunc, TextureBinding, TextureData};
-use pathfinder_gpu::{TextureDataRef, TextureFormat, TextureSamplingFlags, UniformData};
+use pathfinder_gpu::{TextureDataRef, TextureFormat, TextureSamplingFlags, TimestampCalibration};
+use pathfinder_gpu::{TextureUsage, TextureUsageFlags, UNINITIALIZED_BUFFER_FILL_BYTE};
+use pathfinder_gpu::UniformData;
 use pathfinder_gpu::{VertexAttrClass, VertexAttrDescriptor, VertexAttrType};
 use pathfinder_resources::ResourceLoader;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::mem;
 use std::ops::Range;
 use std::str;
 use std::time::Duration;
-use wasm_bindgen::JsCast;
+use wasm_bindgen::{JsCast, JsValue};
 use web_sys::WebGl2RenderingContext as WebGl;
 
+// The query target from `EXT_disjoint_timer_query_webgl2`, which `web_sys` doesn't define.
+const TIME_ELAPSED_EXT: u32 = 0x88bf;
+
 pub struct WebGlDevice {
     context: web_sys::WebGl2RenderingContext,
+    float_linear_filtering: bool,
+    // Whether `EXT_disjoint_timer_query_webgl2` is available.
+    timer_queries: bool,
+    // Whether a render pass begun by `begin_render_pass()` is active.
+    in_render_pass: Cell<bool>,
 }
 
 impl WebGlDevice {
     pub fn new(context: web_sys::WebGl2RenderingContext) -> Self {
         context.get_extension("EXT_color_buffer_float").unwrap();
-        WebGlDevice { context }
+        let float_linear_filtering =
+            matches!(context.get_extension("OES_texture_float_linear"), Ok(Some(_)));
+        let timer_queries =
+            matches!(context.get_extension("EXT_disjoint_timer_query_webgl2"), Ok(Some(_)));
+        WebGlDevice {
+            context,
+            float_linear_filtering,
+            timer_queries,
+            in_render_pass: Cell::new(false),
+        }
     }
 
     // Error checking
@@ -85,6 +111,13 @@ impl WebGlDevice {
         self.context.bind_texture(WebGl::TEXTURE_2D, None);
     }
 
+    fn render_target_format(&self, render_target: &RenderTarget<WebGlDevice>) -> TextureFormat {
+        match *render_target {
+            RenderTarget::Default => TextureFormat::RGBA8,
+            RenderTarget::Framebuffer(framebuffer) => framebuffer.texture.format,
+        }
+    }
+
     #[inline]
     fn bind_render_target(&self, attachment: &RenderTarget<WebGlDevice>) {
         let framebuffer = match *attachment {
@@ -176,14 +209,34 @@ impl WebGlDevice {
     }
 
     fn set_render_state(&self, render_state: &RenderState<WebGlDevice>) {
-        self.bind_render_target(render_state.target);
+        if self.in_render_pass.get() {
+            debug_assert!(
+                render_state.options.load_action == LoadAction::Load
+                    && !render_state.options.clear_ops.has_ops(),
+                "Draws inside a render pass can't clear or discard its target!"
+            );
+        } else {
+            self.bind_render_target(render_state.target);
+        }
 
         let (origin, size) = (render_state.viewport.origin(), render_state.viewport.size());
         self.context
             .viewport(origin.x(), origin.y(), size.x(), size.y());
 
-        if render_state.options.clear_ops.has_ops() {
-            self.clear(&render_state.options.clear_ops);
+        // Set the scissor before clearing, so that clears are scissored too.
+        if let Some(scissor) = render_state.options.scissor {
+            let (origin, size) = (scissor.origin(), scissor.size());
+            self.context
+                .scissor(origin.x(), origin.y(), size.x(), size.y());
+            self.context.enable(WebGl::SCISSOR_TEST);
+        }
+
+        let clear_ops = render_state.options.effective_clear_ops();
+        if render_state.options.load_action == LoadAction::DontCare {
+            self.invalidate_framebuffer(render_state.target, &clear_ops);
+        }
+        if clear_ops.has_ops() {
+            self.clear_framebuffer(&clear_ops);
         }
 
         self.context.use_program(Some(&render_state.program.gl_program));
@@ -203,7 +256,11 @@ impl WebGlDevice {
             &self,
             program: &WebGlProgram,
             texture_bindings: &[TextureBinding<WebGlTextureParameter, WebGlTexture>],
-            _: &[ImageBinding<(), WebGlTexture>]) {
+            image_bindings: &[ImageBinding<(), WebGlTexture>]) {
+        if !image_bindings.is_empty() {
+            panic!("Storage images are unsupported in WebGL!")
+        }
+
         for &(texture_parameter, texture) in texture_bindings {
             self.bind_texture(texture, texture_parameter.texture_unit);
         }
@@ -240,6 +297,8 @@ impl WebGlDevice {
                     BlendFactor::DestAlpha => WebGl::DST_ALPHA,
                     BlendFactor::OneMinusDestAlpha => WebGl::ONE_MINUS_DST_ALPHA,
                     BlendFactor::DestColor => WebGl::DST_COLOR,
+                    BlendFactor::ConstantColor => WebGl::CONSTANT_COLOR,
+                    BlendFactor::OneMinusConstantColor => WebGl::ONE_MINUS_CONSTANT_COLOR,
                 };
 
                 self.context.blend_func_separate(
@@ -248,6 +307,13 @@ impl WebGlDevice {
                     func(blend.src_alpha_factor),
                     func(blend.dest_alpha_factor),
                 );
+                let blend_color = render_options.blend_color;
+                self.context.blend_color(
+                    blend_color.r(),
+                    blend_color.g(),
+                    blend_color.b(),
+                    blend_color.a(),
+                );
                 self.context.enable(WebGl::BLEND);
                 self.ck();
             }
@@ -269,6 +335,13 @@ impl WebGlDevice {
             }
         }
 
+        // Set depth bias.
+        if let Some(ref bias) = render_options.depth_bias {
+            self.context.polygon_offset(bias.slope, bias.constant);
+            self.context.enable(WebGl::POLYGON_OFFSET_FILL);
+            self.ck();
+        }
+
         // Set stencil.
         match render_options.stencil {
             None => {
@@ -301,6 +374,11 @@ impl WebGlDevice {
         self.context
             .color_mask(color_mask, color_mask, color_mask, color_mask);
         self.ck();
+
+        // Primitive restart is always enabled in WebGL 2, with a fixed index.
+        if let Some(restart_index) = render_options.primitive_restart {
+            assert_eq!(restart_index, !0, "WebGL only supports a primitive restart index of !0!");
+        }
     }
 
     fn reset_render_state(&self, render_state: &RenderState<WebGlDevice>) {
@@ -321,6 +399,14 @@ impl WebGlDevice {
             self.context.disable(WebGl::DEPTH_TEST);
         }
 
+        if render_options.depth_bias.is_some() {
+            self.context.disable(WebGl::POLYGON_OFFSET_FILL);
+        }
+
+        if render_options.scissor.is_some() {
+            self.context.disable(WebGl::SCISSOR_TEST);
+        }
+
         if render_options.stencil.is_some() {
             self.context.stencil_mask(!0);
             self.context.disable(WebGl::STENCIL_TEST);
@@ -330,8 +416,58 @@ impl WebGlDevice {
         self.ck();
     }
 
+    // Points `attr` at `offset` bytes into the buffer bound to `ARRAY_BUFFER`, in the bound vertex
+    // array.
+    fn set_vertex_attr_pointer(&self, attr: u32, descriptor: &VertexAttrDescriptor, offset: usize) {
+        let attr_type = descriptor.attr_type.to_gl_type();
+        match descriptor.class {
+            VertexAttrClass::Float | VertexAttrClass::FloatNorm => {
+                let normalized = descriptor.class == VertexAttrClass::FloatNorm;
+                self.context.vertex_attrib_pointer_with_i32(
+                    attr,
+                    descriptor.size as i32,
+                    attr_type,
+                    normalized,
+                    descriptor.stride as i32,
+                    offset as i32,
+                );
+            }
+            VertexAttrClass::Int => {
+                self.context.vertex_attrib_i_pointer_with_i32(
+                    attr,
+                    descriptor.size as i32,
+                    attr_type,
+                    descriptor.stride as i32,
+                    offset as i32,
+                );
+            }
+        }
+    }
+
+    // WebGL has no base instances, so this emulates one by pointing each per-instance attribute
+    // `base_instance` instances further into its buffer. Passing zero restores the configured
+    // offsets.
+    fn offset_instance_attrs(&self, instance_attrs: &[WebGlInstanceAttr], base_instance: u32) {
+        for instance_attr in instance_attrs {
+            let descriptor = &instance_attr.descriptor;
+            assert_eq!(
+                base_instance % descriptor.divisor,
+                0,
+                "The base instance must be a multiple of the attribute divisor!"
+            );
+            let instance = (base_instance / descriptor.divisor) as usize;
+            self.context
+                .bind_buffer(WebGl::ARRAY_BUFFER, instance_attr.buffer.as_ref());
+            self.set_vertex_attr_pointer(
+                instance_attr.attr,
+                descriptor,
+                descriptor.offset + instance * descriptor.stride,
+            );
+        }
+    }
+
     #[inline]
-    fn clear(&self, ops: &ClearOps) {
+    fn clear_framebuffer(&self, ops: &ClearOps) {
         let mut flags = 0;
         if let Some(color) = ops.color {
             self.context.color_mask(true, true, true, true);
@@ -354,6 +490,35 @@ impl WebGlDevice {
         }
     }
 
+    fn invalidate_framebuffer(&self, render_target: &RenderTarget<WebGlDevice>, ops: &ClearOps) {
+        let (color, depth, stencil) = match *render_target {
+            RenderTarget::Default => (WebGl::COLOR, WebGl::DEPTH, WebGl::STENCIL),
+            RenderTarget::Framebuffer(_) => (
+                WebGl::COLOR_ATTACHMENT0,
+                WebGl::DEPTH_ATTACHMENT,
+                WebGl::STENCIL_ATTACHMENT,
+            ),
+        };
+
+        let attachments = Array::new();
+        if ops.color.is_none() {
+            attachments.push(&JsValue::from(color));
+        }
+        if ops.depth.is_none() {
+            attachments.push(&JsValue::from(depth));
+        }
+        if ops.stencil.is_none() {
+            attachments.push(&JsValue::from(stencil));
+        }
+
+        if attachments.length() > 0 {
+            self.context
+                .invalidate_framebuffer(WebGl::FRAMEBUFFER, &attachments)
+                .unwrap();
+            self.ck();
+        }
+    }
+
     fn preprocess(&self, source: &[u8], version: &str) -> String {
         let source = std::str::from_utf8(source).unwrap();
         let mut output = String::new();
@@ -388,6 +553,36 @@ fn slice_to_u8<T>(slice: &[T]) -> &[u8] {
     }
 }
 
+// Keeps the first `channels` channels of RGBA texels.
+fn narrow_channels<T>(rgba_pixels: Vec<T>, channels: usize) -> Vec<T> where T: Copy {
+    if channels == 4 {
+        return rgba_pixels;
+    }
+    rgba_pixels.chunks_exact(4).flat_map(|texel| texel[..channels].to_vec()).collect()
+}
+
+fn flip_y<T>(pixels: &mut [T], size: Vector2I, channels: usize) {
+    let stride = size.x() as usize * channels;
+    for y in 0..(size.y() as usize / 2) {
+        let (index_a, index_b) = (y * stride, (size.y() as usize - y - 1) * stride);
+        for offset in 0..stride {
+            pixels.swap(index_a + offset, index_b + offset);
+        }
+    }
+}
+
+// The number of levels in a full mip chain, down to 1x1.
+fn mip_level_count(size: Vector2I) -> u32 {
+    let max_length = size.x().max(size.y()).max(1) as u32;
+    32 - max_length.leading_zeros()
+}
+
+// Returns the byte offset of the first index of an indexed draw. WebGL itself rejects draws
+// that read past the end of the index buffer.
+fn index_buffer_offset(render_state: &RenderState<WebGlDevice>) -> i32 {
+    (render_state.first_index as usize * mem::size_of::<u32>()) as i32
+}
+
 // this function is unsafe due to the underlying UintXArray::view
 unsafe fn check_and_extract_data(
     data_ref: TextureDataRef,
@@ -397,8 +592,13 @@ unsafe fn check_and_extract_data(
     let channels = match (format, data_ref) {
         (TextureFormat::R8, TextureDataRef::U8(_)) => 1,
         (TextureFormat::RGBA8, TextureDataRef::U8(_)) => 4,
+        (TextureFormat::RGBA8Srgb, TextureDataRef::U8(_)) => 4,
         (TextureFormat::RGBA16F, TextureDataRef::F16(_)) => 4,
         (TextureFormat::RGBA32F, TextureDataRef::F32(_)) => 4,
+        (TextureFormat::BGRA8, _) => panic!("BGRA textures are unsupported in WebGL!"),
+        (TextureFormat::R16, _) | (TextureFormat::RGBA16, _) => {
+            panic!("16-bit normalized textures are unsupported in WebGL!")
+        }
         _ => panic!("Unimplemented texture format!"),
     };
 
@@ -409,6 +609,7 @@ unsafe fn check_and_extract_data(
             assert!(data.len() >= area * channels);
             Uint8Array::view(data).unchecked_into()
         }
+        TextureDataRef::U16(_) => unreachable!(),
         TextureDataRef::F16(data) => {
             assert!(data.len() >= area * channels);
             Uint16Array::view_mut_raw(data.as_ptr() as *mut u16, data.len()).unchecked_into()
@@ -423,14 +624,16 @@ unsafe fn check_and_extract_data(
 impl Device for WebGlDevice {
     type Buffer = WebGlBuffer;
     type BufferDataReceiver = ();
-    type Fence = ();
+    type Fence = WebGlFence;
     type Framebuffer = WebGlFramebuffer;
     type ImageParameter = ();
+    type OcclusionQuery = ();
+    type PipelineStatsQuery = ();
     type Program = WebGlProgram;
     type Shader = WebGlShader;
     type StorageBuffer = ();
     type Texture = WebGlTexture;
-    type TextureDataReceiver = ();
+    type TextureDataReceiver = WebGlTextureDataReceiver;
     type TextureParameter = WebGlTextureParameter;
     type TimerQuery = WebGlTimerQuery;
     type Uniform = WebGlUniform;
@@ -453,12 +656,80 @@ impl Device for WebGlDevice {
         FeatureLevel::D3D10
     }
 
-    fn create_texture(&self, format: TextureFormat, size: Vector2I) -> WebGlTexture {
+    fn supports_feature(&self, feature: Feature) -> bool {
+        match feature {
+            Feature::TessellationShaders => false,
+            Feature::MeshShaders => false,
+            // WebGL 2 omits OpenGL ES 3.0's texture swizzle parameters.
+            Feature::TextureSwizzle => false,
+        }
+    }
+
+    fn device_limits(&self) -> DeviceLimits {
+        // WebGL 2 guarantees at least 2048x2048 textures and 16 vertex attributes.
+        let get_parameter = |pname, minimum| {
+            self.context
+                .get_parameter(pname)
+                .ok()
+                .and_then(|value| value.as_f64())
+                .map_or(minimum, |value| value as u32)
+        };
+        DeviceLimits {
+            max_texture_size: Vector2I::splat(get_parameter(WebGl::MAX_TEXTURE_SIZE, 2048) as i32),
+            max_compute_work_group_size: [0; 3],
+            max_storage_buffer_size: 0,
+            max_vertex_attributes: get_parameter(WebGl::MAX_VERTEX_ATTRIBS, 16),
+        }
+    }
+
+    #[inline]
+    fn storage_buffer_offset_alignment(&self) -> usize {
+        // Storage buffers are unsupported in WebGL, so any offset is trivially aligned.
+        1
+    }
+
+    fn format_is_filterable(&self, format: TextureFormat) -> bool {
+        match format {
+            TextureFormat::RGBA32F => self.float_linear_filtering,
+            _ => true,
+        }
+    }
+
+    fn supports_texture_format(&self, format: TextureFormat, usage: TextureUsage) -> bool {
+        match (format, usage) {
+            (TextureFormat::R16, _) | (TextureFormat::RGBA16, _) | (TextureFormat::BGRA8, _) => {
+                false
+            }
+            (_, TextureUsage::Filtered) => self.format_is_filterable(format),
+            // `EXT_color_buffer_float` is enabled on creation, so float formats are renderable.
+            (_, TextureUsage::Sampled) | (_, TextureUsage::RenderTarget) => true,
+        }
+    }
+
+    fn supports_non_power_of_two_mipmaps(&self) -> bool {
+        // WebGL 1 restricted mipmaps to power-of-two textures, but WebGL 2 doesn't.
+        true
+    }
+
+    fn supported_sample_counts(&self, _: TextureFormat) -> Vec<u32> {
+        // WebGL 2 can only multisample renderbuffers, not textures.
+        vec![1]
+    }
+
+    // TODO: Use a renderbuffer for textures that are only rendered to, as the OpenGL backend
+    // does.
+    fn create_texture_with_usage(
+        &self,
+        format: TextureFormat,
+        size: Vector2I,
+        _: TextureUsageFlags,
+    ) -> WebGlTexture {
         let texture = self.context.create_texture().unwrap();
         let texture = WebGlTexture {
             texture,
             format,
             size,
+            level_count: Cell::new(1),
             context: self.context.clone(),
         };
         self.bind_texture(&texture, 0);
@@ -480,6 +751,26 @@ impl Device for WebGlDevice {
         texture
     }
 
+    unsafe fn import_external_texture(&self, _: ExternalImageHandle) -> WebGlTexture {
+        panic!("External textures are unsupported in WebGL!")
+    }
+
+    fn create_render_target_texture(
+        &self,
+        format: TextureFormat,
+        size: Vector2I,
+    ) -> WebGlTexture {
+        // `EXT_color_buffer_float` is enabled on creation, so all supported formats are
+        // renderable.
+        self.create_texture(format, size)
+    }
+
+    fn create_cube_texture(&self, _: TextureFormat, _: Vector2I) -> WebGlTexture {
+        // TODO: WebGL 2 has cube maps, but framebuffers own their textures here, so only one
+        // face could be rendered to.
+        panic!("Cube textures are unsupported in WebGL!")
+    }
+
     fn create_texture_from_data(
         &self,
         format: TextureFormat,
@@ -495,6 +786,7 @@ impl Device for WebGlDevice {
             texture,
             format,
             size,
+            level_count: Cell::new(1),
             context: self.context.clone(),
         };
 
@@ -536,6 +828,12 @@ impl Device for WebGlDevice {
             ShaderKind::Vertex => WebGl::VERTEX_SHADER,
             ShaderKind::Fragment => WebGl::FRAGMENT_SHADER,
             ShaderKind::Compute => panic!("Compute shaders are unsupported in WebGL!"),
+            ShaderKind::TessControl | ShaderKind::TessEvaluation => {
+                panic!("Tessellation shaders are unsupported in WebGL!")
+            }
+            ShaderKind::Task | ShaderKind::Mesh => {
+                panic!("Mesh shaders are unsupported in WebGL!")
+            }
         };
 
         let gl_shader = self
@@ -549,7 +847,7 @@ impl Device for WebGlDevice {
             .get_shader_parameter(&gl_shader, WebGl::COMPILE_STATUS);
         if !compile_status.as_bool().unwrap_or(false) {
             if let Some(info_log) = self.context.get_shader_info_log(&gl_shader) {
-                info!("Shader info log:\n{}", info_log);
+                info!("Info log for {:?} shader '{}':\n{}", kind, name, info_log);
             }
             panic!("{:?} shader '{}' compilation failed", kind, name);
         }
@@ -572,6 +870,10 @@ impl Device for WebGlDevice {
                 self.context.attach_shader(&gl_program, &vertex.gl_shader);
                 self.context.attach_shader(&gl_program, &fragment.gl_shader);
             }
+            ProgramKind::TessellatedRaster { .. } => {
+                panic!("Tessellation shaders are unsupported in WebGL!")
+            }
+            ProgramKind::MeshRaster { .. } => panic!("Mesh shaders are unsupported in WebGL!"),
             ProgramKind::Compute(ref shader) => {
                 self.context.attach_shader(&gl_program, &shader.gl_shader);
             }
@@ -608,6 +910,8 @@ impl Device for WebGlDevice {
         WebGlVertexArray {
             context: self.context.clone(),
             gl_vertex_array: self.context.create_vertex_array().unwrap(),
+            array_buffer: RefCell::new(None),
+            instance_attrs: RefCell::new(vec![]),
         }
     }
 
@@ -642,13 +946,18 @@ impl Device for WebGlDevice {
     }
 
     fn get_image_parameter(&self, _: &WebGlProgram, _: &str) {
-        // TODO(pcwalton)
+        // Storage images are unsupported in WebGL. Looking up a parameter succeeds so that
+        // programs can be shared with other backends, but binding an image panics.
     }
 
     fn get_storage_buffer(&self, _: &Self::Program, _: &str, _: u32) {
         // TODO(pcwalton)
     }
 
+    fn bind_storage_buffer_range(&self, _: &(), _: &WebGlBuffer, _: usize, _: usize) {
+        panic!("Shader storage buffers are unsupported in WebGL!")
+    }
+
     fn configure_vertex_attr(
         &self,
         vertex_array: &WebGlVertexArray,
@@ -660,36 +969,35 @@ impl Device for WebGlDevice {
         self.context
             .bind_vertex_array(Some(&vertex_array.gl_vertex_array));
 
-        let attr_type = descriptor.attr_type.to_gl_type();
-        match descriptor.class {
-            VertexAttrClass::Float | VertexAttrClass::FloatNorm => {
-                let normalized = descriptor.class == VertexAttrClass::FloatNorm;
-                self.context.vertex_attrib_pointer_with_i32(
-                    attr.attr,
-                    descriptor.size as i32,
-                    attr_type,
-                    normalized,
-                    descriptor.stride as i32,
-                    descriptor.offset as i32,
-                );
-            }
-            VertexAttrClass::Int => {
-                self.context.vertex_attrib_i_pointer_with_i32(
-                    attr.attr,
-                    descriptor.size as i32,
-                    attr_type,
-                    descriptor.stride as i32,
-                    descriptor.offset as i32,
-                );
-            }
-        }
-
+        self.set_vertex_attr_pointer(attr.attr, descriptor, descriptor.offset);
         self.context
             .vertex_attrib_divisor(attr.attr, descriptor.divisor);
         self.context.enable_vertex_attrib_array(attr.attr);
+
+        // Remember per-instance attributes so that `draw_elements_instanced_base()` can offset
+        // them.
+        let mut instance_attrs = vertex_array.instance_attrs.borrow_mut();
+        instance_attrs.retain(|instance_attr| instance_attr.attr != attr.attr);
+        if descriptor.divisor > 0 {
+            instance_attrs.push(WebGlInstanceAttr {
+                attr: attr.attr,
+                descriptor: *descriptor,
+                buffer: vertex_array.array_buffer.borrow().clone(),
+            });
+        }
+        drop(instance_attrs);
+
         self.context.bind_vertex_array(None);
     }
 
+    fn create_framebuffer_for_cube_face(
+        &self,
+        _: &WebGlTexture,
+        _: CubeFace,
+    ) -> WebGlFramebuffer {
+        panic!("Cube textures are unsupported in WebGL!")
+    }
+
     fn create_framebuffer(&self, texture: WebGlTexture) -> WebGlFramebuffer {
         debug!(
             "texture size = {:?}, format = {:?}",
@@ -759,6 +1067,11 @@ impl Device for WebGlDevice {
         self.ck();
         let usage = buffer.mode.to_gl_usage();
         match data {
+            BufferData::Uninitialized(len) if cfg!(debug_assertions) => {
+                let debug_fill = vec![UNINITIALIZED_BUFFER_FILL_BYTE; len * mem::size_of::<T>()];
+                self.context
+                    .buffer_data_with_u8_array(target, &debug_fill, usage)
+            }
             BufferData::Uninitialized(len) => {
                 self.context
                     .buffer_data_with_i32(target, (len * mem::size_of::<T>()) as i32, usage)
@@ -770,6 +1083,10 @@ impl Device for WebGlDevice {
         }
     }
 
+    fn orphan_buffer(&self, _: &WebGlBuffer) {
+        // Browsers manage buffer storage themselves and don't expose orphaning to WebGL.
+    }
+
     fn upload_to_buffer<T>(&self,
                            buffer: &Self::Buffer,
                            position: usize,
@@ -782,6 +1099,10 @@ impl Device for WebGlDevice {
                                                             slice_to_u8(data)); self.ck();
     }
 
+    fn flush_mapped_range(&self, _buffer: &Self::Buffer, _range: Range<usize>) {
+        panic!("Mapped buffers are not supported in WebGL!")
+    }
+
     #[inline]
     fn framebuffer_texture<'f>(&self, framebuffer: &'f Self::Framebuffer) -> &'f Self::Texture {
         &framebuffer.texture
@@ -792,16 +1113,39 @@ impl Device for WebGlDevice {
         texture.size
     }
 
+    fn texture_level_count(&self, texture: &Self::Texture) -> u32 {
+        texture.level_count.get()
+    }
+
+    fn render_target_size(&self, target: &RenderTarget<WebGlDevice>) -> Vector2I {
+        match *target {
+            RenderTarget::Default => {
+                vec2i(self.context.drawing_buffer_width(), self.context.drawing_buffer_height())
+            }
+            RenderTarget::Framebuffer(framebuffer) => framebuffer.texture.size,
+        }
+    }
+
+    fn set_texture_swizzle(&self, _: &Self::Texture, _: [Swizzle; 4]) {
+        panic!("Texture swizzle unsupported in WebGL!")
+    }
+
+    #[inline]
+    fn texture_swizzle(&self, _: &Self::Texture) -> [Swizzle; 4] {
+        Swizzle::IDENTITY
+    }
+
     fn set_texture_sampling_mode(&self, texture: &Self::Texture, flags: TextureSamplingFlags) {
         self.bind_texture(texture, 0);
+        let min_filter = match (flags.contains(TextureSamplingFlags::NEAREST_MIN),
+                                flags.contains(TextureSamplingFlags::MIPMAP)) {
+            (false, false) => WebGl::LINEAR,
+            (true, false) => WebGl::NEAREST,
+            (false, true) => WebGl::LINEAR_MIPMAP_LINEAR,
+            (true, true) => WebGl::NEAREST_MIPMAP_NEAREST,
+        };
         self.context
-            .tex_parameteri(WebGl::TEXTURE_2D,
-                            WebGl::TEXTURE_MIN_FILTER,
-                            if flags.contains(TextureSamplingFlags::NEAREST_MIN) {
-                                WebGl::NEAREST as i32
-                            } else {
-                                WebGl::LINEAR as i32
-                            });
+            .tex_parameteri(WebGl::TEXTURE_2D, WebGl::TEXTURE_MIN_FILTER, min_filter as i32);
         self.context
             .tex_parameteri(WebGl::TEXTURE_2D,
                             WebGl::TEXTURE_MAG_FILTER,
@@ -813,7 +1157,8 @@ impl Device for WebGlDevice {
         self.context
             .tex_parameteri(WebGl::TEXTURE_2D,
                             WebGl::TEXTURE_WRAP_S,
-                            if flags.contains(TextureSamplingFlags::REPEAT_U) {
+                            if flags.contains(TextureSamplingFlags::REPEAT_U) &&
+                                    !flags.contains(TextureSamplingFlags::CLAMP_TO_BORDER_U) {
                                 WebGl::REPEAT as i32
                             } else {
                                 WebGl::CLAMP_TO_EDGE as i32
@@ -821,13 +1166,20 @@ impl Device for WebGlDevice {
         self.context
             .tex_parameteri(WebGl::TEXTURE_2D,
                             WebGl::TEXTURE_WRAP_T,
-                            if flags.contains(TextureSamplingFlags::REPEAT_V) {
+                            if flags.contains(TextureSamplingFlags::REPEAT_V) &&
+                                    !flags.contains(TextureSamplingFlags::CLAMP_TO_BORDER_V) {
                                 WebGl::REPEAT as i32
                             } else {
                                 WebGl::CLAMP_TO_EDGE as i32
                             });
     }
 
+    #[inline]
+    fn set_texture_border_color(&self, _: &Self::Texture, _: ColorF) {
+        // WebGL 2 has no border colors, so `CLAMP_TO_BORDER_U` and `CLAMP_TO_BORDER_V` clamp to
+        // the edge.
+    }
+
     fn upload_to_texture(&self, texture: &WebGlTexture, rect: RectI, data_ref: TextureDataRef) {
         let data = unsafe {
             check_and_extract_data(data_ref, rect.size(), texture.format)
@@ -871,8 +1223,106 @@ impl Device for WebGlDevice {
         self.set_texture_sampling_mode(&texture, TextureSamplingFlags::empty());
     }
 
-    fn read_pixels(&self, _render_target: &RenderTarget<WebGlDevice>, _viewport: RectI) -> () {
-        panic!("read_pixels is not supported");
+    fn generate_mipmaps(&self, texture: &WebGlTexture) {
+        assert!(
+            self.supports_texture_format(texture.format, TextureUsage::Filtered)
+                && self.supports_texture_format(texture.format, TextureUsage::RenderTarget),
+            "Mipmaps can't be generated for {:?}, which must be filterable and renderable!",
+            texture.format
+        );
+        self.bind_texture(texture, 0);
+        self.context.generate_mipmap(WebGl::TEXTURE_2D);
+        texture.level_count.set(mip_level_count(texture.size));
+    }
+
+    // WebGL has no pixel pack buffers that can be polled without stalling, so this reads the
+    // pixels synchronously.
+    fn read_pixels(
+        &self,
+        render_target: &RenderTarget<WebGlDevice>,
+        viewport: RectI,
+        alpha_mode: AlphaMode,
+    ) -> WebGlTextureDataReceiver {
+        let (origin, size) = (viewport.origin(), viewport.size());
+        let format = self.render_target_format(render_target);
+        let channels = format.channels();
+        let rgba_len = size.x() as usize * size.y() as usize * 4;
+        self.bind_render_target(render_target);
+
+        // WebGL only guarantees RGBA readback, with unsigned bytes for normalized formats and
+        // floats for float formats, so texels are read that way and then narrowed to `format`.
+        let mut texture_data = match format {
+            TextureFormat::R8
+            | TextureFormat::RG8
+            | TextureFormat::RGBA8
+            | TextureFormat::RGBA8Srgb => {
+                let mut pixels = vec![0; rgba_len];
+                self.context
+                    .read_pixels_with_opt_u8_array(
+                        origin.x(),
+                        origin.y(),
+                        size.x(),
+                        size.y(),
+                        WebGl::RGBA,
+                        WebGl::UNSIGNED_BYTE,
+                        Some(&mut pixels),
+                    )
+                    .unwrap();
+                TextureData::U8(narrow_channels(pixels, channels))
+            }
+            TextureFormat::R16F
+            | TextureFormat::RG16F
+            | TextureFormat::RGBA16F
+            | TextureFormat::RGBA32F => {
+                let pixels = Float32Array::new_with_length(rgba_len as u32);
+                self.context
+                    .read_pixels_with_opt_array_buffer_view(
+                        origin.x(),
+                        origin.y(),
+                        size.x(),
+                        size.y(),
+                        WebGl::RGBA,
+                        WebGl::FLOAT,
+                        Some(&pixels),
+                    )
+                    .unwrap();
+                let pixels = narrow_channels(pixels.to_vec(), channels);
+                if format == TextureFormat::RGBA32F {
+                    TextureData::F32(pixels)
+                } else {
+                    TextureData::F16(pixels.into_iter().map(f16::from_f32).collect())
+                }
+            }
+            TextureFormat::BGRA8 => panic!("BGRA textures are unsupported in WebGL!"),
+            TextureFormat::R16 | TextureFormat::RGBA16 => {
+                panic!("16-bit normalized textures are unsupported in WebGL!")
+            }
+        };
+        self.ck();
+
+        match texture_data {
+            TextureData::U8(ref mut pixels) => flip_y(pixels, size, channels),
+            TextureData::U16(ref mut pixels) => flip_y(pixels, size, channels),
+            TextureData::F16(ref mut pixels) => flip_y(pixels, size, channels),
+            TextureData::F32(ref mut pixels) => flip_y(pixels, size, channels),
+        }
+
+        if alpha_mode == AlphaMode::Straight {
+            texture_data.unpremultiply(format);
+        }
+
+        WebGlTextureDataReceiver(texture_data)
+    }
+
+    fn copy_texture_to_buffer(
+        &self,
+        _texture: &WebGlTexture,
+        _rect: RectI,
+        _buffer: &WebGlBuffer,
+        _buffer_offset: usize,
+        _bytes_per_row: usize,
+    ) {
+        panic!("copy_texture_to_buffer is not supported");
     }
 
     fn begin_commands(&self) {
@@ -884,6 +1334,49 @@ impl Device for WebGlDevice {
         self.context.flush();
     }
 
+    #[inline]
+    fn flush(&self) {
+        self.context.flush();
+    }
+
+    // WebGL has no debug annotations.
+
+    #[inline]
+    fn push_debug_group(&self, _: &str) {}
+
+    #[inline]
+    fn pop_debug_group(&self) {}
+
+    #[inline]
+    fn set_texture_label(&self, _: &Self::Texture, _: &str) {}
+
+    #[inline]
+    fn set_buffer_label(&self, _: &Self::Buffer, _: &str) {}
+
+    fn clear(&self, target: &RenderTarget<WebGlDevice>, ops: &ClearOps) {
+        self.bind_render_target(target);
+        self.clear_framebuffer(ops);
+    }
+
+    fn begin_render_pass(
+        &self,
+        target: &RenderTarget<WebGlDevice>,
+        clear_ops: &ClearOps,
+        viewport: RectI,
+    ) {
+        assert!(!self.in_render_pass.get(), "Render passes can't be nested!");
+        self.bind_render_target(target);
+        let (origin, size) = (viewport.origin(), viewport.size());
+        self.context
+            .viewport(origin.x(), origin.y(), size.x(), size.y());
+        self.clear_framebuffer(clear_ops);
+        self.in_render_pass.set(true);
+    }
+
+    fn end_render_pass(&self) {
+        assert!(self.in_render_pass.replace(false), "No render pass is active!");
+    }
+
     fn draw_arrays(&self, index_count: u32, render_state: &RenderState<Self>) {
         self.set_render_state(render_state);
         self.context.draw_arrays(
@@ -900,7 +1393,7 @@ impl Device for WebGlDevice {
             render_state.primitive.to_gl_primitive(),
             index_count as i32,
             WebGl::UNSIGNED_INT,
-            0,
+            index_buffer_offset(render_state),
         );
         self.reset_render_state(render_state);
     }
@@ -916,40 +1409,132 @@ impl Device for WebGlDevice {
             render_state.primitive.to_gl_primitive(),
             index_count as i32,
             WebGl::UNSIGNED_INT,
-            0,
+            index_buffer_offset(render_state),
             instance_count as i32,
         );
         self.reset_render_state(render_state);
     }
 
+    fn draw_elements_instanced_base(
+        &self,
+        index_count: u32,
+        instance_count: u32,
+        base_instance: u32,
+        render_state: &RenderState<Self>,
+    ) {
+        self.set_render_state(render_state);
+        let instance_attrs = render_state.vertex_array.instance_attrs.borrow();
+        self.offset_instance_attrs(&instance_attrs, base_instance);
+        self.context.draw_elements_instanced_with_i32(
+            render_state.primitive.to_gl_primitive(),
+            index_count as i32,
+            WebGl::UNSIGNED_INT,
+            index_buffer_offset(render_state),
+            instance_count as i32,
+        );
+        self.offset_instance_attrs(&instance_attrs, 0);
+        self.reset_render_state(render_state);
+    }
+
+    fn draw_elements_indirect(&self, _: &WebGlBuffer, _: usize, _: &RenderState<Self>) {
+        panic!("Indirect draws are unsupported in WebGL!")
+    }
+
     fn dispatch_compute(&self, _: ComputeDimensions, _: &ComputeState<Self>) {
         panic!("Compute shader is unsupported in WebGL!")
     }
 
+    fn draw_mesh_tasks(&self, _: ComputeDimensions, _: &RenderState<Self>) {
+        panic!("Mesh shaders are unsupported in WebGL!")
+    }
+
     #[inline]
+    // Without `EXT_disjoint_timer_query_webgl2`, timer queries do nothing and never produce
+    // results.
     fn create_timer_query(&self) -> WebGlTimerQuery {
-        // FIXME use performance timers
-        WebGlTimerQuery {}
+        let query = if self.timer_queries { self.context.create_query() } else { None };
+        WebGlTimerQuery { context: self.context.clone(), query }
     }
 
     #[inline]
-    fn begin_timer_query(&self, _query: &Self::TimerQuery) {
-        // FIXME use performance timers
+    fn begin_timer_query(&self, query: &Self::TimerQuery) {
+        if let Some(ref query) = query.query {
+            self.context.begin_query(TIME_ELAPSED_EXT, query);
+        }
     }
 
     #[inline]
-    fn end_timer_query(&self, _: &Self::TimerQuery) {
-        // FIXME use performance timers
+    fn end_timer_query(&self, query: &Self::TimerQuery) {
+        if query.query.is_some() {
+            self.context.end_query(TIME_ELAPSED_EXT);
+        }
+    }
+
+    fn try_recv_timer_query(&self, query: &WebGlTimerQuery) -> Option<Duration> {
+        let query = query.query.as_ref()?;
+        let available = self.context.get_query_parameter(query, WebGl::QUERY_RESULT_AVAILABLE);
+        if available.as_bool() != Some(true) {
+            return None;
+        }
+        let nanoseconds = self.context.get_query_parameter(query, WebGl::QUERY_RESULT).as_f64()?;
+        Some(Duration::from_nanos(nanoseconds as u64))
     }
 
+    // WebGL only makes query results available after control returns to the browser, so this
+    // can't block waiting for them.
     #[inline]
-    fn try_recv_timer_query(&self, _query: &WebGlTimerQuery) -> Option<Duration> {
-        None
+    fn recv_timer_query(&self, query: &WebGlTimerQuery) -> Duration {
+        self.try_recv_timer_query(query).unwrap_or_default()
     }
 
+    fn reset_frame_stats(&self) {}
+
+    // For the same reason, there's no way to wait for the GPU times of a batch of frames, so
+    // nothing is ever timed.
     #[inline]
-    fn recv_timer_query(&self, _query: &WebGlTimerQuery) -> Duration {
-        Duration::from_millis(0)
+    fn collect_frame_stats(&self) -> FrameStats {
+        FrameStats::default()
+    }
+
+    // WebGL has no pipeline statistics queries.
+    fn create_pipeline_stats_query(&self) {}
+
+    fn begin_pipeline_stats_query(&self, _: &()) {}
+
+    fn end_pipeline_stats_query(&self, _: &()) {}
+
+    fn recv_pipeline_stats_query(&self, _: &()) -> Option<PipelineStats> {
+        None
+    }
+
+    // WebGL 2 query results only arrive asynchronously and there's no conditional rendering, so
+    // occlusion queries conservatively report that samples always pass.
+    fn create_occlusion_query(&self) {}
+
+    fn begin_occlusion_query(&self, _: &()) {}
+
+    fn end_occlusion_query(&self, _: &()) {}
+
+    fn try_recv_occlusion_query(&self, _: &()) -> Option<bool> {
+        Some(true)
+    }
+
+    fn recv_occlusion_query(&self, _: &()) -> bool {
+        true
+    }
+
+    fn draw_elements_conditional(
+        &self,
+        index_count: u32,
+        render_state: &RenderState<Self>,
+        _: &(),
+    ) {
+        self.draw_elements(index_count, render_state)
+    }
+
+    fn gpu_timestamp_calibration(&self) -> Option<TimestampCalibration> {
+        // Browsers don't expose GPU timestamps to WebGL 2, to mitigate timing attacks.
+        None
     }
 
     fn try_recv_buffer(&self, _: &()) -> Option<Vec<u8>> {
@@ -964,12 +1549,14 @@ impl Device for WebGlDevice {
         unimplemented!()
     }
 
-    fn try_recv_texture_data(&self, _receiver: &Self::TextureDataReceiver) -> Option<TextureData> {
-        None
+    #[inline]
+    fn try_recv_texture_data(&self, receiver: &WebGlTextureDataReceiver) -> Option<TextureData> {
+        Some(receiver.0.clone())
     }
 
-    fn recv_texture_data(&self, _receiver: &Self::TextureDataReceiver) -> TextureData {
-        unimplemented!()
+    #[inline]
+    fn recv_texture_data(&self, receiver: &WebGlTextureDataReceiver) -> TextureData {
+        receiver.0.clone()
     }
 
     #[inline]
@@ -982,6 +1569,9 @@ impl Device for WebGlDevice {
         self.bind_vertex_array(vertex_array);
         self.context
             .bind_buffer(target.to_gl_target(), Some(&buffer.buffer));
+        if let BufferTarget::Vertex = target {
+            *vertex_array.array_buffer.borrow_mut() = Some(buffer.buffer.clone());
+        }
         self.unbind_vertex_array();
     }
 
@@ -993,26 +1583,56 @@ impl Device for WebGlDevice {
         kind: ShaderKind,
     ) -> Self::Shader {
         let suffix = match kind {
-            ShaderKind::Vertex => 'v',
-            ShaderKind::Fragment => 'f',
-            ShaderKind::Compute => 'c',
+            ShaderKind::Vertex => "v",
+            ShaderKind::Fragment => "f",
+            ShaderKind::Compute => "c",
+            ShaderKind::TessControl => "tc",
+            ShaderKind::TessEvaluation => "te",
+            ShaderKind::Task => "t",
+            ShaderKind::Mesh => "m",
         };
         let path = format!("shaders/gl3/{}.{}s.glsl", name, suffix);
         self.create_shader_from_source(name, &resources.slurp(&path).unwrap(), kind)
     }
 
-    fn add_fence(&self) -> Self::Fence {
-        // TODO(pcwalton)
+    fn add_fence(&self) -> WebGlFence {
+        let sync = self.context.fence_sync(WebGl::SYNC_GPU_COMMANDS_COMPLETE, 0);
+        self.context.flush();
+        WebGlFence { context: self.context.clone(), sync }
     }
 
-    fn wait_for_fence(&self, _: &Self::Fence) {
-        // TODO(pcwalton)
+    // WebGL only updates the status of a fence after control returns to the browser, and most
+    // browsers don't allow a nonzero timeout, so this can't block. It only flushes.
+    fn wait_for_fence(&self, fence: &WebGlFence) {
+        if let Some(ref sync) = fence.sync {
+            self.context.client_wait_sync_with_u32(sync, WebGl::SYNC_FLUSH_COMMANDS_BIT, 0);
+        }
+    }
+
+    fn try_wait_for_fence(&self, fence: &WebGlFence) -> bool {
+        match fence.sync {
+            None => true,
+            Some(ref sync) => {
+                let status = self.context.get_sync_parameter(sync, WebGl::SYNC_STATUS);
+                status.as_f64() == Some(WebGl::SIGNALED as f64)
+            }
+        }
     }
 }
 
 pub struct WebGlVertexArray {
     context: web_sys::WebGl2RenderingContext,
     pub gl_vertex_array: web_sys::WebGlVertexArrayObject,
+    // The buffer most recently bound to `ARRAY_BUFFER` through this vertex array.
+    array_buffer: RefCell<Option<web_sys::WebGlBuffer>>,
+    instance_attrs: RefCell<Vec<WebGlInstanceAttr>>,
+}
+
+// A per-instance attribute, along with the buffer it was configured to read from.
+struct WebGlInstanceAttr {
+    attr: u32,
+    descriptor: VertexAttrDescriptor,
+    buffer: Option<web_sys::WebGlBuffer>,
 }
 
 impl Drop for WebGlVertexArray {
@@ -1081,6 +1701,7 @@ pub struct WebGlTexture {
     texture: web_sys::WebGlTexture,
     pub size: Vector2I,
     pub format: TextureFormat,
+    level_count: Cell<u32>,
 }
 impl Drop for WebGlTexture {
     fn drop(&mut self) {
@@ -1088,7 +1709,36 @@ impl Drop for WebGlTexture {
     }
 }
 
-pub struct WebGlTimerQuery {}
+// The pixels read by `read_pixels()`, which are available immediately.
+pub struct WebGlTextureDataReceiver(TextureData);
+
+pub struct WebGlTimerQuery {
+    context: web_sys::WebGl2RenderingContext,
+    // `None` if timer queries are unsupported.
+    query: Option<web_sys::WebGlQuery>,
+}
+
+impl Drop for WebGlTimerQuery {
+    fn drop(&mut self) {
+        if let Some(ref query) = self.query {
+            self.context.delete_query(Some(query));
+        }
+    }
+}
+
+pub struct WebGlFence {
+    context: web_sys::WebGl2RenderingContext,
+    // `None` if the context was lost.
+    sync: Option<web_sys::WebGlSync>,
+}
+
+impl Drop for WebGlFence {
+    fn drop(&mut self) {
+        if let Some(ref sync) = self.sync {
+            self.context.delete_sync(Some(sync));
+        }
+    }
+}
 
 trait BufferTargetExt {
     fn to_gl_target(self) -> u32;
@@ -1139,6 +1789,8 @@ impl PrimitiveExt for Primitive {
         match self {
             Primitive::Triangles => WebGl::TRIANGLES,
             Primitive::Lines => WebGl::LINES,
+            Primitive::Points => WebGl::POINTS,
+            Primitive::Patches(_) => panic!("Tessellation is unsupported in WebGL!"),
         }
     }
 }
@@ -1167,24 +1819,48 @@ impl TextureFormatExt for TextureFormat {
         match self {
             TextureFormat::R8 => WebGl::R8,
             TextureFormat::R16F => WebGl::R16F,
+            TextureFormat::RG8 => WebGl::RG8,
+            TextureFormat::RG16F => WebGl::RG16F,
             TextureFormat::RGBA8 => WebGl::RGBA,
+            TextureFormat::RGBA8Srgb => WebGl::SRGB8_ALPHA8,
             TextureFormat::RGBA16F => WebGl::RGBA16F,
             TextureFormat::RGBA32F => WebGl::RGBA32F,
+            TextureFormat::BGRA8 => panic!("BGRA textures are unsupported in WebGL!"),
+            TextureFormat::R16 | TextureFormat::RGBA16 => {
+                panic!("16-bit normalized textures are unsupported in WebGL!")
+            }
         }
     }
 
     fn gl_format(self) -> u32 {
         match self {
             TextureFormat::R8 | TextureFormat::R16F => WebGl::RED,
-            TextureFormat::RGBA8 | TextureFormat::RGBA16F | TextureFormat::RGBA32F => WebGl::RGBA,
+            TextureFormat::RG8 | TextureFormat::RG16F => WebGl::RG,
+            TextureFormat::RGBA8
+            | TextureFormat::RGBA8Srgb
+            | TextureFormat::RGBA16F
+            | TextureFormat::RGBA32F => WebGl::RGBA,
+            TextureFormat::BGRA8 => panic!("BGRA textures are unsupported in WebGL!"),
+            TextureFormat::R16 | TextureFormat::RGBA16 => {
+                panic!("16-bit normalized textures are unsupported in WebGL!")
+            }
         }
     }
 
     fn gl_type(self) -> u32 {
         match self {
-            TextureFormat::R8 | TextureFormat::RGBA8 => WebGl::UNSIGNED_BYTE,
-            TextureFormat::R16F | TextureFormat::RGBA16F => WebGl::HALF_FLOAT,
+            TextureFormat::R8 |
+            TextureFormat::RG8 |
+            TextureFormat::RGBA8 |
+            TextureFormat::RGBA8Srgb |
+            TextureFormat::BGRA8 => WebGl::UNSIGNED_BYTE,
+            TextureFormat::R16F | TextureFormat::RG16F | TextureFormat::RGBA16F => {
+                WebGl::HALF_FLOAT
+            }
             TextureFormat::RGBA32F => WebGl::FLOAT,
+            TextureFormat::R16 | TextureFormat::RGBA16 => {
+                panic!("16-bit normalized textures are unsupported in WebGL!")
+            }
         }
     }
 }